@@ -1,22 +1,169 @@
-use anyhow::Result;
+//! Dump records of a utmp file, e.g. to see how a file is parsed when reporting a layout bug.
+//!
+//! ```text
+//! cargo run --example dump_utmp -- [--format 32|64|auto] [--json] [--raw-hex] <path>
+//! ```
+//!
+//! Exits with 1 if the file can't be read, 2 on wrong usage,
+//! and 3 if any record fails to be parsed, after dumping the rest.
+
 use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::mem;
 use std::path::PathBuf;
 use std::process;
+use utmp_raw::{x32::utmp as utmp32, x64::utmp as utmp64};
+use utmp_rs::{Format, JsonWriter, ParseError, UtmpEntry, UtmpParserBuilder};
+
+const EXIT_IO: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_PARSE: i32 = 3;
+
+struct Options {
+    format: Format,
+    json: bool,
+    raw_hex: bool,
+    path: PathBuf,
+}
 
-fn main() -> Result<()> {
+fn main() {
     let mut args = env::args_os();
     let program_name = PathBuf::from(args.next().unwrap());
-    let path = match args.next() {
-        Some(path) => PathBuf::from(path),
+    let options = match parse_args(args.map(|arg| arg.to_string_lossy().into_owned())) {
+        Some(options) => options,
         None => {
-            eprintln!("Usage: {} <path>", program_name.display());
-            process::exit(2);
+            eprintln!(
+                "Usage: {} [--format 32|64|auto] [--json] [--raw-hex] <path>",
+                program_name.display()
+            );
+            process::exit(EXIT_USAGE);
+        }
+    };
+    let bytes = match fs::read(&options.path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}: {}", options.path.display(), e);
+            process::exit(EXIT_IO);
         }
     };
+    match dump(&options, &bytes) {
+        Ok(true) => {}
+        Ok(false) => process::exit(EXIT_PARSE),
+        // Output going away, e.g. when piped into `head`, isn't worth reporting.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(EXIT_IO);
+        }
+    }
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Option<Options> {
+    let mut format = Format::Auto;
+    let mut json = false;
+    let mut raw_hex = false;
+    let mut path = None;
+    let mut args = args.peekable();
+    while let Some(arg) = args.next() {
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_owned(), Some(value.into())),
+            _ => (arg, None),
+        };
+        match name.as_str() {
+            "--format" => {
+                format = match value.or_else(|| args.next())?.as_str() {
+                    "32" => Format::X32,
+                    "64" => Format::X64,
+                    "auto" => Format::Auto,
+                    _ => return None,
+                }
+            }
+            "--json" => json = true,
+            "--raw-hex" => raw_hex = true,
+            _ if name.starts_with('-') || path.is_some() => return None,
+            _ => path = Some(PathBuf::from(name)),
+        }
+    }
+    Some(Options {
+        format,
+        json,
+        raw_hex,
+        path: path?,
+    })
+}
+
+/// Dump records to stdout, and return whether all of them were parsed.
+fn dump(options: &Options, bytes: &[u8]) -> io::Result<bool> {
+    let parser = UtmpParserBuilder::new()
+        .format(options.format)
+        .from_bytes(bytes);
+    let record_size = match parser.format() {
+        Format::X64 => mem::size_of::<utmp64>(),
+        _ => mem::size_of::<utmp32>(),
+    };
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let mut parsed = true;
+    let mut index = 0;
+    for entry in parser {
+        let offset = index * record_size;
+        match entry {
+            Ok(entry) => {
+                let raw = &bytes[offset..offset + record_size];
+                write_entry(&mut out, options, &entry, raw)?;
+                index += 1;
+            }
+            // Reported before any record, and doesn't stand for one.
+            Err(e @ ParseError::Misaligned { .. }) => {
+                eprintln!("{}", e);
+                parsed = false;
+            }
+            Err(e) => {
+                out.flush()?;
+                eprintln!("{}", e);
+                parsed = false;
+                index += 1;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(parsed)
+}
+
+fn write_entry(
+    out: &mut impl Write,
+    options: &Options,
+    entry: &UtmpEntry,
+    raw: &[u8],
+) -> io::Result<()> {
+    if options.json {
+        let mut line = Vec::new();
+        JsonWriter::lines(&mut line).write_entry(entry)?;
+        if options.raw_hex {
+            // Add the raw bytes as another member of the object.
+            let end = line.iter().rposition(|&b| b == b'}').unwrap();
+            line.truncate(end);
+            write!(line, r#","raw":"{}"}}"#, hex(raw))?;
+            line.push(b'\n');
+        }
+        out.write_all(&line)
+    } else {
+        writeln!(out, "{}", entry)?;
+        if options.raw_hex {
+            for (i, chunk) in raw.chunks(16).enumerate() {
+                writeln!(out, "    {:04x}: {}", i * 16, hex(chunk))?;
+            }
+        }
+        Ok(())
+    }
+}
 
-    let entries = utmp_rs::parse_from_path(&path)?;
-    for entry in entries {
-        println!("{:?}", entry);
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{:02x}", byte).unwrap();
     }
-    Ok(())
+    hex
 }
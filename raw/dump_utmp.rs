@@ -6,13 +6,10 @@ use std::mem;
 use std::path::PathBuf;
 use std::process;
 use utmp_raw::utmp;
-use zerocopy::LayoutVerified;
+use zerocopy::FromBytes;
 
 const SIZE: usize = mem::size_of::<utmp>();
 
-#[repr(align(8))]
-struct Buffer([u8; SIZE]);
-
 fn main() -> io::Result<()> {
     let mut args = env::args_os();
     let program_name = PathBuf::from(args.next().unwrap());
@@ -25,11 +22,12 @@ fn main() -> io::Result<()> {
     };
 
     let mut f = File::open(&path)?;
-    let mut buffer = Buffer([0; SIZE]);
-    while let Ok(()) = f.read_exact(&mut buffer.0) {
-        let buffer = buffer.0.as_ref();
-        let record = LayoutVerified::<_, utmp>::new(buffer).unwrap().into_ref();
-        println!("{:#?}", record);
+    let mut buffer = [0; SIZE];
+    while let Ok(()) = f.read_exact(&mut buffer) {
+        // Copy the record out, so that the buffer doesn't need to be aligned.
+        if let Some(record) = utmp::read_from(&buffer[..]) {
+            println!("{:#?}", record);
+        }
     }
     Ok(())
 }
@@ -2,12 +2,16 @@
 
 use cfg_if::cfg_if;
 use std::ffi::CStr;
-use std::os::raw::c_short;
 use zerocopy::{FromBytes, FromZeroes};
 
 pub mod x32;
 pub mod x64;
 
+/// Type of `ut_type` and exit status fields, `short` in C
+pub type c_short = i16;
+/// Type of process and session IDs, `pid_t` in C
+pub type pid_t = i32;
+
 /// Record does not contain valid info (formerly known as `UT_UNKNOWN` on Linux)
 pub const EMPTY: c_short = 0;
 /// Change in system run-level (see `init(8)`)
@@ -63,6 +67,10 @@ cfg_if! {
         target_arch = "s390x",
     ))] {
         pub use x64::*;
+    } else if #[cfg(target_arch = "wasm32")] {
+        // There is no utmp on WebAssembly, so use the layout of files
+        // from x86_64 Linux, which are the most likely to be parsed there.
+        pub use x32::*;
     } else {
         compile_error!("The target platform is not supported, please help us add it.");
     }
@@ -1,5 +1,4 @@
-use super::{cstr_from_bytes, exit_status, UT_HOSTSIZE, UT_LINESIZE, UT_NAMESIZE};
-use libc::c_short;
+use super::{c_short, cstr_from_bytes, exit_status, pid_t, UT_HOSTSIZE, UT_LINESIZE, UT_NAMESIZE};
 use std::fmt;
 use zerocopy::{FromBytes, FromZeroes};
 
@@ -18,7 +17,7 @@ pub struct utmp {
     /// Type of record
     pub ut_type: c_short,
     /// PID of login process
-    pub ut_pid: libc::pid_t,
+    pub ut_pid: pid_t,
     /// Device name of tty - `"/dev/"`
     pub ut_line: [u8; UT_LINESIZE],
     /// Terminal name suffix, or `inittab(5)` ID
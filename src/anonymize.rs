@@ -0,0 +1,265 @@
+use crate::builder::{detect_format, native_format, HEAD_SIZE};
+use crate::entry_ref::{address_from_raw, trim_nul};
+use crate::parse::raw_from_record;
+use crate::raw::RawUtmp;
+use crate::{Endianness, Format, UtmpEntry};
+use std::fmt;
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use utmp_raw::{x32::utmp as utmp32, x64::utmp as utmp64};
+
+/// Transformation which scrubs or pseudonymizes users and hosts,
+/// keeping everything else, including times, as is,
+/// e.g. for sharing problematic files in bug reports.
+///
+/// Users and hosts are only rewritten in process records,
+/// since other records use these fields for markers like `reboot` and kernel versions.
+/// Addresses are rewritten along with hosts,
+/// so that numeric hosts keep matching theirs.
+///
+/// ```
+/// # use utmp_rs::{Anonymizer, Format, Redaction};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let anonymizer = Anonymizer::new()
+///     .format(Format::Auto)
+///     .users(Redaction::Hash)
+///     .hosts(Redaction::Map(Box::new(|host| host.replace("corp", "example"))))
+///     .salt("not guessable");
+/// let bytes = anonymizer.anonymize_bytes(&std::fs::read("/var/log/wtmp")?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Anonymizer {
+    format: Format,
+    endianness: Endianness,
+    users: Redaction,
+    hosts: Redaction,
+    salt: String,
+}
+
+/// How `Anonymizer` rewrites a field.
+#[non_exhaustive]
+pub enum Redaction {
+    /// Keep the field as is
+    Keep,
+    /// Clear the field
+    Drop,
+    /// Replace the field with a pseudonym derived from its hash,
+    /// e.g. `user-1f2e3d4c` for users and `host-1f2e3d4c` for hostnames,
+    /// or an address in `10.0.0.0/8` or `fd00::/8` for addresses.
+    ///
+    /// The same value always gets the same pseudonym with the same salt,
+    /// so pseudonyms can be correlated across records and files.
+    /// The hash isn't cryptographic, so the salt should be kept secret
+    /// if values must not be guessed back.
+    Hash,
+    /// Replace the field with what the function returns for it.
+    ///
+    /// Addresses are passed as text, and cleared if the result isn't an address.
+    Map(Box<dyn Fn(&str) -> String + Send + Sync>),
+}
+
+impl fmt::Debug for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Redaction::Keep => f.write_str("Keep"),
+            Redaction::Drop => f.write_str("Drop"),
+            Redaction::Hash => f.write_str("Hash"),
+            Redaction::Map(_) => f.write_str("Map(..)"),
+        }
+    }
+}
+
+impl Default for Anonymizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Anonymizer {
+    /// Create an anonymizer of records in the native format for the target platform,
+    /// which pseudonymizes both users and hosts by hash without salt.
+    pub fn new() -> Self {
+        Anonymizer {
+            format: native_format(),
+            endianness: Endianness::Native,
+            users: Redaction::Hash,
+            hosts: Redaction::Hash,
+            salt: String::new(),
+        }
+    }
+
+    /// Set the layout of records.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the byte order of numeric fields in records.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Set how users are rewritten.
+    pub fn users(mut self, redaction: Redaction) -> Self {
+        self.users = redaction;
+        self
+    }
+
+    /// Set how hosts and addresses are rewritten.
+    pub fn hosts(mut self, redaction: Redaction) -> Self {
+        self.hosts = redaction;
+        self
+    }
+
+    /// Set the salt hashed along with values by `Redaction::Hash`.
+    pub fn salt(mut self, salt: &str) -> Self {
+        self.salt = salt.to_owned();
+        self
+    }
+
+    /// Rewrite the records in the given bytes, leaving the layout intact.
+    ///
+    /// Values are truncated to the size of their fields,
+    /// and trailing bytes not forming a whole record are kept as is.
+    pub fn anonymize_bytes(&self, bytes: &[u8]) -> Vec<u8> {
+        let swap = self.endianness.swaps();
+        let format = match self.format {
+            Format::Auto => {
+                let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+                detect_format(head, Some(bytes.len() as u64), swap)
+            }
+            format => format,
+        };
+        let mut bytes = bytes.to_vec();
+        match format {
+            Format::X64 => self.anonymize_records::<utmp64>(&mut bytes, swap),
+            _ => self.anonymize_records::<utmp32>(&mut bytes, swap),
+        }
+        bytes
+    }
+
+    /// Rewrite the entry.
+    pub fn anonymize_entry(&self, entry: &UtmpEntry) -> UtmpEntry {
+        let mut entry = entry.clone();
+        match &mut entry {
+            UtmpEntry::UserProcess {
+                user,
+                host,
+                address,
+                ..
+            } => {
+                *user = self.user(user);
+                *host = self.host(host);
+                *address = address.and_then(|address| self.address(address));
+            }
+            UtmpEntry::LoginProcess { user, host, .. } => {
+                *user = self.user(user);
+                *host = self.host(host);
+            }
+            _ => {}
+        }
+        entry
+    }
+
+    fn anonymize_records<T: RawUtmp>(&self, bytes: &mut [u8], swap: bool) {
+        for record in bytes.chunks_exact_mut(mem::size_of::<T>()) {
+            let raw = raw_from_record::<T>(record, swap);
+            let process =
+                (utmp_raw::INIT_PROCESS..=utmp_raw::DEAD_PROCESS).contains(&raw.ut_type());
+            if !process {
+                continue;
+            }
+            let user = self.user(&String::from_utf8_lossy(trim_nul(raw.ut_user())));
+            let host = self.host(&String::from_utf8_lossy(trim_nul(raw.ut_host())));
+            let address = address_from_raw(raw.ut_addr_v6()).and_then(|a| self.address(a));
+            if !matches!(self.users, Redaction::Keep) {
+                put_str(&mut record[T::USER_OFFSET..][..raw.ut_user().len()], &user);
+            }
+            if !matches!(self.hosts, Redaction::Keep) {
+                put_str(&mut record[T::HOST_OFFSET..][..raw.ut_host().len()], &host);
+                // Addresses are in network byte order, which is never swapped.
+                let field = &mut record[T::ADDR_OFFSET..][..16];
+                field.fill(0);
+                match address {
+                    Some(IpAddr::V4(address)) => field[..4].copy_from_slice(&address.octets()),
+                    Some(IpAddr::V6(address)) => field.copy_from_slice(&address.octets()),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    fn user(&self, user: &str) -> String {
+        // Login prompts have this in place of users, which isn't worth hiding.
+        if user.is_empty() || user == "LOGIN" {
+            return user.to_owned();
+        }
+        match &self.users {
+            Redaction::Keep => user.to_owned(),
+            Redaction::Drop => String::new(),
+            Redaction::Hash => format!("user-{:08x}", self.hash(user) as u32),
+            Redaction::Map(f) => f(user),
+        }
+    }
+
+    fn host(&self, host: &str) -> String {
+        if host.is_empty() {
+            return String::new();
+        }
+        if let Ok(address) = host.parse::<IpAddr>() {
+            let address = self.address(address);
+            return address.map(|a| a.to_string()).unwrap_or_default();
+        }
+        match &self.hosts {
+            Redaction::Keep => host.to_owned(),
+            Redaction::Drop => String::new(),
+            Redaction::Hash => {
+                // Keep the display of X sessions, e.g. `:0` of `host:0`.
+                let (name, display) = host.split_at(host.find(':').unwrap_or(host.len()));
+                format!("host-{:08x}{}", self.hash(name) as u32, display)
+            }
+            Redaction::Map(f) => f(host),
+        }
+    }
+
+    fn address(&self, address: IpAddr) -> Option<IpAddr> {
+        match &self.hosts {
+            Redaction::Keep => Some(address),
+            Redaction::Drop => None,
+            Redaction::Hash => {
+                let hash = self.hash(&address.to_string()).to_be_bytes();
+                Some(match address {
+                    IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::new(10, hash[0], hash[1], hash[2])),
+                    IpAddr::V6(_) => {
+                        let mut octets = [0; 16];
+                        octets[0] = 0xfd;
+                        octets[8..].copy_from_slice(&hash);
+                        IpAddr::V6(Ipv6Addr::from(octets))
+                    }
+                })
+            }
+            Redaction::Map(f) => f(&address.to_string()).parse().ok(),
+        }
+    }
+
+    /// FNV-1a hash of the salt and the value.
+    fn hash(&self, value: &str) -> u64 {
+        let bytes = self.salt.bytes().chain(Some(0)).chain(value.bytes());
+        bytes.fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
+    }
+}
+
+/// Write the string into the field, truncated at a character boundary and padded with NUL.
+fn put_str(field: &mut [u8], value: &str) {
+    let mut len = value.len().min(field.len());
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+    field.fill(0);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
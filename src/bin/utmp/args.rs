@@ -0,0 +1,117 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Arguments of a subcommand, from which options are taken one by one,
+/// leaving positional arguments at the end.
+///
+/// Options are accepted anywhere before `--`, as `-n 10`, `--name 10` or `--name=10`.
+pub struct Args {
+    args: Vec<String>,
+}
+
+/// Error from invalid arguments, reported along with the usage.
+#[derive(Debug)]
+pub struct UsageError(pub String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for UsageError {}
+
+impl Args {
+    pub fn new<I: IntoIterator<Item = String>>(args: I) -> Self {
+        Args {
+            args: args.into_iter().collect(),
+        }
+    }
+
+    /// Take the flag with any of the given names, returning whether it's present.
+    pub fn flag(&mut self, names: &[&str]) -> bool {
+        let mut found = false;
+        let mut i = 0;
+        while let Some(arg) = self.args.get(i) {
+            if arg == "--" {
+                break;
+            }
+            if names.contains(&arg.as_str()) {
+                self.args.remove(i);
+                found = true;
+            } else {
+                i += 1;
+            }
+        }
+        found
+    }
+
+    /// Take the value of the option with any of the given names, the last one if repeated.
+    pub fn value(&mut self, names: &[&str]) -> Result<Option<String>, UsageError> {
+        let mut value = None;
+        let mut i = 0;
+        while let Some(arg) = self.args.get(i) {
+            if arg == "--" {
+                break;
+            }
+            let inline = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") && names.contains(&name) => {
+                    Some(value.to_owned())
+                }
+                _ => None,
+            };
+            if inline.is_some() {
+                value = inline;
+                self.args.remove(i);
+            } else if names.contains(&arg.as_str()) {
+                if i + 1 >= self.args.len() {
+                    return Err(UsageError(format!("missing value for {}", arg)));
+                }
+                value = Some(self.args.remove(i + 1));
+                self.args.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Take the value of the option with any of the given names, and parse it.
+    pub fn parse<T: FromStr>(&mut self, names: &[&str]) -> Result<Option<T>, UsageError> {
+        match self.value(names)? {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| UsageError(format!("invalid value for {}: {}", names[0], value))),
+            None => Ok(None),
+        }
+    }
+
+    /// Take the remaining positional arguments, rejecting any option not taken.
+    pub fn positionals(self) -> Result<Vec<String>, UsageError> {
+        let mut positionals = Vec::new();
+        let mut args = self.args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positionals.extend(args);
+                break;
+            }
+            if arg.starts_with('-') && arg != "-" {
+                return Err(UsageError(format!("unknown option {}", arg)));
+            }
+            positionals.push(arg);
+        }
+        Ok(positionals)
+    }
+
+    /// Take at most `max` positional arguments.
+    pub fn at_most(self, max: usize) -> Result<Vec<String>, UsageError> {
+        let positionals = self.positionals()?;
+        if positionals.len() > max {
+            let extra = &positionals[max];
+            return Err(UsageError(format!("unexpected argument {}", extra)));
+        }
+        Ok(positionals)
+    }
+}
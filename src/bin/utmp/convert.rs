@@ -0,0 +1,99 @@
+use crate::args::{Args, UsageError};
+use crate::Result;
+use std::fs;
+use utmp_rs::{Converter, Endianness, Format, UtmpDump};
+
+/// Layout of a file given to `convert`.
+#[derive(Clone, Copy)]
+enum Layout {
+    /// Binary records of the layout and byte order
+    Records(Format, Endianness),
+    /// Text of `utmpdump`
+    Text,
+}
+
+const LAYOUTS: &str = "auto, linux32-le, linux32-be, linux64-le, linux64-be, 32, 64 or utmpdump";
+
+impl Layout {
+    fn parse(value: &str) -> std::result::Result<Self, UsageError> {
+        Ok(match value {
+            "auto" => Layout::Records(Format::Auto, Endianness::Native),
+            "32" => Layout::Records(Format::X32, Endianness::Native),
+            "64" => Layout::Records(Format::X64, Endianness::Native),
+            "linux32-le" => Layout::Records(Format::X32, Endianness::Little),
+            "linux32-be" => Layout::Records(Format::X32, Endianness::Big),
+            "linux64-le" => Layout::Records(Format::X64, Endianness::Little),
+            "linux64-be" => Layout::Records(Format::X64, Endianness::Big),
+            "utmpdump" => Layout::Text,
+            _ => {
+                let message = format!("unknown layout {}, expected {}", value, LAYOUTS);
+                return Err(UsageError(message));
+            }
+        })
+    }
+}
+
+/// Rewrite a file in another layout, e.g. `utmp convert --from auto --to linux64-le in out`.
+///
+/// `auto` detects the layout of records in native byte order,
+/// or the text of `utmpdump`, whose lines start with `[` unlike any record.
+pub fn run(mut args: Args) -> Result {
+    let from = args.value(&["--from"])?;
+    let to = args.value(&["--to"])?;
+    let from = Layout::parse(from.as_deref().unwrap_or("auto"))?;
+    let to = match to.as_deref().map(Layout::parse).transpose()? {
+        Some(Layout::Records(Format::Auto, _)) | None => {
+            let message = format!("--to must be one of {}, except auto", LAYOUTS);
+            return Err(UsageError(message).into());
+        }
+        Some(to) => to,
+    };
+    let paths = args.at_most(2)?;
+    let (input, output) = match paths.as_slice() {
+        [input, output] => (input, output),
+        _ => return Err(UsageError("missing input or output file".to_owned()).into()),
+    };
+
+    let bytes = fs::read(input).map_err(|e| crate::path_error(input, e))?;
+    let from = match from {
+        Layout::Records(Format::Auto, _) if bytes.first() == Some(&b'[') => Layout::Text,
+        from => from,
+    };
+    let result = match (from, to) {
+        (Layout::Records(format, endianness), Layout::Records(target, target_endianness)) => {
+            Converter::new(target)
+                .format(format)
+                .endianness(endianness)
+                .target_endianness(target_endianness)
+                .convert_bytes(&bytes)?
+        }
+        (Layout::Records(format, endianness), Layout::Text) => {
+            let mut text = Vec::new();
+            let dump = UtmpDump::new().format(format).endianness(endianness);
+            dump.write(&bytes, &mut text)?;
+            text
+        }
+        (Layout::Text, to) => {
+            let text = String::from_utf8(bytes).map_err(|_| format!("{}: invalid UTF-8", input))?;
+            let (format, endianness) = match to {
+                Layout::Records(format, endianness) => (format, endianness),
+                // Text is normalized through records of the native layout.
+                Layout::Text => (Format::Auto, Endianness::Native),
+            };
+            let dump = UtmpDump::new().format(format).endianness(endianness);
+            let records = dump
+                .undump(&text)
+                .map_err(|e| format!("{}: {}", input, e))?;
+            match to {
+                Layout::Records(..) => records,
+                Layout::Text => {
+                    let mut text = Vec::new();
+                    dump.write(&records, &mut text)?;
+                    text
+                }
+            }
+        }
+    };
+    fs::write(output, result).map_err(|e| crate::path_error(output, e))?;
+    Ok(())
+}
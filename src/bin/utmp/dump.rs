@@ -0,0 +1,31 @@
+use crate::args::{Args, UsageError};
+use crate::{Result, UTMP_PATH};
+use std::fs;
+use std::io::Write;
+use utmp_rs::{CsvWriter, Format, JsonWriter, UtmpDump};
+
+pub fn run(mut args: Args, out: &mut dyn Write) -> Result {
+    let json = args.flag(&["--json"]);
+    let csv = args.flag(&["--csv"]);
+    let path = args.at_most(1)?.pop();
+    let path = path.as_deref().unwrap_or(UTMP_PATH);
+    if json && csv {
+        return Err(UsageError("--json and --csv can't be used together".to_owned()).into());
+    }
+    if json || csv {
+        let entries = utmp_rs::parse_from_path(path)?;
+        if json {
+            let mut writer = JsonWriter::lines(out);
+            writer.write_entries(&entries)?;
+            writer.finish()?;
+        } else {
+            let mut writer = CsvWriter::entries(out)?;
+            entries.iter().try_for_each(|e| writer.write_entry(e))?;
+            writer.finish()?;
+        }
+        return Ok(());
+    }
+    let bytes = fs::read(path).map_err(|e| crate::path_error(path, e))?;
+    UtmpDump::new().format(Format::Auto).write(&bytes, out)?;
+    Ok(())
+}
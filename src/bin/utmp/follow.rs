@@ -0,0 +1,144 @@
+use crate::args::Args;
+use crate::Result;
+use std::io::Write;
+use utmp_rs::{Event, FailedLogin};
+
+/// Print events of the watched files as they happen,
+/// e.g. `2023-02-07 08:07:06 login root pts/0 112.124.2.209`.
+///
+/// Entries of btmp are printed as failed logins,
+/// and entries of other files are interpreted into logins, logouts, boots and shutdowns.
+#[cfg(target_os = "linux")]
+pub fn run(mut args: Args, out: &mut dyn Write) -> Result {
+    use std::collections::HashMap;
+    use utmp_rs::{EventInterpreter, JsonWriter, Source, UtmpWatcher, WatchEvent};
+
+    let json = args.flag(&["--json"]);
+    let since = args.value(&["-s", "--since"])?;
+    let since = since.as_deref().map(crate::parse_time).transpose()?;
+    let mut sources = Vec::new();
+    if args.flag(&["--utmp"]) {
+        sources.push(Source::Utmp);
+    }
+    if args.flag(&["--wtmp"]) {
+        sources.push(Source::Wtmp);
+    }
+    if args.flag(&["--btmp"]) {
+        sources.push(Source::Btmp);
+    }
+    let paths = args.positionals()?;
+    sources.extend(paths.into_iter().map(|path| Source::Other(path.into())));
+    // utmp records the same logins as wtmp, so only wtmp is watched by default.
+    if sources.is_empty() {
+        sources.push(Source::Wtmp);
+    }
+
+    let mut watcher = UtmpWatcher::from_sources(None)?;
+    for source in sources {
+        let path = source.path().display().to_string();
+        match since {
+            Some(since) => watcher.watch_since(source, since)?,
+            None => watcher
+                .watch(source)
+                .map_err(|e| crate::path_error(&path, e))?,
+        }
+    }
+    // Each file is interpreted on its own, since utmp and wtmp record the same logins.
+    let mut interpreters = HashMap::<Source, EventInterpreter>::new();
+    for event in watcher {
+        let (source, entry) = match event? {
+            WatchEvent::Entry(source, entry) => (source, entry),
+            WatchEvent::Rotated(source) => {
+                eprintln!("utmp: {} was rotated", source.path().display());
+                continue;
+            }
+            _ => continue,
+        };
+        if source == Source::Btmp {
+            for attempt in utmp_rs::failed_logins(Some(&entry)) {
+                if json {
+                    JsonWriter::lines(&mut *out).write_failed_login(&attempt)?;
+                } else {
+                    writeln!(out, "{}", describe_failed(&attempt))?;
+                }
+            }
+        } else {
+            let mut events = Vec::new();
+            let interpreter = interpreters.entry(source).or_default();
+            interpreter.feed(&entry, &mut |event| events.push(event));
+            for event in events {
+                if json {
+                    JsonWriter::lines(&mut *out).write_event(&event)?;
+                } else {
+                    writeln!(out, "{}", describe(&event))?;
+                }
+            }
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn run(_args: Args, _out: &mut dyn Write) -> Result {
+    Err("follow is only supported on Linux".into())
+}
+
+/// Event formatted like `2023-02-07 08:07:06 login root pts/0 112.124.2.209`,
+/// with `-` in place of empty hosts.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn describe(event: &Event) -> String {
+    let (time, fields) = match event {
+        Event::UserLoggedIn {
+            user,
+            line,
+            host,
+            time,
+        } => (time, format!("login {} {} {}", user, line, or_dash(host))),
+        Event::UserLoggedOut {
+            user,
+            line,
+            host,
+            time,
+            duration,
+        } => {
+            let seconds = duration.whole_seconds();
+            let duration = format!("{:02}:{:02}", seconds / 3600, seconds / 60 % 60);
+            (
+                time,
+                format!("logout {} {} {} {}", user, line, or_dash(host), duration),
+            )
+        }
+        Event::SystemBooted {
+            kernel_version,
+            time,
+        } => (time, format!("boot {}", kernel_version)),
+        Event::SystemShutdown {
+            kernel_version,
+            time,
+        } => (time, format!("shutdown {}", kernel_version)),
+        _ => return String::new(),
+    };
+    format!("{} {}", crate::format_time(*time), fields)
+}
+
+/// Attempt formatted like `2023-02-07 08:07:06 failed root ssh:notty 112.124.2.209`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn describe_failed(attempt: &FailedLogin) -> String {
+    format!(
+        "{} failed {} {} {}",
+        crate::format_time(attempt.time),
+        attempt.user,
+        attempt.line,
+        or_dash(&attempt.host),
+    )
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn or_dash(value: &str) -> &str {
+    if value.is_empty() {
+        "-"
+    } else {
+        value
+    }
+}
@@ -0,0 +1,132 @@
+use crate::args::Args;
+use crate::{parse_time, Result, BTMP_PATH, WTMP_PATH};
+use std::io::Write;
+use std::path::Path;
+use utmp_rs::{LastEntry, LastLine, Timestamp, UtmpEntry, UtmpParser};
+
+/// Line listed by `last`, with what it's filtered by.
+struct Row {
+    user: String,
+    line: String,
+    time: Timestamp,
+    text: String,
+}
+
+pub fn run(mut args: Args, out: &mut dyn Write) -> Result {
+    let path = args.value(&["-f", "--file"])?;
+    let path = path.as_deref().unwrap_or(WTMP_PATH);
+    let limit = args.parse::<usize>(&["-n", "--limit"])?;
+    let since = args.value(&["-s", "--since"])?;
+    let until = args.value(&["-t", "--until"])?;
+    let since = since.as_deref().map(parse_time).transpose()?;
+    let until = until.as_deref().map(parse_time).transpose()?;
+    let system = args.flag(&["-x", "--system"]);
+    let full = args.flag(&["-F", "--fulltimes"]);
+    let ips = args.flag(&["-i", "--ip"]);
+    let filters = args.positionals()?;
+
+    let mut rows = Vec::new();
+    for entry in utmp_rs::last_from_path(path)? {
+        let (user, line) = match &entry {
+            LastEntry::Session(s) => (s.user.clone(), s.line.clone()),
+            LastEntry::Reboot { .. } => ("reboot".to_owned(), "system boot".to_owned()),
+            LastEntry::Shutdown { .. } if system => {
+                ("shutdown".to_owned(), "system down".to_owned())
+            }
+            _ => continue,
+        };
+        let text = LastLine::new(&entry).full_times(full).ips(ips).to_string();
+        let time = entry.time();
+        rows.push(Row {
+            user,
+            line,
+            time,
+            text,
+        });
+    }
+    if system {
+        let parser = UtmpParser::from_path(path).map_err(|e| crate::path_error(path, e))?;
+        for entry in parser {
+            if let UtmpEntry::RunLevel {
+                level,
+                kernel_version,
+                time,
+                ..
+            } = entry?
+            {
+                let line = format!("(to lvl {})", level);
+                let time_text = last_time(time, full);
+                let text = format!(
+                    "{:<8} {:<12} {:<16} {}",
+                    "runlevel", line, kernel_version, time_text
+                );
+                let user = "runlevel".to_owned();
+                rows.push(Row {
+                    user,
+                    line,
+                    time,
+                    text,
+                });
+            }
+        }
+        rows.sort_by_key(|row| std::cmp::Reverse(row.time));
+    }
+
+    let rows = rows.iter().filter(|row| {
+        let matches = |filter: &String| {
+            *filter == row.user
+                || *filter == row.line
+                || row.line.strip_prefix("tty") == Some(filter.as_str())
+        };
+        (filters.is_empty() || filters.iter().any(matches))
+            && since.map_or(true, |since| row.time >= since)
+            && until.map_or(true, |until| row.time <= until)
+    });
+    for row in rows.take(limit.unwrap_or(usize::MAX)) {
+        writeln!(out, "{}", row.text)?;
+    }
+    write_begins(out, path)
+}
+
+pub fn run_failed(mut args: Args, out: &mut dyn Write) -> Result {
+    let path = args.value(&["-f", "--file"])?;
+    let path = path.as_deref().unwrap_or(BTMP_PATH);
+    args.at_most(0)?;
+    for attempt in utmp_rs::failed_logins(utmp_rs::parse_from_path(path)?) {
+        writeln!(out, "{}", attempt)?;
+    }
+    write_begins(out, path)
+}
+
+/// Write the footer with the time of the first entry, like `wtmp begins Tue Feb  7 08:07:06 2023`.
+fn write_begins(out: &mut dyn Write, path: &str) -> Result {
+    let first = UtmpParser::from_path(path)
+        .map_err(|e| crate::path_error(path, e))?
+        .next()
+        .transpose()?;
+    let begins = first.and_then(|entry| entry.time());
+    let name = Path::new(path).file_name().unwrap_or_default();
+    let begins = begins.map_or_else(|| "now".to_owned(), |time| last_time(time, true));
+    writeln!(out, "\n{} begins {}", name.to_string_lossy(), begins)?;
+    Ok(())
+}
+
+/// Time formatted like `last(1)` does, i.e. `Tue Feb  7 08:07`, or `Tue Feb  7 08:07:06 2023` in full.
+fn last_time(time: Timestamp, full: bool) -> String {
+    let time = crate::datetime(time);
+    let weekday = time.weekday().to_string();
+    let month = time.month().to_string();
+    let short = format!(
+        "{} {} {:>2} {:02}:{:02}",
+        &weekday[..3],
+        &month[..3],
+        time.day(),
+        time.hour(),
+        time.minute(),
+    );
+    if full {
+        format!("{}:{:02} {}", short, time.second(), time.year())
+    } else {
+        short
+    }
+}
@@ -0,0 +1,165 @@
+//! Command line tool over utmp files, in place of `utmpdump`, `who`, `last` and `lastb`.
+
+mod args;
+mod convert;
+mod dump;
+mod follow;
+mod last;
+mod stats;
+mod who;
+
+use args::{Args, UsageError};
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+use std::process;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time};
+use utmp_rs::Timestamp;
+
+const UTMP_PATH: &str = "/var/run/utmp";
+const WTMP_PATH: &str = "/var/log/wtmp";
+const BTMP_PATH: &str = "/var/log/btmp";
+
+const USAGE: &str = "\
+Usage: utmp <command> [options] [arguments]
+
+Commands:
+  dump [--json | --csv] [FILE]    Print entries, in the format of utmpdump by default
+  who [-b] [-r] [-u] [-T] [--ips] [FILE]
+                                  Show who is logged in, with boot time, run level,
+                                  idle time and PID, message status or addresses
+  last [-f FILE] [-n COUNT] [-s TIME] [-t TIME] [-x] [-F] [-i] [USER | TTY...]
+                                  List logins and boots newest first, with shutdowns and
+                                  run level changes, full times or addresses
+  lastb [-f FILE]                 List failed logins, newest first
+  follow [--utmp] [--wtmp] [--btmp] [-s TIME] [--json] [FILE...]
+                                  Print logins, logouts, boots and shutdowns as they happen,
+                                  or failed logins from btmp, after those since the time
+  convert [--from LAYOUT] --to LAYOUT IN OUT
+                                  Rewrite the file in another layout, i.e. linux32-le,
+                                  linux32-be, linux64-le, linux64-be, 32 or 64 in native
+                                  byte order, or the text of utmpdump, detected by auto
+  stats [--prometheus] [-f FILE] [--utmp FILE] [--btmp FILE]
+                                  Count users, logins, boots and failed logins
+
+Files default to /var/run/utmp for dump and who, /var/log/wtmp for last, follow and stats,
+and /var/log/btmp for lastb. Times are in UTC, and given as `now`, `today`, `yesterday`,
+`@SECONDS` since the Unix epoch, or `YYYY-MM-DD[ HH:MM[:SS]]`.
+";
+
+type Result<T = ()> = std::result::Result<T, Box<dyn Error>>;
+
+fn main() {
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let result = run(&mut out).and_then(|()| Ok(out.flush()?));
+    let e = match result {
+        Ok(()) => return,
+        Err(e) => e,
+    };
+    // Output cut short by the reader, e.g. `head`, isn't a failure.
+    if let Some(e) = e.downcast_ref::<io::Error>() {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            return;
+        }
+    }
+    eprintln!("utmp: {}", e);
+    if e.is::<UsageError>() {
+        eprint!("\n{}", USAGE);
+        process::exit(2);
+    }
+    process::exit(1);
+}
+
+fn run(out: &mut dyn Write) -> Result {
+    let mut args = Vec::new();
+    for arg in env::args_os().skip(1) {
+        let arg = arg.into_string();
+        args.push(arg.map_err(|arg| UsageError(format!("invalid argument {:?}", arg)))?);
+    }
+    let command = if args.is_empty() {
+        return Err(UsageError("missing command".to_owned()).into());
+    } else {
+        args.remove(0)
+    };
+    let args = Args::new(args);
+    match command.as_str() {
+        "dump" => dump::run(args, out),
+        "who" => who::run(args, out),
+        "last" => last::run(args, out),
+        "lastb" => last::run_failed(args, out),
+        "follow" => follow::run(args, out),
+        "convert" => convert::run(args),
+        "stats" => stats::run(args, out),
+        "help" | "-h" | "--help" => Ok(out.write_all(USAGE.as_bytes())?),
+        _ => Err(UsageError(format!("unknown command {}", command)).into()),
+    }
+}
+
+/// Error from accessing the file at the path, mentioning the path.
+fn path_error(path: &str, e: io::Error) -> String {
+    format!("{}: {}", path, e)
+}
+
+/// Parse a time given in arguments, in UTC.
+///
+/// It's either `now`, `today`, `yesterday`, seconds since the Unix epoch like `@1675757226`,
+/// or a date with an optional time like `2023-02-07`, `2023-02-07 08:07` or `2023-02-07T08:07:06`.
+fn parse_time(value: &str) -> std::result::Result<Timestamp, UsageError> {
+    let now = OffsetDateTime::now_utc();
+    let time = match value {
+        "now" => Some(now.into()),
+        "today" => Some(now.replace_time(Time::MIDNIGHT).into()),
+        "yesterday" => Some((now.replace_time(Time::MIDNIGHT) - Duration::DAY).into()),
+        _ => match value.strip_prefix('@') {
+            Some(secs) => secs.parse().ok().map(Timestamp::from_secs),
+            None => parse_date_time(value).map(Timestamp::from),
+        },
+    };
+    time.ok_or_else(|| UsageError(format!("invalid time {}", value)))
+}
+
+fn parse_date_time(value: &str) -> Option<OffsetDateTime> {
+    let (date, time) = value.split_once([' ', 'T']).unwrap_or((value, "00:00"));
+    let numbers = |value: &str, separator| {
+        let numbers = value.split(separator).map(str::parse::<u32>);
+        numbers.collect::<std::result::Result<Vec<_>, _>>().ok()
+    };
+    let (year, month, day) = match *numbers(date, '-')?.as_slice() {
+        [year, month, day] => (year, month, day),
+        _ => return None,
+    };
+    let (hour, minute, second) = match *numbers(time, ':')?.as_slice() {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+    let byte = |value| u8::try_from(value).ok();
+    let month = Month::try_from(byte(month)?).ok()?;
+    let date = Date::from_calendar_date(i32::try_from(year).ok()?, month, byte(day)?).ok()?;
+    let time = Time::from_hms(byte(hour)?, byte(minute)?, byte(second)?).ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_utc())
+}
+
+/// Time as a date and time in UTC, clamped to the years `OffsetDateTime` supports.
+fn datetime(time: Timestamp) -> OffsetDateTime {
+    OffsetDateTime::try_from(time).unwrap_or_else(|_| {
+        let date = if time.secs < 0 { Date::MIN } else { Date::MAX };
+        PrimitiveDateTime::new(date, Time::MIDNIGHT).assume_utc()
+    })
+}
+
+/// Time formatted like `2023-02-07 08:07:06`.
+fn format_time(time: Timestamp) -> String {
+    let time = datetime(time);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}:{:02}",
+        time.year(),
+        u8::from(time.month()),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+    )
+}
@@ -0,0 +1,34 @@
+use crate::args::Args;
+use crate::{Result, UTMP_PATH, WTMP_PATH};
+use std::io::Write;
+use utmp_rs::{Metrics, UtmpParser};
+
+pub fn run(mut args: Args, out: &mut dyn Write) -> Result {
+    let prometheus = args.flag(&["--prometheus"]);
+    let wtmp = args.value(&["-f", "--file"])?;
+    let utmp = args.value(&["--utmp"])?;
+    // btmp is only readable by root, so it's only counted when asked for.
+    let btmp = args.value(&["--btmp"])?;
+    args.at_most(0)?;
+
+    let mut metrics = Metrics::new();
+    let utmp = utmp.as_deref().unwrap_or(UTMP_PATH);
+    metrics.set_current(utmp_rs::parse_from_path(utmp)?);
+    let wtmp = wtmp.as_deref().unwrap_or(WTMP_PATH);
+    for entry in UtmpParser::from_path(wtmp).map_err(|e| crate::path_error(wtmp, e))? {
+        metrics.count_history(Some(entry?));
+    }
+    if let Some(btmp) = btmp {
+        metrics.count_failed(utmp_rs::parse_from_path(btmp)?);
+    }
+
+    if prometheus {
+        write!(out, "{}", metrics)?;
+        return Ok(());
+    }
+    writeln!(out, "users          {}", metrics.users())?;
+    writeln!(out, "logins         {}", metrics.logins())?;
+    writeln!(out, "boots          {}", metrics.boots())?;
+    writeln!(out, "failed logins  {}", metrics.failed_logins())?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use crate::args::Args;
+use crate::{Result, UTMP_PATH};
+use std::io::Write;
+use time::Duration;
+use utmp_rs::{Session, UtmpEntry};
+
+/// Show who is logged in like `who(1)`, e.g.
+/// `root     pts/0        2023-02-07 08:07 00:40        1125 (112.124.2.209)` with `-u`.
+pub fn run(mut args: Args, out: &mut dyn Write) -> Result {
+    let boot = args.flag(&["-b", "--boot"]);
+    let run_level = args.flag(&["-r", "--runlevel"]);
+    let idle = args.flag(&["-u", "--users"]);
+    let mesg = args.flag(&["-T", "-w", "--mesg", "--message", "--writable"]);
+    let ips = args.flag(&["--ips"]);
+    let path = args.at_most(1)?.pop();
+    let path = path.as_deref().unwrap_or(UTMP_PATH);
+
+    if boot || run_level {
+        for entry in utmp_rs::parse_from_path(path)? {
+            let shown = match entry {
+                UtmpEntry::BootTime { .. } => boot,
+                UtmpEntry::RunLevel { .. } => run_level,
+                _ => false,
+            };
+            if shown {
+                writeln!(out, "{}", entry)?;
+            }
+        }
+        // Users are only listed along with these if asked for explicitly.
+        if !idle {
+            return Ok(());
+        }
+    }
+    for session in utmp_rs::current_users(path)? {
+        let (writable, idle_time) = tty_status(&session);
+        write!(out, "{:<8} ", session.user)?;
+        if mesg {
+            write!(out, "{} ", writable)?;
+        }
+        write!(out, "{:<12} {}", session.line, who_time(&session))?;
+        if idle {
+            write!(out, " {:<6} {:>10}", format_idle(idle_time), session.pid)?;
+        }
+        let host = match session.address {
+            Some(address) if ips => address.to_string(),
+            _ => session.host,
+        };
+        if !host.is_empty() {
+            write!(out, " ({})", host)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Message status and idle time of the tty of the session, if it's known.
+#[cfg(unix)]
+fn tty_status(session: &Session) -> (char, Option<Duration>) {
+    match utmp_rs::TtyChecker::new().status(&session.line) {
+        Ok(Some(status)) => (status.mesg(), Some(status.idle)),
+        _ => ('?', None),
+    }
+}
+
+#[cfg(not(unix))]
+fn tty_status(_session: &Session) -> (char, Option<Duration>) {
+    ('?', None)
+}
+
+/// Login time formatted like `who(1)` does, i.e. `2023-02-07 08:07`.
+fn who_time(session: &Session) -> String {
+    let login = crate::datetime(session.login);
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02}",
+        login.year(),
+        u8::from(login.month()),
+        login.day(),
+        login.hour(),
+        login.minute(),
+    )
+}
+
+/// Idle time formatted like `who -u` does, i.e. `.` within a minute, `old` beyond a day.
+fn format_idle(idle: Option<Duration>) -> String {
+    match idle.map(|idle| idle.whole_minutes()) {
+        Some(minutes) if minutes < 1 => "  .".to_owned(),
+        Some(minutes) if minutes < 24 * 60 => format!("{:02}:{:02}", minutes / 60, minutes % 60),
+        Some(_) => " old".to_owned(),
+        None => "  ?".to_owned(),
+    }
+}
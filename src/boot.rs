@@ -0,0 +1,118 @@
+use crate::{Timestamp, UtmpEntry};
+use std::borrow::Borrow;
+use time::Duration;
+
+/// Period the system was up, from a boot until it went down.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Boot {
+    /// Kernel version
+    pub kernel_version: String,
+    /// Time of boot
+    pub boot: Timestamp,
+    /// Time of the shutdown afterwards, if any
+    pub shutdown: Option<Timestamp>,
+    /// Time of the next boot, if any
+    pub next_boot: Option<Timestamp>,
+}
+
+impl Boot {
+    /// Time the system went down, or `None` if it's still running.
+    ///
+    /// If it booted again without shutting down, e.g. after a crash,
+    /// the time it went down isn't recorded, so the next boot is taken instead.
+    pub fn end(&self) -> Option<Timestamp> {
+        self.shutdown.or(self.next_boot)
+    }
+
+    /// Whether the system booted again without shutting down.
+    pub fn crashed(&self) -> bool {
+        self.shutdown.is_none() && self.next_boot.is_some()
+    }
+
+    /// Time the system was up within the given period,
+    /// assuming it's still running at the end of the period if it hasn't gone down.
+    pub fn uptime_between(&self, start: Timestamp, end: Timestamp) -> Duration {
+        let up_end = self.end().unwrap_or(end).min(end);
+        (up_end - self.boot.max(start)).max(Duration::ZERO)
+    }
+}
+
+/// Boots extracted from wtmp entries, like `last reboot` lists.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let history = utmp_rs::BootHistory::new(utmp_rs::parse_from_path("/var/log/wtmp")?);
+/// if let Some(boot) = history.last_boot() {
+///     println!("up since {}", boot.boot);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BootHistory {
+    boots: Vec<Boot>,
+}
+
+impl BootHistory {
+    /// Extract boots from the given entries, which are expected to be in order of time.
+    ///
+    /// Shutdowns are paired with the boot before them.
+    pub fn new<I>(entries: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        let mut boots = Vec::<Boot>::new();
+        for entry in entries {
+            match entry.borrow() {
+                UtmpEntry::BootTime {
+                    kernel_version,
+                    time,
+                } => {
+                    if let Some(last) = boots.last_mut() {
+                        last.next_boot = Some(*time);
+                    }
+                    boots.push(Boot {
+                        kernel_version: kernel_version.clone(),
+                        boot: *time,
+                        shutdown: None,
+                        next_boot: None,
+                    });
+                }
+                UtmpEntry::ShutdownTime { time, .. } => {
+                    let last = boots.last_mut().filter(|last| last.end().is_none());
+                    if let Some(last) = last {
+                        last.shutdown = Some(*time);
+                    }
+                }
+                _ => {}
+            }
+        }
+        BootHistory { boots }
+    }
+
+    /// Boots in order of time.
+    pub fn boots(&self) -> &[Boot] {
+        &self.boots
+    }
+
+    /// The last boot, which the system is up since like `uptime -s` shows,
+    /// unless it has gone down afterwards.
+    pub fn last_boot(&self) -> Option<&Boot> {
+        self.boots.last()
+    }
+
+    /// Total time the system was up within the given period.
+    pub fn uptime_between(&self, start: Timestamp, end: Timestamp) -> Duration {
+        let uptimes = self.boots.iter().map(|b| b.uptime_between(start, end));
+        uptimes.fold(Duration::ZERO, |total, uptime| total + uptime)
+    }
+
+    /// Total time the system was down within the given period.
+    ///
+    /// Time before the first boot in the entries is counted as down.
+    pub fn downtime_between(&self, start: Timestamp, end: Timestamp) -> Duration {
+        ((end - start) - self.uptime_between(start, end)).max(Duration::ZERO)
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{FailedLogin, Timestamp};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use time::Duration;
+
+/// Detector of hosts trying to brute-force logins, from failed logins of btmp.
+///
+/// Attempts are grouped by host, and a host is reported when, within any window of time,
+/// it makes `max_attempts` attempts or tries `max_users` distinct usernames.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use utmp_rs::BruteForceDetector;
+/// let attempts = utmp_rs::failed_logins(utmp_rs::parse_from_path("/var/log/btmp")?);
+/// for report in BruteForceDetector::new().max_attempts(20).detect(&attempts) {
+///     println!("{}: {} attempts from {}", report.host, report.attempts, report.start);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BruteForceDetector {
+    window: Duration,
+    max_attempts: usize,
+    max_users: usize,
+}
+
+/// Host reported by `BruteForceDetector`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct BruteForce {
+    /// Host the attempts came from
+    pub host: String,
+    /// Time of the first attempt in the window with the most attempts
+    pub start: Timestamp,
+    /// Time of the last attempt in the window with the most attempts
+    pub end: Timestamp,
+    /// Largest number of attempts within a window
+    pub attempts: usize,
+    /// Largest number of distinct usernames tried within a window
+    pub users: usize,
+    /// Total number of attempts from the host
+    pub total: usize,
+    /// Distinct usernames tried from the host, in order
+    pub usernames: Vec<String>,
+}
+
+impl Default for BruteForceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BruteForceDetector {
+    /// Create a detector reporting hosts with 10 attempts or 5 distinct usernames within a minute.
+    pub fn new() -> Self {
+        BruteForceDetector {
+            window: Duration::minutes(1),
+            max_attempts: 10,
+            max_users: 5,
+        }
+    }
+
+    /// Set the duration of the sliding window attempts are counted in.
+    ///
+    /// Windows which aren't positive are clamped to a nanosecond,
+    /// i.e. only attempts at the same time are counted together.
+    pub fn window(mut self, window: Duration) -> Self {
+        self.window = window.max(Duration::nanoseconds(1));
+        self
+    }
+
+    /// Set the number of attempts within a window for a host to be reported.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Set the number of distinct usernames within a window for a host to be reported.
+    pub fn max_users(mut self, max_users: usize) -> Self {
+        self.max_users = max_users;
+        self
+    }
+
+    /// Report hosts exceeding the thresholds in the failed logins, most attempts first.
+    ///
+    /// Attempts can be in any order, e.g. newest first as `failed_logins` lists them.
+    pub fn detect<I>(&self, attempts: I) -> Vec<BruteForce>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<FailedLogin>,
+    {
+        let mut by_host = BTreeMap::<String, Vec<(Timestamp, String)>>::new();
+        for attempt in attempts {
+            let attempt = attempt.borrow();
+            let host = by_host.entry(attempt.host.clone()).or_default();
+            host.push((attempt.time, attempt.user.clone()));
+        }
+        let mut reports = by_host
+            .into_iter()
+            .filter_map(|(host, attempts)| self.detect_host(host, attempts))
+            .collect::<Vec<_>>();
+        reports.sort_by(|a, b| b.attempts.cmp(&a.attempts).then(b.total.cmp(&a.total)));
+        reports
+    }
+
+    fn detect_host(
+        &self,
+        host: String,
+        mut attempts: Vec<(Timestamp, String)>,
+    ) -> Option<BruteForce> {
+        attempts.sort();
+        let mut users = HashMap::<&str, usize>::new();
+        let (mut peak, mut peak_users, mut peak_window) = (0, 0, (0, 0));
+        let mut start = 0;
+        for (end, (time, user)) in attempts.iter().enumerate() {
+            *users.entry(user).or_default() += 1;
+            while start < attempts.len() && *time - attempts[start].0 >= self.window {
+                let user = &*attempts[start].1;
+                let count = users.get_mut(user).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    users.remove(user);
+                }
+                start += 1;
+            }
+            if end + 1 - start > peak {
+                peak = end + 1 - start;
+                peak_window = (start, end);
+            }
+            peak_users = peak_users.max(users.len());
+        }
+        if peak < self.max_attempts && peak_users < self.max_users {
+            return None;
+        }
+        let usernames = attempts.iter().map(|(_, user)| user.clone());
+        Some(BruteForce {
+            host,
+            start: attempts[peak_window.0].0,
+            end: attempts[peak_window.1].0,
+            attempts: peak,
+            users: peak_users,
+            total: attempts.len(),
+            usernames: usernames.collect::<BTreeSet<_>>().into_iter().collect(),
+        })
+    }
+}
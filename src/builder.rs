@@ -0,0 +1,389 @@
+use crate::parse::{raw_from_record, Options, UtmpParserImpl};
+use crate::raw::{is_plausible, RawUtmp};
+use crate::{ByteSlice, ParseError, Utmp32Parser, Utmp64Parser, UtmpEntry};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::mem;
+use std::path::Path;
+use utmp_raw::{utmp, x32::utmp as utmp32, x64::utmp as utmp64};
+
+/// Layout of records in a utmp file.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Format {
+    /// 32-bit layout with 384-byte records, used by most platforms
+    X32,
+    /// 64-bit layout with 400-byte records, used by e.g. aarch64 and s390x
+    X64,
+    /// Detect the layout from the size and the first records of the input
+    Auto,
+}
+
+/// Byte order of numeric fields in records.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Endianness {
+    /// Byte order of the target platform
+    Native,
+    /// Little endian, as on x86 and most ARM systems
+    Little,
+    /// Big endian, as on s390x and some PowerPC, MIPS and SPARC systems
+    Big,
+}
+
+/// What to do when a record fails to be decoded.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorPolicy {
+    /// Yield the error and continue with the next record, as parsers do by default
+    Continue,
+    /// Yield the error and stop parsing
+    Stop,
+    /// Skip the record without yielding the error
+    Skip,
+    /// Yield `ParseError::Corrupted` and resume parsing from the next plausible record,
+    /// i.e. one with a known type and a sane time, found by scanning forward byte by byte
+    ///
+    /// Records which are decoded but don't look plausible are treated as corrupted as well.
+    /// After resynchronization, offsets of records may no longer be aligned to the record size.
+    Resync,
+}
+
+/// Builder to configure how a utmp file is parsed, created via `UtmpParser::builder()`.
+///
+/// ```
+/// # use utmp_rs::{Endianness, Format, UtmpParser};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let parser = UtmpParser::builder()
+///     .format(Format::Auto)
+///     .endianness(Endianness::Little)
+///     .skip_empty(true)
+///     .from_path("/var/run/utmp")?;
+/// for entry in parser {
+///     let entry = entry?;
+///     // handle entry
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct UtmpParserBuilder {
+    format: Format,
+    endianness: Endianness,
+    lossy: bool,
+    lenient: bool,
+    skip_empty: bool,
+    policy: ErrorPolicy,
+    buffer_capacity: Option<usize>,
+}
+
+impl UtmpParserImpl<(), utmp32> {
+    /// Create a builder of parsers, which parse 32-bit files by default.
+    pub fn builder() -> UtmpParserBuilder {
+        UtmpParserBuilder::new().format(Format::X32)
+    }
+}
+
+impl UtmpParserImpl<(), utmp64> {
+    /// Create a builder of parsers, which parse 64-bit files by default.
+    pub fn builder() -> UtmpParserBuilder {
+        UtmpParserBuilder::new().format(Format::X64)
+    }
+}
+
+impl Default for UtmpParserBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UtmpParserBuilder {
+    /// Create a builder of parsers with the same defaults as `UtmpParser`,
+    /// which parse the native format for the target platform.
+    pub fn new() -> Self {
+        UtmpParserBuilder {
+            format: native_format(),
+            endianness: Endianness::Native,
+            lossy: false,
+            lenient: false,
+            skip_empty: false,
+            policy: ErrorPolicy::Continue,
+            buffer_capacity: None,
+        }
+    }
+
+    /// Set the layout of records.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the byte order of numeric fields, for files from a platform of another endian.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Decode strings lossily, see `UtmpParser::lossy`.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    /// Decode records leniently, see `UtmpParser::lenient`.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
+    /// Skip `EMPTY` records, see `UtmpParser::skip_empty`.
+    pub fn skip_empty(mut self, skip_empty: bool) -> Self {
+        self.skip_empty = skip_empty;
+        self
+    }
+
+    /// Set what to do when a record fails to be decoded.
+    ///
+    /// I/O errors are always yielded, regardless of the policy.
+    pub fn on_error(mut self, policy: ErrorPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the size of blocks read from readers, see `UtmpParser::with_buffer_capacity`.
+    pub fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Create a parser for the given reader.
+    ///
+    /// If the format is to be detected, the first records are read right away.
+    pub fn from_reader<R: Read>(self, reader: R) -> Result<AnyUtmpParser<R>, io::Error> {
+        self.build(reader, None, None)
+    }
+
+    /// Create a parser for the given file.
+    pub fn from_file(self, file: File) -> Result<AnyUtmpParser<BufReader<File>>, io::Error> {
+        self.build_from_file(file, None)
+    }
+
+    /// Create a parser for the given path.
+    pub fn from_path<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> Result<AnyUtmpParser<BufReader<File>>, io::Error> {
+        let path = path.as_ref();
+        self.build_from_file(File::open(path)?, Some(path))
+    }
+
+    /// Create a parser which parses records directly from the given bytes.
+    pub fn from_bytes(self, bytes: &[u8]) -> AnyUtmpParser<ByteSlice<'_>> {
+        let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+        let options = self.options();
+        let parser = match self.resolve_format(head, Some(bytes.len() as u64)) {
+            Format::X64 => {
+                let parser = Utmp64Parser::from_bytes(bytes);
+                AnyParser::X64(parser.configure(options, None, Vec::new()))
+            }
+            _ => {
+                let parser = Utmp32Parser::from_bytes(bytes);
+                AnyParser::X32(parser.configure(options, None, Vec::new()))
+            }
+        };
+        self.finish(parser)
+    }
+
+    fn build_from_file(
+        self,
+        file: File,
+        path: Option<&Path>,
+    ) -> io::Result<AnyUtmpParser<BufReader<File>>> {
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        // The parser reads in blocks by itself.
+        self.build(BufReader::with_capacity(0, file), size, path)
+    }
+
+    fn build<R: Read>(
+        self,
+        mut reader: R,
+        size: Option<u64>,
+        path: Option<&Path>,
+    ) -> io::Result<AnyUtmpParser<R>> {
+        let mut head = Vec::new();
+        if self.format == Format::Auto {
+            reader
+                .by_ref()
+                .take(HEAD_SIZE as u64)
+                .read_to_end(&mut head)?;
+        }
+        let parser = match self.resolve_format(&head, size) {
+            Format::X64 => {
+                let parser = Utmp64Parser::from_reader(reader);
+                AnyParser::X64(self.configure(parser, size, head, path))
+            }
+            _ => {
+                let parser = Utmp32Parser::from_reader(reader);
+                AnyParser::X32(self.configure(parser, size, head, path))
+            }
+        };
+        Ok(self.finish(parser))
+    }
+
+    fn configure<R: Read, T>(
+        &self,
+        parser: UtmpParserImpl<R, T>,
+        size: Option<u64>,
+        head: Vec<u8>,
+        path: Option<&Path>,
+    ) -> UtmpParserImpl<R, T> {
+        let parser = match self.buffer_capacity {
+            Some(capacity) => parser.with_buffer_capacity(capacity),
+            None => parser,
+        };
+        let parser = match path {
+            Some(path) => parser.with_path(path),
+            None => parser,
+        };
+        parser.configure(self.options(), size, head)
+    }
+
+    fn finish<R>(&self, parser: AnyParser<R>) -> AnyUtmpParser<R> {
+        AnyUtmpParser {
+            parser,
+            policy: self.policy,
+            stopped: false,
+        }
+    }
+
+    fn options(&self) -> Options {
+        let swap = self.endianness.swaps();
+        Options {
+            lossy: self.lossy,
+            lenient: self.lenient,
+            resync: self.policy == ErrorPolicy::Resync,
+            skip_empty: self.skip_empty,
+            swap,
+        }
+    }
+
+    fn resolve_format(&self, head: &[u8], size: Option<u64>) -> Format {
+        match self.format {
+            Format::Auto => detect_format(head, size, self.options().swap),
+            format => format,
+        }
+    }
+}
+
+impl Endianness {
+    /// Whether numeric fields need to be swapped to the byte order of the target platform.
+    pub(crate) fn swaps(self) -> bool {
+        match self {
+            Endianness::Native => false,
+            Endianness::Little => cfg!(target_endian = "big"),
+            Endianness::Big => cfg!(target_endian = "little"),
+        }
+    }
+}
+
+/// Parser created via `UtmpParserBuilder`, whose format is chosen at runtime.
+pub struct AnyUtmpParser<R> {
+    parser: AnyParser<R>,
+    policy: ErrorPolicy,
+    /// Whether an error has stopped parsing.
+    stopped: bool,
+}
+
+enum AnyParser<R> {
+    X32(Utmp32Parser<R>),
+    X64(Utmp64Parser<R>),
+}
+
+impl<R> AnyUtmpParser<R> {
+    /// Format of records being parsed, which is never `Format::Auto`.
+    pub fn format(&self) -> Format {
+        match self.parser {
+            AnyParser::X32(_) => Format::X32,
+            AnyParser::X64(_) => Format::X64,
+        }
+    }
+}
+
+impl<R> Iterator for AnyUtmpParser<R>
+where
+    Utmp32Parser<R>: Iterator<Item = Result<UtmpEntry, ParseError>>,
+    Utmp64Parser<R>: Iterator<Item = Result<UtmpEntry, ParseError>>,
+{
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.stopped {
+            let result = match &mut self.parser {
+                AnyParser::X32(parser) => parser.next()?,
+                AnyParser::X64(parser) => parser.next()?,
+            };
+            match (&result, self.policy) {
+                (Err(ParseError::Utmp { .. }), ErrorPolicy::Skip) => continue,
+                (Err(_), ErrorPolicy::Stop) => self.stopped = true,
+                _ => {}
+            }
+            return Some(result);
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = match &self.parser {
+            AnyParser::X32(parser) => parser.size_hint(),
+            AnyParser::X64(parser) => parser.size_hint(),
+        };
+        match self.policy {
+            _ if self.stopped => (0, Some(0)),
+            ErrorPolicy::Continue => (lower, upper),
+            // Skipping misaligns records, so more entries than records may be left.
+            ErrorPolicy::Resync => (0, None),
+            _ => (0, upper),
+        }
+    }
+}
+
+/// Size of the beginning of the input used to detect the format.
+pub(crate) const HEAD_SIZE: usize = 4 * mem::size_of::<utmp64>();
+
+pub(crate) fn native_format() -> Format {
+    if mem::size_of::<utmp>() == mem::size_of::<utmp32>() {
+        Format::X32
+    } else {
+        Format::X64
+    }
+}
+
+/// Guess the format from the size of the input if known,
+/// otherwise from how many of the first records look valid in each format.
+pub(crate) fn detect_format(head: &[u8], size: Option<u64>, swap: bool) -> Format {
+    if let Some(size) = size {
+        let size32 = size % mem::size_of::<utmp32>() as u64 == 0;
+        let size64 = size % mem::size_of::<utmp64>() as u64 == 0;
+        match (size32, size64) {
+            (true, false) => return Format::X32,
+            (false, true) => return Format::X64,
+            _ => {}
+        }
+    }
+    let x32 = plausible_records::<utmp32>(head, swap);
+    let x64 = plausible_records::<utmp64>(head, swap);
+    match x32.cmp(&x64) {
+        Ordering::Greater => Format::X32,
+        Ordering::Less => Format::X64,
+        Ordering::Equal => native_format(),
+    }
+}
+
+/// Number of leading records in the bytes which look valid.
+fn plausible_records<T: RawUtmp>(head: &[u8], swap: bool) -> usize {
+    head.chunks_exact(mem::size_of::<T>())
+        .map(|record| raw_from_record::<T>(record, swap))
+        .take_while(is_plausible)
+        .count()
+}
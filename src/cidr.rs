@@ -0,0 +1,112 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Range of IP addresses in CIDR notation, e.g. `10.0.0.0/8` or `2001:db8::/32`.
+///
+/// ```
+/// # use utmp_rs::Cidr;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let private: Cidr = "10.0.0.0/8".parse()?;
+/// assert!(private.contains("10.1.2.3".parse()?));
+/// assert!(!private.contains("192.0.2.1".parse()?));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+/// Error from parsing a `Cidr`.
+#[derive(Debug, Error)]
+#[error("invalid CIDR range")]
+pub struct ParseCidrError(());
+
+impl Cidr {
+    /// Create the range of addresses sharing the first `prefix_len` bits with the address,
+    /// or `None` if the length is beyond the bits of the address.
+    ///
+    /// Bits of the address after the prefix are ignored.
+    pub fn new(address: IpAddr, prefix_len: u8) -> Option<Self> {
+        let bits = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > bits {
+            return None;
+        }
+        Some(Cidr {
+            address: mask(address, prefix_len),
+            prefix_len,
+        })
+    }
+
+    /// First address of the range.
+    pub fn address(&self) -> IpAddr {
+        self.address
+    }
+
+    /// Number of leading bits addresses of the range share.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Whether the address is in the range.
+    ///
+    /// IPv4 addresses mapped into IPv6, e.g. `::ffff:10.1.2.3`, are matched as IPv4 ones.
+    pub fn contains(&self, address: IpAddr) -> bool {
+        let address = match address {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+            address => address,
+        };
+        address.is_ipv4() == self.address.is_ipv4()
+            && mask(address, self.prefix_len) == self.address
+    }
+}
+
+fn mask(address: IpAddr, prefix_len: u8) -> IpAddr {
+    match address {
+        IpAddr::V4(v4) => {
+            let bits = u32::from(v4);
+            let mask = u32::MAX
+                .checked_shl(32 - u32::from(prefix_len))
+                .unwrap_or(0);
+            IpAddr::V4((bits & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = u128::from(v6);
+            let mask = u128::MAX
+                .checked_shl(128 - u32::from(prefix_len))
+                .unwrap_or(0);
+            IpAddr::V6((bits & mask).into())
+        }
+    }
+}
+
+/// A bare address is parsed as the range of itself alone.
+impl FromStr for Cidr {
+    type Err = ParseCidrError;
+
+    fn from_str(s: &str) -> Result<Self, ParseCidrError> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+        let address = address.parse::<IpAddr>().map_err(|_| ParseCidrError(()))?;
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len.parse().map_err(|_| ParseCidrError(()))?,
+            None if address.is_ipv4() => 32,
+            None => 128,
+        };
+        Cidr::new(address, prefix_len).ok_or(ParseCidrError(()))
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Session, SessionEnd, Timestamp};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Auditor of users logged in several times at once, flagging potential credential sharing.
+///
+/// A login is flagged when it overlaps with sessions of the same user from other hosts,
+/// or when it makes the user exceed the maximum number of simultaneous sessions, if set.
+/// Sessions count until their logout as in `Timeline`.
+/// Sessions whose end is `SessionEnd::Missing`, e.g. those at the end of an archived wtmp,
+/// are only counted at their login, since they would otherwise overlap with every later login,
+/// unless `count_missing` is set.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let sessions = utmp_rs::sessions(utmp_rs::parse_from_path("/var/log/wtmp")?);
+/// let auditor = utmp_rs::ConcurrentLoginAuditor::new().max_sessions(3);
+/// for login in auditor.audit(sessions) {
+///     println!("{} at {}: {:?}", login.user, login.time, login.kind);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ConcurrentLoginAuditor {
+    max_sessions: Option<usize>,
+    count_missing: bool,
+}
+
+/// Login flagged by `ConcurrentLoginAuditor`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct ConcurrentLogin {
+    /// Username
+    pub user: String,
+    /// Time of the login
+    pub time: Timestamp,
+    /// Why the login is flagged
+    pub kind: ConcurrentLoginKind,
+    /// Sessions of the user logged in at the time, including the login, in order of login
+    pub sessions: Vec<Session>,
+}
+
+/// Why a login is flagged by `ConcurrentLoginAuditor`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum ConcurrentLoginKind {
+    /// Sessions of the user are from different hosts
+    DifferentHosts,
+    /// User has more sessions than the maximum
+    TooManySessions,
+}
+
+impl ConcurrentLoginAuditor {
+    /// Create an auditor flagging overlapping sessions from different hosts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also flag logins making a user have more than `max` sessions at once.
+    pub fn max_sessions(mut self, max: usize) -> Self {
+        self.max_sessions = Some(max);
+        self
+    }
+
+    /// Set whether sessions without a known end count as logged in from their login onwards.
+    pub fn count_missing(mut self, count_missing: bool) -> Self {
+        self.count_missing = count_missing;
+        self
+    }
+
+    /// Flag logins of the sessions, in order of time.
+    ///
+    /// A login is reported once for each kind it's flagged for.
+    pub fn audit<I>(&self, sessions: I) -> Vec<ConcurrentLogin>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Session>,
+    {
+        let mut by_user = BTreeMap::<String, Vec<Session>>::new();
+        for session in sessions {
+            let session = session.borrow();
+            by_user
+                .entry(session.user.clone())
+                .or_default()
+                .push(session.clone());
+        }
+        let mut result = Vec::new();
+        for (user, mut sessions) in by_user {
+            sessions.sort_by_key(|session| session.login);
+            let mut active = Vec::<&Session>::new();
+            for session in &sessions {
+                let time = session.login;
+                active.retain(|active| end(active).map_or(true, |end| end > time));
+                active.push(session);
+                let mut flag = |kind| {
+                    result.push(ConcurrentLogin {
+                        user: user.clone(),
+                        time,
+                        kind,
+                        sessions: active.iter().map(|&session| session.clone()).collect(),
+                    })
+                };
+                let hosts = active.iter().map(|session| &*session.host);
+                if hosts.collect::<BTreeSet<_>>().len() > 1 {
+                    flag(ConcurrentLoginKind::DifferentHosts);
+                }
+                if self.max_sessions.is_some_and(|max| active.len() > max) {
+                    flag(ConcurrentLoginKind::TooManySessions);
+                }
+                if session.end == SessionEnd::Missing && !self.count_missing {
+                    active.pop();
+                }
+            }
+        }
+        result.sort_by_key(|login| login.time);
+        result
+    }
+}
+
+/// Time the session ends, or `None` if it's still logged in without a known duration.
+fn end(session: &Session) -> Option<Timestamp> {
+    (session.logout).or_else(|| Some(session.login + session.duration?))
+}
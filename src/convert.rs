@@ -0,0 +1,183 @@
+use crate::builder::{detect_format, native_format, HEAD_SIZE};
+use crate::parse::{io_message, raw_from_record};
+use crate::raw::{RawUtmp, RecordValues};
+use crate::{Endianness, Format};
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use utmp_raw::{x32::utmp as utmp32, x64::utmp as utmp64};
+
+/// Converter of utmp files between record layouts and byte orders,
+/// e.g. for files from 64-bit targets to be read on 32-bit ones.
+///
+/// All fields are kept, including exit status, session ID and the `__unused` padding.
+///
+/// ```no_run
+/// # use utmp_rs::{Converter, Endianness, Format};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// Converter::new(Format::X32)
+///     .endianness(Endianness::Big)
+///     .target_endianness(Endianness::Little)
+///     .convert_path("wtmp.s390x", "wtmp")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Converter {
+    format: Format,
+    endianness: Endianness,
+    target_format: Format,
+    target_endianness: Endianness,
+}
+
+/// Error from converting records by `Converter`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ConvertError {
+    /// Value of a field doesn't fit into the target layout,
+    /// e.g. a time after 2038 in 32-bit records
+    #[error("{field} of record {index} doesn't fit into the target format")]
+    Overflow {
+        /// Index of the record
+        index: u64,
+        /// Name of the field, e.g. `ut_tv`
+        field: &'static str,
+    },
+    /// Size of the input isn't a multiple of the record size of its layout
+    #[error("size {size} is not a multiple of record size {record_size}")]
+    Misaligned {
+        /// Size of the input
+        size: u64,
+        /// Size of records in the layout of the input
+        record_size: u64,
+    },
+    #[error("{}", io_message(.path.as_deref(), .source))]
+    Io {
+        /// Path of the file which failed
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+}
+
+impl Converter {
+    /// Create a converter into the given layout, with native byte order,
+    /// of input whose layout is detected, with native byte order.
+    ///
+    /// `Format::Auto` as the target is the native format for the target platform.
+    pub fn new(target_format: Format) -> Self {
+        let target_format = match target_format {
+            Format::Auto => native_format(),
+            format => format,
+        };
+        Converter {
+            format: Format::Auto,
+            endianness: Endianness::Native,
+            target_format,
+            target_endianness: Endianness::Native,
+        }
+    }
+
+    /// Set the layout of records of the input.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the byte order of numeric fields in records of the input.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Set the byte order of numeric fields in records of the output.
+    pub fn target_endianness(mut self, endianness: Endianness) -> Self {
+        self.target_endianness = endianness;
+        self
+    }
+
+    /// Convert the file at the input path, and write the result to the output path.
+    pub fn convert_path<P, Q>(&self, input: P, output: Q) -> Result<(), ConvertError>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let io_error = |path: &Path| {
+            let path = Some(path.to_owned());
+            move |source| ConvertError::Io { path, source }
+        };
+        let (input, output) = (input.as_ref(), output.as_ref());
+        let bytes = fs::read(input).map_err(io_error(input))?;
+        let bytes = self.convert_bytes(&bytes)?;
+        fs::write(output, bytes).map_err(io_error(output))
+    }
+
+    /// Convert the records in the given bytes.
+    pub fn convert_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, ConvertError> {
+        let swap = self.endianness.swaps();
+        let format = match self.format {
+            Format::Auto => {
+                let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+                detect_format(head, Some(bytes.len() as u64), swap)
+            }
+            format => format,
+        };
+        match (format, self.target_format) {
+            (Format::X64, Format::X64) => self.convert_records::<utmp64, utmp64>(bytes),
+            (Format::X64, _) => self.convert_records::<utmp64, utmp32>(bytes),
+            (_, Format::X64) => self.convert_records::<utmp32, utmp64>(bytes),
+            _ => self.convert_records::<utmp32, utmp32>(bytes),
+        }
+    }
+
+    fn convert_records<T: RawUtmp, U: RawUtmp>(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, ConvertError> {
+        let size = mem::size_of::<T>();
+        if bytes.len() % size != 0 {
+            return Err(ConvertError::Misaligned {
+                size: bytes.len() as u64,
+                record_size: size as u64,
+            });
+        }
+        let (swap, target_swap) = (self.endianness.swaps(), self.target_endianness.swaps());
+        let target_size = mem::size_of::<U>();
+        let mut result = vec![0; bytes.len() / size * target_size];
+        let records = bytes
+            .chunks_exact(size)
+            .zip(result.chunks_exact_mut(target_size));
+        for (index, (record, target)) in records.enumerate() {
+            let raw = raw_from_record::<T>(record, swap);
+            let values = RecordValues::of(&raw);
+            values
+                .write::<U>(target, target_swap)
+                .map_err(|field| ConvertError::Overflow {
+                    index: index as u64,
+                    field,
+                })?;
+        }
+        Ok(result)
+    }
+}
+
+/// Convert the utmp file at the input path into the given layout,
+/// and write the result to the output path.
+///
+/// The layout of the input is detected, and both are in native byte order.
+/// Use `Converter` for other byte orders.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// utmp_rs::convert("wtmp.aarch64", "wtmp", utmp_rs::Format::X32)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn convert<P, Q>(input: P, output: Q, target_format: Format) -> Result<(), ConvertError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    Converter::new(target_format).convert_path(input, output)
+}
@@ -0,0 +1,211 @@
+use crate::fields::Fields;
+use crate::timestamp::format_rfc3339;
+#[cfg(feature = "time")]
+use crate::SessionSink;
+use crate::{EntrySink, UtmpEntry};
+use std::borrow::Borrow;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+/// Columns written by `write_entries_csv`.
+pub const ENTRY_COLUMNS: &[&str] = &[
+    "type",
+    "pid",
+    "line",
+    "id",
+    "user",
+    "host",
+    "address",
+    "session",
+    "termination",
+    "exit",
+    "level",
+    "previous",
+    "kernel_version",
+    "ut_type",
+    "time",
+];
+
+/// Columns written by `write_sessions_csv`.
+#[cfg(feature = "time")]
+pub const SESSION_COLUMNS: &[&str] = &[
+    "user", "line", "host", "address", "pid", "login", "logout", "duration", "end",
+];
+
+/// Streaming writer of entries or sessions as CSV.
+///
+/// Each entry is a row of `ENTRY_COLUMNS`, with the `type` column naming its variant,
+/// and columns of fields the variant lacks left empty.
+/// Each session is a row of `SESSION_COLUMNS`,
+/// with the `duration` column in whole seconds and the `end` column naming the `SessionEnd` variant.
+/// Times are formatted as RFC 3339 in UTC.
+/// Fields are quoted as RFC 4180 specifies, and rows end with CRLF.
+#[derive(Debug)]
+pub struct CsvWriter<W: Write> {
+    writer: W,
+    sessions: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Write entries, starting with a header of `ENTRY_COLUMNS`.
+    pub fn entries(mut writer: W) -> io::Result<Self> {
+        write_row(&mut writer, ENTRY_COLUMNS)?;
+        Ok(CsvWriter {
+            writer,
+            sessions: false,
+        })
+    }
+
+    /// Write sessions, starting with a header of `SESSION_COLUMNS`.
+    #[cfg(feature = "time")]
+    pub fn sessions(mut writer: W) -> io::Result<Self> {
+        write_row(&mut writer, SESSION_COLUMNS)?;
+        Ok(CsvWriter {
+            writer,
+            sessions: true,
+        })
+    }
+
+    /// Write an entry.
+    ///
+    /// It fails with `InvalidInput` if the writer is for sessions.
+    pub fn write_entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        if self.sessions {
+            return Err(mismatch("entry written as session"));
+        }
+        let f = Fields::of(entry);
+        write_row(
+            &mut self.writer,
+            &[
+                f.kind.to_owned(),
+                opt(f.pid),
+                opt(f.line),
+                opt(f.id),
+                opt(f.user),
+                opt(f.host),
+                opt(f.address),
+                opt(f.session),
+                opt(f.termination),
+                opt(f.exit),
+                opt(f.level),
+                opt(f.previous),
+                opt(f.kernel_version),
+                opt(f.ut_type),
+                f.time.map(format_rfc3339).unwrap_or_default(),
+            ],
+        )
+    }
+
+    /// Write a session.
+    ///
+    /// It fails with `InvalidInput` if the writer is for entries.
+    #[cfg(feature = "time")]
+    pub fn write_session(&mut self, session: &crate::Session) -> io::Result<()> {
+        if !self.sessions {
+            return Err(mismatch("session written as entry"));
+        }
+        let s = session;
+        let duration = s.duration.map(|d| d.whole_seconds());
+        write_row(
+            &mut self.writer,
+            &[
+                s.user.clone(),
+                s.line.clone(),
+                s.host.clone(),
+                opt(s.address),
+                s.pid.to_string(),
+                format_rfc3339(s.login),
+                s.logout.map(format_rfc3339).unwrap_or_default(),
+                opt(duration),
+                format!("{:?}", s.end),
+            ],
+        )
+    }
+
+    /// Flush and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> EntrySink for CsvWriter<W> {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        self.write_entry(entry)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(feature = "time")]
+impl<W: Write> SessionSink for CsvWriter<W> {
+    fn session(&mut self, session: &crate::Session) -> io::Result<()> {
+        self.write_session(session)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Write the given entries as CSV with a header of `ENTRY_COLUMNS`, see `CsvWriter`.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let entries = utmp_rs::parse_from_path("/var/log/wtmp")?;
+/// utmp_rs::write_entries_csv(std::io::stdout().lock(), &entries)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn write_entries_csv<W, I>(writer: W, entries: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    let mut writer = CsvWriter::entries(writer)?;
+    for entry in entries {
+        writer.write_entry(entry.borrow())?;
+    }
+    writer.finish().map(drop)
+}
+
+/// Write the given sessions as CSV with a header of `SESSION_COLUMNS`, see `CsvWriter`.
+#[cfg(feature = "time")]
+pub fn write_sessions_csv<W, I>(writer: W, sessions: I) -> io::Result<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: Borrow<crate::Session>,
+{
+    let mut writer = CsvWriter::sessions(writer)?;
+    for session in sessions {
+        writer.write_session(session.borrow())?;
+    }
+    writer.finish().map(drop)
+}
+
+fn mismatch(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+fn opt<T: Display>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn write_row<W: Write, S: AsRef<str>>(writer: &mut W, fields: &[S]) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            writer.write_all(b",")?;
+        }
+        let field = field.as_ref();
+        if field.contains(&[',', '"', '\r', '\n'][..]) || field.trim() != field {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writer.write_all(b"\r\n")
+}
@@ -0,0 +1,163 @@
+use crate::{Enricher, Remote, Session};
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::hash::Hash;
+use std::mem;
+use std::net::{IpAddr, ToSocketAddrs};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Resolver which looks up the other side of hosts of entries and sessions with DNS,
+/// caching the results and giving up lookups taking too long.
+///
+/// As an `Enricher`, it looks up hostnames of remote addresses.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let sessions = utmp_rs::current_users("/var/run/utmp")?;
+/// for enriched in utmp_rs::enrich(sessions, utmp_rs::Resolver::new()) {
+///     println!("{} from {:?}", enriched.item.user, enriched.data);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Resolver {
+    timeout: Duration,
+    names: Mutex<HashMap<IpAddr, Option<String>>>,
+    addresses: Mutex<HashMap<String, Option<IpAddr>>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    /// Create a resolver which gives up lookups after 2 seconds.
+    pub fn new() -> Self {
+        Self::with_timeout(Duration::from_secs(2))
+    }
+
+    /// Create a resolver which gives up lookups after the given timeout.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Resolver {
+            timeout,
+            names: Mutex::default(),
+            addresses: Mutex::default(),
+        }
+    }
+
+    /// Fill in `address` of the session from its host if the host is a name.
+    pub fn resolve_address(&self, session: &mut Session) {
+        let numeric = session.host.parse::<IpAddr>().ok();
+        if session.address.is_none() && numeric.is_none() && !session.host.is_empty() {
+            // Hosts of X sessions carry the display, e.g. `host:0`.
+            let name = session.host.split(':').next().unwrap_or_default();
+            session.address = self.lookup_address(name);
+        }
+    }
+
+    /// Hostname of the address, or `None` if it has none or the lookup fails.
+    ///
+    /// Failed lookups, including ones timed out, are cached as well.
+    pub fn lookup_name(&self, address: IpAddr) -> Option<String> {
+        let timeout = self.timeout;
+        cached(&self.names, address, || {
+            run(timeout, move || reverse(address))
+        })
+    }
+
+    /// First address of the hostname, or `None` if it has none or the lookup fails.
+    ///
+    /// Failed lookups, including ones timed out, are cached as well.
+    pub fn lookup_address(&self, name: &str) -> Option<IpAddr> {
+        let (timeout, host) = (self.timeout, name.to_owned());
+        cached(&self.addresses, name.to_owned(), || {
+            run(timeout, move || {
+                let mut addrs = (&*host, 0).to_socket_addrs().ok()?;
+                addrs.next().map(|addr| addr.ip())
+            })
+        })
+    }
+}
+
+/// Resolver looks up hostnames of remote addresses as an enricher.
+impl Enricher for Resolver {
+    type Output = String;
+
+    fn enrich(&self, remote: &Remote<'_>) -> Option<String> {
+        self.lookup_name(remote.address?)
+    }
+}
+
+fn cached<K, V, F>(cache: &Mutex<HashMap<K, Option<V>>>, key: K, lookup: F) -> Option<V>
+where
+    K: Eq + Hash,
+    V: Clone,
+    F: FnOnce() -> Option<V>,
+{
+    // The cache isn't locked during the lookup, so other lookups aren't blocked.
+    if let Some(value) = cache.lock().unwrap().get(&key) {
+        return value.clone();
+    }
+    let value = lookup();
+    cache.lock().unwrap().insert(key, value.clone());
+    value
+}
+
+/// Run the lookup in another thread, since system resolvers don't support timeouts,
+/// and leave it behind if it doesn't finish in time.
+fn run<T, F>(timeout: Duration, lookup: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Option<T> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(lookup());
+    });
+    receiver.recv_timeout(timeout).ok().flatten()
+}
+
+fn reverse(address: IpAddr) -> Option<String> {
+    const NI_MAXHOST: usize = 1025;
+    // Zeroed socket addresses are valid, and leave fields not set below unspecified.
+    let (storage, len) = unsafe {
+        let mut storage = mem::zeroed::<libc::sockaddr_storage>();
+        let len = match address {
+            IpAddr::V4(v4) => {
+                let sin = &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in);
+                sin.sin_family = libc::AF_INET as libc::sa_family_t;
+                sin.sin_addr.s_addr = u32::from_ne_bytes(v4.octets());
+                mem::size_of::<libc::sockaddr_in>()
+            }
+            IpAddr::V6(v6) => {
+                let sin6 = &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6);
+                sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+                sin6.sin6_addr.s6_addr = v6.octets();
+                mem::size_of::<libc::sockaddr_in6>()
+            }
+        };
+        (storage, len)
+    };
+    let mut host = [0 as libc::c_char; NI_MAXHOST];
+    let result = unsafe {
+        libc::getnameinfo(
+            &storage as *const _ as *const libc::sockaddr,
+            len as libc::socklen_t,
+            host.as_mut_ptr(),
+            NI_MAXHOST as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if result != 0 {
+        return None;
+    }
+    let host = unsafe { CStr::from_ptr(host.as_ptr()) };
+    Some(host.to_string_lossy().into_owned())
+}
@@ -0,0 +1,336 @@
+use crate::builder::{detect_format, native_format, HEAD_SIZE};
+use crate::parse::raw_from_record;
+use crate::raw::{RawUtmp, RecordValues};
+use crate::timestamp::{civil_from_secs, secs_from_civil, Civil};
+use crate::{Endianness, Format};
+use std::io::{self, Write};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use thiserror::Error;
+use utmp_raw::exit_status;
+use utmp_raw::{
+    x32::utmp as utmp32,
+    x64::{timeval as timeval64, utmp as utmp64},
+};
+
+/// Converter between utmp files and the text format of `utmpdump` from util-linux.
+///
+/// Output of `write` is identical to that of `utmpdump` run with `TZ=UTC`,
+/// and `undump` rebuilds records from it the way `utmpdump -r` does,
+/// so dumps can be edited as text and converted back.
+/// Only the fields in the text format are kept,
+/// i.e. exit status and session ID are lost.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let dump = utmp_rs::UtmpDump::new().format(utmp_rs::Format::Auto);
+/// let bytes = std::fs::read("/var/log/wtmp")?;
+/// let mut text = Vec::new();
+/// dump.write(&bytes, &mut text)?;
+/// assert_eq!(dump.undump(std::str::from_utf8(&text)?)?.len(), bytes.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct UtmpDump {
+    format: Format,
+    endianness: Endianness,
+}
+
+/// Error from rebuilding records from text by `UtmpDump::undump`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum DumpError {
+    /// Line of the text isn't a record in the format of `utmpdump`
+    #[error("invalid line {line}: {reason}")]
+    InvalidLine {
+        /// Line number, starting from 1
+        line: usize,
+        /// What is wrong with the line
+        reason: &'static str,
+    },
+}
+
+impl Default for UtmpDump {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UtmpDump {
+    /// Create a converter of records in the native format for the target platform.
+    pub fn new() -> Self {
+        UtmpDump {
+            format: native_format(),
+            endianness: Endianness::Native,
+        }
+    }
+
+    /// Set the layout of records.
+    ///
+    /// `Format::Auto` detects the layout of dumped bytes,
+    /// and rebuilds records in the native format.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the byte order of numeric fields in records.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Write the records in the given bytes as text, one line per record.
+    ///
+    /// Trailing bytes not forming a whole record are ignored.
+    pub fn write<W: Write>(&self, bytes: &[u8], mut writer: W) -> io::Result<()> {
+        let swap = self.endianness.swaps();
+        let format = match self.format {
+            Format::Auto => {
+                let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+                detect_format(head, Some(bytes.len() as u64), swap)
+            }
+            format => format,
+        };
+        match format {
+            Format::X64 => write_records::<utmp64, W>(bytes, swap, &mut writer)?,
+            _ => write_records::<utmp32, W>(bytes, swap, &mut writer)?,
+        }
+        writer.flush()
+    }
+
+    /// Rebuild records from the given text, skipping blank lines.
+    ///
+    /// Fields not in the text format are zeroed.
+    pub fn undump(&self, text: &str) -> Result<Vec<u8>, DumpError> {
+        let swap = self.endianness.swaps();
+        let format = match self.format {
+            Format::Auto => native_format(),
+            format => format,
+        };
+        let mut bytes = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = |reason| DumpError::InvalidLine {
+                line: index + 1,
+                reason,
+            };
+            let fields = parse_line(line).map_err(invalid)?;
+            let result = match format {
+                Format::X64 => fields.append_record::<utmp64>(&mut bytes, swap),
+                _ => fields.append_record::<utmp32>(&mut bytes, swap),
+            };
+            result.map_err(invalid)?;
+        }
+        Ok(bytes)
+    }
+}
+
+fn write_records<T: RawUtmp, W: Write>(bytes: &[u8], swap: bool, writer: &mut W) -> io::Result<()> {
+    for record in bytes.chunks_exact(mem::size_of::<T>()) {
+        let raw = raw_from_record::<T>(record, swap);
+        let tv = raw.ut_tv();
+        writeln!(
+            writer,
+            "[{}] [{:05}] [{:<4}] [{:<8}] [{:<12}] [{:<20}] [{:<15}] [{}]",
+            raw.ut_type(),
+            raw.ut_pid(),
+            cleanse(raw.ut_id()),
+            cleanse(raw.ut_user()),
+            cleanse(raw.ut_line()),
+            cleanse(raw.ut_host()),
+            address(raw.ut_addr_v6()),
+            format_time(tv.tv_sec, tv.tv_usec),
+        )?;
+    }
+    Ok(())
+}
+
+/// The string up to the first NUL, with unprintable bytes replaced by `?`.
+fn cleanse(bytes: &[u8]) -> String {
+    let bytes = bytes.split(|&b| b == 0).next().unwrap_or_default();
+    let printable = |b: u8| if (0x20..0x7f).contains(&b) { b } else { b'?' };
+    bytes.iter().map(|&b| char::from(printable(b))).collect()
+}
+
+/// The address in `ut_addr_v6`, which is IPv4 if only the first word is used.
+fn address(addr: [i32; 4]) -> IpAddr {
+    let mut octets = [0; 16];
+    for (chunk, word) in octets.chunks_exact_mut(4).zip(addr.iter()) {
+        chunk.copy_from_slice(&word.to_ne_bytes());
+    }
+    if addr[1..].iter().all(|&word| word == 0) {
+        IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(octets))
+    }
+}
+
+fn format_time(secs: i64, usec: i64) -> String {
+    let civil = civil_from_secs(secs);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02},{:06}+00:00",
+        civil.year, civil.month, civil.day, civil.hour, civil.minute, civil.second, usec,
+    )
+}
+
+/// Fields of a record in a line of text.
+struct DumpFields<'a> {
+    ut_type: i16,
+    pid: i32,
+    id: &'a str,
+    user: &'a str,
+    line: &'a str,
+    host: &'a str,
+    address: Option<IpAddr>,
+    secs: i64,
+    usec: i64,
+}
+
+impl DumpFields<'_> {
+    fn append_record<T: RawUtmp>(
+        &self,
+        bytes: &mut Vec<u8>,
+        swap: bool,
+    ) -> Result<(), &'static str> {
+        let mut addr = [0; 16];
+        match self.address {
+            Some(IpAddr::V4(address)) => addr[..4].copy_from_slice(&address.octets()),
+            Some(IpAddr::V6(address)) => addr = address.octets(),
+            None => {}
+        }
+        let values = RecordValues {
+            ut_type: self.ut_type,
+            pid: self.pid,
+            line: self.line.as_bytes(),
+            id: self.id.as_bytes(),
+            user: self.user.as_bytes(),
+            host: self.host.as_bytes(),
+            exit: exit_status {
+                e_termination: 0,
+                e_exit: 0,
+            },
+            session: 0,
+            tv: timeval64 {
+                tv_sec: self.secs,
+                tv_usec: self.usec,
+            },
+            addr,
+            unused: &[],
+        };
+        let start = bytes.len();
+        bytes.resize(start + mem::size_of::<T>(), 0);
+        let result = values.write::<T>(&mut bytes[start..], swap);
+        result.map_err(|_| "time doesn't fit the format")
+    }
+}
+
+/// Parse a line as `utmpdump -r` does, with fields in brackets.
+fn parse_line(line: &str) -> Result<DumpFields<'_>, &'static str> {
+    let mut rest = line;
+    let mut token = |name| {
+        let start = rest.find('[').ok_or(name)?;
+        let len = rest[start..].find(']').ok_or(name)?;
+        let token = &rest[start + 1..start + len];
+        rest = &rest[start + len + 1..];
+        Ok::<_, &'static str>(token)
+    };
+    let ut_type = token("missing type")?;
+    let pid = token("missing pid")?;
+    let id = token("missing id")?;
+    let user = token("missing user")?;
+    let line = token("missing line")?;
+    let host = token("missing host")?;
+    let address = token("missing address")?;
+    let time = token("missing time")?;
+    // The id is kept as is, since padding may be part of it, like `%4c` does.
+    let id = id.get(..4).unwrap_or(id);
+    let address = address.trim_end_matches(' ');
+    let address = if address.is_empty() {
+        None
+    } else if address.contains('.') {
+        Some(IpAddr::V4(address.parse().map_err(|_| "invalid address")?))
+    } else {
+        Some(IpAddr::V6(address.parse().map_err(|_| "invalid address")?))
+    };
+    let (secs, usec) = parse_time(time).ok_or("invalid time")?;
+    Ok(DumpFields {
+        ut_type: ut_type.trim().parse().map_err(|_| "invalid type")?,
+        pid: pid.trim().parse().map_err(|_| "invalid pid")?,
+        id,
+        user: user.trim_end_matches(' '),
+        line: line.trim_end_matches(' '),
+        host: host.trim_end_matches(' '),
+        address,
+        secs,
+        usec,
+    })
+}
+
+/// Parse an ISO 8601 time, e.g. `2023-02-07T08:07:06,139552+00:00`,
+/// into seconds since the Unix epoch and microseconds.
+fn parse_time(time: &str) -> Option<(i64, i64)> {
+    let time = time.trim();
+    let number = |range: std::ops::Range<usize>| -> Option<i64> {
+        let digits = time.get(range)?;
+        if !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    };
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    let bytes = time.as_bytes();
+    if bytes.len() < 19
+        || !separators.iter().all(|&(i, c)| bytes[i] == c)
+        || !matches!(bytes[10], b'T' | b' ')
+    {
+        return None;
+    }
+    let civil = Civil {
+        year: number(0..4)?,
+        month: number(5..7)? as u8,
+        day: number(8..10)? as u8,
+        hour: number(11..13)? as u8,
+        minute: number(14..16)? as u8,
+        second: number(17..19)? as u8,
+    };
+    if !(1..=12).contains(&civil.month) || !(1..=31).contains(&civil.day) {
+        return None;
+    }
+    let mut rest = &time[19..];
+    let mut usec = 0;
+    if rest.starts_with(&[',', '.'][..]) {
+        let len = rest[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len() - 1);
+        let fraction = &rest[1..1 + len];
+        if fraction.is_empty() {
+            return None;
+        }
+        // Digits beyond microseconds are dropped.
+        let digits = format!("{:0<6}", &fraction[..fraction.len().min(6)]);
+        usec = digits.parse().ok()?;
+        rest = &rest[1 + len..];
+    }
+    let offset = match rest.as_bytes() {
+        [] | [b'Z'] => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] | [sign @ (b'+' | b'-'), h1, h2, m1, m2] => {
+            let digits = [*h1, *h2, *m1, *m2];
+            if !digits.iter().all(u8::is_ascii_digit) {
+                return None;
+            }
+            let [h1, h2, m1, m2] = digits.map(|d| i64::from(d - b'0'));
+            let offset = (h1 * 10 + h2) * 3600 + (m1 * 10 + m2) * 60;
+            if *sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+    Some((secs_from_civil(&civil) - offset, usec))
+}
@@ -0,0 +1,137 @@
+use crate::UtmpEntry;
+use std::net::IpAddr;
+
+/// Lookup of extra data for remote hosts, e.g. country and ASN from a GeoIP database,
+/// invoked per entry or session by `enrich`.
+///
+/// It's implemented for closures taking a `Remote`.
+pub trait Enricher {
+    /// Data found for a remote host
+    type Output;
+
+    /// Look up data for the remote host, or return `None` if there is nothing known.
+    fn enrich(&self, remote: &Remote<'_>) -> Option<Self::Output>;
+}
+
+impl<F, T> Enricher for F
+where
+    F: Fn(&Remote<'_>) -> Option<T>,
+{
+    type Output = T;
+
+    fn enrich(&self, remote: &Remote<'_>) -> Option<T> {
+        self(remote)
+    }
+}
+
+/// Remote host an entry or session comes from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Remote<'a> {
+    /// Hostname recorded for the login, which may be numeric
+    pub host: &'a str,
+    /// Internet address recorded for the login, or parsed from a numeric host
+    pub address: Option<IpAddr>,
+}
+
+impl<'a> Remote<'a> {
+    /// Remote host of the given host and address, or `None` if neither is set.
+    fn new(host: &'a str, address: Option<IpAddr>) -> Option<Self> {
+        let address = address.or_else(|| host.parse().ok());
+        if host.is_empty() && address.is_none() {
+            return None;
+        }
+        Some(Remote { host, address })
+    }
+}
+
+/// Item which may come from a remote host, i.e. a login entry or a session.
+pub trait HasRemote {
+    /// Remote host of the item, or `None` if it's local or not a login.
+    fn remote(&self) -> Option<Remote<'_>>;
+}
+
+impl HasRemote for UtmpEntry {
+    fn remote(&self) -> Option<Remote<'_>> {
+        match self {
+            UtmpEntry::UserProcess { host, address, .. } => Remote::new(host, *address),
+            UtmpEntry::LoginProcess { host, .. } => Remote::new(host, None),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl HasRemote for crate::Session {
+    fn remote(&self) -> Option<Remote<'_>> {
+        Remote::new(&self.host, self.address)
+    }
+}
+
+impl<T: HasRemote + ?Sized> HasRemote for &T {
+    fn remote(&self) -> Option<Remote<'_>> {
+        (**self).remote()
+    }
+}
+
+/// Item carried alongside the data looked up for its remote host.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enriched<T, D> {
+    /// Entry or session
+    pub item: T,
+    /// Data of its remote host, or `None` if it's local or nothing is known
+    pub data: Option<D>,
+}
+
+/// Iterator of items with data of their remote hosts, see `enrich`.
+#[derive(Clone, Debug)]
+pub struct Enrich<I, E> {
+    items: I,
+    enricher: E,
+}
+
+/// Look up data of the remote host of each item with the given enricher.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let entries = utmp_rs::parse_from_path("/var/log/wtmp")?;
+/// let internal = |remote: &utmp_rs::Remote<'_>| {
+///     let address = remote.address?;
+///     Some(if address.is_loopback() { "local" } else { "external" })
+/// };
+/// for enriched in utmp_rs::enrich(entries, internal) {
+///     println!("{:?} from {:?}", enriched.item, enriched.data);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn enrich<I, E>(items: I, enricher: E) -> Enrich<I::IntoIter, E>
+where
+    I: IntoIterator,
+    I::Item: HasRemote,
+    E: Enricher,
+{
+    Enrich {
+        items: items.into_iter(),
+        enricher,
+    }
+}
+
+impl<I, E> Iterator for Enrich<I, E>
+where
+    I: Iterator,
+    I::Item: HasRemote,
+    E: Enricher,
+{
+    type Item = Enriched<I::Item, E::Output>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.items.next()?;
+        let data = item.remote().and_then(|r| self.enricher.enrich(&r));
+        Some(Enriched { item, data })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
@@ -1,11 +1,14 @@
-use libc::pid_t;
+use crate::timestamp::civil;
+use crate::{Timestamp, UtmpEntryRef};
+use std::cmp::Ordering;
 use std::convert::TryFrom;
-use std::os::raw::c_short;
-use std::str;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::SystemTime;
 use thiserror::Error;
-use time::OffsetDateTime;
 use utmp_raw::x32::utmp as utmp32;
 use utmp_raw::x64::{timeval as timeval64, utmp as utmp64};
+use utmp_raw::{c_short, pid_t};
 
 /// Parsed utmp entry.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -15,37 +18,42 @@ pub enum UtmpEntry {
     Empty,
     /// Change in system run-level (see `init(8)`)
     RunLevel {
-        /// PID of the init process
-        pid: pid_t,
+        /// Run-level changed to, e.g. `'5'`, stored in the lowest byte of `ut_pid`
+        level: char,
+        /// Run-level changed from, stored in the second lowest byte of `ut_pid`,
+        /// or `None` if there was none, e.g. at boot
+        previous: Option<char>,
         /// Kernel version
         kernel_version: String,
         /// Time entry was made
-        time: OffsetDateTime,
+        time: Timestamp,
     },
     /// Time of system boot
     BootTime {
         /// Kernel version
         kernel_version: String,
         /// Time entry was made
-        time: OffsetDateTime,
+        time: Timestamp,
     },
     /// Time of system shutdown
     ShutdownTime {
         /// Kernel version
         kernel_version: String,
         /// Time entry was made
-        time: OffsetDateTime,
+        time: Timestamp,
     },
     /// Time after system clock change
-    NewTime(OffsetDateTime),
+    NewTime(Timestamp),
     /// Time before system clock change
-    OldTime(OffsetDateTime),
+    OldTime(Timestamp),
     /// Process spawned by `init(8)`
     InitProcess {
         /// PID of the init process
         pid: pid_t,
+        /// `inittab(5)` ID
+        id: String,
         /// Time entry was made
-        time: OffsetDateTime,
+        time: Timestamp,
     },
     /// Session leader process for user login
     LoginProcess {
@@ -53,12 +61,14 @@ pub enum UtmpEntry {
         pid: pid_t,
         /// Device name of tty
         line: String,
+        /// Terminal name suffix
+        id: String,
         /// Username
         user: String,
         /// Hostname for remote login
         host: String,
         /// Time entry was made
-        time: OffsetDateTime,
+        time: Timestamp,
     },
     /// Normal process
     UserProcess {
@@ -66,16 +76,18 @@ pub enum UtmpEntry {
         pid: pid_t,
         /// Device name of tty
         line: String,
+        /// Terminal name suffix
+        id: String,
         /// Username
         user: String,
         /// Hostname for remote login
         host: String,
+        /// Internet address of remote host
+        address: Option<IpAddr>,
         /// Session ID (`getsid(2)`)
         session: pid_t,
         /// Time entry was made
-        time: OffsetDateTime,
-        // TODO: Figure out the correct byte order to parse the address
-        // address: IpAddr,
+        time: Timestamp,
     },
     /// Terminated process
     DeadProcess {
@@ -83,34 +95,338 @@ pub enum UtmpEntry {
         pid: pid_t,
         /// Device name of tty
         line: String,
+        /// Terminal name suffix, or `inittab(5)` ID
+        id: String,
+        /// Termination status of the process
+        termination: c_short,
+        /// Exit status of the process
+        exit: c_short,
         /// Time entry was made
-        time: OffsetDateTime,
+        time: Timestamp,
     },
     /// Not implemented
     #[non_exhaustive]
     Accounting,
+    /// Record of a type this crate doesn't know about,
+    /// e.g. from vendor extensions or corrupted files
+    #[non_exhaustive]
+    Unknown {
+        /// Type of the record
+        ut_type: c_short,
+        /// PID in the record
+        pid: pid_t,
+        /// Bytes of the whole record as in the input, to decode vendor-specific fields from,
+        /// or empty if decoded from a record struct rather than bytes
+        raw: Box<[u8]>,
+    },
+}
+
+/// Kind of a utmp entry, i.e. the variant of `UtmpEntry` without its data.
+///
+/// Kinds are ordered in the order of the variants of `UtmpEntry`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum UtmpEntryKind {
+    /// See `UtmpEntry::Empty`
+    Empty,
+    /// See `UtmpEntry::RunLevel`
+    RunLevel,
+    /// See `UtmpEntry::BootTime`
+    BootTime,
+    /// See `UtmpEntry::ShutdownTime`
+    ShutdownTime,
+    /// See `UtmpEntry::NewTime`
+    NewTime,
+    /// See `UtmpEntry::OldTime`
+    OldTime,
+    /// See `UtmpEntry::InitProcess`
+    InitProcess,
+    /// See `UtmpEntry::LoginProcess`
+    LoginProcess,
+    /// See `UtmpEntry::UserProcess`
+    UserProcess,
+    /// See `UtmpEntry::DeadProcess`
+    DeadProcess,
+    /// See `UtmpEntry::Accounting`
+    Accounting,
+    /// See `UtmpEntry::Unknown`
+    Unknown,
+}
+
+impl UtmpEntry {
+    /// Time the entry was made, if the entry carries one.
+    pub fn time(&self) -> Option<Timestamp> {
+        match self {
+            UtmpEntry::RunLevel { time, .. }
+            | UtmpEntry::BootTime { time, .. }
+            | UtmpEntry::ShutdownTime { time, .. }
+            | UtmpEntry::NewTime(time)
+            | UtmpEntry::OldTime(time)
+            | UtmpEntry::InitProcess { time, .. }
+            | UtmpEntry::LoginProcess { time, .. }
+            | UtmpEntry::UserProcess { time, .. }
+            | UtmpEntry::DeadProcess { time, .. } => Some(*time),
+            UtmpEntry::Empty | UtmpEntry::Accounting | UtmpEntry::Unknown { .. } => None,
+        }
+    }
+
+    /// Time the entry was made as a `SystemTime`,
+    /// if the entry carries one and `SystemTime` can represent it.
+    pub fn time_as_system_time(&self) -> Option<SystemTime> {
+        self.time().and_then(Timestamp::to_system_time)
+    }
+
+    /// PID of the process of the entry, if the entry carries one.
+    pub(crate) fn pid(&self) -> Option<pid_t> {
+        match self {
+            UtmpEntry::InitProcess { pid, .. }
+            | UtmpEntry::LoginProcess { pid, .. }
+            | UtmpEntry::UserProcess { pid, .. }
+            | UtmpEntry::DeadProcess { pid, .. }
+            | UtmpEntry::Unknown { pid, .. } => Some(*pid),
+            _ => None,
+        }
+    }
+
+    /// Kind of the entry.
+    pub fn kind(&self) -> UtmpEntryKind {
+        match self {
+            UtmpEntry::Empty => UtmpEntryKind::Empty,
+            UtmpEntry::RunLevel { .. } => UtmpEntryKind::RunLevel,
+            UtmpEntry::BootTime { .. } => UtmpEntryKind::BootTime,
+            UtmpEntry::ShutdownTime { .. } => UtmpEntryKind::ShutdownTime,
+            UtmpEntry::NewTime(_) => UtmpEntryKind::NewTime,
+            UtmpEntry::OldTime(_) => UtmpEntryKind::OldTime,
+            UtmpEntry::InitProcess { .. } => UtmpEntryKind::InitProcess,
+            UtmpEntry::LoginProcess { .. } => UtmpEntryKind::LoginProcess,
+            UtmpEntry::UserProcess { .. } => UtmpEntryKind::UserProcess,
+            UtmpEntry::DeadProcess { .. } => UtmpEntryKind::DeadProcess,
+            UtmpEntry::Accounting => UtmpEntryKind::Accounting,
+            UtmpEntry::Unknown { .. } => UtmpEntryKind::Unknown,
+        }
+    }
+
+    /// Compare the remaining fields of entries of the same variant.
+    fn cmp_fields(&self, other: &Self) -> Ordering {
+        use UtmpEntry::*;
+        match (self, other) {
+            (
+                RunLevel {
+                    level: l1,
+                    previous: p1,
+                    kernel_version: k1,
+                    ..
+                },
+                RunLevel {
+                    level: l2,
+                    previous: p2,
+                    kernel_version: k2,
+                    ..
+                },
+            ) => (l1, p1, k1).cmp(&(l2, p2, k2)),
+            (
+                BootTime {
+                    kernel_version: a, ..
+                },
+                BootTime {
+                    kernel_version: b, ..
+                },
+            )
+            | (
+                ShutdownTime {
+                    kernel_version: a, ..
+                },
+                ShutdownTime {
+                    kernel_version: b, ..
+                },
+            ) => a.cmp(b),
+            (InitProcess { id: a, .. }, InitProcess { id: b, .. }) => a.cmp(b),
+            (
+                LoginProcess {
+                    line: l1,
+                    id: i1,
+                    user: u1,
+                    host: h1,
+                    ..
+                },
+                LoginProcess {
+                    line: l2,
+                    id: i2,
+                    user: u2,
+                    host: h2,
+                    ..
+                },
+            ) => (l1, i1, u1, h1).cmp(&(l2, i2, u2, h2)),
+            (
+                UserProcess {
+                    line: l1,
+                    id: i1,
+                    user: u1,
+                    host: h1,
+                    address: a1,
+                    session: s1,
+                    ..
+                },
+                UserProcess {
+                    line: l2,
+                    id: i2,
+                    user: u2,
+                    host: h2,
+                    address: a2,
+                    session: s2,
+                    ..
+                },
+            ) => (l1, i1, u1, h1, a1, s1).cmp(&(l2, i2, u2, h2, a2, s2)),
+            (
+                DeadProcess {
+                    line: l1,
+                    id: i1,
+                    termination: t1,
+                    exit: e1,
+                    ..
+                },
+                DeadProcess {
+                    line: l2,
+                    id: i2,
+                    termination: t2,
+                    exit: e2,
+                    ..
+                },
+            ) => (l1, i1, t1, e1).cmp(&(l2, i2, t2, e2)),
+            (
+                Unknown {
+                    ut_type: t1,
+                    raw: r1,
+                    ..
+                },
+                Unknown {
+                    ut_type: t2,
+                    raw: r2,
+                    ..
+                },
+            ) => (t1, r1).cmp(&(t2, r2)),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+/// Entries are ordered by time first, with entries without a time ordered first,
+/// then by type and pid, and then by the rest of their fields.
+impl Ord for UtmpEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.time()
+            .cmp(&other.time())
+            .then_with(|| self.kind().cmp(&other.kind()))
+            .then_with(|| self.pid().cmp(&other.pid()))
+            .then_with(|| self.cmp_fields(other))
+    }
+}
+
+impl PartialOrd for UtmpEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Entries are formatted like the output of `who(1)`,
+/// e.g. `root     pts/0        2023-02-07 08:07 (112.124.2.209)`.
+///
+/// Times are formatted in UTC.
+/// `Empty` and `Accounting` entries are formatted as empty strings.
+impl fmt::Display for UtmpEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UtmpEntry::Empty | UtmpEntry::Accounting => Ok(()),
+            UtmpEntry::Unknown { ut_type, pid, .. } => {
+                let label = format!("unknown type {}", ut_type);
+                write!(f, "{:<21} {:>27}", label, pid)
+            }
+            UtmpEntry::RunLevel {
+                level,
+                previous,
+                time,
+                ..
+            } => {
+                let label = format!("run-level {}", level);
+                write!(f, "{:<21} {}", label, WhoTime(*time))?;
+                if let Some(previous) = previous {
+                    write!(f, "{:>18}last={}", "", previous)?;
+                }
+                Ok(())
+            }
+            UtmpEntry::BootTime { time, .. } => {
+                write!(f, "{:<21} {}", "system boot", WhoTime(*time))
+            }
+            UtmpEntry::ShutdownTime { time, .. } => {
+                write!(f, "{:<21} {}", "system shutdown", WhoTime(*time))
+            }
+            UtmpEntry::NewTime(time) => write!(f, "{:<21} {}", "new time", WhoTime(*time)),
+            UtmpEntry::OldTime(time) => write!(f, "{:<21} {}", "old time", WhoTime(*time)),
+            UtmpEntry::InitProcess { pid, id, time } => {
+                write!(f, "{:<21} {} {:>10} id={}", "", WhoTime(*time), pid, id)
+            }
+            UtmpEntry::LoginProcess {
+                pid,
+                line,
+                id,
+                time,
+                ..
+            } => {
+                let time = WhoTime(*time);
+                write!(
+                    f,
+                    "{:<8} {:<12} {} {:>10} id={}",
+                    "LOGIN", line, time, pid, id
+                )
+            }
+            UtmpEntry::UserProcess {
+                line,
+                user,
+                host,
+                time,
+                ..
+            } => {
+                write!(f, "{:<8} {:<12} {}", user, line, WhoTime(*time))?;
+                if !host.is_empty() {
+                    write!(f, " ({})", host)?;
+                }
+                Ok(())
+            }
+            UtmpEntry::DeadProcess {
+                pid,
+                line,
+                id,
+                termination,
+                exit,
+                time,
+            } => {
+                let time = WhoTime(*time);
+                write!(f, "{:<8} {:<12} {} {:>10} id={}", "", line, time, pid, id)?;
+                write!(f, " term={} exit={}", termination, exit)
+            }
+        }
+    }
+}
+
+/// Time formatted like `who(1)` does, i.e. `YYYY-MM-DD HH:MM`.
+struct WhoTime(Timestamp);
+
+impl fmt::Display for WhoTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = civil(self.0);
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            time.year, time.month, time.day, time.hour, time.minute,
+        )
+    }
 }
 
 impl<'a> TryFrom<&'a utmp32> for UtmpEntry {
     type Error = UtmpError;
 
     fn try_from(from: &utmp32) -> Result<Self, UtmpError> {
-        UtmpEntry::try_from(&utmp64 {
-            ut_type: from.ut_type,
-            ut_pid: from.ut_pid,
-            ut_line: from.ut_line,
-            ut_id: from.ut_id,
-            ut_user: from.ut_user,
-            ut_host: from.ut_host,
-            ut_exit: from.ut_exit,
-            ut_session: i64::from(from.ut_session),
-            ut_tv: timeval64 {
-                tv_sec: i64::from(from.ut_tv.tv_sec),
-                tv_usec: i64::from(from.ut_tv.tv_usec),
-            },
-            ut_addr_v6: from.ut_addr_v6,
-            __unused: from.__unused,
-        })
+        UtmpEntryRef::try_from(from).map(|entry| entry.to_owned())
     }
 }
 
@@ -118,58 +434,7 @@ impl<'a> TryFrom<&'a utmp64> for UtmpEntry {
     type Error = UtmpError;
 
     fn try_from(from: &utmp64) -> Result<Self, UtmpError> {
-        Ok(match from.ut_type {
-            utmp_raw::EMPTY => UtmpEntry::Empty,
-            utmp_raw::RUN_LVL => {
-                let kernel_version =
-                    string_from_bytes(&from.ut_host).map_err(UtmpError::InvalidHost)?;
-                let time = time_from_tv(from.ut_tv)?;
-                if from.ut_line[0] == b'~' && from.ut_user.starts_with(b"shutdown\0") {
-                    UtmpEntry::ShutdownTime {
-                        kernel_version,
-                        time,
-                    }
-                } else {
-                    UtmpEntry::RunLevel {
-                        pid: from.ut_pid,
-                        kernel_version,
-                        time,
-                    }
-                }
-            }
-            utmp_raw::BOOT_TIME => UtmpEntry::BootTime {
-                kernel_version: string_from_bytes(&from.ut_host).map_err(UtmpError::InvalidHost)?,
-                time: time_from_tv(from.ut_tv)?,
-            },
-            utmp_raw::NEW_TIME => UtmpEntry::NewTime(time_from_tv(from.ut_tv)?),
-            utmp_raw::OLD_TIME => UtmpEntry::OldTime(time_from_tv(from.ut_tv)?),
-            utmp_raw::INIT_PROCESS => UtmpEntry::InitProcess {
-                pid: from.ut_pid,
-                time: time_from_tv(from.ut_tv)?,
-            },
-            utmp_raw::LOGIN_PROCESS => UtmpEntry::LoginProcess {
-                pid: from.ut_pid,
-                time: time_from_tv(from.ut_tv)?,
-                line: string_from_bytes(&from.ut_line).map_err(UtmpError::InvalidLine)?,
-                user: string_from_bytes(&from.ut_user).map_err(UtmpError::InvalidUser)?,
-                host: string_from_bytes(&from.ut_host).map_err(UtmpError::InvalidHost)?,
-            },
-            utmp_raw::USER_PROCESS => UtmpEntry::UserProcess {
-                pid: from.ut_pid,
-                line: string_from_bytes(&from.ut_line).map_err(UtmpError::InvalidLine)?,
-                user: string_from_bytes(&from.ut_user).map_err(UtmpError::InvalidUser)?,
-                host: string_from_bytes(&from.ut_host).map_err(UtmpError::InvalidHost)?,
-                session: from.ut_session as pid_t,
-                time: time_from_tv(from.ut_tv)?,
-            },
-            utmp_raw::DEAD_PROCESS => UtmpEntry::DeadProcess {
-                pid: from.ut_pid,
-                line: string_from_bytes(&from.ut_line).map_err(UtmpError::InvalidLine)?,
-                time: time_from_tv(from.ut_tv)?,
-            },
-            utmp_raw::ACCOUNTING => UtmpEntry::Accounting,
-            _ => return Err(UtmpError::UnknownType(from.ut_type)),
-        })
+        UtmpEntryRef::try_from(from).map(|entry| entry.to_owned())
     }
 }
 
@@ -182,27 +447,10 @@ pub enum UtmpError {
     InvalidTime(timeval64),
     #[error("invalid line value `{0:?}`")]
     InvalidLine(Box<[u8]>),
+    #[error("invalid id value `{0:?}`")]
+    InvalidId(Box<[u8]>),
     #[error("invalid user value `{0:?}`")]
     InvalidUser(Box<[u8]>),
     #[error("invalid host value `{0:?}`")]
     InvalidHost(Box<[u8]>),
 }
-
-fn time_from_tv(tv: timeval64) -> Result<OffsetDateTime, UtmpError> {
-    let timeval64 { tv_sec, tv_usec } = tv;
-    if tv_usec < 0 {
-        return Err(UtmpError::InvalidTime(tv));
-    }
-    let usec = i128::from(tv_sec) * 1_000_000 + i128::from(tv_usec);
-    OffsetDateTime::from_unix_timestamp_nanos(usec * 1000).map_err(|_| UtmpError::InvalidTime(tv))
-}
-
-fn string_from_bytes(bytes: &[u8]) -> Result<String, Box<[u8]>> {
-    let trimmed = match bytes.iter().position(|b| *b == 0) {
-        Some(pos) => &bytes[..pos],
-        None => bytes,
-    };
-    str::from_utf8(trimmed)
-        .map(|s| s.into())
-        .map_err(|_| bytes.into())
-}
@@ -0,0 +1,375 @@
+use crate::raw::RawUtmp;
+use crate::timestamp::time_from_tv;
+use crate::{Timestamp, UtmpEntry, UtmpError};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str;
+use utmp_raw::x32::utmp as utmp32;
+use utmp_raw::x64::utmp as utmp64;
+use utmp_raw::{c_short, pid_t};
+
+/// Parsed utmp entry borrowing its strings from the raw record.
+///
+/// It has the same variants as `UtmpEntry`, and can be converted into one via `to_owned`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum UtmpEntryRef<'a> {
+    /// Record does not contain valid info
+    Empty,
+    /// Change in system run-level (see `init(8)`)
+    RunLevel {
+        /// Run-level changed to, e.g. `'5'`, stored in the lowest byte of `ut_pid`
+        level: char,
+        /// Run-level changed from, stored in the second lowest byte of `ut_pid`,
+        /// or `None` if there was none, e.g. at boot
+        previous: Option<char>,
+        /// Kernel version
+        kernel_version: &'a str,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Time of system boot
+    BootTime {
+        /// Kernel version
+        kernel_version: &'a str,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Time of system shutdown
+    ShutdownTime {
+        /// Kernel version
+        kernel_version: &'a str,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Time after system clock change
+    NewTime(Timestamp),
+    /// Time before system clock change
+    OldTime(Timestamp),
+    /// Process spawned by `init(8)`
+    InitProcess {
+        /// PID of the init process
+        pid: pid_t,
+        /// `inittab(5)` ID
+        id: &'a str,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Session leader process for user login
+    LoginProcess {
+        /// PID of the login process
+        pid: pid_t,
+        /// Device name of tty
+        line: &'a str,
+        /// Terminal name suffix
+        id: &'a str,
+        /// Username
+        user: &'a str,
+        /// Hostname for remote login
+        host: &'a str,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Normal process
+    UserProcess {
+        /// PID of login process
+        pid: pid_t,
+        /// Device name of tty
+        line: &'a str,
+        /// Terminal name suffix
+        id: &'a str,
+        /// Username
+        user: &'a str,
+        /// Hostname for remote login
+        host: &'a str,
+        /// Internet address of remote host
+        address: Option<IpAddr>,
+        /// Session ID (`getsid(2)`)
+        session: pid_t,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Terminated process
+    DeadProcess {
+        /// PID of the terminated process
+        pid: pid_t,
+        /// Device name of tty
+        line: &'a str,
+        /// Terminal name suffix, or `inittab(5)` ID
+        id: &'a str,
+        /// Termination status of the process
+        termination: c_short,
+        /// Exit status of the process
+        exit: c_short,
+        /// Time entry was made
+        time: Timestamp,
+    },
+    /// Not implemented
+    #[non_exhaustive]
+    Accounting,
+    /// Record of a type this crate doesn't know about,
+    /// e.g. from vendor extensions or corrupted files
+    #[non_exhaustive]
+    Unknown {
+        /// Type of the record
+        ut_type: c_short,
+        /// PID in the record
+        pid: pid_t,
+        /// Bytes of the whole record as in the input, to decode vendor-specific fields from,
+        /// or empty if decoded from a record struct rather than bytes
+        raw: &'a [u8],
+    },
+}
+
+impl<'a> UtmpEntryRef<'a> {
+    /// Convert into an `UtmpEntry` owning its strings.
+    pub fn to_owned(&self) -> UtmpEntry {
+        match *self {
+            UtmpEntryRef::Empty => UtmpEntry::Empty,
+            UtmpEntryRef::RunLevel {
+                level,
+                previous,
+                kernel_version,
+                time,
+            } => UtmpEntry::RunLevel {
+                level,
+                previous,
+                kernel_version: kernel_version.to_owned(),
+                time,
+            },
+            UtmpEntryRef::BootTime {
+                kernel_version,
+                time,
+            } => UtmpEntry::BootTime {
+                kernel_version: kernel_version.to_owned(),
+                time,
+            },
+            UtmpEntryRef::ShutdownTime {
+                kernel_version,
+                time,
+            } => UtmpEntry::ShutdownTime {
+                kernel_version: kernel_version.to_owned(),
+                time,
+            },
+            UtmpEntryRef::NewTime(time) => UtmpEntry::NewTime(time),
+            UtmpEntryRef::OldTime(time) => UtmpEntry::OldTime(time),
+            UtmpEntryRef::InitProcess { pid, id, time } => UtmpEntry::InitProcess {
+                pid,
+                id: id.to_owned(),
+                time,
+            },
+            UtmpEntryRef::LoginProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                time,
+            } => UtmpEntry::LoginProcess {
+                pid,
+                line: line.to_owned(),
+                id: id.to_owned(),
+                user: user.to_owned(),
+                host: host.to_owned(),
+                time,
+            },
+            UtmpEntryRef::UserProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                address,
+                session,
+                time,
+            } => UtmpEntry::UserProcess {
+                pid,
+                line: line.to_owned(),
+                id: id.to_owned(),
+                user: user.to_owned(),
+                host: host.to_owned(),
+                address,
+                session,
+                time,
+            },
+            UtmpEntryRef::DeadProcess {
+                pid,
+                line,
+                id,
+                termination,
+                exit,
+                time,
+            } => UtmpEntry::DeadProcess {
+                pid,
+                line: line.to_owned(),
+                id: id.to_owned(),
+                termination,
+                exit,
+                time,
+            },
+            UtmpEntryRef::Accounting => UtmpEntry::Accounting,
+            UtmpEntryRef::Unknown { ut_type, pid, raw } => UtmpEntry::Unknown {
+                ut_type,
+                pid,
+                raw: raw.into(),
+            },
+        }
+    }
+
+    /// Decode the record from its numeric fields in `raw`, and the given string fields.
+    ///
+    /// The string fields are passed separately so that they can be borrowed
+    /// from somewhere other than `raw` when the record couldn't be referenced in place.
+    /// `record` is the bytes of the whole record, kept for records of unknown types,
+    /// which is empty if they aren't available.
+    pub(crate) fn from_raw<T: RawUtmp>(
+        raw: &T,
+        record: &'a [u8],
+        line: &'a [u8],
+        id: &'a [u8],
+        user: &'a [u8],
+        host: &'a [u8],
+    ) -> Result<Self, UtmpError> {
+        let line_str = || str_from_bytes(line).map_err(UtmpError::InvalidLine);
+        let id_str = || str_from_bytes(id).map_err(UtmpError::InvalidId);
+        let user_str = || str_from_bytes(user).map_err(UtmpError::InvalidUser);
+        let host_str = || str_from_bytes(host).map_err(UtmpError::InvalidHost);
+        let time = || time_from_tv(raw.ut_tv());
+        Ok(match raw.ut_type() {
+            utmp_raw::EMPTY => UtmpEntryRef::Empty,
+            utmp_raw::RUN_LVL => {
+                let kernel_version = host_str()?;
+                let time = time()?;
+                if line.first() == Some(&b'~') && user.starts_with(b"shutdown\0") {
+                    UtmpEntryRef::ShutdownTime {
+                        kernel_version,
+                        time,
+                    }
+                } else {
+                    let (level, previous) = run_levels(raw.ut_pid());
+                    UtmpEntryRef::RunLevel {
+                        level,
+                        previous,
+                        kernel_version,
+                        time,
+                    }
+                }
+            }
+            utmp_raw::BOOT_TIME => UtmpEntryRef::BootTime {
+                kernel_version: host_str()?,
+                time: time()?,
+            },
+            utmp_raw::NEW_TIME => UtmpEntryRef::NewTime(time()?),
+            utmp_raw::OLD_TIME => UtmpEntryRef::OldTime(time()?),
+            utmp_raw::INIT_PROCESS => UtmpEntryRef::InitProcess {
+                pid: raw.ut_pid(),
+                id: id_str()?,
+                time: time()?,
+            },
+            utmp_raw::LOGIN_PROCESS => UtmpEntryRef::LoginProcess {
+                pid: raw.ut_pid(),
+                time: time()?,
+                line: line_str()?,
+                id: id_str()?,
+                user: user_str()?,
+                host: host_str()?,
+            },
+            utmp_raw::USER_PROCESS => UtmpEntryRef::UserProcess {
+                pid: raw.ut_pid(),
+                line: line_str()?,
+                id: id_str()?,
+                user: user_str()?,
+                host: host_str()?,
+                address: address_from_raw(raw.ut_addr_v6()),
+                session: raw.ut_session() as pid_t,
+                time: time()?,
+            },
+            utmp_raw::DEAD_PROCESS => UtmpEntryRef::DeadProcess {
+                pid: raw.ut_pid(),
+                line: line_str()?,
+                id: id_str()?,
+                termination: raw.ut_exit().e_termination,
+                exit: raw.ut_exit().e_exit,
+                time: time()?,
+            },
+            utmp_raw::ACCOUNTING => UtmpEntryRef::Accounting,
+            ut_type => UtmpEntryRef::Unknown {
+                ut_type,
+                pid: raw.ut_pid(),
+                raw: record,
+            },
+        })
+    }
+}
+
+impl<'a> From<UtmpEntryRef<'a>> for UtmpEntry {
+    fn from(entry: UtmpEntryRef<'a>) -> Self {
+        entry.to_owned()
+    }
+}
+
+impl<'a> TryFrom<&'a utmp32> for UtmpEntryRef<'a> {
+    type Error = UtmpError;
+
+    fn try_from(from: &'a utmp32) -> Result<Self, UtmpError> {
+        // Padding of the struct isn't part of its value, so the record isn't available.
+        UtmpEntryRef::from_raw(
+            from,
+            &[],
+            &from.ut_line,
+            &from.ut_id,
+            &from.ut_user,
+            &from.ut_host,
+        )
+    }
+}
+
+impl<'a> TryFrom<&'a utmp64> for UtmpEntryRef<'a> {
+    type Error = UtmpError;
+
+    fn try_from(from: &'a utmp64) -> Result<Self, UtmpError> {
+        // Padding of the struct isn't part of its value, so the record isn't available.
+        UtmpEntryRef::from_raw(
+            from,
+            &[],
+            &from.ut_line,
+            &from.ut_id,
+            &from.ut_user,
+            &from.ut_host,
+        )
+    }
+}
+
+pub(crate) fn str_from_bytes(bytes: &[u8]) -> Result<&str, Box<[u8]>> {
+    str::from_utf8(trim_nul(bytes)).map_err(|_| bytes.into())
+}
+
+/// Bytes of a NUL-terminated string up to the terminator.
+pub(crate) fn trim_nul(bytes: &[u8]) -> &[u8] {
+    match bytes.iter().position(|b| *b == 0) {
+        Some(pos) => &bytes[..pos],
+        None => bytes,
+    }
+}
+
+/// Decode `ut_addr_v6`, which holds the address in network byte order,
+/// with an IPv4 address only using the first word, as glibc and sshd write it.
+pub(crate) fn address_from_raw(addr: [i32; 4]) -> Option<IpAddr> {
+    let mut bytes = [0; 16];
+    for (chunk, word) in bytes.chunks_exact_mut(4).zip(addr.iter()) {
+        chunk.copy_from_slice(&word.to_ne_bytes());
+    }
+    match addr {
+        [0, 0, 0, 0] => None,
+        [_, 0, 0, 0] => Some(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]).into()),
+        _ => Some(Ipv6Addr::from(bytes).into()),
+    }
+}
+
+/// Decode the run-levels `init(8)` stores in `ut_pid` of `RUN_LVL` records,
+/// with the new one in the lowest byte and the previous one, or 0 if none, above it.
+fn run_levels(pid: pid_t) -> (char, Option<char>) {
+    let level = char::from(pid as u8);
+    let previous = (pid >> 8) as u8;
+    (level, Some(char::from(previous)).filter(|_| previous != 0))
+}
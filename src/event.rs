@@ -0,0 +1,202 @@
+use crate::{Timestamp, UtmpEntry};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use time::Duration;
+
+/// Semantic event interpreted from a sequence of utmp entries.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Event {
+    /// User logged in
+    UserLoggedIn {
+        /// Username
+        user: String,
+        /// Device name of tty
+        line: String,
+        /// Hostname for remote login
+        host: String,
+        /// Time of login
+        time: Timestamp,
+    },
+    /// User logged out
+    UserLoggedOut {
+        /// Username
+        user: String,
+        /// Device name of tty
+        line: String,
+        /// Hostname for remote login
+        host: String,
+        /// Time of logout
+        time: Timestamp,
+        /// Time elapsed since the matching login
+        duration: Duration,
+    },
+    /// System booted
+    SystemBooted {
+        /// Kernel version
+        kernel_version: String,
+        /// Time of boot
+        time: Timestamp,
+    },
+    /// System shut down
+    SystemShutdown {
+        /// Kernel version
+        kernel_version: String,
+        /// Time of shutdown
+        time: Timestamp,
+    },
+}
+
+/// Receiver of interpreted events.
+///
+/// It is implemented for any `FnMut(Event)`, so a closure can be used as a sink.
+pub trait EventSink {
+    /// Handle an event.
+    fn event(&mut self, event: Event);
+}
+
+impl<F: FnMut(Event)> EventSink for F {
+    fn event(&mut self, event: Event) {
+        self(event)
+    }
+}
+
+/// Interpreter which turns utmp entries into events.
+///
+/// Logins are paired with logouts by tty line,
+/// or by `ut_id` for those without a line, e.g. ssh sessions without a pty,
+/// as sessions are paired.
+/// A login still open when another one is made on the same line
+/// is logged out at the time of the new login, as sessions end with `SessionEnd::NextLogin`,
+/// and logins still open when the system shuts down are logged out at the time of the shutdown,
+/// as sessions end with `SessionEnd::Shutdown`.
+/// Logins still open when the system boots again without shutting down are discarded,
+/// since their logout records are never written.
+///
+/// ```
+/// # use utmp_rs::{Event, EventInterpreter};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut interpreter = EventInterpreter::new();
+/// let mut sink = |event: Event| println!("{:?}", event);
+/// for entry in utmp_rs::parse_from_path("/var/run/utmp")? {
+///     interpreter.feed(&entry, &mut sink);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EventInterpreter {
+    logins: HashMap<LoginKey, Login>,
+}
+
+/// Line of a login, and its `ut_id` if the line is empty.
+type LoginKey = (String, String);
+
+fn login_key(line: &str, id: &str) -> LoginKey {
+    let id = if line.is_empty() { id } else { "" };
+    (line.to_owned(), id.to_owned())
+}
+
+#[derive(Clone, Debug)]
+struct Login {
+    user: String,
+    host: String,
+    time: Timestamp,
+}
+
+impl EventInterpreter {
+    /// Create an interpreter without any open login.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Interpret the next entry, and send the resulting event, if any, to the sink.
+    pub fn feed<S: EventSink + ?Sized>(&mut self, entry: &UtmpEntry, sink: &mut S) {
+        match entry {
+            UtmpEntry::UserProcess {
+                line,
+                id,
+                user,
+                host,
+                time,
+                ..
+            } => {
+                let login = Login {
+                    user: user.clone(),
+                    host: host.clone(),
+                    time: *time,
+                };
+                if let Some(previous) = self.logins.insert(login_key(line, id), login) {
+                    sink.event(Event::UserLoggedOut {
+                        user: previous.user,
+                        line: line.clone(),
+                        host: previous.host,
+                        time: *time,
+                        duration: *time - previous.time,
+                    });
+                }
+                sink.event(Event::UserLoggedIn {
+                    user: user.clone(),
+                    line: line.clone(),
+                    host: host.clone(),
+                    time: *time,
+                });
+            }
+            UtmpEntry::DeadProcess { line, id, time, .. } => {
+                if let Some(login) = self.logins.remove(&login_key(line, id)) {
+                    sink.event(Event::UserLoggedOut {
+                        user: login.user,
+                        line: line.clone(),
+                        host: login.host,
+                        time: *time,
+                        duration: *time - login.time,
+                    });
+                }
+            }
+            UtmpEntry::BootTime {
+                kernel_version,
+                time,
+            } => {
+                self.logins.clear();
+                sink.event(Event::SystemBooted {
+                    kernel_version: kernel_version.clone(),
+                    time: *time,
+                });
+            }
+            UtmpEntry::ShutdownTime {
+                kernel_version,
+                time,
+            } => {
+                let mut logins = self.logins.drain().collect::<Vec<_>>();
+                logins.sort_by(|(a, x), (b, y)| (x.time, a).cmp(&(y.time, b)));
+                for ((line, _), login) in logins {
+                    sink.event(Event::UserLoggedOut {
+                        user: login.user,
+                        line,
+                        host: login.host,
+                        time: *time,
+                        duration: *time - login.time,
+                    });
+                }
+                sink.event(Event::SystemShutdown {
+                    kernel_version: kernel_version.clone(),
+                    time: *time,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Interpret all the given entries, and send the resulting events to the sink.
+pub fn interpret_events<I, S>(entries: I, sink: &mut S)
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+    S: EventSink + ?Sized,
+{
+    let mut interpreter = EventInterpreter::new();
+    for entry in entries {
+        interpreter.feed(entry.borrow(), sink);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{Timestamp, UtmpEntry};
+use std::net::IpAddr;
+use utmp_raw::{c_short, pid_t};
+
+/// Fields of an entry flattened for exporters, with those the variant lacks left `None`.
+#[derive(Default)]
+pub(crate) struct Fields<'a> {
+    pub(crate) kind: &'static str,
+    pub(crate) pid: Option<pid_t>,
+    pub(crate) line: Option<&'a str>,
+    pub(crate) id: Option<&'a str>,
+    pub(crate) user: Option<&'a str>,
+    pub(crate) host: Option<&'a str>,
+    pub(crate) address: Option<IpAddr>,
+    pub(crate) session: Option<pid_t>,
+    pub(crate) termination: Option<c_short>,
+    pub(crate) exit: Option<c_short>,
+    pub(crate) level: Option<char>,
+    pub(crate) previous: Option<char>,
+    pub(crate) kernel_version: Option<&'a str>,
+    pub(crate) ut_type: Option<c_short>,
+    pub(crate) time: Option<Timestamp>,
+}
+
+impl<'a> Fields<'a> {
+    pub(crate) fn of(entry: &'a UtmpEntry) -> Self {
+        match entry {
+            UtmpEntry::Empty => Fields::kind("Empty"),
+            UtmpEntry::RunLevel {
+                level,
+                previous,
+                kernel_version,
+                time,
+            } => Fields {
+                level: Some(*level),
+                previous: *previous,
+                kernel_version: Some(kernel_version),
+                time: Some(*time),
+                ..Fields::kind("RunLevel")
+            },
+            UtmpEntry::BootTime {
+                kernel_version,
+                time,
+            } => Fields {
+                kernel_version: Some(kernel_version),
+                time: Some(*time),
+                ..Fields::kind("BootTime")
+            },
+            UtmpEntry::ShutdownTime {
+                kernel_version,
+                time,
+            } => Fields {
+                kernel_version: Some(kernel_version),
+                time: Some(*time),
+                ..Fields::kind("ShutdownTime")
+            },
+            UtmpEntry::NewTime(time) => Fields {
+                time: Some(*time),
+                ..Fields::kind("NewTime")
+            },
+            UtmpEntry::OldTime(time) => Fields {
+                time: Some(*time),
+                ..Fields::kind("OldTime")
+            },
+            UtmpEntry::InitProcess { pid, id, time } => Fields {
+                pid: Some(*pid),
+                id: Some(id),
+                time: Some(*time),
+                ..Fields::kind("InitProcess")
+            },
+            UtmpEntry::LoginProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                time,
+            } => Fields {
+                pid: Some(*pid),
+                line: Some(line),
+                id: Some(id),
+                user: Some(user),
+                host: Some(host),
+                time: Some(*time),
+                ..Fields::kind("LoginProcess")
+            },
+            UtmpEntry::UserProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                address,
+                session,
+                time,
+            } => Fields {
+                pid: Some(*pid),
+                line: Some(line),
+                id: Some(id),
+                user: Some(user),
+                host: Some(host),
+                address: *address,
+                session: Some(*session),
+                time: Some(*time),
+                ..Fields::kind("UserProcess")
+            },
+            UtmpEntry::DeadProcess {
+                pid,
+                line,
+                id,
+                termination,
+                exit,
+                time,
+            } => Fields {
+                pid: Some(*pid),
+                line: Some(line),
+                id: Some(id),
+                termination: Some(*termination),
+                exit: Some(*exit),
+                time: Some(*time),
+                ..Fields::kind("DeadProcess")
+            },
+            UtmpEntry::Accounting => Fields::kind("Accounting"),
+            UtmpEntry::Unknown { ut_type, pid, .. } => Fields {
+                ut_type: Some(*ut_type),
+                pid: Some(*pid),
+                ..Fields::kind("Unknown")
+            },
+        }
+    }
+
+    fn kind(kind: &'static str) -> Self {
+        Fields {
+            kind,
+            ..Fields::default()
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::timestamp::datetime;
+use crate::{FailedLogin, Timestamp, UtmpEntry};
+use std::borrow::Borrow;
+use time::{UtcOffset, Weekday};
+
+/// Numbers of logins by day of the week and hour of the day,
+/// for reports of when people log into a system.
+///
+/// Times are bucketed in UTC unless another offset is set with `offset`.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut histogram = utmp_rs::LoginHistogram::new();
+/// histogram.count_logins(&utmp_rs::parse_from_path("/var/log/wtmp")?);
+/// for (hour, count) in histogram.by_hour().iter().enumerate() {
+///     println!("{:02}:00 {}", hour, count);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LoginHistogram {
+    offset: UtcOffset,
+    counts: [[u64; 24]; 7],
+}
+
+impl Default for LoginHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoginHistogram {
+    /// Create a histogram without any login, bucketing times in UTC.
+    pub fn new() -> Self {
+        LoginHistogram {
+            offset: UtcOffset::UTC,
+            counts: [[0; 24]; 7],
+        }
+    }
+
+    /// Set the offset of the local time to bucket times in,
+    /// which applies to logins added afterwards.
+    pub fn offset(mut self, offset: UtcOffset) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Add a login at the time.
+    pub fn add(&mut self, time: Timestamp) {
+        let time = datetime(time).to_offset(self.offset);
+        let day = time.weekday().number_days_from_monday();
+        self.counts[usize::from(day)][usize::from(time.hour())] += 1;
+    }
+
+    /// Count logins in the given entries of wtmp, i.e. `UserProcess` entries.
+    pub fn count_logins<I>(&mut self, entries: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        for entry in entries {
+            if let UtmpEntry::UserProcess { time, .. } = entry.borrow() {
+                self.add(*time);
+            }
+        }
+    }
+
+    /// Count the failed logins, e.g. from `failed_logins`.
+    pub fn count_failed<I>(&mut self, attempts: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<FailedLogin>,
+    {
+        for attempt in attempts {
+            self.add(attempt.borrow().time);
+        }
+    }
+
+    /// Number of logins on the day of the week within the hour of the day.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the hour isn't less than 24.
+    pub fn count(&self, weekday: Weekday, hour: u8) -> u64 {
+        self.counts[usize::from(weekday.number_days_from_monday())][usize::from(hour)]
+    }
+
+    /// Numbers of logins by day of the week from Monday, then by hour of the day.
+    pub fn matrix(&self) -> &[[u64; 24]; 7] {
+        &self.counts
+    }
+
+    /// Numbers of logins by hour of the day, over all days of the week.
+    pub fn by_hour(&self) -> [u64; 24] {
+        let mut hours = [0; 24];
+        for day in &self.counts {
+            for (total, count) in hours.iter_mut().zip(day) {
+                *total += count;
+            }
+        }
+        hours
+    }
+
+    /// Numbers of logins by day of the week from Monday.
+    pub fn by_weekday(&self) -> [u64; 7] {
+        let mut days = [0; 7];
+        for (total, day) in days.iter_mut().zip(&self.counts) {
+            *total = day.iter().sum();
+        }
+        days
+    }
+
+    /// Total number of logins.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().flatten().sum()
+    }
+}
@@ -0,0 +1,151 @@
+use crate::timestamp::datetime;
+use crate::{Session, Timestamp};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use time::{Time, UtcOffset, Weekday};
+
+/// Hours logins are allowed in, e.g. for audits of logins outside business hours.
+///
+/// Hours are on weekdays in UTC unless configured otherwise.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// use time::{Duration, Time, UtcOffset};
+/// use utmp_rs::{BusinessHours, Timestamp};
+/// let hours = BusinessHours::new(Time::from_hms(8, 0, 0)?, Time::from_hms(19, 0, 0)?)
+///     .offset(UtcOffset::from_hms(9, 0, 0)?);
+/// let sessions = utmp_rs::sessions(utmp_rs::parse_from_path("/var/log/wtmp")?);
+/// let now = Timestamp::now();
+/// let report = hours.report(sessions, now - Duration::days(30), now);
+/// for user in &report.users {
+///     println!("{}: {} logins out of hours", user.user, user.logins);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BusinessHours {
+    start: Time,
+    end: Time,
+    /// Whether logins are allowed on each day of the week from Monday.
+    days: [bool; 7],
+    offset: UtcOffset,
+}
+
+/// Logins outside business hours over a period, made by `BusinessHours::report`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct OutOfHoursReport {
+    /// Start of the period
+    pub from: Timestamp,
+    /// End of the period, exclusive
+    pub to: Timestamp,
+    /// Sessions logged in outside business hours, in order of login
+    pub logins: Vec<Session>,
+    /// Summaries of the logins by user, in order of users
+    pub users: Vec<OutOfHoursUser>,
+}
+
+/// Logins of a user outside business hours, in an `OutOfHoursReport`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct OutOfHoursUser {
+    /// Username
+    pub user: String,
+    /// Number of logins outside business hours
+    pub logins: usize,
+    /// Time of the first of the logins
+    pub first: Timestamp,
+    /// Time of the last of the logins
+    pub last: Timestamp,
+    /// Distinct non-empty hosts of the logins, in order
+    pub hosts: Vec<String>,
+}
+
+impl BusinessHours {
+    /// Create business hours from `start` to `end` on weekdays.
+    ///
+    /// An `end` before `start` makes hours span midnight, e.g. for a night shift.
+    pub fn new(start: Time, end: Time) -> Self {
+        BusinessHours {
+            start,
+            end,
+            days: [true, true, true, true, true, false, false],
+            offset: UtcOffset::UTC,
+        }
+    }
+
+    /// Set the days of the week logins are allowed on, replacing weekdays.
+    pub fn days<I: IntoIterator<Item = Weekday>>(mut self, days: I) -> Self {
+        self.days = [false; 7];
+        for day in days {
+            self.days[usize::from(day.number_days_from_monday())] = true;
+        }
+        self
+    }
+
+    /// Set the offset of the local time the hours are in.
+    pub fn offset(mut self, offset: UtcOffset) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Whether the time is within business hours.
+    ///
+    /// For hours spanning midnight, the day is the one they start on.
+    pub fn contains(&self, time: Timestamp) -> bool {
+        let time = datetime(time).to_offset(self.offset);
+        let allowed = |day: Weekday| self.days[usize::from(day.number_days_from_monday())];
+        if self.start <= self.end {
+            allowed(time.weekday()) && self.start <= time.time() && time.time() < self.end
+        } else if time.time() >= self.start {
+            allowed(time.weekday())
+        } else {
+            time.time() < self.end && allowed(time.weekday().previous())
+        }
+    }
+
+    /// List the sessions logged in outside business hours from `from` until before `to`,
+    /// and summarize them by user.
+    pub fn report<I>(&self, sessions: I, from: Timestamp, to: Timestamp) -> OutOfHoursReport
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Session>,
+    {
+        let mut logins = sessions
+            .into_iter()
+            .map(|session| session.borrow().clone())
+            .filter(|session| from <= session.login && session.login < to)
+            .filter(|session| !self.contains(session.login))
+            .collect::<Vec<_>>();
+        logins.sort_by_key(|session| session.login);
+        let mut users = BTreeMap::<&str, (OutOfHoursUser, BTreeSet<&str>)>::new();
+        for session in &logins {
+            let (user, hosts) = users.entry(&session.user).or_insert_with(|| {
+                let user = OutOfHoursUser {
+                    user: session.user.clone(),
+                    logins: 0,
+                    first: session.login,
+                    last: session.login,
+                    hosts: Vec::new(),
+                };
+                (user, BTreeSet::new())
+            });
+            user.logins += 1;
+            user.last = session.login;
+            if !session.host.is_empty() {
+                hosts.insert(&session.host);
+            }
+        }
+        let users = users.into_iter().map(|(_, (mut user, hosts))| {
+            user.hosts = hosts.into_iter().map(str::to_owned).collect();
+            user
+        });
+        OutOfHoursReport {
+            from,
+            to,
+            users: users.collect(),
+            logins,
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::builder::{detect_format, native_format, HEAD_SIZE};
+use crate::entry_ref::{address_from_raw, trim_nul};
+use crate::parse::raw_from_record;
+use crate::raw::RawUtmp;
+use crate::timestamp::time_from_tv;
+use crate::{Endianness, Format, ParseError, Timestamp};
+use std::fs;
+use std::mem;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str;
+use std::time::Duration;
+use utmp_raw::{x32::utmp as utmp32, x64::utmp as utmp64};
+
+/// Checker of utmp files for signs of tampering, e.g. records wiped or edited by an intruder.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let checker = utmp_rs::IntegrityChecker::new();
+/// for anomaly in checker.check_path("/var/log/wtmp")? {
+///     println!("record {}: {:?}", anomaly.index, anomaly.kind);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct IntegrityChecker {
+    format: Format,
+    endianness: Endianness,
+    max_gap: Duration,
+}
+
+/// Suspicious artifact found by `IntegrityChecker`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Anomaly {
+    /// Index of the record
+    pub index: u64,
+    /// Byte offset of the record in the input
+    pub offset: u64,
+    /// What is suspicious about the record
+    pub kind: AnomalyKind,
+}
+
+/// Kind of suspicious artifact in a record.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AnomalyKind {
+    /// Record is zeroed out, while records follow it
+    ZeroedRecord,
+    /// Time of the record is later than the previous one by more than the maximum gap
+    TimeGap {
+        /// Time of the previous record
+        previous: Timestamp,
+        /// Time of the record
+        time: Timestamp,
+    },
+    /// Time of the record is earlier than the previous one
+    TimeBackwards {
+        /// Time of the previous record
+        previous: Timestamp,
+        /// Time of the record
+        time: Timestamp,
+    },
+    /// `__unused` padding of the record isn't zero, which no known program writes
+    NonZeroPadding,
+    /// Host of the record is an IP address other than the one in `ut_addr_v6`,
+    /// which programs writing both fields never leave, unlike tools forging entries
+    HostAddressMismatch {
+        /// Host of the record
+        host: String,
+        /// Address of the record
+        address: IpAddr,
+    },
+}
+
+impl Default for IntegrityChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntegrityChecker {
+    /// Create a checker of files in the native format for the target platform,
+    /// which flags gaps of more than a week.
+    pub fn new() -> Self {
+        IntegrityChecker {
+            format: native_format(),
+            endianness: Endianness::Native,
+            max_gap: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+
+    /// Set the layout of records.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the byte order of numeric fields in records.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Set the longest time between records which isn't flagged as a gap.
+    pub fn max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// Check the file at the given path.
+    pub fn check_path<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Anomaly>, ParseError> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| ParseError::from(e).with_path(path))?;
+        Ok(self.check_bytes(&bytes))
+    }
+
+    /// Check the given bytes, which are expected to be in order of time as wtmp is.
+    ///
+    /// Anomalies are returned in the order of records.
+    /// Trailing bytes not forming a whole record are ignored.
+    pub fn check_bytes(&self, bytes: &[u8]) -> Vec<Anomaly> {
+        let swap = self.endianness.swaps();
+        let format = match self.format {
+            Format::Auto => {
+                let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+                detect_format(head, Some(bytes.len() as u64), swap)
+            }
+            format => format,
+        };
+        match format {
+            Format::X64 => self.check_records::<utmp64>(bytes, swap),
+            _ => self.check_records::<utmp32>(bytes, swap),
+        }
+    }
+
+    fn check_records<T: RawUtmp>(&self, bytes: &[u8], swap: bool) -> Vec<Anomaly> {
+        let size = mem::size_of::<T>();
+        let mut anomalies = Vec::new();
+        // Zeroed records are only flagged once a record follows them.
+        let mut zeroed = Vec::new();
+        let mut previous = None;
+        for (index, record) in bytes.chunks_exact(size).enumerate() {
+            let anomaly = |kind| Anomaly {
+                index: index as u64,
+                offset: (index * size) as u64,
+                kind,
+            };
+            if record.iter().all(|&b| b == 0) {
+                zeroed.push(anomaly(AnomalyKind::ZeroedRecord));
+                continue;
+            }
+            anomalies.append(&mut zeroed);
+            let raw = raw_from_record::<T>(record, swap);
+            if raw.unused().iter().any(|&b| b != 0) {
+                anomalies.push(anomaly(AnomalyKind::NonZeroPadding));
+            }
+            if let Some((host, address)) = host_mismatch(&raw) {
+                anomalies.push(anomaly(AnomalyKind::HostAddressMismatch { host, address }));
+            }
+            let tv = raw.ut_tv();
+            // Records without a valid time can't be compared.
+            let time = match time_from_tv(tv) {
+                Ok(time) if tv.tv_sec != 0 || tv.tv_usec != 0 => time,
+                _ => continue,
+            };
+            if let Some(previous) = previous {
+                if time < previous {
+                    anomalies.push(anomaly(AnomalyKind::TimeBackwards { previous, time }));
+                } else if self.exceeds_gap(previous, time) {
+                    anomalies.push(anomaly(AnomalyKind::TimeGap { previous, time }));
+                }
+            }
+            previous = Some(time);
+        }
+        anomalies
+    }
+
+    fn exceeds_gap(&self, previous: Timestamp, time: Timestamp) -> bool {
+        let gap = (previous.to_system_time())
+            .zip(time.to_system_time())
+            .and_then(|(previous, time)| time.duration_since(previous).ok());
+        gap.is_some_and(|gap| gap > self.max_gap)
+    }
+}
+
+/// Host and address of the record if the host is an IP address other than the address.
+///
+/// Hosts which are names can't be compared without resolving them, so they're never flagged.
+fn host_mismatch<T: RawUtmp>(raw: &T) -> Option<(String, IpAddr)> {
+    let address = address_from_raw(raw.ut_addr_v6())?;
+    let host = str::from_utf8(trim_nul(raw.ut_host())).ok()?;
+    let unmapped = |address: IpAddr| match address {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(address, IpAddr::V4),
+        address => address,
+    };
+    let host_address = host.parse().ok().map(unmapped)?;
+    if host_address == unmapped(address) {
+        return None;
+    }
+    Some((host.to_owned(), address))
+}
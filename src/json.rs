@@ -0,0 +1,419 @@
+use crate::fields::Fields;
+use crate::timestamp::format_rfc3339;
+use crate::{EntrySink, UtmpEntry};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::net::IpAddr;
+
+/// Streaming writer of entries as JSON, either as JSON Lines or as a pretty-printed array.
+///
+/// Entries are represented as objects with a `type` field naming the variant,
+/// alongside the fields of the variant, the same way as the `serde` feature does.
+/// Times are RFC 3339 strings in UTC, and optional fields are omitted when absent.
+///
+/// Entries can be written as documents of the Elastic Common Schema instead with `ecs`,
+/// to be indexed into Elasticsearch as they are.
+///
+/// ```
+/// # use utmp_rs::JsonWriter;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut writer = JsonWriter::lines(std::io::stdout().lock());
+/// for entry in utmp_rs::parse_from_path("/var/log/wtmp")? {
+///     writer.write_entry(&entry)?;
+/// }
+/// writer.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct JsonWriter<W: Write> {
+    writer: W,
+    pretty: bool,
+    schema: Schema,
+    /// Number of entries written.
+    count: usize,
+    /// Whether the array is closed.
+    closed: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+    /// Write entries as JSON Lines, i.e. one object per line, e.g. for piping into `jq`.
+    pub fn lines(writer: W) -> Self {
+        JsonWriter {
+            writer,
+            pretty: false,
+            schema: Schema::Native,
+            count: 0,
+            closed: false,
+        }
+    }
+
+    /// Write entries as a pretty-printed array, e.g. for small dumps read by people.
+    ///
+    /// The array is only closed by `finish`.
+    pub fn pretty_array(writer: W) -> Self {
+        JsonWriter {
+            writer,
+            pretty: true,
+            schema: Schema::Native,
+            count: 0,
+            closed: false,
+        }
+    }
+
+    /// Write entries as documents of the Elastic Common Schema (ECS).
+    ///
+    /// Entries are mapped to the `event` fields of what they record,
+    /// e.g. `event.category` of `authentication` and `session` for logins,
+    /// with `@timestamp`, `user.name`, `source.ip`, `source.domain`, `process.pid`
+    /// and `host.os.kernel` set when the entry has them.
+    /// Logins have `event.outcome` of `success`.
+    /// Fields without counterparts in ECS are kept in the custom `utmp` object,
+    /// e.g. `utmp.type` and `utmp.line`.
+    pub fn ecs(mut self) -> Self {
+        self.schema = Schema::Ecs { failures: false };
+        self
+    }
+
+    /// Write entries as ECS documents like `ecs`,
+    /// but with logins as failed attempts, i.e. `event.outcome` of `failure`,
+    /// for entries of btmp.
+    pub fn ecs_failures(mut self) -> Self {
+        self.schema = Schema::Ecs { failures: true };
+        self
+    }
+
+    /// Write an entry.
+    pub fn write_entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        let object = match self.schema {
+            Schema::Native => entry_object(entry),
+            Schema::Ecs { failures } => ecs_object(entry, failures),
+        };
+        self.write_object(object)
+    }
+
+    /// Write an event as an object with a `type` field naming the variant,
+    /// alongside the fields of the variant, with durations in seconds.
+    ///
+    /// Events are written this way even as ECS documents.
+    #[cfg(feature = "time")]
+    pub fn write_event(&mut self, event: &crate::Event) -> io::Result<()> {
+        self.write_object(event_object(event))
+    }
+
+    /// Write a failed login attempt as an object with a `type` field of `FailedLogin`,
+    /// alongside the fields of the attempt.
+    #[cfg(feature = "time")]
+    pub fn write_failed_login(&mut self, attempt: &crate::FailedLogin) -> io::Result<()> {
+        let mut object = Object::default();
+        object.string("type", Some("FailedLogin"));
+        object.string("user", Some(&attempt.user));
+        object.string("line", Some(&attempt.line));
+        object.string("host", Some(&attempt.host));
+        object.number("pid", Some(attempt.pid));
+        object.string("time", Some(&format_rfc3339(attempt.time)));
+        self.write_object(object)
+    }
+
+    fn write_object(&mut self, object: Object) -> io::Result<()> {
+        let object = object.render(self.pretty, 1);
+        if !self.pretty {
+            writeln!(self.writer, "{}", object)?;
+        } else if self.count == 0 {
+            write!(self.writer, "[\n  {}", object)?;
+        } else {
+            write!(self.writer, ",\n  {}", object)?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Write all the given entries.
+    pub fn write_entries<'a, I>(&mut self, entries: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a UtmpEntry>,
+    {
+        entries
+            .into_iter()
+            .try_for_each(|entry| self.write_entry(entry))
+    }
+
+    /// Close the array if pretty-printing, flush and return the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.close()?;
+        Ok(self.writer)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if self.pretty && !self.closed {
+            let end = if self.count == 0 { "[]\n" } else { "\n]\n" };
+            self.writer.write_all(end.as_bytes())?;
+            self.closed = true;
+        }
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> EntrySink for JsonWriter<W> {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        self.write_entry(entry)
+    }
+
+    /// Close the array if pretty-printing, and flush the underlying writer.
+    ///
+    /// No entry should be written afterwards.
+    fn finish(&mut self) -> io::Result<()> {
+        self.close()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Schema {
+    Native,
+    Ecs { failures: bool },
+}
+
+/// Version of ECS which documents conform to.
+const ECS_VERSION: &str = "8.11.0";
+
+/// Object of the entry with the same fields as the `serde` feature produces.
+fn entry_object(entry: &UtmpEntry) -> Object {
+    let f = Fields::of(entry);
+    let mut object = Object::default();
+    object.string("type", Some(f.kind));
+    object.number("ut_type", f.ut_type);
+    object.number("pid", f.pid);
+    object.string("level", f.level.map(String::from).as_deref());
+    object.string("previous", f.previous.map(String::from).as_deref());
+    object.string("kernel_version", f.kernel_version);
+    object.string("line", f.line);
+    object.string("id", f.id);
+    object.string("user", f.user);
+    object.string("host", f.host);
+    object.string("address", f.address.map(|a| a.to_string()).as_deref());
+    object.number("session", f.session);
+    object.number("termination", f.termination);
+    object.number("exit", f.exit);
+    object.string("time", f.time.map(format_rfc3339).as_deref());
+    object
+}
+
+/// ECS document of the entry.
+#[cfg(feature = "time")]
+fn event_object(event: &crate::Event) -> Object {
+    use crate::Event;
+
+    let mut object = Object::default();
+    match event {
+        Event::UserLoggedIn {
+            user,
+            line,
+            host,
+            time,
+        } => {
+            object.string("type", Some("UserLoggedIn"));
+            object.string("user", Some(user));
+            object.string("line", Some(line));
+            object.string("host", Some(host));
+            object.string("time", Some(&format_rfc3339(*time)));
+        }
+        Event::UserLoggedOut {
+            user,
+            line,
+            host,
+            time,
+            duration,
+        } => {
+            object.string("type", Some("UserLoggedOut"));
+            object.string("user", Some(user));
+            object.string("line", Some(line));
+            object.string("host", Some(host));
+            object.string("time", Some(&format_rfc3339(*time)));
+            object.number("duration", Some(duration.whole_seconds()));
+        }
+        Event::SystemBooted {
+            kernel_version,
+            time,
+        } => {
+            object.string("type", Some("SystemBooted"));
+            object.string("kernel_version", Some(kernel_version));
+            object.string("time", Some(&format_rfc3339(*time)));
+        }
+        Event::SystemShutdown {
+            kernel_version,
+            time,
+        } => {
+            object.string("type", Some("SystemShutdown"));
+            object.string("kernel_version", Some(kernel_version));
+            object.string("time", Some(&format_rfc3339(*time)));
+        }
+    }
+    object
+}
+
+fn ecs_object(entry: &UtmpEntry, failures: bool) -> Object {
+    let f = Fields::of(entry);
+    let login_outcome = if failures { "failure" } else { "success" };
+    let (categories, types, action, outcome): (&[&str], &[&str], _, _) = match entry {
+        UtmpEntry::UserProcess { .. } => (
+            &["authentication", "session"],
+            &["start"],
+            "user-login",
+            Some(login_outcome),
+        ),
+        UtmpEntry::LoginProcess { .. } if failures => (
+            &["authentication"],
+            &["start"],
+            "user-login",
+            Some(login_outcome),
+        ),
+        UtmpEntry::LoginProcess { .. } => (&["process"], &["start"], "login-prompt", None),
+        UtmpEntry::DeadProcess { .. } => (&["session"], &["end"], "user-logout", None),
+        UtmpEntry::InitProcess { .. } => (&["process"], &["start"], "init-process", None),
+        UtmpEntry::BootTime { .. } => (&["host"], &["start"], "system-boot", None),
+        UtmpEntry::ShutdownTime { .. } => (&["host"], &["end"], "system-shutdown", None),
+        UtmpEntry::RunLevel { .. } => (&["host"], &["change"], "runlevel-change", None),
+        UtmpEntry::NewTime(_) | UtmpEntry::OldTime(_) => {
+            (&["host"], &["change"], "clock-change", None)
+        }
+        UtmpEntry::Empty | UtmpEntry::Accounting | UtmpEntry::Unknown { .. } => {
+            (&[], &[], "utmp-record", None)
+        }
+    };
+
+    let mut object = Object::default();
+    object.string("@timestamp", f.time.map(format_rfc3339).as_deref());
+    let mut ecs = Object::default();
+    ecs.string("version", Some(ECS_VERSION));
+    object.object("ecs", ecs);
+
+    let mut event = Object::default();
+    event.string("kind", Some("event"));
+    event.strings("category", categories);
+    event.strings("type", types);
+    event.string("action", Some(action));
+    event.string("outcome", outcome);
+    object.object("event", event);
+
+    let mut user = Object::default();
+    user.string("name", f.user.filter(|user| !user.is_empty()));
+    object.object("user", user);
+
+    // Hosts of remote logins may be numeric, or names with the display of X sessions.
+    let host = f.host.filter(|host| !host.is_empty());
+    let address = f.address.or_else(|| host?.parse().ok());
+    let mut source = Object::default();
+    source.string("ip", address.map(|a| a.to_string()).as_deref());
+    source.string(
+        "domain",
+        host.filter(|host| host.parse::<IpAddr>().is_err()),
+    );
+    object.object("source", source);
+
+    let mut process = Object::default();
+    process.number("pid", f.pid.filter(|&pid| pid != 0));
+    object.object("process", process);
+
+    let mut os = Object::default();
+    os.string("kernel", f.kernel_version);
+    let mut host = Object::default();
+    host.object("os", os);
+    object.object("host", host);
+
+    let mut utmp = Object::default();
+    utmp.string("type", Some(f.kind));
+    utmp.number("ut_type", f.ut_type);
+    utmp.string("line", f.line.filter(|line| !line.is_empty()));
+    utmp.string("id", f.id.filter(|id| !id.is_empty()));
+    utmp.number("session", f.session);
+    utmp.number("termination", f.termination);
+    utmp.number("exit", f.exit);
+    utmp.string("level", f.level.map(String::from).as_deref());
+    utmp.string("previous", f.previous.map(String::from).as_deref());
+    object.object("utmp", utmp);
+    object
+}
+
+/// Members of a JSON object, with absent ones omitted.
+#[derive(Default)]
+struct Object {
+    members: Vec<(&'static str, Value)>,
+}
+
+enum Value {
+    /// Value already formatted as JSON
+    Json(String),
+    Object(Object),
+}
+
+impl Object {
+    fn string(&mut self, name: &'static str, value: Option<&str>) {
+        if let Some(value) = value {
+            self.members.push((name, Value::Json(json_string(value))));
+        }
+    }
+
+    fn number<T: Into<i64>>(&mut self, name: &'static str, value: Option<T>) {
+        if let Some(value) = value {
+            let value = value.into().to_string();
+            self.members.push((name, Value::Json(value)));
+        }
+    }
+
+    /// Add an array of strings, omitted if empty.
+    fn strings(&mut self, name: &'static str, values: &[&str]) {
+        if !values.is_empty() {
+            let values = values.iter().map(|value| json_string(value));
+            let array = format!("[{}]", values.collect::<Vec<_>>().join(","));
+            self.members.push((name, Value::Json(array)));
+        }
+    }
+
+    /// Add a nested object, omitted if empty.
+    fn object(&mut self, name: &'static str, object: Object) {
+        if !object.members.is_empty() {
+            self.members.push((name, Value::Object(object)));
+        }
+    }
+
+    /// Format the object, indented as a value at the given depth if pretty.
+    fn render(&self, pretty: bool, depth: usize) -> String {
+        let members = self.members.iter().map(|(name, value)| {
+            let value = match value {
+                Value::Json(value) => value.clone(),
+                Value::Object(object) => object.render(pretty, depth + 1),
+            };
+            if pretty {
+                format!("{}\"{}\": {}", "  ".repeat(depth + 1), name, value)
+            } else {
+                format!("\"{}\":{}", name, value)
+            }
+        });
+        let members = members.collect::<Vec<_>>();
+        if pretty {
+            format!("{{\n{}\n{}}}", members.join(",\n"), "  ".repeat(depth))
+        } else {
+            format!("{{{}}}", members.join(","))
+        }
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len() + 2);
+    result.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c < ' ' => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
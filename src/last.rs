@@ -0,0 +1,415 @@
+use crate::session::{sessions, Session, SessionEnd};
+use crate::timestamp::datetime;
+use crate::{ParseError, Timestamp, UtmpEntry};
+use std::borrow::Borrow;
+use std::fmt;
+use std::path::Path;
+use time::Duration;
+
+/// Entry listed by `last`, i.e. a session or a pseudo-entry of the system going up or down.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LastEntry {
+    /// Login session
+    Session(Session),
+    /// System boot, listed with user `reboot` by `last(1)`
+    Reboot {
+        /// Kernel version
+        kernel_version: String,
+        /// Time of boot
+        time: Timestamp,
+        /// Time the system went down afterwards, or `None` if it's still running
+        until: Option<Timestamp>,
+    },
+    /// System shutdown, listed with user `shutdown` by `last -x`
+    Shutdown {
+        /// Kernel version
+        kernel_version: String,
+        /// Time of shutdown
+        time: Timestamp,
+        /// Time of the next boot, or `None` if the system hasn't booted again
+        until: Option<Timestamp>,
+    },
+}
+
+impl LastEntry {
+    /// Time the entry starts, by which entries are listed.
+    pub fn time(&self) -> Timestamp {
+        match self {
+            LastEntry::Session(session) => session.login,
+            LastEntry::Reboot { time, .. } | LastEntry::Shutdown { time, .. } => *time,
+        }
+    }
+}
+
+/// List sessions, boots and shutdowns from the given wtmp entries newest first, like `last(1)`.
+///
+/// Entries are expected to be in order of time, as in wtmp.
+/// Sessions not ended in them are listed with `SessionEnd::Missing`,
+/// see `last_from_path` to list them as still logged in.
+/// See `sessions` for how sessions are reconstructed.
+pub fn last<I>(entries: I) -> Vec<LastEntry>
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    last_at(entries, None)
+}
+
+/// List entries newest first, treating them as the live wtmp at `now` if given.
+fn last_at<I>(entries: I, now: Option<Timestamp>) -> Vec<LastEntry>
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    let mut result = Vec::new();
+    let mut system = Vec::new();
+    let entries = entries.into_iter().inspect(|entry| match entry.borrow() {
+        UtmpEntry::BootTime {
+            kernel_version,
+            time,
+        } => {
+            end_system(&mut result, &mut system, *time);
+            system.push(LastEntry::Reboot {
+                kernel_version: kernel_version.clone(),
+                time: *time,
+                until: None,
+            });
+        }
+        UtmpEntry::ShutdownTime {
+            kernel_version,
+            time,
+        } => {
+            end_system(&mut result, &mut system, *time);
+            system.push(LastEntry::Shutdown {
+                kernel_version: kernel_version.clone(),
+                time: *time,
+                until: None,
+            });
+        }
+        _ => {}
+    });
+    let sessions = sessions(entries);
+    let sessions = match now {
+        Some(now) => sessions.live_at(now),
+        None => sessions,
+    };
+    let sessions = sessions.map(LastEntry::Session).collect::<Vec<_>>();
+    result.extend(system);
+    result.extend(sessions);
+    // Entries starting at the same time are listed in reverse order as well.
+    result.reverse();
+    result.sort_by_key(|entry| std::cmp::Reverse(entry.time()));
+    result
+}
+
+/// End the pending boot or shutdown at the given time.
+fn end_system(result: &mut Vec<LastEntry>, system: &mut Vec<LastEntry>, time: Timestamp) {
+    for mut entry in system.drain(..) {
+        match &mut entry {
+            LastEntry::Reboot { until, .. } | LastEntry::Shutdown { until, .. } => {
+                *until = Some(time)
+            }
+            LastEntry::Session(_) => {}
+        }
+        result.push(entry);
+    }
+}
+
+/// List entries of the given wtmp file newest first, like `last(1)`.
+///
+/// It parses the file using the native utmp format in the target platform.
+/// Unlike `last`, the file is assumed to be the live wtmp of the running system,
+/// so sessions not ended in it are listed with `SessionEnd::LoggedIn`,
+/// or with `SessionEnd::Gone` if their process no longer exists on Unix.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for entry in utmp_rs::last_from_path("/var/log/wtmp")? {
+///     println!("{}", entry);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn last_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<LastEntry>, ParseError> {
+    let entries = crate::parse_from_path(path)?;
+    let mut result = last_at(entries, Some(Timestamp::now()));
+    for entry in &mut result {
+        if let LastEntry::Session(session) = entry {
+            if session.end == SessionEnd::LoggedIn && !process_exists(session.pid) {
+                session.end = SessionEnd::Gone;
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Failed login attempt recorded in btmp.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct FailedLogin {
+    /// Username attempted
+    pub user: String,
+    /// Device name of tty, e.g. `ssh:notty` for SSH
+    pub line: String,
+    /// Hostname the attempt came from
+    pub host: String,
+    /// PID of the login process
+    pub pid: utmp_raw::pid_t,
+    /// Time of the attempt
+    pub time: Timestamp,
+}
+
+/// List failed login attempts from the given btmp entries newest first, like `lastb(1)`.
+///
+/// Entries are expected to be in order of time, as in btmp.
+/// Attempts are taken from `LoginProcess` and `UserProcess` entries,
+/// since programs record them with either type.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for attempt in utmp_rs::failed_logins(utmp_rs::parse_from_path("/var/log/btmp")?) {
+///     println!("{}", attempt);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn failed_logins<I>(entries: I) -> Vec<FailedLogin>
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    let mut result = entries
+        .into_iter()
+        .filter_map(|entry| match entry.borrow() {
+            UtmpEntry::LoginProcess {
+                pid,
+                line,
+                user,
+                host,
+                time,
+                ..
+            }
+            | UtmpEntry::UserProcess {
+                pid,
+                line,
+                user,
+                host,
+                time,
+                ..
+            } => Some(FailedLogin {
+                user: user.clone(),
+                line: line.clone(),
+                host: host.clone(),
+                pid: *pid,
+                time: *time,
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    result.reverse();
+    result.sort_by_key(|attempt| std::cmp::Reverse(attempt.time));
+    result
+}
+
+#[cfg(unix)]
+fn process_exists(pid: utmp_raw::pid_t) -> bool {
+    // Signal 0 only checks whether the process can be signaled.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+#[cfg(not(unix))]
+fn process_exists(_pid: utmp_raw::pid_t) -> bool {
+    true
+}
+
+/// Entries are formatted like the output of `last(1)`,
+/// e.g. `root     pts/0        112.124.2.209    Tue Feb  7 08:07 - 08:49  (00:40)`.
+///
+/// Times are formatted in UTC.
+/// See `LastLine` for other options of `last(1)`.
+impl fmt::Display for LastEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        LastLine::new(self).fmt(f)
+    }
+}
+
+/// Entry formatted like a line of `last(1)` with options for its flags,
+/// e.g. `root     pts/0        112.124.2.209    Tue Feb  7 08:07:06 2023 - Tue Feb  7 08:49:01 2023  (00:41)`
+/// with `full_times`.
+///
+/// ```
+/// # use utmp_rs::LastLine;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for entry in utmp_rs::last_from_path("/var/log/wtmp")? {
+///     println!("{}", LastLine::new(&entry).full_times(true).ips(true));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct LastLine<'a> {
+    entry: &'a LastEntry,
+    full_times: bool,
+    ips: bool,
+}
+
+impl<'a> LastLine<'a> {
+    /// Format the entry as `LastEntry` does by default.
+    pub fn new(entry: &'a LastEntry) -> Self {
+        LastLine {
+            entry,
+            full_times: false,
+            ips: false,
+        }
+    }
+
+    /// Set whether times are in full with seconds and years, like `last -F`.
+    pub fn full_times(mut self, full_times: bool) -> Self {
+        self.full_times = full_times;
+        self
+    }
+
+    /// Set whether sessions show their addresses in place of their hosts, like `last -i`.
+    ///
+    /// Sessions without an address still show their hosts.
+    pub fn ips(mut self, ips: bool) -> Self {
+        self.ips = ips;
+        self
+    }
+}
+
+impl fmt::Display for LastLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let full = self.full_times;
+        let address;
+        let (user, line, host, start, end) = match self.entry {
+            LastEntry::Session(s) => {
+                let end = match s.end {
+                    SessionEnd::LoggedIn => End::Text("still logged in"),
+                    SessionEnd::Gone | SessionEnd::Missing => End::Text("gone - no logout"),
+                    SessionEnd::Shutdown => End::State("down ", s.duration),
+                    SessionEnd::Crash => End::State("crash", s.duration),
+                    _ => match s.logout {
+                        Some(logout) => End::Time(logout, s.duration),
+                        None => End::Text(""),
+                    },
+                };
+                let host = match s.address {
+                    Some(a) if self.ips => {
+                        address = a.to_string();
+                        &*address
+                    }
+                    _ => &*s.host,
+                };
+                (&*s.user, &*s.line, host, s.login, end)
+            }
+            LastEntry::Reboot {
+                kernel_version,
+                time,
+                until,
+            } => {
+                let end = system_end(*time, *until, "still running");
+                ("reboot", "system boot", &**kernel_version, *time, end)
+            }
+            LastEntry::Shutdown {
+                kernel_version,
+                time,
+                until,
+            } => {
+                let end = system_end(*time, *until, "");
+                ("shutdown", "system down", &**kernel_version, *time, end)
+            }
+        };
+        let start = LastTime(start, full);
+        let head = format!("{:<8} {:<12} {:<16} {}", user, line, host, start);
+        match end {
+            End::Text("") => f.write_str(&head),
+            End::Text(text) => write!(f, "{}   {}", head, text),
+            End::Time(time, duration) if full => {
+                write!(f, "{} - {}", head, LastTime(time, true))?;
+                write_duration(f, duration)
+            }
+            End::Time(time, duration) => {
+                let time = datetime(time);
+                let (hour, minute) = (time.hour(), time.minute());
+                write!(f, "{} - {:02}:{:02}", head, hour, minute)?;
+                write_duration(f, duration)
+            }
+            End::State(state, duration) => {
+                write!(f, "{} - {}", head, state)?;
+                write_duration(f, duration)
+            }
+        }
+    }
+}
+
+/// Attempts are formatted like the output of `lastb(1)`,
+/// e.g. `root     ssh:notty    112.124.2.209    Tue Feb  7 08:07 - 08:07  (00:00)`.
+///
+/// Times are formatted in UTC.
+impl fmt::Display for FailedLogin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let time = LastTime(self.time, false);
+        let (user, line, host) = (&self.user, &self.line, &self.host);
+        write!(f, "{:<8} {:<12} {:<16} {}", user, line, host, time)?;
+        let time = datetime(self.time);
+        let (hour, minute) = (time.hour(), time.minute());
+        write!(f, " - {:02}:{:02}", hour, minute)?;
+        write_duration(f, Some(Duration::ZERO))
+    }
+}
+
+enum End {
+    Text(&'static str),
+    Time(Timestamp, Option<Duration>),
+    State(&'static str, Option<Duration>),
+}
+
+fn system_end(time: Timestamp, until: Option<Timestamp>, running: &'static str) -> End {
+    match until {
+        Some(until) => End::Time(until, Some(until - time)),
+        None => End::Text(running),
+    }
+}
+
+/// Duration formatted like `last(1)` does, i.e. `(HH:MM)` or `(D+HH:MM)`.
+fn write_duration(f: &mut fmt::Formatter<'_>, duration: Option<Duration>) -> fmt::Result {
+    let minutes = match duration {
+        Some(duration) => duration.whole_minutes().max(0),
+        None => return Ok(()),
+    };
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        write!(f, " ({}+{:02}:{:02})", days, hours, minutes)
+    } else {
+        write!(f, "  ({:02}:{:02})", hours, minutes)
+    }
+}
+
+/// Time formatted like `last(1)` does, i.e. `Tue Feb  7 08:07`,
+/// or `Tue Feb  7 08:07:06 2023` in full.
+struct LastTime(Timestamp, bool);
+
+impl fmt::Display for LastTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let LastTime(time, full) = *self;
+        let time = datetime(time);
+        let weekday = time.weekday().to_string();
+        let month = time.month().to_string();
+        write!(
+            f,
+            "{} {} {:>2} {:02}:{:02}",
+            &weekday[..3],
+            &month[..3],
+            time.day(),
+            time.hour(),
+            time.minute(),
+        )?;
+        if full {
+            write!(f, ":{:02} {}", time.second(), time.year())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+use crate::entry_ref::{str_from_bytes, trim_nul};
+use crate::raw::RawUtmp;
+use crate::timestamp::time_from_tv;
+use crate::{Timestamp, UtmpEntry, UtmpEntryRef, UtmpError};
+use std::convert::TryFrom;
+use utmp_raw::x32::utmp as utmp32;
+use utmp_raw::x64::utmp as utmp64;
+use utmp_raw::{c_short, pid_t};
+
+/// Utmp record which is decoded only when its fields are accessed.
+///
+/// It keeps a copy of the raw record,
+/// so filtering by type or time doesn't need to validate any string.
+/// The `*_bytes` accessors give the exact bytes of string fields,
+/// even if they are not valid UTF-8.
+///
+/// ```
+/// # use utmp_rs::UtmpParser;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut parser = UtmpParser::from_path("/var/run/utmp")?;
+/// while let Some(entry) = parser.next_lazy() {
+///     let entry = entry?;
+///     if entry.ut_type() == utmp_raw::USER_PROCESS {
+///         println!("{}", entry.user()?);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct LazyEntry {
+    raw: Raw,
+}
+
+#[derive(Clone, Copy)]
+enum Raw {
+    X32(utmp32),
+    X64(utmp64),
+}
+
+impl LazyEntry {
+    fn raw(&self) -> &dyn RawFields {
+        match &self.raw {
+            Raw::X32(raw) => raw,
+            Raw::X64(raw) => raw,
+        }
+    }
+
+    /// Type of the record, which is one of the constants in `utmp_raw`.
+    pub fn ut_type(&self) -> c_short {
+        self.raw().ut_type()
+    }
+
+    /// PID of the process of the record.
+    pub fn pid(&self) -> pid_t {
+        self.raw().ut_pid()
+    }
+
+    /// Time of the record.
+    pub fn time(&self) -> Result<Timestamp, UtmpError> {
+        time_from_tv(self.raw().ut_tv())
+    }
+
+    /// Device name of tty.
+    pub fn line(&self) -> Result<&str, UtmpError> {
+        str_from_bytes(self.raw().ut_line()).map_err(UtmpError::InvalidLine)
+    }
+
+    /// Terminal name suffix, or `inittab(5)` ID.
+    pub fn id(&self) -> Result<&str, UtmpError> {
+        str_from_bytes(self.raw().ut_id()).map_err(UtmpError::InvalidId)
+    }
+
+    /// Username.
+    pub fn user(&self) -> Result<&str, UtmpError> {
+        str_from_bytes(self.raw().ut_user()).map_err(UtmpError::InvalidUser)
+    }
+
+    /// Hostname for remote login, or kernel version for some types of records.
+    pub fn host(&self) -> Result<&str, UtmpError> {
+        str_from_bytes(self.raw().ut_host()).map_err(UtmpError::InvalidHost)
+    }
+
+    /// Bytes of `line`, up to the first NUL, without validating them.
+    pub fn line_bytes(&self) -> &[u8] {
+        trim_nul(self.raw().ut_line())
+    }
+
+    /// Bytes of `id`, up to the first NUL, without validating them.
+    pub fn id_bytes(&self) -> &[u8] {
+        trim_nul(self.raw().ut_id())
+    }
+
+    /// Bytes of `user`, up to the first NUL, without validating them.
+    pub fn user_bytes(&self) -> &[u8] {
+        trim_nul(self.raw().ut_user())
+    }
+
+    /// Bytes of `host`, up to the first NUL, without validating them.
+    pub fn host_bytes(&self) -> &[u8] {
+        trim_nul(self.raw().ut_host())
+    }
+
+    /// Decode the whole record borrowing strings from it.
+    pub fn decode_ref(&self) -> Result<UtmpEntryRef<'_>, UtmpError> {
+        match &self.raw {
+            Raw::X32(raw) => UtmpEntryRef::try_from(raw),
+            Raw::X64(raw) => UtmpEntryRef::try_from(raw),
+        }
+    }
+
+    /// Decode the whole record.
+    pub fn decode(&self) -> Result<UtmpEntry, UtmpError> {
+        self.decode_ref().map(|entry| entry.to_owned())
+    }
+}
+
+impl From<utmp32> for LazyEntry {
+    fn from(raw: utmp32) -> Self {
+        LazyEntry { raw: Raw::X32(raw) }
+    }
+}
+
+impl From<utmp64> for LazyEntry {
+    fn from(raw: utmp64) -> Self {
+        LazyEntry { raw: Raw::X64(raw) }
+    }
+}
+
+impl TryFrom<&LazyEntry> for UtmpEntry {
+    type Error = UtmpError;
+
+    fn try_from(entry: &LazyEntry) -> Result<Self, UtmpError> {
+        entry.decode()
+    }
+}
+
+/// Object-safe subset of `RawUtmp`.
+trait RawFields {
+    fn ut_type(&self) -> c_short;
+    fn ut_pid(&self) -> pid_t;
+    fn ut_tv(&self) -> utmp_raw::x64::timeval;
+    fn ut_line(&self) -> &[u8];
+    fn ut_id(&self) -> &[u8];
+    fn ut_user(&self) -> &[u8];
+    fn ut_host(&self) -> &[u8];
+}
+
+impl<T: RawUtmp> RawFields for T {
+    fn ut_type(&self) -> c_short {
+        RawUtmp::ut_type(self)
+    }
+
+    fn ut_pid(&self) -> pid_t {
+        RawUtmp::ut_pid(self)
+    }
+
+    fn ut_tv(&self) -> utmp_raw::x64::timeval {
+        RawUtmp::ut_tv(self)
+    }
+
+    fn ut_line(&self) -> &[u8] {
+        RawUtmp::ut_line(self)
+    }
+
+    fn ut_id(&self) -> &[u8] {
+        RawUtmp::ut_id(self)
+    }
+
+    fn ut_user(&self) -> &[u8] {
+        RawUtmp::ut_user(self)
+    }
+
+    fn ut_host(&self) -> &[u8] {
+        RawUtmp::ut_host(self)
+    }
+}
@@ -30,11 +30,151 @@
 //! All the `parse_from_*` functions as well as `UtmpParser` parse `utmp` file
 //! based on the native format for the target platform.
 //! If cross-platform parsing is needed,
-//! `Utmp32Parser` or `Utmp64Parser` can be used instead of `UtmpParser`.
+//! `Utmp32Parser` or `Utmp64Parser` can be used instead of `UtmpParser`,
+//! or `UtmpParser::builder()` can be used to choose the format and byte order at runtime.
+//!
+//! Parsing works on any platform, including Windows and WebAssembly,
+//! so files copied from Linux systems can be analysed elsewhere.
+//! Integrations with the running system, i.e. `Mmap`, `StaleChecker`, `TtyChecker` and `UtmpWatcher`,
+//! are only available on Unix or Linux.
+//!
+//! Parsing never panics, whatever the content and the alignment of the input,
+//! so untrusted files can be parsed safely.
+//! Invalid records are reported as errors instead.
+//!
+//! ## Optional features
+//!
+//! * `time` (default): conversions of `Timestamp` from and to `time::OffsetDateTime`,
+//!   as well as `EventInterpreter`, `sessions`, `last`, `failed_logins`,
+//!   `BootHistory`, `Timeline`, `LoginHistogram`, `BruteForceDetector`, `BusinessHours`,
+//!   `ConcurrentLoginAuditor`, `StaleChecker` and `TtyChecker`.
+//! * `notify`: `UtmpWatcher` to wait for new entries written to utmp files.
+//! * `dns`: `Resolver` to resolve hosts of sessions with DNS on Unix.
+//! * `syslog`: `SyslogSink` to forward events to syslog endpoints as RFC 5424 messages.
+//! * `mmap`: `UtmpParser::from_mmap` to parse files from memory mappings without copying.
+//! * `serde`: `Serialize` and `Deserialize` implementations for `UtmpEntry`.
+//! * `cli`: the `utmp` command line tool, with `dump`, `who`, `last`, `lastb`, `follow`, `convert` and `stats`.
+//! * `testing`: `testing::UtmpFileBuilder` to generate utmp files as fixtures for tests,
+//!   and `testing::inject_defect` to corrupt them.
 
+mod anonymize;
+#[cfg(feature = "time")]
+mod boot;
+#[cfg(feature = "time")]
+mod brute_force;
+mod builder;
+mod cidr;
+#[cfg(feature = "time")]
+mod concurrent;
+mod convert;
+mod csv;
+#[cfg(all(feature = "dns", unix))]
+mod dns;
+mod dump;
+mod enrich;
 mod entry;
+mod entry_ref;
+#[cfg(feature = "time")]
+mod event;
+mod fields;
+#[cfg(feature = "time")]
+mod histogram;
+#[cfg(feature = "time")]
+mod hours;
+mod integrity;
+mod json;
+#[cfg(feature = "time")]
+mod last;
+mod lazy;
+mod log;
+mod merge;
+mod metrics;
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+mod new_host;
 mod parse;
+mod query;
+mod raw;
+mod rotated;
+#[cfg(feature = "serde")]
+mod serde;
+#[cfg(feature = "time")]
+mod session;
+mod sink;
+#[cfg(all(feature = "time", target_os = "linux"))]
+mod stale;
+mod state;
+mod summary;
+#[cfg(feature = "syslog")]
+mod syslog;
+mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "time")]
+mod timeline;
+mod timestamp;
+#[cfg(all(feature = "time", unix))]
+mod tty;
+#[cfg(all(feature = "notify", target_os = "linux"))]
+mod watch;
 
-pub use entry::{UtmpEntry, UtmpError};
-pub use parse::{parse_from_file, parse_from_path, parse_from_reader};
-pub use parse::{ParseError, Utmp32Parser, Utmp64Parser, UtmpParser};
+pub use anonymize::{Anonymizer, Redaction};
+#[cfg(feature = "time")]
+pub use boot::{Boot, BootHistory};
+#[cfg(feature = "time")]
+pub use brute_force::{BruteForce, BruteForceDetector};
+pub use builder::{AnyUtmpParser, Endianness, ErrorPolicy, Format, UtmpParserBuilder};
+pub use cidr::{Cidr, ParseCidrError};
+#[cfg(feature = "time")]
+pub use concurrent::{ConcurrentLogin, ConcurrentLoginAuditor, ConcurrentLoginKind};
+pub use convert::{convert, ConvertError, Converter};
+pub use csv::{write_entries_csv, CsvWriter, ENTRY_COLUMNS};
+#[cfg(feature = "time")]
+pub use csv::{write_sessions_csv, SESSION_COLUMNS};
+#[cfg(all(feature = "dns", unix))]
+pub use dns::Resolver;
+pub use dump::{DumpError, UtmpDump};
+pub use enrich::{enrich, Enrich, Enriched, Enricher, HasRemote, Remote};
+pub use entry::{UtmpEntry, UtmpEntryKind, UtmpError};
+pub use entry_ref::UtmpEntryRef;
+#[cfg(feature = "time")]
+pub use event::{interpret_events, Event, EventInterpreter, EventSink};
+#[cfg(feature = "time")]
+pub use histogram::LoginHistogram;
+#[cfg(feature = "time")]
+pub use hours::{BusinessHours, OutOfHoursReport, OutOfHoursUser};
+pub use integrity::{Anomaly, AnomalyKind, IntegrityChecker};
+pub use json::JsonWriter;
+#[cfg(feature = "time")]
+pub use last::{failed_logins, last, last_from_path, FailedLogin, LastEntry, LastLine};
+pub use lazy::LazyEntry;
+pub use log::UtmpLog;
+pub use merge::{merge, Merge};
+pub use metrics::Metrics;
+#[cfg(all(feature = "mmap", unix))]
+pub use mmap::Mmap;
+pub use new_host::{NewHost, NewHostDetector};
+pub use parse::{parse_from_bytes, parse_from_file, parse_from_path, parse_from_reader};
+pub use parse::{ByteSlice, EntriesBetween, ParseError, RawEntries};
+pub use parse::{Utmp32Parser, Utmp64Parser, UtmpParser};
+pub use query::Query;
+pub use rotated::{Decoder, Rotated32Parser, Rotated64Parser, RotatedParser};
+#[cfg(feature = "time")]
+pub use session::{current_users, diff, sessions, Session, SessionEnd, Sessions, UtmpDiff};
+#[cfg(feature = "time")]
+pub use sink::SessionSink;
+pub use sink::{EntrySink, FanOut};
+#[cfg(all(feature = "time", target_os = "linux"))]
+pub use stale::{StaleChecker, Staleness};
+pub use state::UtmpState;
+pub use summary::Summary;
+#[cfg(feature = "syslog")]
+pub use syslog::SyslogSink;
+pub use template::{Template, TemplateError};
+#[cfg(feature = "time")]
+pub use timeline::{Bucket, Step, Timeline};
+pub use timestamp::Timestamp;
+#[cfg(all(feature = "time", unix))]
+pub use tty::{TtyChecker, TtyStatus, WhoLine};
+#[cfg(all(feature = "notify", target_os = "linux"))]
+pub use watch::{Source, UtmpWatcher, WatchEvent};
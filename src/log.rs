@@ -0,0 +1,184 @@
+use crate::{Query, Timestamp, UtmpEntry};
+use std::collections::{BTreeSet, HashMap};
+use std::iter::FromIterator;
+use std::ops::Bound;
+use std::slice;
+use utmp_raw::pid_t;
+
+/// Collection of utmp entries indexed by pid, tty line, username and time.
+///
+/// ```
+/// # use utmp_rs::UtmpLog;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let log: UtmpLog = utmp_rs::parse_from_path("/var/run/utmp")?.into_iter().collect();
+/// for entry in log.entries_for_user("root") {
+///     // handle entry
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct UtmpLog {
+    entries: Vec<UtmpEntry>,
+    by_pid: HashMap<pid_t, Vec<usize>>,
+    by_line: HashMap<String, Vec<usize>>,
+    by_user: HashMap<String, Vec<usize>>,
+    /// Times and indices of entries with a time, in order of time, then of index.
+    by_time: BTreeSet<(Timestamp, usize)>,
+}
+
+impl UtmpLog {
+    /// Create an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry to the log.
+    pub fn push(&mut self, entry: UtmpEntry) {
+        let index = self.entries.len();
+        if let Some(pid) = entry.pid() {
+            self.by_pid.entry(pid).or_default().push(index);
+        }
+        if let Some(line) = entry_line(&entry) {
+            self.by_line.entry(line.to_owned()).or_default().push(index);
+        }
+        if let Some(user) = entry_user(&entry) {
+            self.by_user.entry(user.to_owned()).or_default().push(index);
+        }
+        if let Some(time) = entry.time() {
+            // Entries with the same time are kept in the order they are added.
+            self.by_time.insert((time, index));
+        }
+        self.entries.push(entry);
+    }
+
+    /// Number of entries in the log.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the log contains no entry.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries in the order they were added.
+    pub fn entries(&self) -> &[UtmpEntry] {
+        &self.entries
+    }
+
+    /// Entries with a time, in order of time.
+    pub fn entries_by_time(&self) -> impl Iterator<Item = &UtmpEntry> {
+        self.resolve(self.by_time.iter().map(|(_, index)| index))
+    }
+
+    /// Entries of processes with the given pid.
+    pub fn entries_for_pid(&self, pid: pid_t) -> impl Iterator<Item = &UtmpEntry> {
+        self.resolve(self.by_pid.get(&pid).map_or(&[][..], Vec::as_slice))
+    }
+
+    /// Entries on the given tty line.
+    pub fn entries_for_line(&self, line: &str) -> impl Iterator<Item = &UtmpEntry> {
+        self.resolve(self.by_line.get(line).map_or(&[][..], Vec::as_slice))
+    }
+
+    /// Entries of the given user.
+    pub fn entries_for_user(&self, user: &str) -> impl Iterator<Item = &UtmpEntry> {
+        self.resolve(self.by_user.get(user).map_or(&[][..], Vec::as_slice))
+    }
+
+    /// Entries matching the given query.
+    ///
+    /// Candidates are looked up in the index of the user, line or pid the query has,
+    /// whichever has the fewest entries, and yielded in the order they were added.
+    /// Otherwise, if the query has a period, entries within it are yielded in order of time.
+    pub fn query<'a>(&'a self, query: &'a Query) -> impl Iterator<Item = &'a UtmpEntry> {
+        let indexed = [
+            query.user.as_ref().map(|user| self.by_user.get(user)),
+            query.line.as_ref().map(|line| self.by_line.get(line)),
+            query.pid.as_ref().map(|pid| self.by_pid.get(pid)),
+        ];
+        let indexed = indexed
+            .iter()
+            .flatten()
+            .map(|indices| indices.map_or(&[][..], Vec::as_slice))
+            .min_by_key(|indices| indices.len());
+        // Candidates are narrowed down by the indices, or by the period, or not at all.
+        let in_period = match indexed {
+            None if query.from.is_some() || query.to.is_some() => {
+                let start = query
+                    .from
+                    .map_or(Bound::Unbounded, |from| Bound::Included((from, 0)));
+                // Ranges must not end before they start.
+                let to = query
+                    .to
+                    .map(|to| query.from.map_or(to, |from| to.max(from)));
+                let end = to.map_or(Bound::Unbounded, |to| Bound::Excluded((to, 0)));
+                Some(self.by_time.range((start, end)).map(|(_, index)| index))
+            }
+            _ => None,
+        };
+        let all = match (indexed, &in_period) {
+            (None, None) => &self.entries[..],
+            _ => &[][..],
+        };
+        let candidates = indexed
+            .into_iter()
+            .flatten()
+            .chain(in_period.into_iter().flatten());
+        (self.resolve(candidates).chain(all)).filter(move |entry| query.matches(entry))
+    }
+
+    /// Return the entries in the order they were added.
+    pub fn into_entries(self) -> Vec<UtmpEntry> {
+        self.entries
+    }
+
+    fn resolve<'a, I>(&'a self, indices: I) -> impl Iterator<Item = &'a UtmpEntry>
+    where
+        I: IntoIterator<Item = &'a usize>,
+    {
+        indices.into_iter().map(move |&i| &self.entries[i])
+    }
+}
+
+impl Extend<UtmpEntry> for UtmpLog {
+    fn extend<I: IntoIterator<Item = UtmpEntry>>(&mut self, iter: I) {
+        for entry in iter {
+            self.push(entry);
+        }
+    }
+}
+
+impl FromIterator<UtmpEntry> for UtmpLog {
+    fn from_iter<I: IntoIterator<Item = UtmpEntry>>(iter: I) -> Self {
+        let mut log = UtmpLog::new();
+        log.extend(iter);
+        log
+    }
+}
+
+impl<'a> IntoIterator for &'a UtmpLog {
+    type Item = &'a UtmpEntry;
+    type IntoIter = slice::Iter<'a, UtmpEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+pub(crate) fn entry_line(entry: &UtmpEntry) -> Option<&str> {
+    match entry {
+        UtmpEntry::LoginProcess { line, .. }
+        | UtmpEntry::UserProcess { line, .. }
+        | UtmpEntry::DeadProcess { line, .. } => Some(line),
+        _ => None,
+    }
+}
+
+pub(crate) fn entry_user(entry: &UtmpEntry) -> Option<&str> {
+    match entry {
+        UtmpEntry::LoginProcess { user, .. } | UtmpEntry::UserProcess { user, .. } => Some(user),
+        _ => None,
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{Timestamp, UtmpEntry};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Iterator merging entries of multiple sources in order of time, see `merge`.
+#[derive(Clone, Debug)]
+pub struct Merge<I> {
+    sources: Vec<I>,
+    /// Next entry of each source, if it's waiting in the heap.
+    heads: Vec<Option<UtmpEntry>>,
+    /// Time and index of the sources with a head, earliest first.
+    heap: BinaryHeap<Reverse<(Timestamp, usize)>>,
+    /// Sources to read the next entry from before yielding from the heap, last one first.
+    to_read: Vec<usize>,
+}
+
+/// Merge entries of the given sources, e.g. wtmp files from different hosts,
+/// into one iterator in order of time.
+///
+/// Each source is expected to be in order of time, as wtmp is.
+/// Entries with the same time are yielded in the order of the sources,
+/// so the result is deterministic.
+/// Entries without a time and errors are yielded as soon as they are read,
+/// and a source continues after an error if it can.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let sources = ["/var/log/wtmp.1", "/var/log/wtmp"]
+///     .iter()
+///     .map(utmp_rs::UtmpParser::from_path)
+///     .collect::<Result<Vec<_>, _>>()?;
+/// for entry in utmp_rs::merge(sources) {
+///     let entry = entry?;
+///     // handle entry
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn merge<S, I, E>(sources: S) -> Merge<I>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator<IntoIter = I>,
+    I: Iterator<Item = Result<UtmpEntry, E>>,
+{
+    let sources = sources
+        .into_iter()
+        .map(IntoIterator::into_iter)
+        .collect::<Vec<_>>();
+    Merge {
+        heads: sources.iter().map(|_| None).collect(),
+        heap: BinaryHeap::with_capacity(sources.len()),
+        to_read: (0..sources.len()).rev().collect(),
+        sources,
+    }
+}
+
+impl<I> Merge<I> {
+    /// Return the sources, which may have entries read ahead but not yielded yet.
+    pub fn into_inner(self) -> Vec<I> {
+        self.sources
+    }
+}
+
+impl<I, E> Iterator for Merge<I>
+where
+    I: Iterator<Item = Result<UtmpEntry, E>>,
+{
+    type Item = Result<UtmpEntry, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&index) = self.to_read.last() {
+            match self.sources[index].next() {
+                Some(Ok(entry)) => match entry.time() {
+                    Some(time) => {
+                        self.heads[index] = Some(entry);
+                        self.heap.push(Reverse((time, index)));
+                    }
+                    None => return Some(Ok(entry)),
+                },
+                // The source is read again for its next entry afterwards.
+                Some(Err(e)) => return Some(Err(e)),
+                None => {}
+            }
+            self.to_read.pop();
+        }
+        let Reverse((_, index)) = self.heap.pop()?;
+        self.to_read.push(index);
+        self.heads[index].take().map(Ok)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) =
+            (self.sources.iter()).fold((0usize, Some(0usize)), |(lower, upper), s| {
+                let (l, u) = s.size_hint();
+                let upper = upper.zip(u).and_then(|(a, b)| a.checked_add(b));
+                (lower.saturating_add(l), upper)
+            });
+        let heads = self.heap.len();
+        (
+            lower.saturating_add(heads),
+            upper.and_then(|u| u.checked_add(heads)),
+        )
+    }
+}
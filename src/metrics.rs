@@ -0,0 +1,171 @@
+use crate::{EntrySink, UtmpEntry};
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+
+/// Gauges and counters maintained from utmp files,
+/// rendered in the Prometheus text exposition format by `Display`.
+///
+/// Gauges are set from the current utmp, e.g. `/var/run/utmp`, with `set_current`,
+/// while counters accumulate entries of wtmp and btmp fed with `count_history` and `count_failed`,
+/// which can be fed incrementally, e.g. from `UtmpWatcher`.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut metrics = utmp_rs::Metrics::new();
+/// metrics.count_history(&utmp_rs::parse_from_path("/var/log/wtmp")?);
+/// print!("{}", metrics);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Metrics {
+    sessions: BTreeMap<String, u64>,
+    boots: u64,
+    logins: u64,
+    failed_logins: u64,
+}
+
+impl Metrics {
+    /// Create metrics without any entry observed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the gauges of logged-in users from entries of the current utmp,
+    /// replacing those set before.
+    pub fn set_current<I>(&mut self, entries: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        self.sessions.clear();
+        for entry in entries {
+            if let UtmpEntry::UserProcess { user, .. } = entry.borrow() {
+                *self.sessions.entry(user.clone()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Count boots and logins in the given entries of wtmp.
+    pub fn count_history<I>(&mut self, entries: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        for entry in entries {
+            match entry.borrow() {
+                UtmpEntry::BootTime { .. } => self.boots += 1,
+                UtmpEntry::UserProcess { .. } => self.logins += 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// Count failed logins in the given entries of btmp,
+    /// i.e. `LoginProcess` and `UserProcess` entries as `failed_logins` takes.
+    pub fn count_failed<I>(&mut self, entries: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        let failed = entries.into_iter().filter(|entry| {
+            matches!(
+                entry.borrow(),
+                UtmpEntry::LoginProcess { .. } | UtmpEntry::UserProcess { .. }
+            )
+        });
+        self.failed_logins += failed.count() as u64;
+    }
+
+    /// Number of distinct users logged in.
+    pub fn users(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Number of sessions logged in by the user.
+    pub fn sessions_of(&self, user: &str) -> u64 {
+        self.sessions.get(user).copied().unwrap_or_default()
+    }
+
+    /// Number of boots counted.
+    pub fn boots(&self) -> u64 {
+        self.boots
+    }
+
+    /// Number of logins counted.
+    pub fn logins(&self) -> u64 {
+        self.logins
+    }
+
+    /// Number of failed logins counted.
+    pub fn failed_logins(&self) -> u64 {
+        self.failed_logins
+    }
+}
+
+/// Entries are counted as wtmp ones, see `count_history`.
+impl EntrySink for Metrics {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        self.count_history(std::iter::once(entry));
+        Ok(())
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = |f: &mut fmt::Formatter<'_>, name, kind, help| {
+            writeln!(f, "# HELP {} {}", name, help)?;
+            writeln!(f, "# TYPE {} {}", name, kind)
+        };
+        header(
+            f,
+            "utmp_users",
+            "gauge",
+            "Number of distinct users logged in.",
+        )?;
+        writeln!(f, "utmp_users {}", self.users())?;
+        header(
+            f,
+            "utmp_sessions",
+            "gauge",
+            "Number of sessions logged in by user.",
+        )?;
+        for (user, count) in &self.sessions {
+            writeln!(
+                f,
+                "utmp_sessions{{user=\"{}\"}} {}",
+                label_value(user),
+                count
+            )?;
+        }
+        header(
+            f,
+            "utmp_boots_total",
+            "counter",
+            "Number of boots recorded in wtmp.",
+        )?;
+        writeln!(f, "utmp_boots_total {}", self.boots)?;
+        header(
+            f,
+            "utmp_logins_total",
+            "counter",
+            "Number of logins recorded in wtmp.",
+        )?;
+        writeln!(f, "utmp_logins_total {}", self.logins)?;
+        header(
+            f,
+            "utmp_failed_logins_total",
+            "counter",
+            "Number of failed logins recorded in btmp.",
+        )?;
+        writeln!(f, "utmp_failed_logins_total {}", self.failed_logins)
+    }
+}
+
+/// Escape the label value as the text exposition format requires.
+fn label_value(value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    value.replace('\n', "\\n")
+}
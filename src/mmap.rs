@@ -0,0 +1,73 @@
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::ptr::{self, NonNull};
+use std::slice;
+
+/// Read-only memory mapping of a whole file.
+///
+/// It is used as the input of parsers created via `from_mmap`.
+pub struct Mmap {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// The mapping is read-only, so it can be shared with other threads like `&[u8]`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Map the given file into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated while the mapping is alive,
+    /// otherwise accessing the mapping may crash the process with `SIGBUS`.
+    pub(crate) unsafe fn map(file: &File) -> io::Result<Self> {
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "file too large to map"))?;
+        // Mapping an empty file fails, and there is nothing to map anyway.
+        if len == 0 {
+            return Ok(Mmap {
+                ptr: NonNull::dangling(),
+                len,
+            });
+        }
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap {
+            ptr: NonNull::new_unchecked(ptr as *mut u8),
+            len,
+        })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // This is safe because the mapping stays valid until it's dropped.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            unsafe {
+                libc::munmap(self.ptr.as_ptr() as *mut libc::c_void, self.len);
+            }
+        }
+    }
+}
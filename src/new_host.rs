@@ -0,0 +1,164 @@
+use crate::{Timestamp, UtmpEntry};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+
+/// Detector of users logging in from hosts they have never logged in from,
+/// for lightweight alerting on compromised accounts.
+///
+/// The hosts seen so far can be saved and loaded back,
+/// so that the detector keeps its knowledge across runs.
+///
+/// ```no_run
+/// # use std::fs::File;
+/// # use std::io::BufReader;
+/// # use utmp_rs::NewHostDetector;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut detector = match File::open("hosts.state") {
+///     Ok(file) => NewHostDetector::load(BufReader::new(file))?,
+///     Err(_) => NewHostDetector::new(),
+/// };
+/// for entry in utmp_rs::parse_from_path("/var/log/wtmp")? {
+///     if let Some(new) = detector.check(&entry) {
+///         println!("{} logged in from new host {}", new.user, new.host);
+///     }
+/// }
+/// detector.save(File::create("hosts.state")?)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NewHostDetector {
+    hosts: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Login from a host the user hasn't logged in from before, reported by `NewHostDetector`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct NewHost {
+    /// User logging in
+    pub user: String,
+    /// Host the user logged in from
+    pub host: String,
+    /// Device name of tty
+    pub line: String,
+    /// Time of the login
+    pub time: Timestamp,
+    /// Number of hosts seen for the user before, with 0 for the first login of the user
+    pub known_hosts: usize,
+}
+
+impl NewHostDetector {
+    /// Create a detector which hasn't seen any host.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record hosts of logins in the entries without reporting them,
+    /// e.g. to learn from the history before alerting on new entries.
+    pub fn learn<I>(&mut self, entries: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        for entry in entries {
+            self.check(entry.borrow());
+        }
+    }
+
+    /// Record the host of the entry if it's a login from a remote host,
+    /// and return the login if the host hasn't been seen for the user.
+    pub fn check(&mut self, entry: &UtmpEntry) -> Option<NewHost> {
+        let (user, host, line, time) = match entry {
+            UtmpEntry::UserProcess {
+                user,
+                host,
+                line,
+                time,
+                ..
+            } if !host.is_empty() => (user, host, line, time),
+            _ => return None,
+        };
+        let hosts = self.hosts.entry(user.clone()).or_default();
+        if hosts.contains(host) {
+            return None;
+        }
+        hosts.insert(host.clone());
+        Some(NewHost {
+            user: user.clone(),
+            host: host.clone(),
+            line: line.clone(),
+            time: *time,
+            known_hosts: hosts.len() - 1,
+        })
+    }
+
+    /// Hosts seen for the user, in order.
+    pub fn hosts_of(&self, user: &str) -> impl Iterator<Item = &str> {
+        self.hosts
+            .get(user)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Write the hosts seen, as lines of a user and a host separated by a tab,
+    /// with tabs, newlines, carriage returns and backslashes escaped.
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        for (user, hosts) in &self.hosts {
+            for host in hosts {
+                writeln!(writer, "{}\t{}", escape(user), escape(host))?;
+            }
+        }
+        writer.flush()
+    }
+
+    /// Read hosts written by `save`.
+    pub fn load<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut detector = Self::new();
+        for line in reader.lines() {
+            let line = line?;
+            let (user, host) = line.split_once('\t').ok_or_else(|| {
+                let message = format!("invalid line of hosts: {:?}", line);
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+            let hosts = detector.hosts.entry(unescape(user)).or_default();
+            hosts.insert(unescape(host));
+        }
+        Ok(detector)
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\t' => result.push_str("\\t"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('\\' | 't' | 'n' | 'r'))) => {
+                result.push(match next {
+                    't' => '\t',
+                    'n' => '\n',
+                    'r' => '\r',
+                    _ => '\\',
+                });
+                chars.next();
+            }
+            (c, _) => result.push(c),
+        }
+    }
+    result
+}
@@ -1,34 +1,310 @@
-use crate::{UtmpEntry, UtmpError};
+use crate::entry_ref::trim_nul;
+use crate::raw::{is_plausible, RawUtmp};
+use crate::timestamp::time_from_tv;
+#[cfg(all(feature = "mmap", unix))]
+use crate::Mmap;
+use crate::{LazyEntry, Timestamp, UtmpEntry, UtmpEntryRef, UtmpError};
 use std::convert::TryFrom;
 use std::fs::File;
-use std::io::{self, BufReader, Read};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
 use std::marker::PhantomData;
 use std::mem;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 use utmp_raw::{utmp, x32::utmp as utmp32, x64::utmp as utmp64};
-use zerocopy::FromBytes;
+use utmp_raw::{UT_HOSTSIZE, UT_LINESIZE, UT_NAMESIZE};
 
 #[doc(hidden)]
-pub struct UtmpParserImpl<R, T = utmp>(R, PhantomData<T>);
+pub struct UtmpParserImpl<R, T = utmp> {
+    reader: R,
+    offset: u64,
+    /// Data read ahead from the reader.
+    buffer: Block,
+    /// Offset of the last record yielded from the back, or the end of whole records, if known.
+    back: Option<u64>,
+    /// Size of the input, if known.
+    size: Option<u64>,
+    /// Whether `ParseError::Misaligned` is yet to be reported.
+    misaligned: bool,
+    options: Options,
+    /// Path of the input to attach to I/O errors, if known.
+    path: Option<PathBuf>,
+    marker: PhantomData<T>,
+}
 
 impl<R: Read, T> UtmpParserImpl<R, T> {
     pub fn from_reader(reader: R) -> Self {
-        UtmpParserImpl(reader, PhantomData)
+        UtmpParserImpl {
+            reader,
+            offset: 0,
+            buffer: Block::new(BLOCK_SIZE),
+            back: None,
+            size: None,
+            misaligned: false,
+            options: Options::default(),
+            path: None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Set the size of blocks read from the reader at once, which is 64 KiB by default.
+    ///
+    /// A capacity smaller than a record is treated as the size of one record.
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer.capacity = capacity;
+        self
     }
 
+    /// Unwrap the reader.
+    ///
+    /// Since the input is read in blocks, the reader may have been read past `offset`.
     pub fn into_inner(self) -> R {
-        self.0
+        self.reader
+    }
+
+    /// Byte offset of the next record to be parsed.
+    ///
+    /// It can be saved and passed to `resume_from_reader` or `resume_from_path`
+    /// later to continue parsing from where it stops.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+impl<R, T> UtmpParserImpl<R, T> {
+    /// Decode strings containing invalid UTF-8 with `String::from_utf8_lossy`
+    /// instead of failing with an error.
+    ///
+    /// It only applies to `UtmpEntry`s yielded from the parser,
+    /// since borrowed entries can't hold replaced strings.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.options.lossy = lossy;
+        self
+    }
+
+    /// Decode records leniently, yielding an entry even if some of its fields are invalid.
+    ///
+    /// Strings containing invalid UTF-8 are decoded as with `lossy`,
+    /// and a time with invalid microseconds is truncated to the second.
+    /// The exact bytes of string fields are still available via `next_lazy`.
+    ///
+    /// Like `lossy`, it only applies to `UtmpEntry`s yielded from the parser.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.options.lenient = lenient;
+        self
+    }
+
+    /// Turn into an iterator of raw records, without decoding them.
+    ///
+    /// ```
+    /// # use utmp_rs::Utmp64Parser;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// for raw in Utmp64Parser::from_path("/var/run/utmp")?.raw_entries() {
+    ///     let raw: utmp_raw::x64::utmp = raw?;
+    ///     // handle raw record
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn raw_entries(self) -> RawEntries<Self> {
+        RawEntries(self)
+    }
+
+    /// Turn into an iterator of entries made at or after `from` and before `to`.
+    ///
+    /// Only the type and the time of records outside the period are read,
+    /// so they don't cost decoding their strings, nor fail for invalid strings.
+    /// Records without a time, like `EMPTY`, are skipped.
+    /// See `entries_between_in_order` to also skip reading records outside the period.
+    pub fn entries_between(self, from: Timestamp, to: Timestamp) -> EntriesBetween<Self> {
+        EntriesBetween {
+            parser: self,
+            range: from..to,
+            in_order: false,
+            done: false,
+        }
+    }
+
+    /// Skip `EMPTY` records, which live utmp files contain as placeholders of free slots,
+    /// instead of yielding them as `UtmpEntry::Empty`.
+    pub fn skip_empty(mut self, skip_empty: bool) -> Self {
+        self.options.skip_empty = skip_empty;
+        self
+    }
+
+    /// Apply options set via `UtmpParserBuilder`,
+    /// with the size of the input if known and data already read from the reader.
+    pub(crate) fn configure(mut self, options: Options, size: Option<u64>, data: Vec<u8>) -> Self {
+        self.options = options;
+        self.size = size;
+        self.buffer.end = data.len();
+        self.buffer.data = data;
+        self.check_alignment()
+    }
+
+    /// Arrange for `ParseError::Misaligned` to be reported before any record
+    /// if the size of the input isn't a multiple of the record size,
+    /// and leave the partial record at the end out.
+    fn check_alignment(mut self) -> Self {
+        if let Some(size) = self.size {
+            let trailing = size % mem::size_of::<T>() as u64;
+            if trailing != 0 {
+                self.back = Some(size - trailing);
+                self.misaligned = true;
+            }
+        }
+        self
+    }
+
+    /// Take `ParseError::Misaligned` if it's yet to be reported.
+    fn take_misaligned(&mut self) -> Option<ParseError> {
+        let file_size = self.size.filter(|_| mem::take(&mut self.misaligned))?;
+        let record_size = mem::size_of::<T>() as u64;
+        Some(ParseError::Misaligned {
+            file_size,
+            record_size,
+            trailing_bytes: file_size % record_size,
+        })
+    }
+
+    /// Attach the given path to I/O errors from the parser.
+    pub(crate) fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Attach the path of the input to the error if it's an I/O error.
+    fn path_error(&self, error: ParseError) -> ParseError {
+        match &self.path {
+            Some(path) => error.with_path(path),
+            None => error,
+        }
+    }
+
+    /// Number of records left to parse in an input of the given size,
+    /// counting a partial record at the end as one.
+    fn remaining(&self, size: u64) -> usize {
+        let end = self.back.unwrap_or(size);
+        let record_size = mem::size_of::<T>() as u64;
+        let bytes = end.saturating_sub(self.offset);
+        let records = bytes / record_size + u64::from(bytes % record_size != 0);
+        let records = records + u64::from(self.misaligned);
+        usize::try_from(records).unwrap_or(usize::MAX)
+    }
+
+    fn size_hint_from_size(&self) -> (usize, Option<usize>) {
+        match self.size {
+            Some(_) if self.options.skip_empty => (0, self.size.map(|size| self.remaining(size))),
+            Some(size) => {
+                let remaining = self.remaining(size);
+                (remaining, Some(remaining))
+            }
+            None => (0, None),
+        }
+    }
+
+    /// Exact number of entries left to be yielded from the given bytes.
+    fn size_hint_from_bytes(&self, bytes: &[u8]) -> (usize, Option<usize>) {
+        let remaining = if self.options.skip_empty {
+            let size = mem::size_of::<T>();
+            let rest = &front_bytes(bytes, self.back)[self.offset as usize..];
+            let records = rest.chunks_exact(size);
+            let partial = !records.remainder().is_empty();
+            records.filter(|record| !is_empty_record(record)).count() + partial as usize
+        } else {
+            self.remaining(bytes.len() as u64)
+        };
+        (remaining, Some(remaining))
+    }
+}
+
+impl<R: Read + Seek, T> UtmpParserImpl<R, T> {
+    /// Create a parser which continues parsing from the given offset.
+    ///
+    /// The offset must be aligned to a record and must not exceed the size of the input.
+    pub fn resume_from_reader(mut reader: R, offset: u64) -> Result<Self, io::Error> {
+        if offset % mem::size_of::<T>() as u64 != 0 {
+            let msg = "offset not aligned to record size";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        let size = reader.seek(SeekFrom::End(0))?;
+        if offset > size {
+            let msg = "offset beyond end of input";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        reader.seek(SeekFrom::Start(offset))?;
+        Ok(UtmpParserImpl {
+            reader,
+            offset,
+            buffer: Block::new(BLOCK_SIZE),
+            back: None,
+            size: Some(size),
+            misaligned: false,
+            options: Options::default(),
+            path: None,
+            marker: PhantomData,
+        })
+    }
+}
+
+impl<R, T> UtmpParserImpl<R, T>
+where
+    R: Read + Seek,
+    Self: Iterator<Item = Result<UtmpEntry, ParseError>>,
+{
+    /// Number of complete records in the input.
+    ///
+    /// The reader is expected to be at the beginning of the input when the parser was created.
+    pub fn len(&mut self) -> Result<u64, io::Error> {
+        let pos = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(pos))?;
+        Ok(end / mem::size_of::<T>() as u64)
+    }
+
+    /// Whether the input contains no complete record.
+    pub fn is_empty(&mut self) -> Result<bool, io::Error> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Parse the record at the given index.
+    ///
+    /// Parsing continues from the record after it afterwards.
+    /// If `EMPTY` records are skipped, the first non-empty record from the index is parsed.
+    pub fn get(&mut self, index: u64) -> Option<Result<UtmpEntry, ParseError>> {
+        let offset = index.checked_mul(mem::size_of::<T>() as u64)?;
+        if let Err(e) = self.reader.seek(SeekFrom::Start(offset)) {
+            return Some(Err(self.path_error(e.into())));
+        }
+        self.offset = offset;
+        self.buffer.clear();
+        self.next()
     }
 }
 
 impl<T> UtmpParserImpl<BufReader<File>, T> {
     pub fn from_file(file: File) -> Self {
-        UtmpParserImpl(BufReader::new(file), PhantomData)
+        let size = file.metadata().ok().map(|metadata| metadata.len());
+        // The parser reads in blocks by itself.
+        let mut parser = Self::from_reader(BufReader::with_capacity(0, file));
+        parser.size = size;
+        parser.check_alignment()
     }
 
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
-        Ok(Self::from_file(File::open(path)?))
+        let path = path.as_ref();
+        Ok(Self::from_file(File::open(path)?).with_path(path))
+    }
+
+    /// Create a parser which continues parsing the given path from the given offset.
+    ///
+    /// See `resume_from_reader` for requirements of the offset.
+    pub fn resume_from_path<P: AsRef<Path>>(path: P, offset: u64) -> Result<Self, io::Error> {
+        let path = path.as_ref();
+        let reader = BufReader::with_capacity(0, File::open(path)?);
+        let parser = Self::resume_from_reader(reader, offset)?;
+        Ok(parser.with_path(path).check_alignment())
     }
 }
 
@@ -52,21 +328,35 @@ pub type Utmp32Parser<R> = UtmpParserImpl<R, utmp32>;
 /// Parser to parse a 64-bit utmp file.
 pub type Utmp64Parser<R> = UtmpParserImpl<R, utmp64>;
 
-const UTMP32_SIZE: usize = mem::size_of::<utmp32>();
+/// Options of how records are parsed.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Options {
+    /// Whether to decode invalid UTF-8 in strings lossily.
+    pub(crate) lossy: bool,
+    /// Whether to skip `EMPTY` records.
+    pub(crate) skip_empty: bool,
+    /// Whether to work around invalid fields where possible.
+    pub(crate) lenient: bool,
+    /// Whether to scan for the next plausible record after a corrupted one.
+    pub(crate) resync: bool,
+    /// Whether numeric fields are in the opposite byte order of the target.
+    pub(crate) swap: bool,
+}
+
 const UTMP64_SIZE: usize = mem::size_of::<utmp64>();
 
+/// Size of blocks read from the reader at once.
+const BLOCK_SIZE: usize = 64 * 1024;
+
 impl<R: Read> Iterator for UtmpParserImpl<R, utmp32> {
     type Item = Result<UtmpEntry, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        #[repr(align(4))]
-        struct Buffer([u8; UTMP32_SIZE]);
-        let mut buffer = Buffer([0; UTMP32_SIZE]);
-        match read_entry::<_, utmp32>(&mut self.0, buffer.0.as_mut()) {
-            Ok(None) => None,
-            Ok(Some(entry)) => Some(UtmpEntry::try_from(entry).map_err(ParseError::Utmp)),
-            Err(e) => Some(Err(e)),
-        }
+        self.read_next_owned::<utmp32>().transpose()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint_from_size()
     }
 }
 
@@ -74,71 +364,1187 @@ impl<R: Read> Iterator for UtmpParserImpl<R, utmp64> {
     type Item = Result<UtmpEntry, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        #[repr(align(8))]
-        struct Buffer([u8; UTMP64_SIZE]);
-        let mut buffer = Buffer([0; UTMP64_SIZE]);
-        match read_entry::<_, utmp64>(&mut self.0, buffer.0.as_mut()) {
+        self.read_next_owned::<utmp64>().transpose()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint_from_size()
+    }
+}
+
+impl<R: Read + Seek> DoubleEndedIterator for UtmpParserImpl<R, utmp32> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.read_back::<utmp32>()
+    }
+}
+
+impl<R: Read + Seek> DoubleEndedIterator for UtmpParserImpl<R, utmp64> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.read_back::<utmp64>()
+    }
+}
+
+impl<R: Read + Seek, T> UtmpParserImpl<R, T> {
+    /// Parse the record before the ones already yielded from the back,
+    /// and move the reader back to where forward parsing is.
+    fn read_back<U: RawUtmp>(&mut self) -> Option<Result<UtmpEntry, ParseError>> {
+        if let Some(e) = self.take_misaligned() {
+            return Some(Err(e));
+        }
+        let mut read_back = || loop {
+            let len = match self.back {
+                Some(back) => back,
+                None => self.reader.seek(SeekFrom::End(0))?,
+            };
+            let start = match back_record::<U>(len, self.offset, &mut self.back) {
+                Some(result) => result?,
+                None => return Ok(None),
+            };
+            self.reader.seek(SeekFrom::Start(start))?;
+            let mut record = [0; UTMP64_SIZE];
+            let record = &mut record[..mem::size_of::<U>()];
+            let result = read_record(&mut self.reader, record);
+            self.reader.seek(SeekFrom::Start(self.offset))?;
+            self.buffer.clear();
+            if !result? {
+                return Err(unaligned_eof());
+            }
+            if self.options.skip_empty && is_empty_record(record) {
+                continue;
+            }
+            let entry = entry_from_record::<U>(record, self.options);
+            return Ok(Some(entry.map_err(record_error::<U>(start))?));
+        };
+        let result = read_back();
+        result.map_err(|e| self.path_error(e)).transpose()
+    }
+
+    /// Move to the first record made at or after the given time with binary search,
+    /// probing the times of raw records without decoding them.
+    fn seek_to_time_of<U: RawUtmp>(&mut self, time: Timestamp) -> Result<(), ParseError> {
+        let size = mem::size_of::<U>() as u64;
+        let len = self.reader.seek(SeekFrom::End(0));
+        let (mut lo, mut hi) = (0, len.map_err(|e| self.path_error(e.into()))? / size);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.probe_time::<U>(mid, hi)? {
+                Some((index, entry_time)) if entry_time < time => lo = index + 1,
+                _ => hi = mid,
+            }
+        }
+        let offset = lo * size;
+        let result = self.reader.seek(SeekFrom::Start(offset));
+        result.map_err(|e| self.path_error(e.into()))?;
+        self.offset = offset;
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Find the first record from `start` and before `end` with a valid time,
+    /// reading records in sequence from `start`.
+    ///
+    /// Records which don't carry a time or whose time is invalid are skipped,
+    /// and other fields aren't decoded, so they don't fail the search.
+    fn probe_time<U: RawUtmp>(
+        &mut self,
+        start: u64,
+        end: u64,
+    ) -> Result<Option<(u64, Timestamp)>, ParseError> {
+        let size = mem::size_of::<U>();
+        let result = self.reader.seek(SeekFrom::Start(start * size as u64));
+        result.map_err(|e| self.path_error(e.into()))?;
+        self.buffer.clear();
+        let swap = self.options.swap;
+        for index in start..end {
+            let raw = match self.buffer.next_record(&mut self.reader, size) {
+                Ok(Some(record)) => raw_from_record::<U>(record, swap),
+                Ok(None) => break,
+                Err(e) => return Err(self.path_error(e)),
+            };
+            if !(utmp_raw::RUN_LVL..=utmp_raw::DEAD_PROCESS).contains(&raw.ut_type()) {
+                continue;
+            }
+            if let Ok(time) = time_from_tv(raw.ut_tv()) {
+                return Ok(Some((index, time)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<R: Read + Seek> UtmpParserImpl<R, utmp32> {
+    /// Move to the first record made at or after the given time,
+    /// assuming records are in order of time, as in wtmp.
+    ///
+    /// It uses binary search on raw records, so only a few records are read,
+    /// and none is decoded.
+    /// Records without a valid time, like `Empty`, are ignored for comparison,
+    /// and neither records failing to decode nor a misaligned input fail the search.
+    pub fn seek_to_time(&mut self, time: Timestamp) -> Result<(), ParseError> {
+        self.seek_to_time_of::<utmp32>(time)
+    }
+
+    /// Turn into an iterator of entries made at or after `from` and before `to`,
+    /// assuming records are in order of time, as in wtmp.
+    ///
+    /// Unlike `entries_between`, it moves to the start of the period via `seek_to_time`,
+    /// and stops at the first record made after the period,
+    /// so records outside the period are mostly not read at all.
+    pub fn entries_between_in_order(
+        mut self,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<EntriesBetween<Self>, ParseError> {
+        self.seek_to_time(from)?;
+        let mut entries = self.entries_between(from, to);
+        entries.in_order = true;
+        Ok(entries)
+    }
+}
+
+impl<R: Read> UtmpParserImpl<R, utmp32> {
+    /// Parse the next entry without allocating.
+    ///
+    /// The returned entry borrows its strings from the internal buffer of the parser.
+    pub fn next_entry(&mut self) -> Result<Option<UtmpEntryRef<'_>>, ParseError> {
+        self.read_next::<utmp32>()
+    }
+
+    /// Read the next record without decoding it.
+    pub fn next_lazy(&mut self) -> Option<Result<LazyEntry, ParseError>> {
+        self.next_raw().map(|raw| raw.map(LazyEntry::from))
+    }
+
+    /// Read the next raw record.
+    pub fn next_raw(&mut self) -> Option<Result<utmp32, ParseError>> {
+        self.read_next_raw::<utmp32>().transpose()
+    }
+}
+
+impl<R: Read + Seek> UtmpParserImpl<R, utmp64> {
+    /// Move to the first record made at or after the given time,
+    /// assuming records are in order of time, as in wtmp.
+    ///
+    /// It uses binary search on raw records, so only a few records are read,
+    /// and none is decoded.
+    /// Records without a valid time, like `Empty`, are ignored for comparison,
+    /// and neither records failing to decode nor a misaligned input fail the search.
+    pub fn seek_to_time(&mut self, time: Timestamp) -> Result<(), ParseError> {
+        self.seek_to_time_of::<utmp64>(time)
+    }
+
+    /// Turn into an iterator of entries made at or after `from` and before `to`,
+    /// assuming records are in order of time, as in wtmp.
+    ///
+    /// Unlike `entries_between`, it moves to the start of the period via `seek_to_time`,
+    /// and stops at the first record made after the period,
+    /// so records outside the period are mostly not read at all.
+    pub fn entries_between_in_order(
+        mut self,
+        from: Timestamp,
+        to: Timestamp,
+    ) -> Result<EntriesBetween<Self>, ParseError> {
+        self.seek_to_time(from)?;
+        let mut entries = self.entries_between(from, to);
+        entries.in_order = true;
+        Ok(entries)
+    }
+}
+
+impl<R: Read> UtmpParserImpl<R, utmp64> {
+    /// Parse the next entry without allocating.
+    ///
+    /// The returned entry borrows its strings from the internal buffer of the parser.
+    pub fn next_entry(&mut self) -> Result<Option<UtmpEntryRef<'_>>, ParseError> {
+        self.read_next::<utmp64>()
+    }
+
+    /// Read the next record without decoding it.
+    pub fn next_lazy(&mut self) -> Option<Result<LazyEntry, ParseError>> {
+        self.next_raw().map(|raw| raw.map(LazyEntry::from))
+    }
+
+    /// Read the next raw record.
+    pub fn next_raw(&mut self) -> Option<Result<utmp64, ParseError>> {
+        self.read_next_raw::<utmp64>().transpose()
+    }
+}
+
+impl<R: Read, T> UtmpParserImpl<R, T> {
+    fn read_next<U: RawUtmp>(&mut self) -> Result<Option<UtmpEntryRef<'_>>, ParseError> {
+        let swap = self.options.swap;
+        match self.read_next_record::<U>()? {
+            Some((start, record)) => {
+                let entry = entry_ref_from_record::<U>(record, swap);
+                Ok(Some(entry.map_err(record_error::<U>(start))?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn read_next_owned<U: RawUtmp>(&mut self) -> Result<Option<UtmpEntry>, ParseError> {
+        let options = self.options;
+        match self.read_next_record::<U>()? {
+            Some((start, record)) => {
+                let entry = entry_from_record::<U>(record, options);
+                if options.resync && !is_intact::<U>(record, &entry, options.swap) {
+                    return Err(self.resync::<U>(start));
+                }
+                Ok(Some(entry.map_err(record_error::<U>(start))?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Scan forward byte by byte from after the start of the last record taken,
+    /// which started at `start`, until a plausible record is found,
+    /// and continue parsing from there.
+    fn resync<U: RawUtmp>(&mut self, start: u64) -> ParseError {
+        let size = mem::size_of::<U>();
+        loop {
+            // The last record taken is still in the buffer, so step back into it.
+            self.buffer.start -= size - 1;
+            self.offset -= size as u64 - 1;
+            let available = self.offset + (self.buffer.end - self.buffer.start) as u64;
+            let end = match self.back {
+                Some(back) if self.offset + size as u64 > back => back,
+                _ => match self.buffer.next_record(&mut self.reader, size) {
+                    Ok(Some(record)) => {
+                        if !is_resync_point::<U>(record, self.options.swap) {
+                            self.offset += size as u64;
+                            continue;
+                        }
+                        // Put the record back to be parsed next.
+                        self.buffer.start -= size;
+                        self.offset
+                    }
+                    Ok(None) => available,
+                    Err(ParseError::Io { source, .. })
+                        if source.kind() == io::ErrorKind::UnexpectedEof =>
+                    {
+                        available
+                    }
+                    Err(e) => return self.path_error(e),
+                },
+            };
+            self.offset = end;
+            return ParseError::Corrupted { start, end };
+        }
+    }
+
+    /// Take the next record, and decode it if it was made within the period.
+    fn read_next_within<U: RawUtmp>(&mut self, range: &Range<Timestamp>) -> Option<Within> {
+        let options = self.options;
+        match self.read_next_record::<U>() {
+            Ok(Some((start, record))) => Some(entry_within::<U>(record, start, options, range)),
             Ok(None) => None,
-            Ok(Some(entry)) => Some(UtmpEntry::try_from(entry).map_err(ParseError::Utmp)),
-            Err(e) => Some(Err(e)),
+            Err(e) => Some(Within::Entry(Err(e))),
         }
     }
+
+    fn read_next_raw<U: RawUtmp>(&mut self) -> Result<Option<U>, ParseError> {
+        let swap = self.options.swap;
+        match self.read_next_record::<U>()? {
+            Some((_, record)) => Ok(Some(raw_from_record::<U>(record, swap))),
+            None => Ok(None),
+        }
+    }
+
+    /// Take the next record and its offset.
+    fn read_next_record<U>(&mut self) -> Result<Option<(u64, &[u8])>, ParseError> {
+        if let Some(e) = self.take_misaligned() {
+            return Err(e);
+        }
+        let size = mem::size_of::<U>();
+        loop {
+            if self.back.is_some_and(|back| self.offset >= back) {
+                return Ok(None);
+            }
+            let skip = match self.buffer.next_record(&mut self.reader, size) {
+                Ok(Some(record)) => self.options.skip_empty && is_empty_record(record),
+                Ok(None) => return Ok(None),
+                Err(e) => return Err(self.path_error(e)),
+            };
+            let start = self.offset;
+            self.offset += size as u64;
+            if !skip {
+                return Ok(Some((start, self.buffer.last_record(size))));
+            }
+        }
+    }
+}
+
+/// Buffer of data read from the reader in blocks,
+/// so that reading many small records doesn't need a syscall for each.
+struct Block {
+    data: Vec<u8>,
+    /// Size of blocks to read.
+    capacity: usize,
+    /// Start of data not yet consumed.
+    start: usize,
+    /// End of data read into the buffer.
+    end: usize,
 }
 
-fn read_entry<R: Read, T: FromBytes>(
-    mut reader: R,
-    buffer: &mut [u8],
-) -> Result<Option<&T>, ParseError> {
+impl Block {
+    fn new(capacity: usize) -> Self {
+        Block {
+            data: Vec::new(),
+            capacity,
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// The record of the given size last taken via `next_record`.
+    fn last_record(&self, size: usize) -> &[u8] {
+        &self.data[self.start - size..self.start]
+    }
+
+    /// Discard data read ahead, e.g. after seeking the reader.
+    fn clear(&mut self) {
+        self.start = 0;
+        self.end = 0;
+    }
+
+    /// Take the next record of the given size, reading more from the reader if needed,
+    /// or return `None` if there is no more record.
+    fn next_record<R: Read>(
+        &mut self,
+        mut reader: R,
+        size: usize,
+    ) -> Result<Option<&[u8]>, ParseError> {
+        if self.end - self.start < size {
+            // Move the partial record to the front to make room for the next block.
+            self.data.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+            self.data.resize(self.capacity.max(size), 0);
+            while self.end < size {
+                match reader.read(&mut self.data[self.end..]) {
+                    // If nothing has been read, then we just passed the last item.
+                    Ok(0) if self.end == 0 => return Ok(None),
+                    // Otherwise this is an unexpected EOF.
+                    Ok(0) => {
+                        self.clear();
+                        return Err(unaligned_eof());
+                    }
+                    Ok(n) => self.end += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        let record = &self.data[self.start..][..size];
+        self.start += size;
+        Ok(Some(record))
+    }
+}
+
+/// Fill the buffer with a record, or return `false` if there is no more record.
+fn read_record<R: Read>(mut reader: R, buffer: &mut [u8]) -> Result<bool, ParseError> {
     let size = buffer.len();
     let mut buf = &mut buffer[..];
     loop {
         match reader.read(buf) {
             // If the buffer has not been filled, then we just passed the last item.
-            Ok(0) if buf.len() == size => return Ok(None),
+            Ok(0) if buf.len() == size => return Ok(false),
             // Otherwise this is an unexpected EOF.
-            Ok(0) => {
-                let inner = io::Error::new(io::ErrorKind::UnexpectedEof, "size not aligned");
-                return Err(inner.into());
-            }
+            Ok(0) => return Err(unaligned_eof()),
             Ok(n) => {
                 buf = &mut buf[n..];
                 if buf.is_empty() {
-                    break;
+                    return Ok(true);
                 }
             }
             Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
             Err(e) => return Err(e.into()),
         }
     }
-    Ok(Some(T::ref_from(buffer).unwrap()))
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl<T> UtmpParserImpl<Mmap, T> {
+    /// Create a parser which parses records directly from a memory mapping of the given path,
+    /// without copying them.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated while the parser is alive,
+    /// otherwise parsing may crash the process with `SIGBUS`.
+    pub unsafe fn from_mmap<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let mmap = Mmap::map(&File::open(path)?)?;
+        Ok(UtmpParserImpl {
+            reader: mmap,
+            offset: 0,
+            buffer: Block::new(BLOCK_SIZE),
+            back: None,
+            size: None,
+            misaligned: false,
+            options: Options::default(),
+            path: None,
+            marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl UtmpParserImpl<Mmap, utmp32> {
+    /// Parse the next entry without copying its strings out of the mapping.
+    pub fn next_ref(&mut self) -> Option<Result<UtmpEntryRef<'_>, ParseError>> {
+        let bytes = front_bytes(&self.reader, self.back);
+        entry_ref_from_bytes::<utmp32>(bytes, &mut self.offset, self.options)
+    }
+
+    /// Read the next record without decoding it.
+    pub fn next_lazy(&mut self) -> Option<Result<LazyEntry, ParseError>> {
+        self.next_raw().map(|raw| raw.map(LazyEntry::from))
+    }
+
+    /// Read the next raw record.
+    pub fn next_raw(&mut self) -> Option<Result<utmp32, ParseError>> {
+        let bytes = front_bytes(&self.reader, self.back);
+        let Options {
+            skip_empty, swap, ..
+        } = self.options;
+        let record = record_from_bytes::<utmp32>(bytes, &mut self.offset, skip_empty)?;
+        Some(record.map(|record| raw_from_record::<utmp32>(record, swap)))
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl UtmpParserImpl<Mmap, utmp64> {
+    /// Parse the next entry without copying its strings out of the mapping.
+    pub fn next_ref(&mut self) -> Option<Result<UtmpEntryRef<'_>, ParseError>> {
+        let bytes = front_bytes(&self.reader, self.back);
+        entry_ref_from_bytes::<utmp64>(bytes, &mut self.offset, self.options)
+    }
+
+    /// Read the next record without decoding it.
+    pub fn next_lazy(&mut self) -> Option<Result<LazyEntry, ParseError>> {
+        self.next_raw().map(|raw| raw.map(LazyEntry::from))
+    }
+
+    /// Read the next raw record.
+    pub fn next_raw(&mut self) -> Option<Result<utmp64, ParseError>> {
+        let bytes = front_bytes(&self.reader, self.back);
+        let Options {
+            skip_empty, swap, ..
+        } = self.options;
+        let record = record_from_bytes::<utmp64>(bytes, &mut self.offset, skip_empty)?;
+        Some(record.map(|record| raw_from_record::<utmp64>(record, swap)))
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Iterator for UtmpParserImpl<Mmap, utmp32> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = front_bytes(&self.reader, self.back);
+        entry_from_bytes::<utmp32>(bytes, &mut self.offset, self.options)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint_from_bytes(&self.reader)
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl DoubleEndedIterator for UtmpParserImpl<Mmap, utmp32> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        entry_from_bytes_back::<utmp32>(&self.reader, self.offset, &mut self.back, self.options)
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl ExactSizeIterator for UtmpParserImpl<Mmap, utmp32> {}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Iterator for UtmpParserImpl<Mmap, utmp64> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = front_bytes(&self.reader, self.back);
+        entry_from_bytes::<utmp64>(bytes, &mut self.offset, self.options)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint_from_bytes(&self.reader)
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl DoubleEndedIterator for UtmpParserImpl<Mmap, utmp64> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        entry_from_bytes_back::<utmp64>(&self.reader, self.offset, &mut self.back, self.options)
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl ExactSizeIterator for UtmpParserImpl<Mmap, utmp64> {}
+
+/// Iterator of raw records of a parser, created via `raw_entries`.
+pub struct RawEntries<P>(P);
+
+impl<P> RawEntries<P> {
+    /// Unwrap the parser.
+    pub fn into_inner(self) -> P {
+        self.0
+    }
+}
+
+impl<R: Read> Iterator for RawEntries<UtmpParserImpl<R, utmp32>> {
+    type Item = Result<utmp32, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_raw()
+    }
+}
+
+impl<R: Read> Iterator for RawEntries<UtmpParserImpl<R, utmp64>> {
+    type Item = Result<utmp64, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_raw()
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Iterator for RawEntries<UtmpParserImpl<Mmap, utmp32>> {
+    type Item = Result<utmp32, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_raw()
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Iterator for RawEntries<UtmpParserImpl<Mmap, utmp64>> {
+    type Item = Result<utmp64, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_raw()
+    }
+}
+
+impl Iterator for RawEntries<UtmpParserImpl<ByteSlice<'_>, utmp32>> {
+    type Item = Result<utmp32, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_raw()
+    }
+}
+
+impl Iterator for RawEntries<UtmpParserImpl<ByteSlice<'_>, utmp64>> {
+    type Item = Result<utmp64, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_raw()
+    }
+}
+
+/// Iterator of entries made within a period of time, created via `entries_between`.
+pub struct EntriesBetween<P> {
+    parser: P,
+    range: Range<Timestamp>,
+    /// Whether records are in order of time, so nothing is left after the period.
+    in_order: bool,
+    done: bool,
+}
+
+impl<P> EntriesBetween<P> {
+    /// Unwrap the parser.
+    pub fn into_inner(self) -> P {
+        self.parser
+    }
+
+    /// Take records via `next` until one within the period is found.
+    fn next_within<F>(&mut self, mut next: F) -> Option<Result<UtmpEntry, ParseError>>
+    where
+        F: FnMut(&mut P, &Range<Timestamp>) -> Option<Within>,
+    {
+        while !self.done {
+            match next(&mut self.parser, &self.range)? {
+                Within::Outside => {}
+                Within::After => self.done = self.in_order,
+                Within::Entry(entry) => return Some(entry),
+            }
+        }
+        None
+    }
+}
+
+impl<R: Read> Iterator for EntriesBetween<UtmpParserImpl<R, utmp32>> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_within(|parser, range| parser.read_next_within::<utmp32>(range))
+    }
+}
+
+impl<R: Read> Iterator for EntriesBetween<UtmpParserImpl<R, utmp64>> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_within(|parser, range| parser.read_next_within::<utmp64>(range))
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Iterator for EntriesBetween<UtmpParserImpl<Mmap, utmp32>> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_within(|parser, range| {
+            let bytes = front_bytes(&parser.reader, parser.back);
+            entry_within_bytes::<utmp32>(bytes, &mut parser.offset, parser.options, range)
+        })
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+impl Iterator for EntriesBetween<UtmpParserImpl<Mmap, utmp64>> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_within(|parser, range| {
+            let bytes = front_bytes(&parser.reader, parser.back);
+            entry_within_bytes::<utmp64>(bytes, &mut parser.offset, parser.options, range)
+        })
+    }
+}
+
+impl Iterator for EntriesBetween<UtmpParserImpl<ByteSlice<'_>, utmp32>> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_within(|parser, range| {
+            let bytes = front_bytes(parser.reader.0, parser.back);
+            entry_within_bytes::<utmp32>(bytes, &mut parser.offset, parser.options, range)
+        })
+    }
+}
+
+impl Iterator for EntriesBetween<UtmpParserImpl<ByteSlice<'_>, utmp64>> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_within(|parser, range| {
+            let bytes = front_bytes(parser.reader.0, parser.back);
+            entry_within_bytes::<utmp64>(bytes, &mut parser.offset, parser.options, range)
+        })
+    }
+}
+
+/// Where a record is relative to a period of time.
+enum Within {
+    /// Before the period, or without a time
+    Outside,
+    /// At or after the end of the period
+    After,
+    /// Within the period, or with an invalid time, with the decoded entry
+    Entry(Result<UtmpEntry, ParseError>),
+}
+
+/// Decode the record, which started at `start`, only if it was made within the period.
+fn entry_within<T: RawUtmp>(
+    record: &[u8],
+    start: u64,
+    options: Options,
+    range: &Range<Timestamp>,
+) -> Within {
+    let raw = raw_from_record::<T>(record, options.swap);
+    if !(utmp_raw::RUN_LVL..=utmp_raw::DEAD_PROCESS).contains(&raw.ut_type()) {
+        return Within::Outside;
+    }
+    match time_from_tv(raw.ut_tv()) {
+        Ok(time) if time < range.start => Within::Outside,
+        Ok(time) if time >= range.end => Within::After,
+        // An invalid time is reported when decoding.
+        _ => {
+            Within::Entry(entry_from_record::<T>(record, options).map_err(record_error::<T>(start)))
+        }
+    }
+}
+
+/// Take the record at the given offset of the bytes, advance the offset past it,
+/// and decode it if it was made within the period.
+fn entry_within_bytes<T: RawUtmp>(
+    bytes: &[u8],
+    offset: &mut u64,
+    options: Options,
+    range: &Range<Timestamp>,
+) -> Option<Within> {
+    Some(
+        match record_from_bytes::<T>(bytes, offset, options.skip_empty)? {
+            Ok(record) => {
+                let start = *offset - record.len() as u64;
+                entry_within::<T>(record, start, options, range)
+            }
+            Err(e) => Within::Entry(Err(e)),
+        },
+    )
+}
+
+/// Bytes in memory used as the input of parsers created via `from_bytes`.
+#[derive(Clone, Copy, Debug)]
+pub struct ByteSlice<'a>(&'a [u8]);
+
+impl<'a, T> UtmpParserImpl<ByteSlice<'a>, T> {
+    /// Create a parser which parses records directly from the given bytes.
+    pub fn from_bytes(bytes: &'a [u8]) -> Self {
+        UtmpParserImpl {
+            reader: ByteSlice(bytes),
+            offset: 0,
+            buffer: Block::new(BLOCK_SIZE),
+            back: None,
+            size: None,
+            misaligned: false,
+            options: Options::default(),
+            path: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a> UtmpParserImpl<ByteSlice<'a>, utmp32> {
+    /// Parse the next entry borrowing its strings from the bytes.
+    pub fn next_ref(&mut self) -> Option<Result<UtmpEntryRef<'a>, ParseError>> {
+        let bytes = front_bytes(self.reader.0, self.back);
+        entry_ref_from_bytes::<utmp32>(bytes, &mut self.offset, self.options)
+    }
+
+    /// Read the next record without decoding it.
+    pub fn next_lazy(&mut self) -> Option<Result<LazyEntry, ParseError>> {
+        self.next_raw().map(|raw| raw.map(LazyEntry::from))
+    }
+
+    /// Read the next raw record.
+    pub fn next_raw(&mut self) -> Option<Result<utmp32, ParseError>> {
+        let bytes = front_bytes(self.reader.0, self.back);
+        let Options {
+            skip_empty, swap, ..
+        } = self.options;
+        let record = record_from_bytes::<utmp32>(bytes, &mut self.offset, skip_empty)?;
+        Some(record.map(|record| raw_from_record::<utmp32>(record, swap)))
+    }
+}
+
+impl<'a> UtmpParserImpl<ByteSlice<'a>, utmp64> {
+    /// Parse the next entry borrowing its strings from the bytes.
+    pub fn next_ref(&mut self) -> Option<Result<UtmpEntryRef<'a>, ParseError>> {
+        let bytes = front_bytes(self.reader.0, self.back);
+        entry_ref_from_bytes::<utmp64>(bytes, &mut self.offset, self.options)
+    }
+
+    /// Read the next record without decoding it.
+    pub fn next_lazy(&mut self) -> Option<Result<LazyEntry, ParseError>> {
+        self.next_raw().map(|raw| raw.map(LazyEntry::from))
+    }
+
+    /// Read the next raw record.
+    pub fn next_raw(&mut self) -> Option<Result<utmp64, ParseError>> {
+        let bytes = front_bytes(self.reader.0, self.back);
+        let Options {
+            skip_empty, swap, ..
+        } = self.options;
+        let record = record_from_bytes::<utmp64>(bytes, &mut self.offset, skip_empty)?;
+        Some(record.map(|record| raw_from_record::<utmp64>(record, swap)))
+    }
+}
+
+impl Iterator for UtmpParserImpl<ByteSlice<'_>, utmp32> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = front_bytes(self.reader.0, self.back);
+        entry_from_bytes::<utmp32>(bytes, &mut self.offset, self.options)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint_from_bytes(self.reader.0)
+    }
+}
+
+impl DoubleEndedIterator for UtmpParserImpl<ByteSlice<'_>, utmp32> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        entry_from_bytes_back::<utmp32>(self.reader.0, self.offset, &mut self.back, self.options)
+    }
+}
+
+impl ExactSizeIterator for UtmpParserImpl<ByteSlice<'_>, utmp32> {}
+
+impl Iterator for UtmpParserImpl<ByteSlice<'_>, utmp64> {
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = front_bytes(self.reader.0, self.back);
+        entry_from_bytes::<utmp64>(bytes, &mut self.offset, self.options)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.size_hint_from_bytes(self.reader.0)
+    }
+}
+
+impl DoubleEndedIterator for UtmpParserImpl<ByteSlice<'_>, utmp64> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        entry_from_bytes_back::<utmp64>(self.reader.0, self.offset, &mut self.back, self.options)
+    }
+}
+
+impl ExactSizeIterator for UtmpParserImpl<ByteSlice<'_>, utmp64> {}
+
+/// Parse the record at the given offset of the bytes, and advance the offset past it.
+fn entry_ref_from_bytes<'a, T: RawUtmp>(
+    bytes: &'a [u8],
+    offset: &mut u64,
+    options: Options,
+) -> Option<Result<UtmpEntryRef<'a>, ParseError>> {
+    let record = match record_from_bytes::<T>(bytes, offset, options.skip_empty)? {
+        Ok(record) => record,
+        Err(e) => return Some(Err(e)),
+    };
+    let start = *offset - record.len() as u64;
+    Some(entry_ref_from_record::<T>(record, options.swap).map_err(record_error::<T>(start)))
+}
+
+/// Take the record at the given offset of the bytes, and advance the offset past it.
+fn record_from_bytes<'a, T>(
+    bytes: &'a [u8],
+    offset: &mut u64,
+    skip_empty: bool,
+) -> Option<Result<&'a [u8], ParseError>> {
+    let size = mem::size_of::<T>();
+    loop {
+        let rest = usize::try_from(*offset)
+            .ok()
+            .and_then(|offset| bytes.get(offset..))
+            .filter(|rest| !rest.is_empty())?;
+        if rest.len() < size {
+            *offset = bytes.len() as u64;
+            return Some(Err(unaligned_eof()));
+        }
+        *offset += size as u64;
+        let record = &rest[..size];
+        if !(skip_empty && is_empty_record(record)) {
+            return Some(Ok(record));
+        }
+    }
+}
+
+/// Decode a record whose size matches `T`,
+/// swapping the byte order of numeric fields if `swap` is set.
+fn entry_ref_from_record<T: RawUtmp>(
+    record: &[u8],
+    swap: bool,
+) -> Result<UtmpEntryRef<'_>, UtmpError> {
+    match T::ref_from(record).filter(|_| !swap) {
+        Some(raw) => UtmpEntryRef::from_raw(
+            raw,
+            record,
+            raw.ut_line(),
+            raw.ut_id(),
+            raw.ut_user(),
+            raw.ut_host(),
+        ),
+        // The record can't be referenced in place if it's not properly aligned
+        // or needs swapping, so copy out the numeric fields,
+        // and borrow strings from the bytes directly.
+        None => {
+            let raw = raw_from_record::<T>(record, swap);
+            let line = &record[T::LINE_OFFSET..][..UT_LINESIZE];
+            let id = &record[T::ID_OFFSET..][..4];
+            let user = &record[T::USER_OFFSET..][..UT_NAMESIZE];
+            let host = &record[T::HOST_OFFSET..][..UT_HOSTSIZE];
+            UtmpEntryRef::from_raw(&raw, record, line, id, user, host)
+        }
+    }
+}
+
+/// Decode a record whose size matches `T` into an owned entry,
+/// replacing invalid UTF-8 in strings if `lossy` or `lenient` is set,
+/// and dropping invalid microseconds of the time if `lenient` is set.
+fn entry_from_record<T: RawUtmp>(record: &[u8], options: Options) -> Result<UtmpEntry, UtmpError> {
+    match entry_ref_from_record::<T>(record, options.swap) {
+        Ok(entry) => Ok(entry.to_owned()),
+        Err(e) if !is_recoverable(&e, options) => Err(e),
+        Err(_) => {
+            let mut raw = raw_from_record::<T>(record, options.swap);
+            if options.lenient && time_from_tv(raw.ut_tv()).is_err() {
+                raw.clear_tv_usec();
+            }
+            let line = lossy_bytes(&record[T::LINE_OFFSET..][..UT_LINESIZE]);
+            let id = lossy_bytes(&record[T::ID_OFFSET..][..4]);
+            let user = lossy_bytes(&record[T::USER_OFFSET..][..UT_NAMESIZE]);
+            let host = lossy_bytes(&record[T::HOST_OFFSET..][..UT_HOSTSIZE]);
+            let entry = UtmpEntryRef::from_raw(&raw, record, &line, &id, &user, &host);
+            entry.map(|entry| entry.to_owned())
+        }
+    }
+}
+
+/// Whether the error can be worked around with the options.
+fn is_recoverable(error: &UtmpError, options: Options) -> bool {
+    match error {
+        UtmpError::InvalidLine(_)
+        | UtmpError::InvalidId(_)
+        | UtmpError::InvalidUser(_)
+        | UtmpError::InvalidHost(_) => options.lossy || options.lenient,
+        UtmpError::InvalidTime(_) => options.lenient,
+        _ => false,
+    }
+}
+
+/// Copy out a record whose size matches `T`,
+/// swapping the byte order of numeric fields if `swap` is set.
+pub(crate) fn raw_from_record<T: RawUtmp>(record: &[u8], swap: bool) -> T {
+    // This never fails, since callers only pass whole records.
+    let mut raw = T::read_from(record).unwrap();
+    if swap {
+        raw.swap_bytes();
+    }
+    raw
+}
+
+/// Replace invalid UTF-8 in the NUL-terminated string,
+/// keeping the terminator, which is significant for some records.
+fn lossy_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut result = String::from_utf8_lossy(trim_nul(bytes)).into_owned();
+    result.push('\0');
+    result.into_bytes()
+}
+
+/// Bytes not yet yielded from the back.
+fn front_bytes(bytes: &[u8], back: Option<u64>) -> &[u8] {
+    match back {
+        // This is fine because `back` never exceeds the length of the bytes.
+        Some(back) => &bytes[..back as usize],
+        None => bytes,
+    }
+}
+
+fn entry_from_bytes_back<T: RawUtmp>(
+    bytes: &[u8],
+    offset: u64,
+    back: &mut Option<u64>,
+    options: Options,
+) -> Option<Result<UtmpEntry, ParseError>> {
+    loop {
+        let start = match back_record::<T>(bytes.len() as u64, offset, back)? {
+            Ok(start) => start as usize,
+            Err(e) => return Some(Err(e)),
+        };
+        let record = &bytes[start..][..mem::size_of::<T>()];
+        if options.skip_empty && is_empty_record(record) {
+            continue;
+        }
+        let entry = entry_from_record::<T>(record, options);
+        return Some(entry.map_err(record_error::<T>(start as u64)));
+    }
+}
+
+/// Find the offset of the next record to be yielded from the back, and move `back` to it.
+///
+/// `end` is the size of the input if nothing has been yielded from the back yet,
+/// or `back` otherwise.
+/// A partial record at the end of the input is reported as an error first.
+fn back_record<T>(
+    end: u64,
+    offset: u64,
+    back: &mut Option<u64>,
+) -> Option<Result<u64, ParseError>> {
+    let size = mem::size_of::<T>() as u64;
+    if back.is_none() {
+        let aligned = end - end % size;
+        *back = Some(aligned);
+        if aligned != end && aligned >= offset {
+            return Some(Err(unaligned_eof()));
+        }
+    }
+    let end = back.unwrap();
+    if end < offset + size {
+        return None;
+    }
+    *back = Some(end - size);
+    Some(Ok(end - size))
+}
+
+fn entry_from_bytes<T: RawUtmp>(
+    bytes: &[u8],
+    offset: &mut u64,
+    options: Options,
+) -> Option<Result<UtmpEntry, ParseError>> {
+    let entry = match record_from_bytes::<T>(bytes, offset, options.skip_empty)? {
+        Ok(record) => {
+            let start = *offset - record.len() as u64;
+            let entry = entry_from_record::<T>(record, options);
+            if options.resync && !is_intact::<T>(record, &entry, options.swap) {
+                return Some(Err(resync_bytes::<T>(bytes, start, offset, options.swap)));
+            }
+            entry.map_err(record_error::<T>(start))
+        }
+        Err(e) => Err(e),
+    };
+    Some(entry)
+}
+
+/// Scan forward byte by byte from after the corrupted record at `start`
+/// until a plausible record is found, and move the offset to it.
+fn resync_bytes<T: RawUtmp>(bytes: &[u8], start: u64, offset: &mut u64, swap: bool) -> ParseError {
+    let size = mem::size_of::<T>();
+    let first = start as usize + 1;
+    let end = (first..=bytes.len().saturating_sub(size))
+        .find(|&pos| is_resync_point::<T>(&bytes[pos..][..size], swap))
+        .unwrap_or_else(|| bytes.len().max(first));
+    *offset = end as u64;
+    ParseError::Corrupted {
+        start,
+        end: end as u64,
+    }
+}
+
+/// Whether parsing can resume from the record, i.e. it's plausible, and it's not empty,
+/// since an empty record can easily be found by chance in zeroed fields.
+fn is_resync_point<T: RawUtmp>(record: &[u8], swap: bool) -> bool {
+    let raw = raw_from_record::<T>(record, swap);
+    raw.ut_type() != utmp_raw::EMPTY && raw.ut_tv().tv_sec != 0 && is_plausible(&raw)
+}
+
+/// Whether the record was decoded and looks valid, i.e. doesn't need resynchronization.
+fn is_intact<T: RawUtmp>(record: &[u8], entry: &Result<UtmpEntry, UtmpError>, swap: bool) -> bool {
+    entry.is_ok() && is_plausible(&raw_from_record::<T>(record, swap))
+}
+
+/// Attach the position of the record at the given offset to its error.
+fn record_error<T>(offset: u64) -> impl FnOnce(UtmpError) -> ParseError {
+    move |source| ParseError::Utmp {
+        index: offset / mem::size_of::<T>() as u64,
+        offset,
+        source,
+    }
+}
+
+/// Whether the record is of type `EMPTY`, which is always the first field.
+fn is_empty_record(record: &[u8]) -> bool {
+    record[..2] == utmp_raw::EMPTY.to_ne_bytes()
+}
+
+fn unaligned_eof() -> ParseError {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "size not aligned").into()
 }
 
 /// Parse utmp entries from the given path.
 ///
 /// It parses the given path using the native utmp format in the target platform.
 pub fn parse_from_path<P: AsRef<Path>>(path: P) -> Result<Vec<UtmpEntry>, ParseError> {
-    UtmpParser::from_path(path)?.collect()
+    let path = path.as_ref();
+    let parser = UtmpParser::from_path(path).map_err(|e| ParseError::from(e).with_path(path))?;
+    collect_entries(parser).map_err(|e| e.with_path(path))
 }
 
 /// Parse utmp entries from the given file.
 ///
 /// It parses the given file using the native utmp format in the target platform.
 pub fn parse_from_file(file: File) -> Result<Vec<UtmpEntry>, ParseError> {
-    UtmpParser::from_file(file).collect()
+    collect_entries(UtmpParser::from_file(file))
 }
 
 /// Parse utmp entries from the given reader.
 ///
 /// It parses from the given reader using the native utmp format in the target platform.
 pub fn parse_from_reader<R: Read>(reader: R) -> Result<Vec<UtmpEntry>, ParseError> {
-    UtmpParser::from_reader(reader).collect()
+    collect_entries(UtmpParser::from_reader(reader))
+}
+
+/// Parse utmp entries from the given bytes.
+///
+/// It parses the given bytes using the native utmp format in the target platform.
+pub fn parse_from_bytes(bytes: &[u8]) -> Result<Vec<UtmpEntry>, ParseError> {
+    collect_entries(UtmpParser::from_bytes(bytes))
+}
+
+/// Collect entries into a `Vec` preallocated from the size hint,
+/// failing early if the input is too large to fit in memory.
+fn collect_entries<I>(entries: I) -> Result<Vec<UtmpEntry>, ParseError>
+where
+    I: Iterator<Item = Result<UtmpEntry, ParseError>>,
+{
+    let mut result = Vec::new();
+    result
+        .try_reserve_exact(entries.size_hint().0)
+        .map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, "too many entries to collect"))?;
+    for entry in entries {
+        result.push(entry?);
+    }
+    Ok(result)
 }
 
 #[derive(Debug, Error)]
 #[non_exhaustive]
 pub enum ParseError {
-    #[error(transparent)]
-    Utmp(#[from] UtmpError),
-    #[error(transparent)]
-    Io(#[from] io::Error),
+    #[error("invalid record {index} at offset {offset}: {source}")]
+    Utmp {
+        /// Index of the record in the input
+        index: u64,
+        /// Byte offset of the record in the input
+        offset: u64,
+        source: UtmpError,
+    },
+    /// Bytes skipped to recover from corrupted records, see `ErrorPolicy::Resync`
+    #[error("skipped corrupted bytes from offset {start} to {end}")]
+    Corrupted {
+        /// Byte offset of the first corrupted record
+        start: u64,
+        /// Byte offset where parsing resumes
+        end: u64,
+    },
+    /// Size of the file isn't a multiple of the record size,
+    /// which suggests that it's in another format, e.g. 64-bit parsed as 32-bit.
+    /// It's reported before any record, and the partial record at the end is left out.
+    #[error(
+        "file size {file_size} is not a multiple of record size {record_size}, \
+         leaving {trailing_bytes} trailing bytes"
+    )]
+    Misaligned {
+        /// Size of the file
+        file_size: u64,
+        /// Size of records in the format being parsed
+        record_size: u64,
+        /// Bytes after the last whole record
+        trailing_bytes: u64,
+    },
+    #[error("{}", io_message(.path.as_deref(), .source))]
+    Io {
+        /// Path of the file which failed, if known
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+}
+
+impl ParseError {
+    /// Attach the path to the error if it's an I/O error without a path yet.
+    pub(crate) fn with_path(self, path: &Path) -> Self {
+        match self {
+            ParseError::Io { path: None, source } => ParseError::Io {
+                path: Some(path.to_owned()),
+                source,
+            },
+            error => error,
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(source: io::Error) -> Self {
+        ParseError::Io { path: None, source }
+    }
+}
+
+pub(crate) fn io_message(path: Option<&Path>, source: &io::Error) -> String {
+    match path {
+        Some(path) => format!("{}: {}", path.display(), source),
+        None => source.to_string(),
+    }
 }
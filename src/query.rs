@@ -0,0 +1,178 @@
+use crate::log::{entry_line, entry_user};
+use crate::{Cidr, Timestamp, UtmpEntry, UtmpEntryKind};
+use std::borrow::Borrow;
+use std::net::IpAddr;
+use utmp_raw::pid_t;
+
+/// Filter of utmp entries, built by combining conditions which all have to match.
+///
+/// It can be applied to any iterator of entries via `apply`,
+/// and to `UtmpLog` via `UtmpLog::query`, which makes use of its indices.
+///
+/// ```
+/// # use utmp_rs::{Query, UtmpEntryKind};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let query = Query::new()
+///     .user("root")
+///     .host_prefix("10.")
+///     .exclude_cidr("10.0.0.0/24".parse()?)
+///     .kind(UtmpEntryKind::UserProcess);
+/// for entry in query.apply(utmp_rs::parse_from_path("/var/log/wtmp")?) {
+///     // handle entry
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Query {
+    pub(crate) user: Option<String>,
+    pub(crate) line: Option<String>,
+    pub(crate) host_prefix: Option<String>,
+    /// Ranges any of which the address has to be in, or any address if empty.
+    pub(crate) cidrs: Vec<Cidr>,
+    /// Ranges none of which the address can be in.
+    pub(crate) excluded_cidrs: Vec<Cidr>,
+    pub(crate) pid: Option<pid_t>,
+    /// Kinds any of which matches, or any kind if empty.
+    pub(crate) kinds: Vec<UtmpEntryKind>,
+    pub(crate) from: Option<Timestamp>,
+    pub(crate) to: Option<Timestamp>,
+}
+
+impl Query {
+    /// Create a query which matches all entries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match entries of the given user.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Match entries on the given tty line.
+    pub fn line(mut self, line: impl Into<String>) -> Self {
+        self.line = Some(line.into());
+        self
+    }
+
+    /// Match entries whose host starts with the given prefix, e.g. `10.` for a private network.
+    pub fn host_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.host_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Match entries from addresses in the given range, e.g. a subnet of bastion hosts.
+    ///
+    /// The address of an entry is its `address`, or its host if it's an IP address,
+    /// and entries without an address don't match.
+    /// It can be called multiple times to match entries in any of the ranges.
+    pub fn cidr(mut self, cidr: Cidr) -> Self {
+        self.cidrs.push(cidr);
+        self
+    }
+
+    /// Match entries not from addresses in the given range, e.g. `10.0.0.0/8` for internal ones.
+    ///
+    /// Entries without an address match. It can be called multiple times to exclude more ranges.
+    pub fn exclude_cidr(mut self, cidr: Cidr) -> Self {
+        self.excluded_cidrs.push(cidr);
+        self
+    }
+
+    /// Match entries of processes with the given pid.
+    pub fn pid(mut self, pid: pid_t) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    /// Match entries of the given kind.
+    ///
+    /// It can be called multiple times to match entries of any of the kinds.
+    pub fn kind(mut self, kind: UtmpEntryKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Match entries made at or after `from` and before `to`.
+    pub fn between(self, from: Timestamp, to: Timestamp) -> Self {
+        self.since(from).until(to)
+    }
+
+    /// Match entries made at or after the given time.
+    pub fn since(mut self, from: Timestamp) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Match entries made before the given time.
+    pub fn until(mut self, to: Timestamp) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Whether the entry matches all the conditions.
+    pub fn matches(&self, entry: &UtmpEntry) -> bool {
+        let is = |expected: &Option<String>, actual: Option<&str>| match expected {
+            Some(expected) => actual == Some(expected),
+            None => true,
+        };
+        is(&self.user, entry_user(entry))
+            && is(&self.line, entry_line(entry))
+            && (self.host_prefix.as_ref()).map_or(true, |prefix| {
+                entry_host(entry).is_some_and(|h| h.starts_with(&**prefix))
+            })
+            && self.matches_address(entry_address(entry), entry_host(entry).unwrap_or_default())
+            && self.pid.map_or(true, |pid| entry.pid() == Some(pid))
+            && (self.kinds.is_empty() || self.kinds.contains(&entry.kind()))
+            && self.matches_time(entry.time())
+    }
+
+    /// Whether the address, or the host if there is no address, is in the ranges of the query,
+    /// e.g. to filter `Session`s with `address` and `host`.
+    pub fn matches_address(&self, address: Option<IpAddr>, host: &str) -> bool {
+        if self.cidrs.is_empty() && self.excluded_cidrs.is_empty() {
+            return true;
+        }
+        let address = address.or_else(|| host.parse().ok());
+        let in_any = |cidrs: &[Cidr]| address.is_some_and(|a| cidrs.iter().any(|c| c.contains(a)));
+        (self.cidrs.is_empty() || in_any(&self.cidrs)) && !in_any(&self.excluded_cidrs)
+    }
+
+    /// Whether the time is within the period, if the query has one.
+    pub(crate) fn matches_time(&self, time: Option<Timestamp>) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        time.is_some_and(|time| {
+            self.from.map_or(true, |from| from <= time) && self.to.map_or(true, |to| time < to)
+        })
+    }
+
+    /// Keep the matching entries of the given iterator.
+    pub fn apply<'a, I>(&'a self, entries: I) -> impl Iterator<Item = I::Item> + 'a
+    where
+        I: IntoIterator,
+        I::IntoIter: 'a,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        entries
+            .into_iter()
+            .filter(move |entry| self.matches(entry.borrow()))
+    }
+}
+
+fn entry_address(entry: &UtmpEntry) -> Option<IpAddr> {
+    match entry {
+        UtmpEntry::UserProcess { address, .. } => *address,
+        _ => None,
+    }
+}
+
+fn entry_host(entry: &UtmpEntry) -> Option<&str> {
+    match entry {
+        UtmpEntry::LoginProcess { host, .. } | UtmpEntry::UserProcess { host, .. } => Some(host),
+        _ => None,
+    }
+}
@@ -0,0 +1,301 @@
+use std::convert::TryFrom;
+use std::mem;
+use utmp_raw::exit_status;
+use utmp_raw::x32::utmp as utmp32;
+use utmp_raw::x64::{timeval as timeval64, utmp as utmp64};
+use utmp_raw::{c_short, pid_t, UT_HOSTSIZE, UT_LINESIZE, UT_NAMESIZE};
+use zerocopy::FromBytes;
+
+/// Common access to fields of raw utmp records in different layouts.
+pub(crate) trait RawUtmp: FromBytes + 'static {
+    /// Offset of `ut_pid` in the record.
+    const PID_OFFSET: usize;
+    /// Offset of `ut_line` in the record.
+    const LINE_OFFSET: usize;
+    /// Offset of `ut_id` in the record.
+    const ID_OFFSET: usize;
+    /// Offset of `ut_user` in the record.
+    const USER_OFFSET: usize;
+    /// Offset of `ut_host` in the record.
+    const HOST_OFFSET: usize;
+    /// Offset of `ut_exit` in the record.
+    const EXIT_OFFSET: usize;
+    /// Offset of `ut_session` in the record.
+    const SESSION_OFFSET: usize;
+    /// Offset of `ut_tv` in the record.
+    const TV_OFFSET: usize;
+    /// Size of `ut_session` and of each field of `ut_tv`, which differs between layouts.
+    const WORD_SIZE: usize;
+    /// Offset of `ut_addr_v6` in the record.
+    const ADDR_OFFSET: usize;
+    /// Offset of `__unused` in the record.
+    const UNUSED_OFFSET: usize;
+
+    fn ut_type(&self) -> c_short;
+    fn ut_pid(&self) -> pid_t;
+    fn ut_exit(&self) -> exit_status;
+    fn ut_session(&self) -> i64;
+    fn ut_tv(&self) -> timeval64;
+    fn ut_addr_v6(&self) -> [i32; 4];
+    fn ut_line(&self) -> &[u8];
+    fn ut_id(&self) -> &[u8];
+    fn ut_user(&self) -> &[u8];
+    fn ut_host(&self) -> &[u8];
+    fn unused(&self) -> &[u8];
+
+    /// Swap the byte order of numeric fields, for records from a target of the other endian.
+    ///
+    /// `ut_addr_v6` is left as is, since it holds the address in network byte order.
+    fn swap_bytes(&mut self);
+
+    /// Set the microseconds of `ut_tv` to zero.
+    fn clear_tv_usec(&mut self);
+}
+
+impl RawUtmp for utmp32 {
+    const PID_OFFSET: usize = mem::offset_of!(utmp32, ut_pid);
+    const LINE_OFFSET: usize = mem::offset_of!(utmp32, ut_line);
+    const ID_OFFSET: usize = mem::offset_of!(utmp32, ut_id);
+    const USER_OFFSET: usize = mem::offset_of!(utmp32, ut_user);
+    const HOST_OFFSET: usize = mem::offset_of!(utmp32, ut_host);
+    const EXIT_OFFSET: usize = mem::offset_of!(utmp32, ut_exit);
+    const SESSION_OFFSET: usize = mem::offset_of!(utmp32, ut_session);
+    const TV_OFFSET: usize = mem::offset_of!(utmp32, ut_tv);
+    const WORD_SIZE: usize = 4;
+    const ADDR_OFFSET: usize = mem::offset_of!(utmp32, ut_addr_v6);
+    const UNUSED_OFFSET: usize = mem::offset_of!(utmp32, __unused);
+
+    fn ut_type(&self) -> c_short {
+        self.ut_type
+    }
+
+    fn ut_pid(&self) -> pid_t {
+        self.ut_pid
+    }
+
+    fn ut_exit(&self) -> exit_status {
+        self.ut_exit
+    }
+
+    fn ut_session(&self) -> i64 {
+        i64::from(self.ut_session)
+    }
+
+    fn ut_tv(&self) -> timeval64 {
+        timeval64 {
+            tv_sec: i64::from(self.ut_tv.tv_sec),
+            tv_usec: i64::from(self.ut_tv.tv_usec),
+        }
+    }
+
+    fn ut_addr_v6(&self) -> [i32; 4] {
+        self.ut_addr_v6
+    }
+
+    fn ut_line(&self) -> &[u8] {
+        &self.ut_line
+    }
+
+    fn ut_id(&self) -> &[u8] {
+        &self.ut_id
+    }
+
+    fn ut_user(&self) -> &[u8] {
+        &self.ut_user
+    }
+
+    fn ut_host(&self) -> &[u8] {
+        &self.ut_host
+    }
+
+    fn unused(&self) -> &[u8] {
+        &self.__unused
+    }
+
+    fn swap_bytes(&mut self) {
+        self.ut_type = self.ut_type.swap_bytes();
+        self.ut_pid = self.ut_pid.swap_bytes();
+        self.ut_exit.e_termination = self.ut_exit.e_termination.swap_bytes();
+        self.ut_exit.e_exit = self.ut_exit.e_exit.swap_bytes();
+        self.ut_session = self.ut_session.swap_bytes();
+        self.ut_tv.tv_sec = self.ut_tv.tv_sec.swap_bytes();
+        self.ut_tv.tv_usec = self.ut_tv.tv_usec.swap_bytes();
+    }
+
+    fn clear_tv_usec(&mut self) {
+        self.ut_tv.tv_usec = 0;
+    }
+}
+
+impl RawUtmp for utmp64 {
+    const PID_OFFSET: usize = mem::offset_of!(utmp64, ut_pid);
+    const LINE_OFFSET: usize = mem::offset_of!(utmp64, ut_line);
+    const ID_OFFSET: usize = mem::offset_of!(utmp64, ut_id);
+    const USER_OFFSET: usize = mem::offset_of!(utmp64, ut_user);
+    const HOST_OFFSET: usize = mem::offset_of!(utmp64, ut_host);
+    const EXIT_OFFSET: usize = mem::offset_of!(utmp64, ut_exit);
+    const SESSION_OFFSET: usize = mem::offset_of!(utmp64, ut_session);
+    const TV_OFFSET: usize = mem::offset_of!(utmp64, ut_tv);
+    const WORD_SIZE: usize = 8;
+    const ADDR_OFFSET: usize = mem::offset_of!(utmp64, ut_addr_v6);
+    const UNUSED_OFFSET: usize = mem::offset_of!(utmp64, __unused);
+
+    fn ut_type(&self) -> c_short {
+        self.ut_type
+    }
+
+    fn ut_pid(&self) -> pid_t {
+        self.ut_pid
+    }
+
+    fn ut_exit(&self) -> exit_status {
+        self.ut_exit
+    }
+
+    fn ut_session(&self) -> i64 {
+        self.ut_session
+    }
+
+    fn ut_tv(&self) -> timeval64 {
+        self.ut_tv
+    }
+
+    fn ut_addr_v6(&self) -> [i32; 4] {
+        self.ut_addr_v6
+    }
+
+    fn ut_line(&self) -> &[u8] {
+        &self.ut_line
+    }
+
+    fn ut_id(&self) -> &[u8] {
+        &self.ut_id
+    }
+
+    fn ut_user(&self) -> &[u8] {
+        &self.ut_user
+    }
+
+    fn ut_host(&self) -> &[u8] {
+        &self.ut_host
+    }
+
+    fn unused(&self) -> &[u8] {
+        &self.__unused
+    }
+
+    fn swap_bytes(&mut self) {
+        self.ut_type = self.ut_type.swap_bytes();
+        self.ut_pid = self.ut_pid.swap_bytes();
+        self.ut_exit.e_termination = self.ut_exit.e_termination.swap_bytes();
+        self.ut_exit.e_exit = self.ut_exit.e_exit.swap_bytes();
+        self.ut_session = self.ut_session.swap_bytes();
+        self.ut_tv.tv_sec = self.ut_tv.tv_sec.swap_bytes();
+        self.ut_tv.tv_usec = self.ut_tv.tv_usec.swap_bytes();
+    }
+
+    fn clear_tv_usec(&mut self) {
+        self.ut_tv.tv_usec = 0;
+    }
+}
+
+/// Whether the record looks like a valid one, with a known type and a sane time.
+pub(crate) fn is_plausible<T: RawUtmp>(raw: &T) -> bool {
+    let known_type = (utmp_raw::EMPTY..=utmp_raw::ACCOUNTING).contains(&raw.ut_type());
+    let timeval64 { tv_sec, tv_usec } = raw.ut_tv();
+    // Records are made after 1973 and their time fits in 32 bits, unless it's unset.
+    let sane_time = (tv_sec == 0 && tv_usec == 0)
+        || ((100_000_000..=i64::from(u32::MAX)).contains(&tv_sec)
+            && (0..1_000_000).contains(&tv_usec));
+    known_type && sane_time
+}
+
+/// Values of the fields of a record independent of its layout, for writing records.
+pub(crate) struct RecordValues<'a> {
+    pub(crate) ut_type: c_short,
+    pub(crate) pid: pid_t,
+    pub(crate) line: &'a [u8],
+    pub(crate) id: &'a [u8],
+    pub(crate) user: &'a [u8],
+    pub(crate) host: &'a [u8],
+    pub(crate) exit: exit_status,
+    pub(crate) session: i64,
+    pub(crate) tv: timeval64,
+    /// Address in network byte order
+    pub(crate) addr: [u8; 16],
+    pub(crate) unused: &'a [u8],
+}
+
+impl<'a> RecordValues<'a> {
+    /// Values of the given record.
+    pub(crate) fn of<T: RawUtmp>(raw: &'a T) -> Self {
+        let mut addr = [0; 16];
+        for (chunk, word) in addr.chunks_exact_mut(4).zip(raw.ut_addr_v6().iter()) {
+            chunk.copy_from_slice(&word.to_ne_bytes());
+        }
+        RecordValues {
+            ut_type: raw.ut_type(),
+            pid: raw.ut_pid(),
+            line: raw.ut_line(),
+            id: raw.ut_id(),
+            user: raw.ut_user(),
+            host: raw.ut_host(),
+            exit: raw.ut_exit(),
+            session: raw.ut_session(),
+            tv: raw.ut_tv(),
+            addr,
+            unused: raw.unused(),
+        }
+    }
+
+    /// Write the values into the zeroed record in the layout of `T`,
+    /// with numeric fields in the other byte order if `swap`.
+    ///
+    /// Strings are truncated to the size of their fields.
+    /// It fails with the name of the field whose value doesn't fit, e.g. `ut_tv` after 2038 in 32-bit.
+    pub(crate) fn write<T: RawUtmp>(
+        &self,
+        record: &mut [u8],
+        swap: bool,
+    ) -> Result<(), &'static str> {
+        let word = |value: i64, field| -> Result<Vec<u8>, &'static str> {
+            if T::WORD_SIZE == 4 {
+                let value = i32::try_from(value).map_err(|_| field)?;
+                Ok(value.to_ne_bytes().to_vec())
+            } else {
+                Ok(value.to_ne_bytes().to_vec())
+            }
+        };
+        let session = word(self.session, "ut_session")?;
+        let tv_sec = word(self.tv.tv_sec, "ut_tv")?;
+        let tv_usec = word(self.tv.tv_usec, "ut_tv")?;
+
+        let mut put = |offset: usize, value: &[u8]| {
+            let field = &mut record[offset..offset + value.len()];
+            field.copy_from_slice(value);
+            if swap {
+                field.reverse();
+            }
+        };
+        put(0, &self.ut_type.to_ne_bytes());
+        put(T::PID_OFFSET, &self.pid.to_ne_bytes());
+        put(T::EXIT_OFFSET, &self.exit.e_termination.to_ne_bytes());
+        put(T::EXIT_OFFSET + 2, &self.exit.e_exit.to_ne_bytes());
+        put(T::SESSION_OFFSET, &session);
+        put(T::TV_OFFSET, &tv_sec);
+        put(T::TV_OFFSET + T::WORD_SIZE, &tv_usec);
+
+        let mut put_bytes = |offset: usize, len: usize, value: &[u8]| {
+            let value = &value[..value.len().min(len)];
+            record[offset..offset + value.len()].copy_from_slice(value);
+        };
+        put_bytes(T::LINE_OFFSET, UT_LINESIZE, self.line);
+        put_bytes(T::ID_OFFSET, 4, self.id);
+        put_bytes(T::USER_OFFSET, UT_NAMESIZE, self.user);
+        put_bytes(T::HOST_OFFSET, UT_HOSTSIZE, self.host);
+        // The address is in network byte order, which is never swapped.
+        put_bytes(T::ADDR_OFFSET, 16, &self.addr);
+        put_bytes(T::UNUSED_OFFSET, 20, self.unused);
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use crate::parse::UtmpParserImpl;
+use crate::{ParseError, UtmpEntry};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use utmp_raw::{utmp, x32::utmp as utmp32, x64::utmp as utmp64};
+
+/// Extensions of compressed generations left by logrotate.
+const COMPRESSED_EXTENSIONS: &[&str] = &["gz", "xz", "zst", "bz2"];
+
+/// Function which wraps a compressed file into a reader of its decompressed content.
+pub type Decoder = Box<dyn Fn(File) -> io::Result<Box<dyn Read + Send>> + Send>;
+
+#[doc(hidden)]
+pub struct RotatedParserImpl<T = utmp> {
+    paths: VecDeque<PathBuf>,
+    decoders: Vec<(String, Decoder)>,
+    current: Option<CurrentParser<T>>,
+}
+
+/// Parser of the generation being parsed.
+enum CurrentParser<T> {
+    /// Uncompressed file, read in blocks by the parser itself
+    File(UtmpParserImpl<BufReader<File>, T>),
+    /// Compressed file, read through its decoder
+    Decoded(UtmpParserImpl<BufReader<Box<dyn Read + Send>>, T>),
+}
+
+impl<T> RotatedParserImpl<T> {
+    /// Create a parser for the given path and its rotated generations.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        Ok(RotatedParserImpl {
+            paths: rotated_paths(path.as_ref())?.into(),
+            decoders: Vec::new(),
+            current: None,
+        })
+    }
+
+    /// Decompress generations with the given extension using the given decoder.
+    ///
+    /// Only generations with one of the extensions `gz`, `xz`, `zst` and `bz2` are discovered.
+    ///
+    /// ```no_run
+    /// # use utmp_rs::RotatedParser;
+    /// # use std::io::Read;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # struct GzDecoder<R>(R);
+    /// # impl<R: Read> Read for GzDecoder<R> {
+    /// #     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> { self.0.read(buf) }
+    /// # }
+    /// # impl<R> GzDecoder<R> { fn new(r: R) -> Self { GzDecoder(r) } }
+    /// // `GzDecoder` from e.g. the `flate2` crate
+    /// let parser = RotatedParser::from_path("/var/log/wtmp")?
+    ///     .with_decoder("gz", |file| Ok(Box::new(GzDecoder::new(file))));
+    /// for entry in parser {
+    ///     let entry = entry?;
+    ///     // handle entry
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_decoder<F>(mut self, extension: &str, decoder: F) -> Self
+    where
+        F: Fn(File) -> io::Result<Box<dyn Read + Send>> + Send + 'static,
+    {
+        self.decoders
+            .push((extension.to_owned(), Box::new(decoder)));
+        self
+    }
+
+    fn open(&self, path: &Path) -> io::Result<CurrentParser<T>> {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        let decoder = self
+            .decoders
+            .iter()
+            .find(|(ext, _)| Some(ext.as_str()) == extension);
+        match decoder {
+            Some((_, decoder)) => {
+                let reader = BufReader::new(decoder(File::open(path)?)?);
+                let parser = UtmpParserImpl::from_reader(reader).with_path(path);
+                Ok(CurrentParser::Decoded(parser))
+            }
+            None if is_compressed(path) => {
+                let msg = "no decoder for compressed generation";
+                Err(io::Error::new(io::ErrorKind::InvalidData, msg))
+            }
+            None => Ok(CurrentParser::File(UtmpParserImpl::from_path(path)?)),
+        }
+    }
+
+    /// Paths of generations which haven't been parsed yet, from the oldest.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+}
+
+/// Parser which parses a utmp file together with its rotated generations,
+/// like `wtmp.2.gz`, `wtmp.1` and `wtmp`, in chronological order.
+///
+/// Both numbered generations and dated generations (logrotate's `dateext`)
+/// are discovered from the directory of the given path.
+/// Compressed generations can be parsed with a decoder set via `with_decoder`,
+/// otherwise they are yielded as an error,
+/// and parsing continues with the next generation.
+///
+/// ```
+/// # use utmp_rs::RotatedParser;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for entry in RotatedParser::from_path("/var/run/utmp")? {
+///     let entry = entry?;
+///     // handle entry
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub type RotatedParser = RotatedParserImpl<utmp>;
+
+/// Parser to parse a 32-bit utmp file and its rotated generations.
+pub type Rotated32Parser = RotatedParserImpl<utmp32>;
+
+/// Parser to parse a 64-bit utmp file and its rotated generations.
+pub type Rotated64Parser = RotatedParserImpl<utmp64>;
+
+impl<T> Iterator for RotatedParserImpl<T>
+where
+    UtmpParserImpl<BufReader<File>, T>: Iterator<Item = Result<UtmpEntry, ParseError>>,
+    UtmpParserImpl<BufReader<Box<dyn Read + Send>>, T>:
+        Iterator<Item = Result<UtmpEntry, ParseError>>,
+{
+    type Item = Result<UtmpEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match &mut self.current {
+                Some(CurrentParser::File(parser)) => parser.next(),
+                Some(CurrentParser::Decoded(parser)) => parser.next(),
+                None => None,
+            };
+            if let Some(entry) = entry {
+                return Some(entry);
+            }
+            let path = self.paths.pop_front()?;
+            match self.open(&path) {
+                Ok(current) => self.current = Some(current),
+                Err(e) => {
+                    self.current = None;
+                    return Some(Err(ParseError::from(e).with_path(&path)));
+                }
+            }
+        }
+    }
+}
+
+/// Generation of a rotated file, identified by its suffix.
+#[derive(Debug, Eq, PartialEq)]
+enum Generation {
+    /// Dated generation like `wtmp-20200101`
+    Dated(String),
+    /// Numbered generation like `wtmp.1`
+    Numbered(u32),
+    /// The file itself
+    Current,
+}
+
+impl Ord for Generation {
+    /// Older generations are ordered first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        use Generation::*;
+        match (self, other) {
+            (Dated(a), Dated(b)) => a.cmp(b),
+            (Numbered(a), Numbered(b)) => b.cmp(a),
+            (Current, Current) => Ordering::Equal,
+            (Dated(_), _) | (Numbered(_), Current) => Ordering::Less,
+            _ => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Generation {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Find the given path and its rotated generations, from the oldest.
+fn rotated_paths(path: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid file name"))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut generations = Vec::new();
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+        let file_name = dir_entry.file_name();
+        let generation = file_name
+            .to_str()
+            .and_then(|file_name| file_name.strip_prefix(name))
+            .and_then(generation_from_suffix);
+        if let Some(generation) = generation {
+            generations.push((generation, dir.join(&file_name)));
+        }
+    }
+    generations.sort();
+    Ok(generations.into_iter().map(|(_, path)| path).collect())
+}
+
+fn generation_from_suffix(suffix: &str) -> Option<Generation> {
+    if suffix.is_empty() {
+        return Some(Generation::Current);
+    }
+    let suffix = COMPRESSED_EXTENSIONS
+        .iter()
+        .find_map(|ext| suffix.strip_suffix(ext)?.strip_suffix('.'))
+        .unwrap_or(suffix);
+    if let Some(number) = suffix.strip_prefix('.') {
+        return number.parse().ok().map(Generation::Numbered);
+    }
+    let date = suffix.strip_prefix('-')?;
+    let is_date = !date.is_empty() && date.bytes().all(|b| b.is_ascii_digit() || b == b'-');
+    is_date.then(|| Generation::Dated(date.to_owned()))
+}
+
+fn is_compressed(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| COMPRESSED_EXTENSIONS.contains(&ext))
+}
@@ -0,0 +1,371 @@
+use crate::timestamp::format_rfc3339;
+use crate::{Timestamp, UtmpEntry};
+use serde_core::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde_core::ser::{Serialize, SerializeMap, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Range;
+use std::str::{self, FromStr};
+use time::{Date, Month, PrimitiveDateTime, Time, UtcOffset};
+use utmp_raw::{c_short, pid_t};
+
+/// Entries are represented as maps with a `type` field naming the variant,
+/// alongside the fields of the variant.
+/// Times are represented as RFC 3339 strings in UTC,
+/// addresses as strings, which are omitted when absent,
+/// run-levels as single characters, of which the previous one is omitted when absent,
+/// and raw records of unknown types as hexadecimal strings.
+///
+/// For example, a `UserProcess` entry in JSON looks like:
+/// ```json
+/// {
+///   "type": "UserProcess",
+///   "pid": 1125,
+///   "line": "pts/0",
+///   "id": "ts/0",
+///   "user": "root",
+///   "host": "112.124.2.209",
+///   "address": "112.124.2.209",
+///   "session": 0,
+///   "time": "2023-02-07T08:07:06.139552Z"
+/// }
+/// ```
+impl Serialize for UtmpEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            UtmpEntry::Empty => map.serialize_entry("type", "Empty")?,
+            UtmpEntry::RunLevel {
+                level,
+                previous,
+                kernel_version,
+                time,
+            } => {
+                map.serialize_entry("type", "RunLevel")?;
+                map.serialize_entry("level", level)?;
+                if let Some(previous) = previous {
+                    map.serialize_entry("previous", previous)?;
+                }
+                map.serialize_entry("kernel_version", kernel_version)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::BootTime {
+                kernel_version,
+                time,
+            } => {
+                map.serialize_entry("type", "BootTime")?;
+                map.serialize_entry("kernel_version", kernel_version)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::ShutdownTime {
+                kernel_version,
+                time,
+            } => {
+                map.serialize_entry("type", "ShutdownTime")?;
+                map.serialize_entry("kernel_version", kernel_version)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::NewTime(time) => {
+                map.serialize_entry("type", "NewTime")?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::OldTime(time) => {
+                map.serialize_entry("type", "OldTime")?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::InitProcess { pid, id, time } => {
+                map.serialize_entry("type", "InitProcess")?;
+                map.serialize_entry("pid", pid)?;
+                map.serialize_entry("id", id)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::LoginProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                time,
+            } => {
+                map.serialize_entry("type", "LoginProcess")?;
+                map.serialize_entry("pid", pid)?;
+                map.serialize_entry("line", line)?;
+                map.serialize_entry("id", id)?;
+                map.serialize_entry("user", user)?;
+                map.serialize_entry("host", host)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::UserProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                address,
+                session,
+                time,
+            } => {
+                map.serialize_entry("type", "UserProcess")?;
+                map.serialize_entry("pid", pid)?;
+                map.serialize_entry("line", line)?;
+                map.serialize_entry("id", id)?;
+                map.serialize_entry("user", user)?;
+                map.serialize_entry("host", host)?;
+                if let Some(address) = address {
+                    map.serialize_entry("address", &address.to_string())?;
+                }
+                map.serialize_entry("session", session)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::DeadProcess {
+                pid,
+                line,
+                id,
+                termination,
+                exit,
+                time,
+            } => {
+                map.serialize_entry("type", "DeadProcess")?;
+                map.serialize_entry("pid", pid)?;
+                map.serialize_entry("line", line)?;
+                map.serialize_entry("id", id)?;
+                map.serialize_entry("termination", termination)?;
+                map.serialize_entry("exit", exit)?;
+                map.serialize_entry("time", &format_rfc3339(*time))?;
+            }
+            UtmpEntry::Accounting => map.serialize_entry("type", "Accounting")?,
+            UtmpEntry::Unknown { ut_type, pid, raw } => {
+                map.serialize_entry("type", "Unknown")?;
+                map.serialize_entry("ut_type", ut_type)?;
+                map.serialize_entry("pid", pid)?;
+                map.serialize_entry("raw", &encode_hex(raw))?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for UtmpEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(EntryVisitor)
+    }
+}
+
+struct EntryVisitor;
+
+impl<'de> Visitor<'de> for EntryVisitor {
+    type Value = UtmpEntry;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a utmp entry")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<UtmpEntry, A::Error> {
+        let mut ty: Option<String> = None;
+        let mut pid: Option<pid_t> = None;
+        let mut level: Option<char> = None;
+        let mut previous: Option<char> = None;
+        let mut line: Option<String> = None;
+        let mut id: Option<String> = None;
+        let mut user: Option<String> = None;
+        let mut host: Option<String> = None;
+        let mut address: Option<String> = None;
+        let mut session: Option<pid_t> = None;
+        let mut ut_type: Option<c_short> = None;
+        let mut termination: Option<c_short> = None;
+        let mut exit: Option<c_short> = None;
+        let mut kernel_version: Option<String> = None;
+        let mut raw: Option<String> = None;
+        let mut time: Option<String> = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "type" => ty = Some(map.next_value()?),
+                "pid" => pid = Some(map.next_value()?),
+                "level" => level = Some(map.next_value()?),
+                "previous" => previous = Some(map.next_value()?),
+                "line" => line = Some(map.next_value()?),
+                "id" => id = Some(map.next_value()?),
+                "user" => user = Some(map.next_value()?),
+                "host" => host = Some(map.next_value()?),
+                "address" => address = Some(map.next_value()?),
+                "session" => session = Some(map.next_value()?),
+                "ut_type" => ut_type = Some(map.next_value()?),
+                "termination" => termination = Some(map.next_value()?),
+                "exit" => exit = Some(map.next_value()?),
+                "kernel_version" => kernel_version = Some(map.next_value()?),
+                "time" => time = Some(map.next_value()?),
+                "raw" => raw = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        fn field<T, E: de::Error>(value: Option<T>, name: &'static str) -> Result<T, E> {
+            value.ok_or_else(|| E::missing_field(name))
+        }
+        let time = || {
+            let time = field(time.as_deref(), "time")?;
+            parse_time(time).ok_or_else(|| {
+                de::Error::invalid_value(de::Unexpected::Str(time), &"an RFC 3339 time")
+            })
+        };
+        let ty = field(ty, "type")?;
+        Ok(match ty.as_str() {
+            "Empty" => UtmpEntry::Empty,
+            "RunLevel" => UtmpEntry::RunLevel {
+                level: field(level, "level")?,
+                previous,
+                kernel_version: field(kernel_version, "kernel_version")?,
+                time: time()?,
+            },
+            "BootTime" => UtmpEntry::BootTime {
+                kernel_version: field(kernel_version, "kernel_version")?,
+                time: time()?,
+            },
+            "ShutdownTime" => UtmpEntry::ShutdownTime {
+                kernel_version: field(kernel_version, "kernel_version")?,
+                time: time()?,
+            },
+            "NewTime" => UtmpEntry::NewTime(time()?),
+            "OldTime" => UtmpEntry::OldTime(time()?),
+            "InitProcess" => UtmpEntry::InitProcess {
+                pid: field(pid, "pid")?,
+                id: field(id, "id")?,
+                time: time()?,
+            },
+            "LoginProcess" => UtmpEntry::LoginProcess {
+                pid: field(pid, "pid")?,
+                line: field(line, "line")?,
+                id: field(id, "id")?,
+                user: field(user, "user")?,
+                host: field(host, "host")?,
+                time: time()?,
+            },
+            "UserProcess" => UtmpEntry::UserProcess {
+                pid: field(pid, "pid")?,
+                line: field(line, "line")?,
+                id: field(id, "id")?,
+                user: field(user, "user")?,
+                host: field(host, "host")?,
+                address: match address {
+                    Some(address) => Some(address.parse().map_err(|_| {
+                        de::Error::invalid_value(de::Unexpected::Str(&address), &"an IP address")
+                    })?),
+                    None => None,
+                },
+                session: field(session, "session")?,
+                time: time()?,
+            },
+            "DeadProcess" => UtmpEntry::DeadProcess {
+                pid: field(pid, "pid")?,
+                line: field(line, "line")?,
+                id: field(id, "id")?,
+                termination: field(termination, "termination")?,
+                exit: field(exit, "exit")?,
+                time: time()?,
+            },
+            "Accounting" => UtmpEntry::Accounting,
+            "Unknown" => UtmpEntry::Unknown {
+                ut_type: field(ut_type, "ut_type")?,
+                pid: field(pid, "pid")?,
+                raw: {
+                    let raw = field(raw.as_deref(), "raw")?;
+                    decode_hex(raw).ok_or_else(|| {
+                        de::Error::invalid_value(de::Unexpected::Str(raw), &"hexadecimal bytes")
+                    })?
+                },
+            },
+            _ => return Err(de::Error::unknown_variant(&ty, VARIANTS)),
+        })
+    }
+}
+
+const VARIANTS: &[&str] = &[
+    "Empty",
+    "RunLevel",
+    "BootTime",
+    "ShutdownTime",
+    "NewTime",
+    "OldTime",
+    "InitProcess",
+    "LoginProcess",
+    "UserProcess",
+    "DeadProcess",
+    "Accounting",
+    "Unknown",
+];
+
+/// Format the bytes as lowercase hexadecimal digits.
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse bytes formatted by `encode_hex`.
+fn decode_hex(s: &str) -> Option<Box<[u8]>> {
+    if s.len() % 2 != 0 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let digits = (0..s.len()).step_by(2);
+    digits
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Parse an RFC 3339 time, i.e. `YYYY-MM-DDTHH:MM:SS[.fraction](Z|+HH:MM|-HH:MM)`.
+fn parse_time(s: &str) -> Option<Timestamp> {
+    fn number<T: FromStr>(s: &str, len: usize) -> Option<T> {
+        if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().ok()
+        } else {
+            None
+        }
+    }
+    let s = s.as_bytes();
+    let text = |range: Range<usize>| str::from_utf8(s.get(range)?).ok();
+    if s.len() < 20 || s[4] != b'-' || s[7] != b'-' || !matches!(s[10], b'T' | b't' | b' ') {
+        return None;
+    }
+    if s[13] != b':' || s[16] != b':' {
+        return None;
+    }
+    let month = Month::try_from(number::<u8>(text(5..7)?, 2)?).ok()?;
+    let date = Date::from_calendar_date(number(text(0..4)?, 4)?, month, number(text(8..10)?, 2)?);
+    let mut rest = 19;
+    let mut nanos = 0;
+    if s[rest] == b'.' {
+        let digits = s[rest + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return None;
+        }
+        for (i, b) in s[rest + 1..][..digits].iter().enumerate().take(9) {
+            nanos += u32::from(b - b'0') * 10u32.pow(8 - i as u32);
+        }
+        rest += digits + 1;
+    }
+    let offset = match s.get(rest)? {
+        b'Z' | b'z' if s.len() == rest + 1 => UtcOffset::UTC,
+        sign @ (b'+' | b'-') if s.len() == rest + 6 && s[rest + 3] == b':' => {
+            let hours: i8 = number(text(rest + 1..rest + 3)?, 2)?;
+            let minutes: i8 = number(text(rest + 4..rest + 6)?, 2)?;
+            let (hours, minutes) = if *sign == b'-' {
+                (-hours, -minutes)
+            } else {
+                (hours, minutes)
+            };
+            UtcOffset::from_hms(hours, minutes, 0).ok()?
+        }
+        _ => return None,
+    };
+    let time = Time::from_hms_nano(
+        number(text(11..13)?, 2)?,
+        number(text(14..16)?, 2)?,
+        number(text(17..19)?, 2)?,
+        nanos,
+    );
+    let datetime = PrimitiveDateTime::new(date.ok()?, time.ok()?);
+    Some(datetime.assume_offset(offset).into())
+}
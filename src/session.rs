@@ -0,0 +1,373 @@
+use crate::{ParseError, Timestamp, UtmpEntry, UtmpParser};
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::path::Path;
+use time::Duration;
+use utmp_raw::pid_t;
+
+/// Login session reconstructed from utmp entries.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Session {
+    /// Username
+    pub user: String,
+    /// Device name of tty
+    pub line: String,
+    /// Hostname for remote login
+    pub host: String,
+    /// Internet address of remote host
+    pub address: Option<IpAddr>,
+    /// PID of login process
+    pub pid: pid_t,
+    /// Time of login
+    pub login: Timestamp,
+    /// Time the session ended, if it did
+    pub logout: Option<Timestamp>,
+    /// Time elapsed from login to logout if the session ended,
+    /// or to the reference time if it's still logged in
+    pub duration: Option<Duration>,
+    /// How the session ended
+    pub end: SessionEnd,
+}
+
+/// How a session ended.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum SessionEnd {
+    /// User logged out, i.e. there is a matching `DeadProcess` entry
+    Logout,
+    /// Another login on the same tty line followed without a logout in between
+    NextLogin,
+    /// System shut down
+    Shutdown,
+    /// System booted again without shutting down, e.g. after a crash
+    Crash,
+    /// Session has not ended in the live wtmp, see `Sessions::live_at`
+    LoggedIn,
+    /// Session has not ended in the live wtmp, but its process no longer exists,
+    /// see `last_from_path`
+    Gone,
+    /// No record ending the session is in the entries given,
+    /// e.g. since they are from an archived generation of wtmp
+    Missing,
+}
+
+/// Iterator of sessions reconstructed from utmp entries, see `sessions`.
+#[derive(Clone, Debug)]
+pub struct Sessions<I> {
+    entries: I,
+    tracker: SessionTracker,
+    /// Reference time if the entries are from the live wtmp.
+    now: Option<Timestamp>,
+    done: bool,
+}
+
+impl<I> Sessions<I> {
+    /// Treat the entries as the live wtmp at the given time,
+    /// so that sessions not ended in them are still logged in,
+    /// instead of missing their logout records.
+    pub fn live_at(mut self, now: Timestamp) -> Self {
+        self.now = Some(now);
+        self
+    }
+}
+
+impl<I> Iterator for Sessions<I>
+where
+    I: Iterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    type Item = Session;
+
+    fn next(&mut self) -> Option<Session> {
+        loop {
+            if let Some(session) = self.tracker.ended.pop_front() {
+                return Some(session);
+            }
+            if self.done {
+                return None;
+            }
+            match self.entries.next() {
+                Some(entry) => self.tracker.feed(entry.borrow()),
+                None => {
+                    self.tracker.finish(self.now);
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+/// Reconstruct sessions from the given entries, which are expected to be in order of time,
+/// as in wtmp.
+///
+/// Logins are paired with the first following `DeadProcess` entry of the same tty line,
+/// or of the same `inittab(5)` ID if the line is empty,
+/// preferring the one with the same PID if multiple logins are open on the line.
+/// Logins open when the system shuts down or boots again end at that time.
+///
+/// Sessions are yielded in the order they end,
+/// followed by the ones not ended in the entries, in the order of login,
+/// which are `SessionEnd::Missing` unless `Sessions::live_at` is used.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for session in utmp_rs::sessions(utmp_rs::parse_from_path("/var/log/wtmp")?) {
+///     println!("{} on {} for {:?}", session.user, session.line, session.duration);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn sessions<I>(entries: I) -> Sessions<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    Sessions {
+        entries: entries.into_iter(),
+        tracker: SessionTracker::default(),
+        now: None,
+        done: false,
+    }
+}
+
+/// Users currently logged in according to the given utmp file, like `who(1)` shows.
+///
+/// Each `UserProcess` entry gives a session still logged in, without a duration,
+/// and only the latest one is kept if there are multiple on the same tty line.
+/// Sessions are in order of login.
+///
+/// Entries may be left behind by sessions which didn't clean up,
+/// see `StaleChecker` to find them.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// for session in utmp_rs::current_users("/var/run/utmp")? {
+///     println!("{} {} {}", session.user, session.line, session.login);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn current_users<P: AsRef<Path>>(path: P) -> Result<Vec<Session>, ParseError> {
+    let path = path.as_ref();
+    let parser = UtmpParser::from_path(path).map_err(|e| ParseError::from(e).with_path(path))?;
+    let entries = parser.skip_empty(true).collect::<Result<Vec<_>, _>>()?;
+    let mut sessions = logged_in(&entries).into_values().collect::<Vec<_>>();
+    sessions.sort_by(|a, b| (a.login, &a.line).cmp(&(b.login, &b.line)));
+    Ok(sessions)
+}
+
+/// Sessions logged in according to entries of utmp, keyed by their tty line,
+/// or by their ID if they have no line.
+fn logged_in<I>(entries: I) -> HashMap<(String, String), Session>
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+{
+    let mut sessions = HashMap::<_, Session>::new();
+    for entry in entries {
+        if let UtmpEntry::UserProcess {
+            pid,
+            line,
+            id,
+            user,
+            host,
+            address,
+            time,
+            ..
+        } = entry.borrow()
+        {
+            // The ID tells entries without a line apart.
+            let id = if line.is_empty() {
+                id.clone()
+            } else {
+                String::new()
+            };
+            let key = (line.clone(), id);
+            if sessions.get(&key).is_some_and(|s| s.login > *time) {
+                continue;
+            }
+            let session = Session {
+                user: user.clone(),
+                line: line.clone(),
+                host: host.clone(),
+                address: *address,
+                pid: *pid,
+                login: *time,
+                logout: None,
+                duration: None,
+                end: SessionEnd::LoggedIn,
+            };
+            sessions.insert(key, session);
+        }
+    }
+    sessions
+}
+
+/// Sessions which differ between two snapshots of utmp, see `diff`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct UtmpDiff {
+    /// Sessions logged in since the old snapshot
+    pub added: Vec<Session>,
+    /// Sessions of the old snapshot no longer logged in
+    pub removed: Vec<Session>,
+    /// Sessions in both snapshots with other fields changed, e.g. the host, as old and new
+    pub changed: Vec<(Session, Session)>,
+}
+
+impl UtmpDiff {
+    /// Whether the snapshots have the same sessions.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare sessions in two snapshots of utmp, e.g. `/var/run/utmp` polled by an agent.
+///
+/// Sessions are those `current_users` lists. A session is the same in both
+/// if it's on the same tty line by the same process since the same time,
+/// so another login on the line is a removed session and an added one.
+/// Sessions in the result are in order of login.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let old = utmp_rs::parse_from_path("/var/run/utmp")?;
+/// // Some time later.
+/// let new = utmp_rs::parse_from_path("/var/run/utmp")?;
+/// for session in utmp_rs::diff(&old, &new).added {
+///     println!("{} logged in on {}", session.user, session.line);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn diff<I, J>(old: I, new: J) -> UtmpDiff
+where
+    I: IntoIterator,
+    I::Item: Borrow<UtmpEntry>,
+    J: IntoIterator,
+    J::Item: Borrow<UtmpEntry>,
+{
+    let mut old = logged_in(old);
+    let mut result = UtmpDiff::default();
+    for (key, session) in logged_in(new) {
+        match old.remove(&key) {
+            Some(old) if (old.pid, old.login) != (session.pid, session.login) => {
+                result.removed.push(old);
+                result.added.push(session);
+            }
+            Some(old) if old != session => result.changed.push((old, session)),
+            Some(_) => {}
+            None => result.added.push(session),
+        }
+    }
+    result.removed.extend(old.into_values());
+    let order = |s: &Session| (s.login, s.line.clone());
+    result.added.sort_by_key(order);
+    result.removed.sort_by_key(order);
+    result.changed.sort_by_key(|(_, new)| order(new));
+    result
+}
+
+#[derive(Clone, Debug, Default)]
+struct SessionTracker {
+    /// Logins yet to end, in order of login.
+    open: Vec<Open>,
+    /// Sessions ended but not yielded yet.
+    ended: VecDeque<Session>,
+}
+
+#[derive(Clone, Debug)]
+struct Open {
+    id: String,
+    session: Session,
+}
+
+impl SessionTracker {
+    fn feed(&mut self, entry: &UtmpEntry) {
+        match entry {
+            UtmpEntry::UserProcess {
+                pid,
+                line,
+                id,
+                user,
+                host,
+                address,
+                time,
+                ..
+            } => {
+                if !line.is_empty() {
+                    while let Some(pos) = self.open.iter().position(|o| o.session.line == *line) {
+                        self.end(pos, SessionEnd::NextLogin, *time);
+                    }
+                }
+                self.open.push(Open {
+                    id: id.clone(),
+                    session: Session {
+                        user: user.clone(),
+                        line: line.clone(),
+                        host: host.clone(),
+                        address: *address,
+                        pid: *pid,
+                        login: *time,
+                        logout: None,
+                        duration: None,
+                        end: SessionEnd::Missing,
+                    },
+                });
+            }
+            UtmpEntry::DeadProcess {
+                pid,
+                line,
+                id,
+                time,
+                ..
+            } => {
+                let matches = |o: &Open| {
+                    if line.is_empty() {
+                        !id.is_empty() && o.id == *id
+                    } else {
+                        o.session.line == *line
+                    }
+                };
+                let pos = (self.open.iter())
+                    .position(|o| matches(o) && o.session.pid == *pid)
+                    .or_else(|| self.open.iter().position(matches));
+                if let Some(pos) = pos {
+                    self.end(pos, SessionEnd::Logout, *time);
+                }
+            }
+            UtmpEntry::ShutdownTime { time, .. } => self.end_all(SessionEnd::Shutdown, *time),
+            UtmpEntry::BootTime { time, .. } => self.end_all(SessionEnd::Crash, *time),
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, pos: usize, end: SessionEnd, time: Timestamp) {
+        let mut session = self.open.remove(pos).session;
+        session.logout = Some(time);
+        session.duration = Some(time - session.login);
+        session.end = end;
+        self.ended.push_back(session);
+    }
+
+    fn end_all(&mut self, end: SessionEnd, time: Timestamp) {
+        while !self.open.is_empty() {
+            self.end(0, end, time);
+        }
+    }
+
+    /// Yield the sessions not ended, which are still logged in at `now` if given.
+    fn finish(&mut self, now: Option<Timestamp>) {
+        let open = self.open.drain(..).map(|open| {
+            let mut session = open.session;
+            if let Some(now) = now {
+                session.duration = Some(now - session.login);
+                session.end = SessionEnd::LoggedIn;
+            }
+            session
+        });
+        self.ended.extend(open);
+    }
+}
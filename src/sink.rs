@@ -0,0 +1,168 @@
+use crate::UtmpEntry;
+use std::io;
+use std::iter::FromIterator;
+
+/// Destination of entries, e.g. a file being exported to.
+///
+/// It's implemented by the exporters of this crate, i.e. `CsvWriter`, `JsonWriter`,
+/// `Metrics` and `SyslogSink`, and can be implemented for other destinations.
+/// Sinks can be combined with `FanOut`.
+///
+/// ```
+/// # use utmp_rs::{CsvWriter, EntrySink, FanOut, JsonWriter};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let csv = CsvWriter::entries(std::io::stdout())?;
+/// let json = JsonWriter::lines(std::io::stderr());
+/// let mut sink = FanOut::<Box<dyn EntrySink>>::new()
+///     .with(Box::new(csv))
+///     .with(Box::new(json));
+/// for entry in utmp_rs::parse_from_path("/var/log/wtmp")? {
+///     sink.entry(&entry)?;
+/// }
+/// sink.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait EntrySink {
+    /// Handle an entry.
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()>;
+
+    /// Complete the output after the last entry, e.g. flush buffered writers.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Destination of sessions, e.g. a file being exported to.
+///
+/// It's implemented by `CsvWriter`, and can be implemented for other destinations.
+/// Sinks can be combined with `FanOut`.
+#[cfg(feature = "time")]
+pub trait SessionSink {
+    /// Handle a session.
+    fn session(&mut self, session: &crate::Session) -> io::Result<()>;
+
+    /// Complete the output after the last session, e.g. flush buffered writers.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: EntrySink + ?Sized> EntrySink for &mut S {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        (**self).entry(entry)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+impl<S: EntrySink + ?Sized> EntrySink for Box<S> {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        (**self).entry(entry)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+#[cfg(feature = "time")]
+impl<S: SessionSink + ?Sized> SessionSink for &mut S {
+    fn session(&mut self, session: &crate::Session) -> io::Result<()> {
+        (**self).session(session)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+#[cfg(feature = "time")]
+impl<S: SessionSink + ?Sized> SessionSink for Box<S> {
+    fn session(&mut self, session: &crate::Session) -> io::Result<()> {
+        (**self).session(session)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+/// Sink which passes everything on to each of its sinks in order.
+///
+/// Every sink is given each item even if an earlier one fails,
+/// and the first error is returned.
+#[derive(Clone, Debug)]
+pub struct FanOut<S> {
+    sinks: Vec<S>,
+}
+
+impl<S> Default for FanOut<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> FanOut<S> {
+    /// Create a sink without any sink to pass on to.
+    pub fn new() -> Self {
+        FanOut { sinks: Vec::new() }
+    }
+
+    /// Add a sink to pass on to.
+    pub fn with(mut self, sink: S) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Sinks passed on to.
+    pub fn sinks(&self) -> &[S] {
+        &self.sinks
+    }
+
+    /// Take the sinks back.
+    pub fn into_inner(self) -> Vec<S> {
+        self.sinks
+    }
+
+    fn each(&mut self, mut f: impl FnMut(&mut S) -> io::Result<()>) -> io::Result<()> {
+        let mut result = Ok(());
+        for sink in &mut self.sinks {
+            if let Err(e) = f(sink) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+impl<S> FromIterator<S> for FanOut<S> {
+    fn from_iter<I: IntoIterator<Item = S>>(sinks: I) -> Self {
+        FanOut {
+            sinks: sinks.into_iter().collect(),
+        }
+    }
+}
+
+impl<S: EntrySink> EntrySink for FanOut<S> {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        self.each(|sink| sink.entry(entry))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.each(|sink| sink.finish())
+    }
+}
+
+#[cfg(feature = "time")]
+impl<S: SessionSink> SessionSink for FanOut<S> {
+    fn session(&mut self, session: &crate::Session) -> io::Result<()> {
+        self.each(|sink| sink.session(session))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.each(|sink| sink.finish())
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Timestamp, UtmpEntry};
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use time::Duration;
+use utmp_raw::pid_t;
+
+/// Reason why a `UserProcess` entry is considered stale.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Staleness {
+    /// Process of the entry no longer exists
+    ProcessGone,
+    /// Process with the same PID started after the entry was made,
+    /// so the PID has been reused by an unrelated process
+    PidReused,
+    /// Process has a controlling tty other than the line of the entry
+    TtyMismatch,
+}
+
+/// Checker which cross-checks `UserProcess` entries against `/proc`
+/// to find records left behind by sessions which didn't clean up.
+///
+/// ```
+/// # use utmp_rs::{StaleChecker, UtmpEntry};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let checker = StaleChecker::new()?;
+/// for entry in utmp_rs::parse_from_path("/var/run/utmp")? {
+///     if let Some(reason) = checker.check(&entry)? {
+///         println!("stale entry {:?}: {:?}", entry, reason);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct StaleChecker {
+    proc_path: PathBuf,
+    boot_time: Timestamp,
+    ticks_per_second: u64,
+}
+
+/// Allowed difference between the start time of the process and the time of the entry.
+///
+/// Boot time is only available in whole seconds, so this avoids false positives
+/// for processes which write their entry right after they start.
+const START_TIME_TOLERANCE: Duration = Duration::seconds(1);
+
+impl StaleChecker {
+    /// Create a checker using `/proc`.
+    pub fn new() -> io::Result<Self> {
+        Self::with_proc_path("/proc")
+    }
+
+    /// Create a checker using procfs mounted at the given path.
+    pub fn with_proc_path<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let proc_path = path.as_ref().to_owned();
+        let stat = fs::read_to_string(proc_path.join("stat"))?;
+        let boot_time = stat
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|btime| btime.trim().parse().ok())
+            .map(Timestamp::from_secs)
+            .ok_or_else(|| invalid_data("missing btime in stat"))?;
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks_per_second <= 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(StaleChecker {
+            proc_path,
+            boot_time,
+            ticks_per_second: ticks_per_second as u64,
+        })
+    }
+
+    /// Check whether the given entry is stale.
+    ///
+    /// It returns `None` if the entry is not a `UserProcess`,
+    /// or if its process still looks alive.
+    pub fn check(&self, entry: &UtmpEntry) -> io::Result<Option<Staleness>> {
+        let (pid, line, time) = match entry {
+            UtmpEntry::UserProcess {
+                pid, line, time, ..
+            } => (*pid, line, *time),
+            _ => return Ok(None),
+        };
+        let stat = match self.read_stat(pid)? {
+            Some(stat) => stat,
+            None => return Ok(Some(Staleness::ProcessGone)),
+        };
+        let secs = stat.start_time / self.ticks_per_second;
+        let nanos = stat.start_time % self.ticks_per_second * 1_000_000_000 / self.ticks_per_second;
+        let start_time =
+            self.boot_time + Duration::seconds(secs as i64) + Duration::nanoseconds(nanos as i64);
+        if start_time > time + START_TIME_TOLERANCE {
+            return Ok(Some(Staleness::PidReused));
+        }
+        // A session leader like sshd may not have a controlling tty at all,
+        // and the line may not be a device, e.g. for X displays.
+        if stat.tty_nr != 0 {
+            if let Ok(metadata) = fs::metadata(Path::new("/dev").join(line)) {
+                if metadata.rdev() != stat.tty_nr {
+                    return Ok(Some(Staleness::TtyMismatch));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the interesting fields from `/proc/<pid>/stat`,
+    /// or `None` if the process doesn't exist.
+    fn read_stat(&self, pid: pid_t) -> io::Result<Option<ProcStat>> {
+        let content = match fs::read_to_string(self.proc_path.join(pid.to_string()).join("stat")) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        // The command name is wrapped in parentheses and may contain spaces,
+        // so fields are counted from the last closing parenthesis.
+        let fields = content
+            .rfind(')')
+            .map(|pos| content[pos + 1..].split_whitespace().collect::<Vec<_>>())
+            .ok_or_else(|| invalid_data("malformed process stat"))?;
+        let field = |n: usize| {
+            // Field numbers start from 1, and the first field after the command is 3.
+            fields
+                .get(n - 3)
+                .and_then(|field| field.parse().ok())
+                .ok_or_else(|| invalid_data("malformed process stat"))
+        };
+        Ok(Some(ProcStat {
+            tty_nr: field(7)?,
+            start_time: field(22)?,
+        }))
+    }
+}
+
+struct ProcStat {
+    tty_nr: u64,
+    /// Time the process started after boot, in clock ticks
+    start_time: u64,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,134 @@
+use crate::{EntrySink, UtmpEntry};
+use std::borrow::Borrow;
+use std::io;
+use std::iter::FromIterator;
+
+/// Entries of utmp maintained by applying entries one by one,
+/// the way `init(8)` and login programs maintain `/var/run/utmp`.
+///
+/// Process entries replace the one with the same `ut_id`,
+/// or the one on the same line if either has no ID as in entries written by `logwtmp(3)`,
+/// and system entries replace the one of the same type,
+/// e.g. a new run-level replaces the previous one.
+/// Utmp doesn't survive reboots, so boots and shutdowns clear everything before them.
+///
+/// Applying the entries of wtmp thus gives the utmp after them, either replayed from the start
+/// or fed from the live stream of entries, e.g. by `UtmpWatcher`.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let state: utmp_rs::UtmpState = utmp_rs::parse_from_path("/var/log/wtmp")?.into_iter().collect();
+/// for entry in state.users() {
+///     println!("{:?}", entry);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UtmpState {
+    entries: Vec<UtmpEntry>,
+}
+
+impl UtmpState {
+    /// Create an empty state, as utmp is after a boot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Apply the entry, replacing the one it updates if any.
+    pub fn apply(&mut self, entry: &UtmpEntry) {
+        let slot = match Slot::of(entry) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if let UtmpEntry::BootTime { .. } | UtmpEntry::ShutdownTime { .. } = entry {
+            self.entries.clear();
+        }
+        let existing = (self.entries.iter_mut()).find(|e| Slot::of(e).is_some_and(|s| s.is(slot)));
+        match existing {
+            Some(existing) => *existing = entry.clone(),
+            None => self.entries.push(entry.clone()),
+        }
+    }
+
+    /// Entries in the order their slots were first taken, as records in utmp are.
+    pub fn entries(&self) -> &[UtmpEntry] {
+        &self.entries
+    }
+
+    /// Entries of sessions logged in, i.e. `UserProcess` entries.
+    pub fn users(&self) -> impl Iterator<Item = &UtmpEntry> {
+        (self.entries.iter()).filter(|entry| matches!(entry, UtmpEntry::UserProcess { .. }))
+    }
+
+    /// Entry of the process on the line, if any.
+    pub fn entry_for_line(&self, line: &str) -> Option<&UtmpEntry> {
+        self.entries
+            .iter()
+            .find(|entry| crate::log::entry_line(entry) == Some(line))
+    }
+}
+
+/// Record of utmp an entry is written to, as `getutid(3)` finds it.
+#[derive(Clone, Copy, Debug)]
+enum Slot<'a> {
+    /// Record of the type, for system entries
+    Type(&'static str),
+    /// Record of a process
+    Process { id: &'a str, line: &'a str },
+}
+
+impl<'a> Slot<'a> {
+    fn of(entry: &'a UtmpEntry) -> Option<Self> {
+        Some(match entry {
+            // Shutdowns are run-level records.
+            UtmpEntry::RunLevel { .. } | UtmpEntry::ShutdownTime { .. } => Slot::Type("RUN_LVL"),
+            UtmpEntry::BootTime { .. } => Slot::Type("BOOT_TIME"),
+            UtmpEntry::NewTime(_) => Slot::Type("NEW_TIME"),
+            UtmpEntry::OldTime(_) => Slot::Type("OLD_TIME"),
+            UtmpEntry::InitProcess { id, .. } => Slot::Process { id, line: "" },
+            UtmpEntry::LoginProcess { id, line, .. }
+            | UtmpEntry::UserProcess { id, line, .. }
+            | UtmpEntry::DeadProcess { id, line, .. } => Slot::Process { id, line },
+            _ => return None,
+        })
+    }
+
+    fn is(self, other: Slot<'_>) -> bool {
+        match (self, other) {
+            (Slot::Type(a), Slot::Type(b)) => a == b,
+            (Slot::Process { id: a, .. }, Slot::Process { id: b, .. })
+                if !a.is_empty() && !b.is_empty() =>
+            {
+                a == b
+            }
+            (Slot::Process { line: a, .. }, Slot::Process { line: b, .. }) => {
+                !a.is_empty() && a == b
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<E: Borrow<UtmpEntry>> Extend<E> for UtmpState {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, entries: I) {
+        for entry in entries {
+            self.apply(entry.borrow());
+        }
+    }
+}
+
+impl<E: Borrow<UtmpEntry>> FromIterator<E> for UtmpState {
+    fn from_iter<I: IntoIterator<Item = E>>(entries: I) -> Self {
+        let mut state = Self::new();
+        state.extend(entries);
+        state
+    }
+}
+
+impl EntrySink for UtmpState {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        self.apply(entry);
+        Ok(())
+    }
+}
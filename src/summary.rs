@@ -0,0 +1,115 @@
+use crate::{EntrySink, Timestamp, UtmpEntry, UtmpEntryKind};
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io;
+
+/// Overview of a utmp file computed in a single pass over its entries,
+/// e.g. for a dashboard listing every rotated wtmp.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let parser = utmp_rs::UtmpParser::from_path("/var/log/wtmp")?;
+/// let summary = utmp_rs::Summary::from_entries(parser.filter_map(Result::ok));
+/// println!("{} records from {} users", summary.records(), summary.users().len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Summary {
+    records: u64,
+    kinds: BTreeMap<UtmpEntryKind, u64>,
+    first: Option<Timestamp>,
+    last: Option<Timestamp>,
+    users: BTreeSet<String>,
+    hosts: BTreeSet<String>,
+}
+
+impl Summary {
+    /// Create a summary without any entry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Summarize the entries.
+    pub fn from_entries<I>(entries: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Borrow<UtmpEntry>,
+    {
+        let mut summary = Self::new();
+        summary.extend(entries);
+        summary
+    }
+
+    /// Add the entry to the summary.
+    pub fn add(&mut self, entry: &UtmpEntry) {
+        self.records += 1;
+        *self.kinds.entry(entry.kind()).or_default() += 1;
+        if let Some(time) = entry.time() {
+            self.first = Some(self.first.map_or(time, |first| first.min(time)));
+            self.last = Some(self.last.map_or(time, |last| last.max(time)));
+        }
+        let (user, host) = match entry {
+            UtmpEntry::LoginProcess { user, host, .. } => (user, host),
+            UtmpEntry::UserProcess { user, host, .. } => (user, host),
+            _ => return,
+        };
+        // `LOGIN` is the placeholder `getty(8)` writes before anyone logs in.
+        if !user.is_empty() && user != "LOGIN" && !self.users.contains(user) {
+            self.users.insert(user.clone());
+        }
+        if !host.is_empty() && !self.hosts.contains(host) {
+            self.hosts.insert(host.clone());
+        }
+    }
+
+    /// Number of records summarized.
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Number of entries of the kind.
+    pub fn count(&self, kind: UtmpEntryKind) -> u64 {
+        self.kinds.get(&kind).copied().unwrap_or_default()
+    }
+
+    /// Numbers of entries of each kind present, in order of kinds.
+    pub fn counts(&self) -> impl Iterator<Item = (UtmpEntryKind, u64)> + '_ {
+        self.kinds.iter().map(|(kind, count)| (*kind, *count))
+    }
+
+    /// Earliest time of the entries, if any has a time.
+    pub fn first(&self) -> Option<Timestamp> {
+        self.first
+    }
+
+    /// Latest time of the entries, if any has a time.
+    pub fn last(&self) -> Option<Timestamp> {
+        self.last
+    }
+
+    /// Distinct users of login and user process entries.
+    pub fn users(&self) -> &BTreeSet<String> {
+        &self.users
+    }
+
+    /// Distinct non-empty hosts of login and user process entries.
+    pub fn hosts(&self) -> &BTreeSet<String> {
+        &self.hosts
+    }
+}
+
+impl<E: Borrow<UtmpEntry>> Extend<E> for Summary {
+    fn extend<I: IntoIterator<Item = E>>(&mut self, entries: I) {
+        for entry in entries {
+            self.add(entry.borrow());
+        }
+    }
+}
+
+impl EntrySink for Summary {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        self.add(entry);
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use crate::timestamp::format_rfc3339;
+use crate::{EntrySink, Event, EventInterpreter, EventSink, UtmpEntry};
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::Path;
+
+/// Sink which forwards events to a syslog endpoint as RFC 5424 messages.
+///
+/// Each event is a message with the event as the MSGID,
+/// i.e. `login`, `logout`, `boot` or `shutdown`,
+/// its fields as structured data,
+/// and a summary in text like `root logged in on pts/0 from example.com`.
+/// Messages are sent with the `authpriv` facility and the `info` severity by default.
+///
+/// The SD-ID of the structured data is `utmp@32473` by default,
+/// but 32473 is the enterprise number RFC 5612 reserves for documentation,
+/// so set an SD-ID with your own enterprise number with `sd_id` for messages in production.
+///
+/// As an `EntrySink`, it interprets entries into events with an `EventInterpreter` of its own.
+/// Errors from sending can't be returned through `EventSink`,
+/// so the first one is kept for `take_error`, and later events are still sent.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut sink = utmp_rs::SyslogSink::udp("logs.example.com:514")?.sd_id("utmp@32473");
+/// utmp_rs::interpret_events(utmp_rs::parse_from_path("/var/log/wtmp")?, &mut sink);
+/// if let Some(e) = sink.take_error() {
+///     eprintln!("failed to forward events: {}", e);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SyslogSink {
+    transport: Transport,
+    facility: u8,
+    severity: u8,
+    hostname: String,
+    app_name: String,
+    sd_id: String,
+    interpreter: EventInterpreter,
+    error: Option<io::Error>,
+}
+
+#[derive(Debug)]
+enum Transport {
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl SyslogSink {
+    /// Send messages to the local syslog daemon through the Unix datagram socket at the path,
+    /// usually `/dev/log`.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::new(Transport::Unix(socket)))
+    }
+
+    /// Send messages over UDP as RFC 5426 specifies.
+    pub fn udp<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let address = (address.to_socket_addrs()?.next())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+        let local = if address.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(address)?;
+        Ok(Self::new(Transport::Udp(socket)))
+    }
+
+    /// Send messages over TCP with octet-counting framing as RFC 6587 specifies.
+    ///
+    /// The connection isn't reestablished once it fails.
+    pub fn tcp<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        Ok(Self::new(Transport::Tcp(stream)))
+    }
+
+    fn new(transport: Transport) -> Self {
+        SyslogSink {
+            transport,
+            facility: 10,
+            severity: 6,
+            hostname: local_hostname().unwrap_or_else(|| "-".to_owned()),
+            app_name: "utmp-rs".to_owned(),
+            sd_id: "utmp@32473".to_owned(),
+            interpreter: EventInterpreter::new(),
+            error: None,
+        }
+    }
+
+    /// Set the facility code of messages, e.g. 4 for `auth` or 10 for `authpriv`.
+    ///
+    /// Codes beyond 23 are capped.
+    pub fn facility(mut self, facility: u8) -> Self {
+        self.facility = facility.min(23);
+        self
+    }
+
+    /// Set the severity code of messages, e.g. 5 for `notice` or 6 for `info`.
+    ///
+    /// Codes beyond 7 are capped.
+    pub fn severity(mut self, severity: u8) -> Self {
+        self.severity = severity.min(7);
+        self
+    }
+
+    /// Set the HOSTNAME of messages, which is the hostname of the local system by default.
+    pub fn hostname(mut self, hostname: &str) -> Self {
+        self.hostname = header_field(hostname, 255);
+        self
+    }
+
+    /// Set the APP-NAME of messages, which is `utmp-rs` by default.
+    pub fn app_name(mut self, app_name: &str) -> Self {
+        self.app_name = header_field(app_name, 48);
+        self
+    }
+
+    /// Set the SD-ID of the structured data, e.g. `utmp@` followed by your enterprise number.
+    ///
+    /// Characters not allowed in SD-IDs are dropped, IDs are truncated to 32 characters,
+    /// and empty IDs are ignored.
+    pub fn sd_id(mut self, sd_id: &str) -> Self {
+        let sd_id = (sd_id.chars())
+            .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+            .take(32)
+            .collect::<String>();
+        if !sd_id.is_empty() {
+            self.sd_id = sd_id;
+        }
+        self
+    }
+
+    /// Send the event.
+    pub fn send(&mut self, event: &Event) -> io::Result<()> {
+        let message = self.format(event);
+        match &mut self.transport {
+            #[cfg(unix)]
+            Transport::Unix(socket) => socket.send(message.as_bytes()).map(drop),
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(drop),
+            Transport::Tcp(stream) => write!(stream, "{} {}", message.len(), message),
+        }
+    }
+
+    /// Take the first error from sending events through `EventSink`, if any.
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Format the event as an RFC 5424 message.
+    fn format(&self, event: &Event) -> String {
+        let (msg_id, time, params, text) = match event {
+            Event::UserLoggedIn {
+                user,
+                line,
+                host,
+                time,
+            } => (
+                "login",
+                time,
+                vec![
+                    ("user", user.clone()),
+                    ("line", line.clone()),
+                    ("host", host.clone()),
+                ],
+                format!("{} logged in on {}{}", user, line, from(host)),
+            ),
+            Event::UserLoggedOut {
+                user,
+                line,
+                host,
+                time,
+                duration,
+            } => (
+                "logout",
+                time,
+                vec![
+                    ("user", user.clone()),
+                    ("line", line.clone()),
+                    ("host", host.clone()),
+                    ("duration", duration.whole_seconds().to_string()),
+                ],
+                format!("{} logged out of {}{}", user, line, from(host)),
+            ),
+            Event::SystemBooted {
+                kernel_version,
+                time,
+            } => (
+                "boot",
+                time,
+                vec![("kernel_version", kernel_version.clone())],
+                format!("system booted with kernel {}", kernel_version),
+            ),
+            Event::SystemShutdown {
+                kernel_version,
+                time,
+            } => (
+                "shutdown",
+                time,
+                vec![("kernel_version", kernel_version.clone())],
+                format!("system shut down with kernel {}", kernel_version),
+            ),
+        };
+        let params = params.iter().map(|(name, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            format!(" {}=\"{}\"", name, value.replace(']', "\\]"))
+        });
+        format!(
+            "<{}>1 {} {} {} {} {} [{}{}] {}",
+            self.facility * 8 + self.severity,
+            format_rfc3339(*time),
+            self.hostname,
+            self.app_name,
+            std::process::id(),
+            msg_id,
+            self.sd_id,
+            params.collect::<String>(),
+            text,
+        )
+    }
+}
+
+impl EventSink for SyslogSink {
+    fn event(&mut self, event: Event) {
+        if let Err(e) = self.send(&event) {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+impl EntrySink for SyslogSink {
+    fn entry(&mut self, entry: &UtmpEntry) -> io::Result<()> {
+        let mut events = Vec::new();
+        self.interpreter
+            .feed(entry, &mut |event| events.push(event));
+        events.iter().try_for_each(|event| self.send(event))
+    }
+}
+
+fn from(host: &str) -> String {
+    if host.is_empty() {
+        String::new()
+    } else {
+        format!(" from {}", host)
+    }
+}
+
+/// Header field of printable ASCII without spaces, or `-` if nothing is left.
+fn header_field(value: &str, max_len: usize) -> String {
+    let value = value.chars().filter(|c| c.is_ascii_graphic());
+    let value = value.take(max_len).collect::<String>();
+    if value.is_empty() {
+        "-".to_owned()
+    } else {
+        value
+    }
+}
+
+#[cfg(unix)]
+fn local_hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // The buffer is valid for writes of its length, and truncated names are rejected below.
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|&b| b == 0)?;
+    Some(header_field(std::str::from_utf8(&buffer[..len]).ok()?, 255))
+}
+
+#[cfg(not(unix))]
+fn local_hostname() -> Option<String> {
+    None
+}
@@ -0,0 +1,347 @@
+use crate::fields::Fields;
+use crate::timestamp::{civil_from_secs, format_rfc3339};
+use crate::{Timestamp, UtmpEntry};
+use std::fmt::Write as _;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Format string for rendering entries and sessions, like `find -printf`.
+///
+/// Directives are replaced by fields, and render empty if the entry or session lacks them:
+///
+/// * `%u`: user
+/// * `%l`: tty line
+/// * `%h`: host, or kernel version of boot and run-level entries
+/// * `%i`: terminal name suffix, or `inittab(5)` ID
+/// * `%p`: PID
+/// * `%a`: address of the remote host
+/// * `%k`: type of entry, e.g. `UserProcess`
+/// * `%t`: time of entry or login of session
+/// * `%o`: time of logout of session
+/// * `%d`: duration of session, as `HH:MM` with days prepended like `last` does, e.g. `1+02:03`
+/// * `%e`: how session ended, e.g. `Logout`
+/// * `%%`: a literal `%`
+///
+/// A width can be given between `%` and the directive to pad the field with spaces,
+/// aligned to the right, or to the left with `-`, e.g. `%-8u`, up to `Template::MAX_WIDTH` columns.
+/// Times are RFC 3339 in UTC, unless formatted with `strftime`-like codes in braces after the directive,
+/// e.g. `%t{%Y-%m-%d %H:%M}`, with `%Y`, `%m`, `%d`, `%e`, `%H`, `%M`, `%S`, `%f` (microseconds),
+/// `%s` (seconds since the Unix epoch), `%a`, `%b`, `%F`, `%T` and `%%` supported.
+/// Escapes `\n`, `\t` and `\\` are also recognized, for templates given in command lines.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let template: utmp_rs::Template = "%-8u %-12l %t{%F %T}".parse()?;
+/// for entry in utmp_rs::parse_from_path("/var/log/wtmp")? {
+///     println!("{}", template.render_entry(&entry));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+/// Error from parsing a `Template`.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum TemplateError {
+    /// Directive isn't one of those supported
+    #[error("unknown directive `%{directive}` at offset {offset}")]
+    UnknownDirective {
+        /// Byte offset of the `%` in the template
+        offset: usize,
+        /// Character following the `%`
+        directive: char,
+    },
+    /// Time format code isn't one of those supported
+    #[error("unknown time format code `%{code}` at offset {offset}")]
+    UnknownTimeCode {
+        /// Byte offset of the `%` in the template
+        offset: usize,
+        /// Character following the `%`
+        code: char,
+    },
+    /// Width of a directive is larger than `Template::MAX_WIDTH`
+    #[error("width of directive at offset {offset} is too large")]
+    WidthTooLarge {
+        /// Byte offset of the `%` in the template
+        offset: usize,
+    },
+    /// Template ends within a directive or a time format
+    #[error("unterminated directive at offset {offset}")]
+    Unterminated {
+        /// Byte offset of the `%` in the template
+        offset: usize,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Part {
+    Literal(String),
+    Field {
+        field: Field,
+        width: usize,
+        left: bool,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Field {
+    User,
+    Line,
+    Host,
+    Id,
+    Pid,
+    Address,
+    Kind,
+    Time(Option<Vec<TimePart>>),
+    Logout(Option<Vec<TimePart>>),
+    Duration,
+    End,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TimePart {
+    Literal(String),
+    Code(char),
+}
+
+const TIME_CODES: &str = "YmdeHMSfsabFT";
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, TemplateError> {
+        Template::parse(s)
+    }
+}
+
+impl Template {
+    /// Largest width fields can be padded to.
+    pub const MAX_WIDTH: usize = 1024;
+
+    /// Parse the template.
+    pub fn parse(template: &str) -> Result<Self, TemplateError> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.char_indices().peekable();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '\\' => match chars.peek() {
+                    Some(&(_, 'n')) => literal.push('\n'),
+                    Some(&(_, 't')) => literal.push('\t'),
+                    Some(&(_, '\\')) => literal.push('\\'),
+                    _ => {
+                        literal.push('\\');
+                        continue;
+                    }
+                },
+                '%' => {
+                    let unterminated = TemplateError::Unterminated { offset };
+                    let left = chars.next_if(|&(_, c)| c == '-').is_some();
+                    let mut width = 0usize;
+                    while let Some((_, digit)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                        let digit = digit.to_digit(10).unwrap_or_default() as usize;
+                        width = (width.checked_mul(10))
+                            .and_then(|width| width.checked_add(digit))
+                            .filter(|&width| width <= Template::MAX_WIDTH)
+                            .ok_or(TemplateError::WidthTooLarge { offset })?;
+                    }
+                    let (_, directive) = chars.next().ok_or(unterminated.clone())?;
+                    let mut time_format = || -> Result<_, TemplateError> {
+                        if chars.next_if(|&(_, c)| c == '{').is_none() {
+                            return Ok(None);
+                        }
+                        let mut format = String::new();
+                        loop {
+                            match chars.next() {
+                                Some((_, '}')) => break,
+                                Some((_, c)) => format.push(c),
+                                None => return Err(unterminated.clone()),
+                            }
+                        }
+                        parse_time_format(&format, offset).map(Some)
+                    };
+                    let field = match directive {
+                        '%' => {
+                            literal.push('%');
+                            continue;
+                        }
+                        'u' => Field::User,
+                        'l' => Field::Line,
+                        'h' => Field::Host,
+                        'i' => Field::Id,
+                        'p' => Field::Pid,
+                        'a' => Field::Address,
+                        'k' => Field::Kind,
+                        't' => Field::Time(time_format()?),
+                        'o' => Field::Logout(time_format()?),
+                        'd' => Field::Duration,
+                        'e' => Field::End,
+                        directive => {
+                            return Err(TemplateError::UnknownDirective { offset, directive });
+                        }
+                    };
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field { field, width, left });
+                    continue;
+                }
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            }
+            // Skip the escaped character.
+            chars.next();
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Render the entry.
+    pub fn render_entry(&self, entry: &UtmpEntry) -> String {
+        let f = Fields::of(entry);
+        self.render(|field| match field {
+            Field::User => f.user.map(String::from),
+            Field::Line => f.line.map(String::from),
+            Field::Host => f.host.or(f.kernel_version).map(String::from),
+            Field::Id => f.id.map(String::from),
+            Field::Pid => f.pid.map(|pid| pid.to_string()),
+            Field::Address => f.address.map(|address| address.to_string()),
+            Field::Kind => Some(f.kind.to_owned()),
+            Field::Time(format) => f.time.map(|time| format_time(time, format.as_deref())),
+            Field::Logout(_) | Field::Duration | Field::End => None,
+        })
+    }
+
+    /// Render the session.
+    #[cfg(feature = "time")]
+    pub fn render_session(&self, session: &crate::Session) -> String {
+        self.render(|field| match field {
+            Field::User => Some(session.user.clone()),
+            Field::Line => Some(session.line.clone()),
+            Field::Host => Some(session.host.clone()),
+            Field::Pid => Some(session.pid.to_string()),
+            Field::Address => session.address.map(|address| address.to_string()),
+            Field::Time(format) => Some(format_time(session.login, format.as_deref())),
+            Field::Logout(format) => {
+                (session.logout).map(|time| format_time(time, format.as_deref()))
+            }
+            Field::Duration => session.duration.map(|duration| {
+                let minutes = duration.whole_minutes().max(0);
+                let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+                if days > 0 {
+                    format!("{}+{:02}:{:02}", days, hours, minutes)
+                } else {
+                    format!("{:02}:{:02}", hours, minutes)
+                }
+            }),
+            Field::End => Some(format!("{:?}", session.end)),
+            Field::Id | Field::Kind => None,
+        })
+    }
+
+    fn render(&self, value: impl Fn(&Field) -> Option<String>) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => result.push_str(literal),
+                Part::Field { field, width, left } => {
+                    let value = value(field).unwrap_or_default();
+                    let _ = if *left {
+                        write!(result, "{:<1$}", value, width)
+                    } else {
+                        write!(result, "{:>1$}", value, width)
+                    };
+                }
+            }
+        }
+        result
+    }
+}
+
+fn parse_time_format(format: &str, offset: usize) -> Result<Vec<TimePart>, TemplateError> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => literal.push('%'),
+            Some(code) if TIME_CODES.contains(code) => {
+                if !literal.is_empty() {
+                    parts.push(TimePart::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(TimePart::Code(code));
+            }
+            Some(code) => return Err(TemplateError::UnknownTimeCode { offset, code }),
+            None => return Err(TemplateError::Unterminated { offset }),
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(TimePart::Literal(literal));
+    }
+    Ok(parts)
+}
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format the time in UTC with the given codes, or as RFC 3339 without.
+fn format_time(time: Timestamp, format: Option<&[TimePart]>) -> String {
+    let format = match format {
+        Some(format) => format,
+        None => return format_rfc3339(time),
+    };
+    let Timestamp { secs, micros } = time;
+    let civil = civil_from_secs(secs);
+    let mut result = String::new();
+    for part in format {
+        let code = match part {
+            TimePart::Literal(literal) => {
+                result.push_str(literal);
+                continue;
+            }
+            TimePart::Code(code) => code,
+        };
+        let _ = match code {
+            'Y' => write!(result, "{:04}", civil.year),
+            'm' => write!(result, "{:02}", civil.month),
+            'd' => write!(result, "{:02}", civil.day),
+            'e' => write!(result, "{:>2}", civil.day),
+            'H' => write!(result, "{:02}", civil.hour),
+            'M' => write!(result, "{:02}", civil.minute),
+            'S' => write!(result, "{:02}", civil.second),
+            'f' => write!(result, "{:06}", micros),
+            's' => write!(result, "{}", secs),
+            'a' => {
+                let weekday = (secs.div_euclid(86400) + 4).rem_euclid(7);
+                write!(result, "{}", WEEKDAYS[weekday as usize])
+            }
+            'b' => write!(result, "{}", MONTHS[usize::from(civil.month) - 1]),
+            'F' => write!(
+                result,
+                "{:04}-{:02}-{:02}",
+                civil.year, civil.month, civil.day
+            ),
+            'T' => write!(
+                result,
+                "{:02}:{:02}:{:02}",
+                civil.hour, civil.minute, civil.second
+            ),
+            _ => Ok(()),
+        };
+    }
+    result
+}
@@ -0,0 +1,392 @@
+//! Utilities to generate utmp files for tests of tools built on this crate,
+//! instead of copying binary samples around,
+//! and to corrupt them for tests of how errors are handled.
+//!
+//! ```
+//! # use utmp_rs::testing::UtmpFileBuilder;
+//! # use utmp_rs::{Format, UtmpParser};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let time = utmp_rs::Timestamp::from_secs;
+//! let bytes = UtmpFileBuilder::new()
+//!     .format(Format::X64)
+//!     .boot("5.4.0-135-generic", time(1675756860))
+//!     .login("root", "pts/0", "112.124.2.209", 1125, time(1675757226))
+//!     .logout("pts/0", 1125, time(1675759026))
+//!     .build()?;
+//! assert_eq!(bytes.len(), 3 * 400);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ```
+//! # use utmp_rs::testing::{inject_defect, Defect};
+//! # use utmp_rs::{Format, UtmpParser};
+//! # let mut bytes = vec![0; 3 * 400];
+//! inject_defect(&mut bytes, Format::X64, Defect::FlipType(1));
+//! inject_defect(&mut bytes, Format::X64, Defect::TruncateMidRecord);
+//! assert_eq!(bytes.len(), 2 * 400 + 200);
+//! ```
+
+use crate::builder::{detect_format, native_format, HEAD_SIZE};
+use crate::convert::ConvertError;
+use crate::parse::raw_from_record;
+use crate::raw::{RawUtmp, RecordValues};
+use crate::{Endianness, Format, Timestamp, UtmpEntry};
+use std::fs;
+use std::mem;
+use std::net::IpAddr;
+use std::ops::Range;
+use std::path::Path;
+use utmp_raw::x64::{timeval as timeval64, utmp as utmp64};
+use utmp_raw::{exit_status, pid_t, x32::utmp as utmp32};
+
+/// Builder of utmp files from a sequence of entries, in any layout and byte order.
+///
+/// Entries are written as glibc, `init(8)` and `sshd(8)` write them,
+/// e.g. boots with `~` as the line and `reboot` as the user.
+/// Strings are truncated to the size of their fields,
+/// so a host of `UT_HOSTSIZE` bytes or more fills `ut_host` without a terminating NUL,
+/// which is a quirk parsers have to cope with.
+/// `Unknown` entries whose raw record has the size of records of the layout
+/// are written back as they are.
+#[derive(Clone, Debug)]
+pub struct UtmpFileBuilder {
+    format: Format,
+    endianness: Endianness,
+    entries: Vec<UtmpEntry>,
+}
+
+impl Default for UtmpFileBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UtmpFileBuilder {
+    /// Create a builder of files in the native format for the target platform.
+    pub fn new() -> Self {
+        UtmpFileBuilder {
+            format: native_format(),
+            endianness: Endianness::Native,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Set the layout of records, with `Format::Auto` for the native one.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = match format {
+            Format::Auto => native_format(),
+            format => format,
+        };
+        self
+    }
+
+    /// Set the byte order of numeric fields.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    /// Append an entry.
+    pub fn entry(mut self, entry: UtmpEntry) -> Self {
+        self.entries.push(entry);
+        self
+    }
+
+    /// Append entries.
+    pub fn entries<I: IntoIterator<Item = UtmpEntry>>(mut self, entries: I) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+
+    /// Append a boot of the system.
+    pub fn boot(self, kernel_version: &str, time: Timestamp) -> Self {
+        self.entry(UtmpEntry::BootTime {
+            kernel_version: kernel_version.to_owned(),
+            time,
+        })
+    }
+
+    /// Append a shutdown of the system.
+    pub fn shutdown(self, kernel_version: &str, time: Timestamp) -> Self {
+        self.entry(UtmpEntry::ShutdownTime {
+            kernel_version: kernel_version.to_owned(),
+            time,
+        })
+    }
+
+    /// Append a login of the user on the line from the host,
+    /// with the address of the host if it's an IP address, as `sshd(8)` writes it.
+    pub fn login(self, user: &str, line: &str, host: &str, pid: pid_t, time: Timestamp) -> Self {
+        self.entry(UtmpEntry::UserProcess {
+            pid,
+            line: line.to_owned(),
+            id: line_id(line).to_owned(),
+            user: user.to_owned(),
+            host: host.to_owned(),
+            address: host.parse().ok(),
+            session: 0,
+            time,
+        })
+    }
+
+    /// Append a logout from the line, of the session started by the process.
+    pub fn logout(self, line: &str, pid: pid_t, time: Timestamp) -> Self {
+        self.entry(UtmpEntry::DeadProcess {
+            pid,
+            line: line.to_owned(),
+            id: line_id(line).to_owned(),
+            termination: 0,
+            exit: 0,
+            time,
+        })
+    }
+
+    /// Build the records of the entries.
+    ///
+    /// It fails with `ConvertError::Overflow` if a value doesn't fit the format,
+    /// e.g. a time after 2038 in 32-bit records.
+    pub fn build(&self) -> Result<Vec<u8>, ConvertError> {
+        match self.format {
+            Format::X64 => self.build_records::<utmp64>(),
+            _ => self.build_records::<utmp32>(),
+        }
+    }
+
+    /// Build the records of the entries, and write them to the path.
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> Result<(), ConvertError> {
+        let path = path.as_ref();
+        fs::write(path, self.build()?).map_err(|source| ConvertError::Io {
+            path: Some(path.to_owned()),
+            source,
+        })
+    }
+
+    fn build_records<T: RawUtmp>(&self) -> Result<Vec<u8>, ConvertError> {
+        let size = mem::size_of::<T>();
+        let swap = self.endianness.swaps();
+        let mut bytes = vec![0; self.entries.len() * size];
+        let records = self.entries.iter().zip(bytes.chunks_exact_mut(size));
+        for (index, (entry, record)) in records.enumerate() {
+            if let UtmpEntry::Unknown { raw, .. } = entry {
+                if raw.len() == size {
+                    record.copy_from_slice(raw);
+                    continue;
+                }
+            }
+            let overflow = |field| ConvertError::Overflow {
+                index: index as u64,
+                field,
+            };
+            record_values(entry)
+                .write::<T>(record, swap)
+                .map_err(overflow)?;
+        }
+        Ok(bytes)
+    }
+}
+
+/// Defect to inject into records, each standing for a class of failures found in the wild.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum Defect {
+    /// Cut the last record in half, as if a write was interrupted
+    TruncateMidRecord,
+    /// Invert the bits of `ut_type` of the record at the index, making it an unknown type
+    FlipType(usize),
+    /// Zero all bytes of the record at the index, as left by a crash before the data was written
+    ZeroRecord(usize),
+    /// Swap the byte order of numeric fields of the record at the index,
+    /// as if it was written by a system of the other endianness
+    SwapEndianness(usize),
+}
+
+/// Inject the defect into the records in the given layout, with `Format::Auto` to detect it.
+///
+/// # Panics
+///
+/// Panics if the defect refers to a record beyond the end of the bytes.
+pub fn inject_defect(bytes: &mut Vec<u8>, format: Format, defect: Defect) {
+    let format = match format {
+        Format::Auto => {
+            let head = &bytes[..bytes.len().min(HEAD_SIZE)];
+            detect_format(head, Some(bytes.len() as u64), false)
+        }
+        format => format,
+    };
+    match format {
+        Format::X64 => inject::<utmp64>(bytes, defect),
+        _ => inject::<utmp32>(bytes, defect),
+    }
+}
+
+fn inject<T: RawUtmp>(bytes: &mut Vec<u8>, defect: Defect) {
+    let size = mem::size_of::<T>();
+    let record = |bytes: &[u8], index: usize| -> Range<usize> {
+        let range = index * size..(index + 1) * size;
+        assert!(range.end <= bytes.len(), "no record {} to corrupt", index);
+        range
+    };
+    match defect {
+        Defect::TruncateMidRecord => {
+            let len = bytes.len() / size * size;
+            bytes.truncate(len.saturating_sub(size / 2));
+        }
+        Defect::FlipType(index) => {
+            let range = record(bytes, index);
+            // `ut_type` comes first in both layouts.
+            bytes[range.start] ^= 0xff;
+            bytes[range.start + 1] ^= 0xff;
+        }
+        Defect::ZeroRecord(index) => {
+            let range = record(bytes, index);
+            bytes[range].iter_mut().for_each(|b| *b = 0);
+        }
+        Defect::SwapEndianness(index) => {
+            let range = record(bytes, index);
+            let raw = raw_from_record::<T>(&bytes[range.clone()], true);
+            let mut swapped = vec![0; size];
+            // Values read from a record of the layout always fit back into it.
+            RecordValues::of(&raw)
+                .write::<T>(&mut swapped, false)
+                .unwrap();
+            bytes[range].copy_from_slice(&swapped);
+        }
+    }
+}
+
+/// ID of the line as `sshd(8)` derives it, i.e. its last 4 bytes like `ts/0` of `pts/0`.
+fn line_id(line: &str) -> &str {
+    let start = (line.len().saturating_sub(4)..line.len())
+        .find(|&start| line.is_char_boundary(start))
+        .unwrap_or(line.len());
+    &line[start..]
+}
+
+/// Values of the fields of the record the entry would be parsed from.
+fn record_values(entry: &UtmpEntry) -> RecordValues<'_> {
+    let mut values = RecordValues {
+        ut_type: utmp_raw::EMPTY,
+        pid: 0,
+        line: &[],
+        id: &[],
+        user: &[],
+        host: &[],
+        exit: exit_status {
+            e_termination: 0,
+            e_exit: 0,
+        },
+        session: 0,
+        tv: timeval64 {
+            tv_sec: 0,
+            tv_usec: 0,
+        },
+        addr: [0; 16],
+        unused: &[],
+    };
+    let system = |values: &mut RecordValues<'_>, user: &'static str| {
+        values.line = b"~";
+        values.id = b"~~";
+        values.user = user.as_bytes();
+    };
+    match entry {
+        UtmpEntry::Empty => {}
+        UtmpEntry::RunLevel {
+            level,
+            previous,
+            kernel_version,
+            ..
+        } => {
+            system(&mut values, "runlevel");
+            values.ut_type = utmp_raw::RUN_LVL;
+            let previous = previous.map_or(0, |previous| previous as pid_t);
+            values.pid = (*level as pid_t & 0xff) | (previous & 0xff) << 8;
+            values.host = kernel_version.as_bytes();
+        }
+        UtmpEntry::BootTime { kernel_version, .. } => {
+            system(&mut values, "reboot");
+            values.ut_type = utmp_raw::BOOT_TIME;
+            values.host = kernel_version.as_bytes();
+        }
+        UtmpEntry::ShutdownTime { kernel_version, .. } => {
+            system(&mut values, "shutdown");
+            values.ut_type = utmp_raw::RUN_LVL;
+            values.host = kernel_version.as_bytes();
+        }
+        UtmpEntry::NewTime(_) => values.ut_type = utmp_raw::NEW_TIME,
+        UtmpEntry::OldTime(_) => values.ut_type = utmp_raw::OLD_TIME,
+        UtmpEntry::InitProcess { pid, id, .. } => {
+            values.ut_type = utmp_raw::INIT_PROCESS;
+            values.pid = *pid;
+            values.id = id.as_bytes();
+        }
+        UtmpEntry::LoginProcess {
+            pid,
+            line,
+            id,
+            user,
+            host,
+            ..
+        } => {
+            values.ut_type = utmp_raw::LOGIN_PROCESS;
+            values.pid = *pid;
+            values.line = line.as_bytes();
+            values.id = id.as_bytes();
+            values.user = user.as_bytes();
+            values.host = host.as_bytes();
+        }
+        UtmpEntry::UserProcess {
+            pid,
+            line,
+            id,
+            user,
+            host,
+            address,
+            session,
+            ..
+        } => {
+            values.ut_type = utmp_raw::USER_PROCESS;
+            values.pid = *pid;
+            values.line = line.as_bytes();
+            values.id = id.as_bytes();
+            values.user = user.as_bytes();
+            values.host = host.as_bytes();
+            values.session = i64::from(*session);
+            match address {
+                Some(IpAddr::V4(address)) => values.addr[..4].copy_from_slice(&address.octets()),
+                Some(IpAddr::V6(address)) => values.addr = address.octets(),
+                None => {}
+            }
+        }
+        UtmpEntry::DeadProcess {
+            pid,
+            line,
+            id,
+            termination,
+            exit,
+            ..
+        } => {
+            values.ut_type = utmp_raw::DEAD_PROCESS;
+            values.pid = *pid;
+            values.line = line.as_bytes();
+            values.id = id.as_bytes();
+            values.exit = exit_status {
+                e_termination: *termination,
+                e_exit: *exit,
+            };
+        }
+        UtmpEntry::Accounting => values.ut_type = utmp_raw::ACCOUNTING,
+        UtmpEntry::Unknown { ut_type, pid, .. } => {
+            values.ut_type = *ut_type;
+            values.pid = *pid;
+        }
+    }
+    if let Some(time) = entry.time() {
+        let Timestamp { secs, micros } = time;
+        values.tv = timeval64 {
+            tv_sec: secs,
+            tv_usec: i64::from(micros),
+        };
+    }
+    values
+}
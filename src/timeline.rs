@@ -0,0 +1,129 @@
+use crate::{Session, Timestamp};
+use std::borrow::Borrow;
+use time::Duration;
+
+/// Number of sessions logged in at the same time, as a step function of time.
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let sessions = utmp_rs::sessions(utmp_rs::parse_from_path("/var/log/wtmp")?);
+/// let timeline = utmp_rs::Timeline::new(sessions);
+/// if let Some(peak) = timeline.peak() {
+///     println!("{} sessions at {}", peak.count, peak.time);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Timeline {
+    steps: Vec<Step>,
+}
+
+/// Change of the number of concurrent sessions in a `Timeline`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Step {
+    /// Time the number changes
+    pub time: Timestamp,
+    /// Number of sessions from the time until the next step
+    pub count: usize,
+}
+
+/// Period of a bucketed `Timeline`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Bucket {
+    /// Start of the period
+    pub start: Timestamp,
+    /// End of the period, exclusive
+    pub end: Timestamp,
+    /// Largest number of concurrent sessions within the period
+    pub peak: usize,
+}
+
+impl Timeline {
+    /// Build the timeline of the given sessions.
+    ///
+    /// Sessions still logged in count until the end of their duration if they have one,
+    /// e.g. with `Sessions::live_at`, and indefinitely otherwise.
+    /// Sessions ending at the same time others start aren't counted together.
+    pub fn new<I>(sessions: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Session>,
+    {
+        let mut changes = Vec::new();
+        for session in sessions {
+            let session = session.borrow();
+            let end = (session.logout).or_else(|| Some(session.login + session.duration?));
+            changes.push((session.login, 1));
+            if let Some(end) = end {
+                changes.push((end.max(session.login), -1));
+            }
+        }
+        changes.sort_by_key(|&(time, _)| time);
+        let mut steps = Vec::<Step>::new();
+        let mut count = 0i64;
+        let mut changes = changes.into_iter().peekable();
+        while let Some((time, delta)) = changes.next() {
+            count += delta;
+            if changes.peek().is_some_and(|&(next, _)| next == time) {
+                continue;
+            }
+            let count = count.max(0) as usize;
+            if steps.last().map_or(0, |step| step.count) != count {
+                steps.push(Step { time, count });
+            }
+        }
+        Timeline { steps }
+    }
+
+    /// Steps in order of time, starting from zero sessions before the first one.
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Number of sessions logged in at the given time.
+    pub fn count_at(&self, time: Timestamp) -> usize {
+        match self.steps.partition_point(|step| step.time <= time) {
+            0 => 0,
+            n => self.steps[n - 1].count,
+        }
+    }
+
+    /// The first step with the largest number of concurrent sessions.
+    pub fn peak(&self) -> Option<&Step> {
+        let peak = self.steps.iter().rev().max_by_key(|step| step.count);
+        peak.filter(|step| step.count > 0)
+    }
+
+    /// Split the given period into buckets of the given width,
+    /// with the peak number of sessions within each.
+    ///
+    /// The last bucket is cut at the end of the period.
+    /// It returns no bucket if the width is not positive.
+    pub fn buckets(&self, start: Timestamp, end: Timestamp, width: Duration) -> Vec<Bucket> {
+        let mut buckets = Vec::new();
+        if !width.is_positive() {
+            return buckets;
+        }
+        let mut bucket_start = start;
+        while bucket_start < end {
+            let bucket_end = (bucket_start + width).min(end);
+            let first = self.steps.partition_point(|step| step.time <= bucket_start);
+            let within = self.steps[first..]
+                .iter()
+                .take_while(|step| step.time < bucket_end);
+            let peak = within
+                .map(|step| step.count)
+                .fold(self.count_at(bucket_start), usize::max);
+            buckets.push(Bucket {
+                start: bucket_start,
+                end: bucket_end,
+                peak,
+            });
+            bucket_start = bucket_end;
+        }
+        buckets
+    }
+}
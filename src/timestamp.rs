@@ -0,0 +1,241 @@
+use crate::UtmpError;
+use std::convert::TryFrom;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+use utmp_raw::x64::timeval as timeval64;
+
+/// Time an entry was made, as seconds and microseconds since the Unix epoch.
+///
+/// With the `time` feature, it converts from and to `time::OffsetDateTime`,
+/// and `time::Duration` can be added to and subtracted from it.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Timestamp {
+    /// Seconds since the Unix epoch
+    pub secs: i64,
+    /// Microseconds within the second, less than 1,000,000
+    pub micros: u32,
+}
+
+impl Timestamp {
+    /// The Unix epoch, i.e. 1970-01-01 00:00:00 UTC.
+    pub const UNIX_EPOCH: Timestamp = Timestamp { secs: 0, micros: 0 };
+
+    /// Create a timestamp of whole seconds since the Unix epoch.
+    pub fn from_secs(secs: i64) -> Self {
+        Timestamp { secs, micros: 0 }
+    }
+
+    /// Current time of the system.
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now())
+    }
+
+    /// Time of the system time, saturated to the range of timestamps.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let micros = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(since) => i128::try_from(since.as_micros()).unwrap_or(i128::MAX),
+            Err(e) => -i128::try_from(e.duration().as_micros()).unwrap_or(i128::MAX),
+        };
+        Self::from_micros(micros)
+    }
+
+    /// Time as a system time, or `None` if it's beyond what `SystemTime` can represent.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let micros = Duration::from_micros(u64::from(self.micros));
+        let secs = Duration::from_secs(self.secs.unsigned_abs());
+        let time = if self.secs >= 0 {
+            SystemTime::UNIX_EPOCH.checked_add(secs)
+        } else {
+            SystemTime::UNIX_EPOCH.checked_sub(secs)
+        };
+        time?.checked_add(micros)
+    }
+
+    /// Microseconds since the Unix epoch.
+    #[cfg(feature = "time")]
+    fn as_micros(self) -> i128 {
+        i128::from(self.secs) * 1_000_000 + i128::from(self.micros)
+    }
+
+    /// Time of microseconds since the Unix epoch, saturated to the range of timestamps.
+    fn from_micros(micros: i128) -> Self {
+        let secs = micros.div_euclid(1_000_000);
+        match i64::try_from(secs) {
+            Ok(secs) => Timestamp {
+                secs,
+                micros: micros.rem_euclid(1_000_000) as u32,
+            },
+            Err(_) if secs < 0 => Timestamp::from_secs(i64::MIN),
+            Err(_) => Timestamp {
+                secs: i64::MAX,
+                micros: 999_999,
+            },
+        }
+    }
+}
+
+/// Timestamps are displayed as RFC 3339 in UTC with microseconds,
+/// e.g. `2023-02-07T08:07:06.139552Z`.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_rfc3339(*self))
+    }
+}
+
+/// Times beyond the range of `OffsetDateTime`, i.e. the years from -9999 to 9999, fail to convert.
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    type Error = time::error::ComponentRange;
+
+    fn try_from(time: Timestamp) -> Result<Self, Self::Error> {
+        let nanos = time.as_micros() * 1000;
+        time::OffsetDateTime::from_unix_timestamp_nanos(nanos)
+    }
+}
+
+/// Nanoseconds beyond the microseconds utmp records are truncated.
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(time: time::OffsetDateTime) -> Self {
+        Timestamp {
+            secs: time.unix_timestamp(),
+            micros: time.microsecond(),
+        }
+    }
+}
+
+/// The result saturates at the range of timestamps.
+#[cfg(feature = "time")]
+impl std::ops::Add<time::Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: time::Duration) -> Timestamp {
+        Timestamp::from_micros(
+            self.as_micros()
+                .saturating_add(duration.whole_microseconds()),
+        )
+    }
+}
+
+/// The result saturates at the range of timestamps.
+#[cfg(feature = "time")]
+impl std::ops::Sub<time::Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: time::Duration) -> Timestamp {
+        Timestamp::from_micros(
+            self.as_micros()
+                .saturating_sub(duration.whole_microseconds()),
+        )
+    }
+}
+
+/// The result saturates at the range of `time::Duration`.
+#[cfg(feature = "time")]
+impl std::ops::Sub for Timestamp {
+    type Output = time::Duration;
+
+    fn sub(self, other: Timestamp) -> time::Duration {
+        let micros = self.as_micros() - other.as_micros();
+        // Seconds and microseconds have the same sign, so that they don't carry.
+        match i64::try_from(micros / 1_000_000) {
+            Ok(secs) => time::Duration::new(secs, (micros % 1_000_000) as i32 * 1000),
+            Err(_) if micros < 0 => time::Duration::MIN,
+            Err(_) => time::Duration::MAX,
+        }
+    }
+}
+
+/// Time as a date and time in UTC, clamped to the range of `OffsetDateTime`,
+/// for breaking it down into calendar fields.
+#[cfg(feature = "time")]
+pub(crate) fn datetime(time: Timestamp) -> time::OffsetDateTime {
+    use time::{Date, OffsetDateTime, PrimitiveDateTime, Time};
+    OffsetDateTime::try_from(time).unwrap_or_else(|_| {
+        let datetime = if time.secs < 0 {
+            PrimitiveDateTime::new(Date::MIN, Time::MIDNIGHT)
+        } else {
+            PrimitiveDateTime::new(
+                Date::MAX,
+                Time::from_hms_micro(23, 59, 59, 999_999).unwrap(),
+            )
+        };
+        datetime.assume_utc()
+    })
+}
+
+pub(crate) fn time_from_tv(tv: timeval64) -> Result<Timestamp, UtmpError> {
+    let timeval64 { tv_sec, tv_usec } = tv;
+    if tv_usec < 0 {
+        return Err(UtmpError::InvalidTime(tv));
+    }
+    let secs = tv_sec.checked_add(tv_usec / 1_000_000);
+    let secs = secs.ok_or(UtmpError::InvalidTime(tv))?;
+    let micros = (tv_usec % 1_000_000) as u32;
+    Ok(Timestamp { secs, micros })
+}
+
+/// Format the time as RFC 3339 in UTC with microseconds, which is the precision of utmp.
+pub(crate) fn format_rfc3339(time: Timestamp) -> String {
+    let civil = civil(time);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        civil.year, civil.month, civil.day, civil.hour, civil.minute, civil.second, time.micros,
+    )
+}
+
+/// Date and time of day of a timestamp.
+pub(crate) struct Civil {
+    pub(crate) year: i64,
+    pub(crate) month: u8,
+    pub(crate) day: u8,
+    pub(crate) hour: u8,
+    pub(crate) minute: u8,
+    pub(crate) second: u8,
+}
+
+/// Break the timestamp down in UTC.
+pub(crate) fn civil(time: Timestamp) -> Civil {
+    civil_from_secs(time.secs)
+}
+
+/// Break seconds since the Unix epoch down in UTC.
+pub(crate) fn civil_from_secs(secs: i64) -> Civil {
+    let days = secs.div_euclid(86400);
+    let secs = secs.rem_euclid(86400);
+    // Convert days since the epoch to a date in the proleptic Gregorian calendar,
+    // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    Civil {
+        year,
+        month: month as u8,
+        day: day as u8,
+        hour: (secs / 3600) as u8,
+        minute: (secs / 60 % 60) as u8,
+        second: (secs % 60) as u8,
+    }
+}
+
+/// Seconds since the Unix epoch of the date and time of day in UTC,
+/// the inverse of `civil_from_secs`.
+pub(crate) fn secs_from_civil(civil: &Civil) -> i64 {
+    // See http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let month = i64::from(civil.month);
+    let year = civil.year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(civil.day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let time = i64::from(civil.hour) * 3600 + i64::from(civil.minute) * 60;
+    days * 86400 + time + i64::from(civil.second)
+}
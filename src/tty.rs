@@ -0,0 +1,146 @@
+use crate::timestamp::datetime;
+use crate::{Session, Timestamp};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use time::Duration;
+
+/// State of the tty of a session, like `who -u -T` shows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct TtyStatus {
+    /// Time since the tty was last accessed, i.e. since the user typed something
+    pub idle: Duration,
+    /// Whether other users can write to the tty, as `mesg(1)` sets
+    pub writable: bool,
+}
+
+impl TtyStatus {
+    /// Message status shown by `who -T`, i.e. `+` if writable, `-` otherwise.
+    pub fn mesg(&self) -> char {
+        if self.writable {
+            '+'
+        } else {
+            '-'
+        }
+    }
+}
+
+/// Checker which stats tty devices under `/dev` to get the status of sessions on them.
+///
+/// ```
+/// # use utmp_rs::{TtyChecker, WhoLine};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let checker = TtyChecker::new();
+/// for session in utmp_rs::current_users("/var/run/utmp")? {
+///     let status = checker.status(&session.line)?;
+///     println!("{}", WhoLine::new(&session, status.as_ref()));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TtyChecker {
+    dev_path: PathBuf,
+}
+
+impl Default for TtyChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TtyChecker {
+    /// Create a checker using `/dev`.
+    pub fn new() -> Self {
+        Self::with_dev_path("/dev")
+    }
+
+    /// Create a checker using devices under the given path.
+    pub fn with_dev_path<P: AsRef<Path>>(path: P) -> Self {
+        TtyChecker {
+            dev_path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// Get the status of the tty on the given line, with the idle time up to now.
+    ///
+    /// It returns `None` if the line is empty or there is no such device,
+    /// e.g. for X displays.
+    pub fn status(&self, line: &str) -> io::Result<Option<TtyStatus>> {
+        self.status_at(line, Timestamp::now())
+    }
+
+    /// Get the status of the tty on the given line, with the idle time up to the given time.
+    pub fn status_at(&self, line: &str, now: Timestamp) -> io::Result<Option<TtyStatus>> {
+        // Lines are relative to `/dev`, and shouldn't escape it.
+        if line.is_empty() || line.split('/').any(|part| part == "..") {
+            return Ok(None);
+        }
+        let metadata = match fs::metadata(self.dev_path.join(line)) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let accessed = Timestamp::from_secs(metadata.atime());
+        // `mesg(1)` grants the tty group write permission, i.e. `S_IWGRP`,
+        // whose type varies across platforms.
+        Ok(Some(TtyStatus {
+            idle: (now - accessed).max(Duration::ZERO),
+            writable: metadata.mode() & 0o020 != 0,
+        }))
+    }
+}
+
+/// Session formatted like a line of `who -u -T`,
+/// e.g. `root     + pts/0        2023-02-07 08:07 00:40        1125 (112.124.2.209)`.
+///
+/// Without a tty status, message status is `?` and idle time is left empty.
+/// Times are formatted in UTC.
+#[derive(Clone, Copy, Debug)]
+pub struct WhoLine<'a> {
+    session: &'a Session,
+    status: Option<&'a TtyStatus>,
+}
+
+impl<'a> WhoLine<'a> {
+    /// Format the session with the given status of its tty.
+    pub fn new(session: &'a Session, status: Option<&'a TtyStatus>) -> Self {
+        WhoLine { session, status }
+    }
+}
+
+impl fmt::Display for WhoLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = self.session;
+        let mesg = self.status.map_or('?', TtyStatus::mesg);
+        let login = datetime(s.login);
+        let time = format!(
+            "{}-{:02}-{:02} {:02}:{:02}",
+            login.year(),
+            u8::from(login.month()),
+            login.day(),
+            login.hour(),
+            login.minute(),
+        );
+        let idle = match self.status.map(|status| status.idle.whole_minutes()) {
+            Some(minutes) if minutes < 1 => "  .".to_owned(),
+            Some(minutes) if minutes < 24 * 60 => {
+                format!("{:02}:{:02}", minutes / 60, minutes % 60)
+            }
+            Some(_) => " old".to_owned(),
+            None => String::new(),
+        };
+        let line = format!(
+            "{:<8} {} {:<12} {:<16} {:<6} {:>10} ",
+            s.user, mesg, s.line, time, idle, s.pid
+        );
+        if s.host.is_empty() {
+            f.write_str(line.trim_end())
+        } else {
+            write!(f, "{}({})", line, s.host)
+        }
+    }
+}
@@ -0,0 +1,404 @@
+use crate::{ParseError, Timestamp, UtmpEntry, UtmpParser};
+use std::collections::VecDeque;
+use std::convert::TryInto;
+use std::ffi::{CString, OsString};
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::mem;
+use std::os::raw::c_int;
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::{Path, PathBuf};
+use utmp_raw::utmp;
+
+const UTMP_PATH: &str = "/var/run/utmp";
+const WTMP_PATH: &str = "/var/log/wtmp";
+const BTMP_PATH: &str = "/var/log/btmp";
+
+const UTMP_SIZE: u64 = mem::size_of::<utmp>() as u64;
+/// Size of the fixed part of `struct inotify_event`.
+const EVENT_HEADER_SIZE: usize = mem::size_of::<libc::inotify_event>();
+
+/// File watched by `UtmpWatcher`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Source {
+    /// `/var/run/utmp`, for users currently logged in
+    Utmp,
+    /// `/var/log/wtmp`, for history of logins
+    Wtmp,
+    /// `/var/log/btmp`, for failed login attempts
+    Btmp,
+    /// Any other file
+    Other(PathBuf),
+}
+
+impl Source {
+    /// Path of the file.
+    pub fn path(&self) -> &Path {
+        match self {
+            Source::Utmp => Path::new(UTMP_PATH),
+            Source::Wtmp => Path::new(WTMP_PATH),
+            Source::Btmp => Path::new(BTMP_PATH),
+            Source::Other(path) => path,
+        }
+    }
+}
+
+/// Event yielded by `UtmpWatcher`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum WatchEvent {
+    /// New entry written to a watched file
+    Entry(Source, UtmpEntry),
+    /// Watched file was replaced or truncated, and has been reopened from the beginning
+    Rotated(Source),
+}
+
+/// Watcher which yields entries written to utmp files as they arrive.
+///
+/// It waits on inotify for modifications of the watched files,
+/// so iterating it blocks until new entries are written.
+/// Only entries written after the file started being watched are yielded.
+///
+/// Entries are appended to wtmp and btmp, but `pututline(3)` rewrites records of utmp in place,
+/// e.g. a logout replaces the login on the same line.
+/// So records of `Source::Utmp`, and of files watched with `watch_rewritten`,
+/// are compared with those read before, and records changed or added are yielded.
+///
+/// When a watched file is renamed away and recreated (as logrotate does),
+/// or is truncated, the watcher yields `WatchEvent::Rotated`
+/// and continues reading the new content from the beginning.
+///
+/// Entries are tagged with the file they come from.
+/// When several files get written at the same time,
+/// their entries are merged in order of timestamp.
+///
+/// ```no_run
+/// # use utmp_rs::{Source, UtmpWatcher, WatchEvent};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let sources = [Source::Utmp, Source::Wtmp, Source::Btmp];
+/// for event in UtmpWatcher::from_sources(sources)? {
+///     if let WatchEvent::Entry(source, entry) = event? {
+///         // handle entry
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct UtmpWatcher {
+    inotify: File,
+    files: Vec<WatchedFile>,
+    pending: VecDeque<Result<WatchEvent, ParseError>>,
+}
+
+struct WatchedFile {
+    source: Source,
+    file: File,
+    /// Watch descriptor of the parent directory.
+    wd: c_int,
+    /// File name inside the parent directory.
+    name: OsString,
+    /// Offset of the next record to read.
+    offset: u64,
+    /// Records read so far, for files rewritten in place.
+    records: Option<Vec<u8>>,
+}
+
+impl UtmpWatcher {
+    /// Create a watcher for `/var/run/utmp` and `/var/log/wtmp`.
+    pub fn new() -> io::Result<Self> {
+        Self::from_sources([Source::Utmp, Source::Wtmp])
+    }
+
+    /// Create a watcher for the given paths.
+    ///
+    /// Entries are tagged with `Source::Other`.
+    pub fn from_paths<I, P>(paths: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let sources = paths
+            .into_iter()
+            .map(|path| Source::Other(path.as_ref().to_owned()));
+        Self::from_sources(sources)
+    }
+
+    /// Create a watcher for the given sources.
+    pub fn from_sources<I: IntoIterator<Item = Source>>(sources: I) -> io::Result<Self> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // This is safe because we just created the descriptor and nothing else owns it.
+        let inotify = unsafe { File::from_raw_fd(fd) };
+        let mut watcher = UtmpWatcher {
+            inotify,
+            files: Vec::new(),
+            pending: VecDeque::new(),
+        };
+        for source in sources {
+            watcher.watch(source)?;
+        }
+        Ok(watcher)
+    }
+
+    /// Start watching an additional source.
+    ///
+    /// Records of `Source::Utmp` are watched for being rewritten in place as well.
+    pub fn watch(&mut self, source: Source) -> io::Result<()> {
+        let rewritten = source == Source::Utmp;
+        self.watch_file(source, rewritten)
+    }
+
+    /// Start watching an additional source whose records are rewritten in place, as utmp is,
+    /// yielding records changed as well as those appended.
+    pub fn watch_rewritten(&mut self, source: Source) -> io::Result<()> {
+        self.watch_file(source, true)
+    }
+
+    fn watch_file(&mut self, source: Source, rewritten: bool) -> io::Result<()> {
+        let path = source.path();
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?
+            .to_owned();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        // Watch the parent directory rather than the file itself,
+        // so that we get notified when the file is replaced.
+        let mask = libc::IN_MODIFY | libc::IN_CREATE | libc::IN_MOVED_TO;
+        let wd = add_watch(&self.inotify, dir, mask)?;
+        let mut file = File::open(path)?;
+        let offset = file.seek(SeekFrom::End(0))?;
+        let mut watched = WatchedFile {
+            source,
+            file,
+            wd,
+            name,
+            offset,
+            records: None,
+        };
+        if rewritten {
+            // Take the records present as read, so that only later changes are yielded.
+            watched.records = Some(Vec::new());
+            watched.read_changed(offset, &mut VecDeque::new())?;
+        }
+        self.files.push(watched);
+        Ok(())
+    }
+
+    /// Start watching an additional source, yielding its entries made at or after the given time
+    /// before new ones, e.g. to backfill history before following it.
+    ///
+    /// Records are assumed to be in order of time, as in wtmp and btmp,
+    /// so the first of them is found with `UtmpParser::seek_to_time`.
+    /// Entries already in the file are merged in order of timestamp
+    /// with those queued from other sources.
+    pub fn watch_since(&mut self, source: Source, since: Timestamp) -> Result<(), ParseError> {
+        let path = source.path().to_owned();
+        let mut parser =
+            UtmpParser::from_path(&path).map_err(|e| ParseError::from(e).with_path(&path))?;
+        parser.seek_to_time(since)?;
+        self.watch(source)?;
+        let file = self.files.last_mut().expect("source just watched");
+        // Read up to where watching starts, so nothing is missed or repeated.
+        let len = mem::replace(&mut file.offset, parser.offset());
+        let mut batch = VecDeque::new();
+        file.read_appended(len, &mut batch)?;
+        let pending = mem::take(&mut self.pending);
+        merge_batches(&mut self.pending, vec![pending, batch]);
+        Ok(())
+    }
+
+    /// Wait for the next batch of inotify events and queue any new entries.
+    fn wait(&mut self) -> io::Result<()> {
+        let events = read_events(&mut self.inotify)?;
+        let mut batches = Vec::new();
+        for file in self.files.iter_mut() {
+            if events
+                .iter()
+                .any(|(wd, name)| *wd == file.wd && *name == file.name)
+            {
+                let mut batch = VecDeque::new();
+                file.update(&mut batch)?;
+                batches.push(batch);
+            }
+        }
+        merge_batches(&mut self.pending, batches);
+        Ok(())
+    }
+}
+
+/// Merge events from different files in order of timestamp,
+/// while keeping the order of events from the same file.
+fn merge_batches(
+    pending: &mut VecDeque<Result<WatchEvent, ParseError>>,
+    mut batches: Vec<VecDeque<Result<WatchEvent, ParseError>>>,
+) {
+    fn event_time(event: &Result<WatchEvent, ParseError>) -> Option<Timestamp> {
+        match event {
+            Ok(WatchEvent::Entry(_, entry)) => entry.time(),
+            _ => None,
+        }
+    }
+    // Events without timestamp are taken first, and ties go to the file watched first.
+    while let Some(index) = batches
+        .iter()
+        .enumerate()
+        .filter_map(|(i, batch)| batch.front().map(|event| (i, event_time(event))))
+        .min_by_key(|(_, time)| *time)
+        .map(|(i, _)| i)
+    {
+        pending.extend(batches[index].pop_front());
+    }
+}
+
+impl WatchedFile {
+    /// Queue new entries, reopening the file if it has been replaced or truncated.
+    fn update(&mut self, pending: &mut VecDeque<Result<WatchEvent, ParseError>>) -> io::Result<()> {
+        let current = self.file.metadata()?;
+        let replaced = match fs::metadata(self.source.path()) {
+            Ok(new) => new.dev() != current.dev() || new.ino() != current.ino(),
+            // The file has been moved away but not recreated yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+        if replaced {
+            // Pick up whatever was written to the old file before it got replaced.
+            self.read_new(current.len(), pending)?;
+            self.file = File::open(self.source.path())?;
+            self.reset();
+            pending.push_back(Ok(WatchEvent::Rotated(self.source.clone())));
+            let len = self.file.metadata()?.len();
+            self.read_new(len, pending)
+        } else {
+            if current.len() < self.offset {
+                self.reset();
+                pending.push_back(Ok(WatchEvent::Rotated(self.source.clone())));
+            }
+            self.read_new(current.len(), pending)
+        }
+    }
+
+    /// Forget what has been read, to read the file from the beginning.
+    fn reset(&mut self) {
+        self.offset = 0;
+        if let Some(records) = &mut self.records {
+            records.clear();
+        }
+    }
+
+    fn read_new(
+        &mut self,
+        len: u64,
+        pending: &mut VecDeque<Result<WatchEvent, ParseError>>,
+    ) -> io::Result<()> {
+        match self.records {
+            Some(_) => self.read_changed(len, pending),
+            None => self.read_appended(len, pending),
+        }
+    }
+
+    /// Queue entries of records appended since the last read.
+    fn read_appended(
+        &mut self,
+        len: u64,
+        pending: &mut VecDeque<Result<WatchEvent, ParseError>>,
+    ) -> io::Result<()> {
+        // Leave partially written records for the next round.
+        let available = len.saturating_sub(self.offset) / UTMP_SIZE * UTMP_SIZE;
+        if available == 0 {
+            return Ok(());
+        }
+        self.file.seek(SeekFrom::Start(self.offset))?;
+        let entries = UtmpParser::from_reader((&self.file).take(available));
+        let source = &self.source;
+        pending.extend(entries.map(|entry| Ok(WatchEvent::Entry(source.clone(), entry?))));
+        self.offset += available;
+        Ok(())
+    }
+
+    /// Queue entries of records changed or added since the last read.
+    fn read_changed(
+        &mut self,
+        len: u64,
+        pending: &mut VecDeque<Result<WatchEvent, ParseError>>,
+    ) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(0))?;
+        let mut data = Vec::new();
+        (&self.file).take(len).read_to_end(&mut data)?;
+        // Leave partially written records for the next round.
+        data.truncate(data.len() / UTMP_SIZE as usize * UTMP_SIZE as usize);
+        let old = self.records.take().unwrap_or_default();
+        let records = data.chunks_exact(UTMP_SIZE as usize);
+        let mut old_records = old.chunks_exact(UTMP_SIZE as usize);
+        for record in records {
+            if old_records.next() == Some(record) {
+                continue;
+            }
+            let source = &self.source;
+            let entries = UtmpParser::from_reader(record);
+            pending.extend(entries.map(|entry| Ok(WatchEvent::Entry(source.clone(), entry?))));
+        }
+        self.offset = data.len() as u64;
+        self.records = Some(data);
+        Ok(())
+    }
+}
+
+impl Iterator for UtmpWatcher {
+    type Item = Result<WatchEvent, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if let Err(e) = self.wait() {
+                return Some(Err(e.into()));
+            }
+        }
+    }
+}
+
+fn add_watch(inotify: &File, path: &Path, mask: u32) -> io::Result<c_int> {
+    let path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let wd = unsafe { libc::inotify_add_watch(inotify.as_raw_fd(), path.as_ptr(), mask) };
+    if wd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(wd)
+}
+
+/// Block until some inotify events are available and return them as
+/// pairs of watch descriptor and file name.
+fn read_events(inotify: &mut File) -> io::Result<Vec<(c_int, OsString)>> {
+    let mut buffer = [0; 4096];
+    let len = loop {
+        match inotify.read(&mut buffer) {
+            Ok(len) => break len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    };
+    let mut events = Vec::new();
+    let mut rest = &buffer[..len];
+    while rest.len() >= EVENT_HEADER_SIZE {
+        let wd = c_int::from_ne_bytes(rest[0..4].try_into().unwrap());
+        let name_len = u32::from_ne_bytes(rest[12..16].try_into().unwrap()) as usize;
+        let end = (EVENT_HEADER_SIZE + name_len).min(rest.len());
+        let name = &rest[EVENT_HEADER_SIZE..end];
+        let name = match name.iter().position(|b| *b == 0) {
+            Some(pos) => &name[..pos],
+            None => name,
+        };
+        events.push((wd, OsString::from_vec(name.to_vec())));
+        rest = &rest[end..];
+    }
+    Ok(events)
+}
@@ -1,17 +1,27 @@
 use anyhow::Result;
 use once_cell::sync::Lazy;
-use std::fs::File;
-use std::io::{self, BufReader, Read};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Cursor, Read};
 use std::iter::FromIterator;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::path::PathBuf;
-use time::OffsetDateTime;
-use utmp_rs::{parse_from_path, Utmp32Parser, Utmp64Parser, UtmpEntry};
+use std::time::{Duration, SystemTime};
+use utmp_rs::ParseError;
+use utmp_rs::{parse_from_path, AnomalyKind, Endianness, ErrorPolicy, Format, IntegrityChecker};
+use utmp_rs::{Anonymizer, ConvertError, Converter, Redaction};
+use utmp_rs::{Timestamp, UtmpEntry, UtmpEntryKind, UtmpEntryRef, UtmpError};
+use utmp_rs::{Utmp32Parser, Utmp64Parser, UtmpParser};
 
 static SAMPLES_PATH: Lazy<PathBuf> =
     Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
 
-fn timestamp(nanos: i128) -> OffsetDateTime {
-    OffsetDateTime::from_unix_timestamp_nanos(nanos).unwrap()
+fn timestamp(nanos: i128) -> Timestamp {
+    Timestamp {
+        secs: (nanos / 1_000_000_000) as i64,
+        micros: (nanos % 1_000_000_000 / 1000) as u32,
+    }
 }
 
 fn get_basic32_expected() -> Vec<UtmpEntry> {
@@ -21,23 +31,28 @@ fn get_basic32_expected() -> Vec<UtmpEntry> {
             time: timestamp(1581199438_054727_000),
         },
         UtmpEntry::RunLevel {
-            pid: 53,
+            level: '5',
+            previous: None,
             kernel_version: "5.3.0-29-generic".to_owned(),
             time: timestamp(1581199447_558900_000),
         },
         UtmpEntry::UserProcess {
             pid: 2555,
             line: ":1".to_owned(),
+            id: "".to_owned(),
             user: "upsuper".to_owned(),
             host: ":1".to_owned(),
+            address: None,
             session: 0,
             time: timestamp(1581199675_609322_000),
         },
         UtmpEntry::UserProcess {
             pid: 28885,
             line: "tty3".to_owned(),
+            id: "tty3".to_owned(),
             user: "upsuper".to_owned(),
             host: "".to_owned(),
+            address: None,
             session: 28786,
             time: timestamp(1581217267_195722_000),
         },
@@ -45,6 +60,7 @@ fn get_basic32_expected() -> Vec<UtmpEntry> {
             pid: 28965,
             time: timestamp(1581217268_463588_000),
             line: "tty4".to_owned(),
+            id: "tty4".to_owned(),
             user: "LOGIN".to_owned(),
             host: "".to_owned(),
         },
@@ -62,21 +78,25 @@ fn get_with_host_32_expected() -> Vec<UtmpEntry> {
             time: timestamp(1675756860_150698_000),
         },
         UtmpEntry::RunLevel {
-            pid: 53,
+            level: '5',
+            previous: None,
             kernel_version: "5.4.0-135-generic".to_owned(),
             time: timestamp(1675756874_594747_000),
         },
         UtmpEntry::InitProcess {
             pid: 627,
+            id: "tyS0".to_owned(),
             time: timestamp(1675756875_303010_000),
         },
         UtmpEntry::InitProcess {
             pid: 644,
+            id: "tty1".to_owned(),
             time: timestamp(1675756875_305313_000),
         },
         UtmpEntry::LoginProcess {
             pid: 644,
             line: "tty1".to_owned(),
+            id: "tty1".to_owned(),
             user: "LOGIN".to_owned(),
             host: "".to_owned(),
             time: timestamp(1675756875_305313_000),
@@ -84,6 +104,7 @@ fn get_with_host_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 627,
             line: "ttyS0".to_owned(),
+            id: "tyS0".to_owned(),
             user: "LOGIN".to_owned(),
             host: "".to_owned(),
             time: timestamp(1675756875_303010_000),
@@ -91,84 +112,112 @@ fn get_with_host_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::UserProcess {
             pid: 1125,
             line: "pts/0".to_owned(),
+            id: "ts/0".to_owned(),
             user: "root".to_owned(),
             host: "112.124.2.209".to_owned(),
+            address: Some(Ipv4Addr::new(112, 124, 2, 209).into()),
             session: 0,
             time: timestamp(1675757226_139552_000),
         },
         UtmpEntry::UserProcess {
             pid: 1127,
             line: "pts/1".to_owned(),
+            id: "ts/1".to_owned(),
             user: "root".to_owned(),
             host: "112.124.2.209".to_owned(),
+            address: Some(Ipv4Addr::new(112, 124, 2, 209).into()),
             session: 0,
             time: timestamp(1675757226_284647_000),
         },
         UtmpEntry::DeadProcess {
             pid: 1020,
             line: "pts/0".to_owned(),
+            id: "".to_owned(),
+            termination: 0,
+            exit: 0,
             time: timestamp(1675757226_404205_000),
         },
         UtmpEntry::DeadProcess {
             pid: 1020,
             line: "pts/1".to_owned(),
+            id: "".to_owned(),
+            termination: 0,
+            exit: 0,
             time: timestamp(1675757227_275375_000),
         },
         UtmpEntry::UserProcess {
             pid: 1225,
             line: "pts/0".to_owned(),
+            id: "ts/0".to_owned(),
             user: "root".to_owned(),
             host: "112.124.2.209".to_owned(),
+            address: Some(Ipv4Addr::new(112, 124, 2, 209).into()),
             session: 0,
             time: timestamp(1675757312_920719_000),
         },
         UtmpEntry::UserProcess {
             pid: 2454,
             line: "pts/1".to_owned(),
+            id: "".to_owned(),
             user: "root".to_owned(),
             host: "".to_owned(),
+            address: None,
             session: 0,
             time: timestamp(1675758317_098468_000),
         },
         UtmpEntry::UserProcess {
             pid: 2714,
             line: "pts/1".to_owned(),
+            id: "".to_owned(),
             user: "root".to_owned(),
             host: "".to_owned(),
+            address: None,
             session: 0,
             time: timestamp(1675758522_887514_000),
         },
         UtmpEntry::DeadProcess {
             pid: 1189,
             line: "pts/0".to_owned(),
+            id: "".to_owned(),
+            termination: 0,
+            exit: 0,
             time: timestamp(1675759743_147069_000),
         },
         UtmpEntry::UserProcess {
             pid: 4343,
             line: "pts/0".to_owned(),
+            id: "ts/0".to_owned(),
             user: "root".to_owned(),
             host: "112.124.2.209".to_owned(),
+            address: Some(Ipv4Addr::new(112, 124, 2, 209).into()),
             session: 0,
             time: timestamp(1675759955_391532_000),
         },
         UtmpEntry::UserProcess {
             pid: 5022,
             line: "pts/1".to_owned(),
+            id: "".to_owned(),
             user: "root".to_owned(),
             host: "".to_owned(),
+            address: None,
             session: 0,
             time: timestamp(1675760619_783753_000),
         },
         UtmpEntry::DeadProcess {
             pid: 4305,
             line: "pts/0".to_owned(),
+            id: "".to_owned(),
+            termination: 0,
+            exit: 0,
             time: timestamp(1675761785_613258_000),
         },
         UtmpEntry::UserProcess {
             pid: 13369,
             line: "pts/0".to_owned(),
+            id: "ts/0".to_owned(),
             user: "root".to_owned(),
             host: "112.124.2.209".to_owned(),
+            address: Some(Ipv4Addr::new(112, 124, 2, 209).into()),
             session: 0,
             time: timestamp(1675768806_832709_000),
         },
@@ -180,6 +229,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 1872475,
             line: "pts/1".to_owned(),
+            id: "1".to_owned(),
             user: "abc".to_owned(),
             host: "".to_owned(),
             time: timestamp(1675278673_563046_000),
@@ -187,6 +237,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 1874257,
             line: "pts/1".to_owned(),
+            id: "1".to_owned(),
             user: "abc".to_owned(),
             host: "".to_owned(),
             time: timestamp(1675278942_329935_000),
@@ -194,6 +245,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 1875352,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "abc".to_owned(),
             host: "10.11.0.169".to_owned(),
             time: timestamp(1675279200_000000_000),
@@ -201,6 +253,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 1875352,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "abc".to_owned(),
             host: "10.11.0.169".to_owned(),
             time: timestamp(1675279205_000000_000),
@@ -208,6 +261,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 1875352,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "abc".to_owned(),
             host: "10.11.0.169".to_owned(),
             time: timestamp(1675279206_000000_000),
@@ -215,6 +269,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2199784,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423140_000000_000),
@@ -222,6 +277,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2199784,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423143_000000_000),
@@ -229,6 +285,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2199784,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423148_000000_000),
@@ -236,6 +293,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2200630,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423317_000000_000),
@@ -243,6 +301,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2200630,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423321_000000_000),
@@ -250,6 +309,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2200630,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423325_000000_000),
@@ -257,6 +317,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2200630,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675423330_000000_000),
@@ -264,6 +325,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2203029,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675424016_000000_000),
@@ -271,6 +333,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2203029,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675424020_000000_000),
@@ -278,6 +341,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2203029,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675424024_000000_000),
@@ -285,6 +349,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2203029,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675424031_000000_000),
@@ -292,6 +357,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2214635,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675424626_000000_000),
@@ -299,6 +365,7 @@ fn get_long_user_32_expected() -> Vec<UtmpEntry> {
         UtmpEntry::LoginProcess {
             pid: 2214635,
             line: "ssh:notty".to_owned(),
+            id: "".to_owned(),
             user: "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb".to_owned(),
             host: "10.10.4.230".to_owned(),
             time: timestamp(1675424630_000000_000),
@@ -313,7 +380,8 @@ fn get_basic64_expected() -> Vec<UtmpEntry> {
             time: timestamp(1658083371_314869_000),
         },
         UtmpEntry::RunLevel {
-            pid: 53,
+            level: '5',
+            previous: None,
             kernel_version: "5.15.0-41-generic".to_owned(),
             time: timestamp(1658083400_855073_000),
         },
@@ -321,6 +389,7 @@ fn get_basic64_expected() -> Vec<UtmpEntry> {
             pid: 1219,
             time: timestamp(1658083400_866391_000),
             line: "ttyAMA0".to_owned(),
+            id: "AMA0".to_owned(),
             user: "LOGIN".to_owned(),
             host: "".to_owned(),
         },
@@ -387,3 +456,969 @@ impl<R: Read> Read for ByteReader<R> {
         }
     }
 }
+
+#[test]
+fn parse_in_blocks() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let mut reader = CountingReader(File::open(&path)?, 0);
+    let actual = Utmp32Parser::from_reader(&mut reader).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, get_with_host_32_expected());
+    // One read for all the records, and another for the end of file.
+    assert_eq!(reader.1, 2);
+
+    let mut reader = CountingReader(File::open(&path)?, 0);
+    let actual = Utmp32Parser::from_reader(&mut reader)
+        .with_buffer_capacity(384 * 2)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, get_with_host_32_expected());
+    assert_eq!(reader.1, 11);
+
+    let actual = Utmp32Parser::from_path(&path)?
+        .with_buffer_capacity(1)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, get_with_host_32_expected());
+    Ok(())
+}
+
+struct CountingReader<R>(R, usize);
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.1 += 1;
+        self.0.read(buf)
+    }
+}
+
+#[test]
+fn resume_from_offset() -> Result<()> {
+    let path = SAMPLES_PATH.join("basic32.utmp");
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    let head = parser.by_ref().take(2).collect::<Result<Vec<_>, _>>()?;
+    let offset = parser.offset();
+    assert_eq!(offset, 2 * 384);
+    let tail = Utmp32Parser::resume_from_path(&path, offset)?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!([head, tail].concat(), get_basic32_expected());
+    assert!(Utmp32Parser::resume_from_path(&path, offset + 1).is_err());
+    assert!(Utmp32Parser::resume_from_path(&path, 384 * 100).is_err());
+    Ok(())
+}
+
+#[cfg(all(feature = "mmap", unix))]
+#[test]
+fn parse_with_mmap() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let actual = unsafe { Utmp32Parser::from_mmap(&path)? }.collect::<Result<Vec<_>, _>>()?;
+    let expected = get_with_host_32_expected();
+    assert_eq!(actual, expected);
+
+    let path = SAMPLES_PATH.join("empty.utmp");
+    let actual = unsafe { Utmp64Parser::from_mmap(&path)? }.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, vec![]);
+    Ok(())
+}
+
+#[test]
+fn parse_from_bytes() -> Result<()> {
+    let data = fs::read(SAMPLES_PATH.join("basic64.utmp"))?;
+    let actual = Utmp64Parser::from_bytes(&data).collect::<Result<Vec<_>, _>>()?;
+    let expected = get_basic64_expected();
+    assert_eq!(actual, expected);
+
+    // Records don't need to be aligned in memory.
+    let mut unaligned = vec![0];
+    unaligned.extend_from_slice(&data);
+    let actual = Utmp64Parser::from_bytes(&unaligned[1..]).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+
+    let mut parser = Utmp64Parser::from_bytes(&data[..data.len() - 1]);
+    assert_eq!(parser.by_ref().take(2).count(), 2);
+    let err = parser.next().unwrap().unwrap_err();
+    assert!(
+        matches!(err, ParseError::Io { source: e, .. } if e.kind() == io::ErrorKind::UnexpectedEof)
+    );
+    assert!(parser.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn parse_borrowed_from_bytes() -> Result<()> {
+    let data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let expected = get_with_host_32_expected();
+    let mut unaligned = vec![0];
+    unaligned.extend_from_slice(&data);
+    for bytes in [&data[..], &unaligned[1..]] {
+        let mut parser = Utmp32Parser::from_bytes(bytes);
+        let mut actual = Vec::new();
+        while let Some(entry) = parser.next_ref() {
+            actual.push(entry?);
+        }
+        assert_eq!(
+            actual[7],
+            UtmpEntryRef::UserProcess {
+                pid: 1125,
+                line: "pts/0",
+                id: "ts/0",
+                user: "root",
+                host: "112.124.2.209",
+                address: Some(Ipv4Addr::new(112, 124, 2, 209).into()),
+                session: 0,
+                time: timestamp(1675757226_139552_000),
+            }
+        );
+        let actual = actual
+            .iter()
+            .map(UtmpEntryRef::to_owned)
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn parse_with_reused_buffer() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let reader = ByteReader(BufReader::new(File::open(&path)?));
+    let mut parser = Utmp32Parser::from_reader(reader);
+    let mut actual = Vec::new();
+    while let Some(entry) = parser.next_entry()? {
+        actual.push(entry.to_owned());
+    }
+    assert_eq!(actual, get_with_host_32_expected());
+    assert_eq!(parser.offset(), 384 * actual.len() as u64);
+    Ok(())
+}
+
+#[test]
+fn random_access() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let expected = get_with_host_32_expected();
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    assert_eq!(parser.len()?, expected.len() as u64);
+    assert_eq!(parser.get(7).transpose()?, Some(expected[7].clone()));
+    assert_eq!(parser.next().transpose()?, Some(expected[8].clone()));
+    assert_eq!(parser.get(0).transpose()?, Some(expected[0].clone()));
+    assert_eq!(parser.len()?, expected.len() as u64);
+    assert_eq!(parser.next().transpose()?, Some(expected[1].clone()));
+    assert!(parser.get(expected.len() as u64).is_none());
+
+    let mut parser = Utmp64Parser::from_path(SAMPLES_PATH.join("empty.utmp"))?;
+    assert!(parser.is_empty()?);
+    Ok(())
+}
+
+#[test]
+fn parse_in_reverse() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let mut expected = get_with_host_32_expected();
+    expected.reverse();
+    let actual = Utmp32Parser::from_path(&path)?
+        .rev()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+
+    let data = fs::read(&path)?;
+    let actual = Utmp32Parser::from_bytes(&data)
+        .rev()
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+
+    // Parsing from both ends meets in the middle.
+    let expected = get_with_host_32_expected();
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    while let Some(entry) = parser.next() {
+        front.push(entry?);
+        match parser.next_back() {
+            Some(entry) => back.push(entry?),
+            None => break,
+        }
+    }
+    back.reverse();
+    assert_eq!([front, back].concat(), expected);
+
+    // A partial record at the end is reported first.
+    let mut parser = Utmp32Parser::from_bytes(&data[..data.len() - 1]);
+    let err = parser.next_back().unwrap().unwrap_err();
+    assert!(
+        matches!(err, ParseError::Io { source: e, .. } if e.kind() == io::ErrorKind::UnexpectedEof)
+    );
+    assert_eq!(parser.next_back().transpose()?, expected.get(17).cloned());
+    assert_eq!(parser.count(), 17);
+    Ok(())
+}
+
+#[test]
+fn size_hint_from_input_size() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    assert_eq!(parser.size_hint(), (19, Some(19)));
+    parser.next().transpose()?;
+    parser.next_back().transpose()?;
+    assert_eq!(parser.size_hint(), (17, Some(17)));
+
+    let parser = Utmp32Parser::from_reader(File::open(&path)?);
+    assert_eq!(parser.size_hint(), (0, None));
+
+    let data = fs::read(&path)?;
+    let mut parser = Utmp32Parser::from_bytes(&data[..data.len() - 1]);
+    assert_eq!(parser.len(), 19);
+    parser.nth(17);
+    assert_eq!(parser.len(), 1);
+    assert!(parser.next().unwrap().is_err());
+    assert_eq!(parser.len(), 0);
+    Ok(())
+}
+
+#[test]
+fn seek_to_time() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let expected = get_with_host_32_expected();
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    parser.seek_to_time(timestamp(1675757226_404205_000))?;
+    assert_eq!(parser.offset(), 9 * 384);
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, &expected[9..]);
+
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    parser.seek_to_time(timestamp(1675757226_404206_000))?;
+    assert_eq!(parser.next().transpose()?, Some(expected[10].clone()));
+    parser.seek_to_time(timestamp(0))?;
+    assert_eq!(parser.next().transpose()?, Some(expected[0].clone()));
+    parser.seek_to_time(timestamp(2000000000_000000_000))?;
+    assert!(parser.next().is_none());
+
+    // Records failing to decode and a misaligned input don't fail the search.
+    let mut data = fs::read(&path)?;
+    for record in data.chunks_exact_mut(384) {
+        record[76] = 0xff;
+    }
+    data.push(0);
+    let mut parser = Utmp32Parser::from_reader(Cursor::new(data));
+    parser.seek_to_time(timestamp(1675757226_404205_000))?;
+    assert_eq!(parser.offset(), 9 * 384);
+    Ok(())
+}
+
+#[test]
+fn parse_lazily() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let expected = get_with_host_32_expected();
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    let mut actual = Vec::new();
+    while let Some(entry) = parser.next_lazy() {
+        actual.push(entry?);
+    }
+    assert_eq!(actual.len(), expected.len());
+    assert_eq!(actual[7].pid(), 1125);
+    assert_eq!(actual[7].id()?, "ts/0");
+    assert_eq!(actual[7].user()?, "root");
+    assert_eq!(actual[7].host()?, "112.124.2.209");
+    assert_eq!(actual[7].time()?, timestamp(1675757226_139552_000));
+    let actual = actual
+        .iter()
+        .map(|entry| entry.decode())
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+
+    let data = fs::read(&path)?;
+    let mut parser = Utmp32Parser::from_bytes(&data[1..]);
+    assert!(parser.next_lazy().unwrap().is_ok());
+    assert!(parser.nth(16).is_some());
+    assert!(parser.next_lazy().unwrap().is_err());
+    Ok(())
+}
+
+#[test]
+fn order_by_time() {
+    let expected = get_with_host_32_expected();
+    let mut entries = [get_basic32_expected(), expected.clone(), expected.clone()].concat();
+    entries.reverse();
+    entries.sort();
+    entries.dedup();
+    assert_eq!(entries.len(), expected.len() + 5);
+    assert!(entries.windows(2).all(|w| w[0].time() <= w[1].time()));
+    // Entries at the same time are ordered by type, then by pid.
+    assert!(expected[4] < expected[5]);
+    assert!(expected[3] < expected[6]);
+    let init = |pid| UtmpEntry::InitProcess {
+        pid,
+        id: "tty1".to_owned(),
+        time: timestamp(1675756875_305313_000),
+    };
+    assert!(init(643) < expected[4] && expected[4] < init(645));
+    assert!(UtmpEntry::Empty < expected[0]);
+}
+
+#[test]
+fn group_by_kind() {
+    let mut counts = HashMap::new();
+    for entry in get_with_host_32_expected() {
+        *counts.entry(entry.kind()).or_insert(0) += 1;
+    }
+    assert_eq!(counts.get(&UtmpEntryKind::BootTime), Some(&1));
+    assert_eq!(counts.get(&UtmpEntryKind::RunLevel), Some(&1));
+    assert_eq!(counts.get(&UtmpEntryKind::UserProcess), Some(&8));
+    assert_eq!(counts.get(&UtmpEntryKind::Accounting), None);
+    assert_eq!(UtmpEntry::Empty.kind(), UtmpEntryKind::Empty);
+}
+
+#[test]
+fn display_like_who() {
+    let lines: Vec<_> = get_with_host_32_expected()
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect();
+    assert_eq!(lines[1], "system boot           2023-02-07 08:01");
+    assert_eq!(lines[2], "run-level 5           2023-02-07 08:01");
+    assert_eq!(
+        lines[5],
+        "LOGIN    tty1         2023-02-07 08:01        644 id=tty1"
+    );
+    assert_eq!(
+        lines[7],
+        "root     pts/0        2023-02-07 08:07 (112.124.2.209)"
+    );
+    assert_eq!(UtmpEntry::Empty.to_string(), "");
+}
+
+#[test]
+fn parse_exit_status() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    // Set `ut_exit` of the first dead process.
+    let record = &mut data[9 * 384..][..384];
+    record[332..336].copy_from_slice(&[1, 0, 2, 0]);
+    let entry = Utmp32Parser::from_bytes(&data).nth(9).unwrap()?;
+    assert_eq!(
+        entry,
+        UtmpEntry::DeadProcess {
+            pid: 1020,
+            line: "pts/0".to_owned(),
+            id: "".to_owned(),
+            termination: 1,
+            exit: 2,
+            time: timestamp(1675757226_404205_000),
+        }
+    );
+    Ok(())
+}
+
+#[test]
+fn parse_ipv6_address() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    // Set `ut_addr_v6` of the first user process to 2001:db8::1.
+    let record = &mut data[7 * 384..][..384];
+    let address = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+    record[348..364].copy_from_slice(&address.octets());
+    match Utmp32Parser::from_bytes(&data).nth(7).unwrap()? {
+        UtmpEntry::UserProcess { address: a, .. } => assert_eq!(a, Some(address.into())),
+        entry => panic!("unexpected entry {:?}", entry),
+    }
+    Ok(())
+}
+
+#[test]
+fn parse_lossily() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    // Replace the user of the first user process with invalid UTF-8.
+    let record = &mut data[7 * 384..][..384];
+    record[44..48].copy_from_slice(b"ro\xfft");
+    let result = Utmp32Parser::from_bytes(&data).nth(7).unwrap();
+    assert!(matches!(
+        result,
+        Err(ParseError::Utmp {
+            source: UtmpError::InvalidUser(_),
+            ..
+        })
+    ));
+
+    let mut expected = get_with_host_32_expected();
+    if let UtmpEntry::UserProcess { user, .. } = &mut expected[7] {
+        *user = "ro\u{fffd}t".to_owned();
+    }
+    let actual = Utmp32Parser::from_bytes(&data)
+        .lossy(true)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+    let actual = Utmp32Parser::from_reader(&data[..])
+        .lossy(true)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+    Ok(())
+}
+
+#[test]
+fn parse_leniently() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    // Give the first user process invalid UTF-8 in its user and negative microseconds.
+    let record = &mut data[7 * 384..][..384];
+    record[44..48].copy_from_slice(b"ro\xfft");
+    record[344..348].copy_from_slice(&(-1i32).to_ne_bytes());
+    let result = Utmp32Parser::from_bytes(&data).lossy(true).nth(7).unwrap();
+    assert!(matches!(
+        result,
+        Err(ParseError::Utmp {
+            source: UtmpError::InvalidTime(_),
+            ..
+        })
+    ));
+
+    let mut expected = get_with_host_32_expected();
+    if let UtmpEntry::UserProcess { user, time, .. } = &mut expected[7] {
+        *user = "ro\u{fffd}t".to_owned();
+        *time = timestamp(1675757226_000000_000);
+    }
+    let actual = Utmp32Parser::from_bytes(&data)
+        .lenient(true)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+    let actual = Utmp32Parser::builder()
+        .lenient(true)
+        .from_reader(&data[..])?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+    Ok(())
+}
+
+#[test]
+fn raw_bytes_of_fields() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let record = &mut data[7 * 384..][..384];
+    record[44..48].copy_from_slice(b"ro\xfft");
+    let mut parser = Utmp32Parser::from_bytes(&data);
+    parser.nth(6);
+    let entry = parser.next_lazy().unwrap()?;
+    assert!(entry.user().is_err());
+    assert_eq!(entry.user_bytes(), b"ro\xfft");
+    assert_eq!(entry.line_bytes(), b"pts/0");
+    assert_eq!(entry.id_bytes(), b"ts/0");
+    assert_eq!(entry.host_bytes(), b"112.124.2.209");
+    Ok(())
+}
+
+#[test]
+fn parse_unknown_type() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    data[7 * 384..][..2].copy_from_slice(&42i16.to_ne_bytes());
+    let mut expected = get_with_host_32_expected();
+    let entry = Utmp32Parser::from_bytes(&data).nth(7).unwrap()?;
+    assert!(matches!(
+        entry,
+        UtmpEntry::Unknown {
+            ut_type: 42,
+            pid: 1125,
+            ..
+        }
+    ));
+    let record = &data[7 * 384..][..384];
+    match &entry {
+        UtmpEntry::Unknown { raw, .. } => assert_eq!(&**raw, record),
+        entry => panic!("unexpected entry {:?}", entry),
+    }
+    expected[7] = entry;
+    let actual = Utmp32Parser::from_bytes(&data).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, expected);
+    let mut parser = Utmp32Parser::from_reader(&data[7 * 384..]);
+    match parser.next_entry()? {
+        Some(UtmpEntryRef::Unknown { raw, .. }) => assert_eq!(raw, record),
+        entry => panic!("unexpected entry {:?}", entry),
+    }
+    Ok(())
+}
+
+#[test]
+fn skip_empty_records() -> Result<()> {
+    let data = fs::read(SAMPLES_PATH.join("basic32.utmp"))?;
+    let expected = get_basic32_expected();
+    let mut padded = vec![0; 384];
+    for record in data.chunks(384) {
+        padded.extend_from_slice(record);
+        padded.extend_from_slice(&[0; 384]);
+    }
+    let parser = Utmp32Parser::from_bytes(&padded);
+    assert_eq!(parser.len(), expected.len() * 2 + 1);
+    let parser = parser.skip_empty(true);
+    assert_eq!(parser.len(), expected.len());
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, expected);
+    let actual = Utmp32Parser::from_bytes(&padded).skip_empty(true).rev();
+    let mut reversed = expected.clone();
+    reversed.reverse();
+    assert_eq!(actual.collect::<Result<Vec<_>, _>>()?, reversed);
+
+    let parser = Utmp32Parser::from_reader(io::Cursor::new(&padded)).skip_empty(true);
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, expected);
+    let actual = Utmp32Parser::from_reader(io::Cursor::new(&padded)).skip_empty(true);
+    assert_eq!(actual.rev().collect::<Result<Vec<_>, _>>()?, reversed);
+    Ok(())
+}
+
+#[test]
+fn position_of_invalid_record() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    data[9 * 384 + 8] = 0xff;
+    let check = |result: Option<Result<UtmpEntry, ParseError>>| match result {
+        Some(Err(ParseError::Utmp { index, offset, .. })) => {
+            assert_eq!((index, offset), (9, 9 * 384))
+        }
+        result => panic!("unexpected result {:?}", result),
+    };
+    check(Utmp32Parser::from_bytes(&data).nth(9));
+    check(Utmp32Parser::from_bytes(&data).nth_back(9));
+    check(Utmp32Parser::from_reader(&data[..]).nth(9));
+    check(Utmp32Parser::from_reader(io::Cursor::new(&data)).nth_back(9));
+    let mut parser = Utmp32Parser::from_bytes(&data);
+    parser.nth(8);
+    assert!(matches!(
+        parser.next_ref(),
+        Some(Err(ParseError::Utmp { index: 9, .. }))
+    ));
+    Ok(())
+}
+
+#[test]
+fn parse_raw_entries() -> Result<()> {
+    let path = SAMPLES_PATH.join("basic64.utmp");
+    let raw = Utmp64Parser::from_path(&path)?
+        .raw_entries()
+        .collect::<Result<Vec<_>, _>>()?;
+    let actual = raw
+        .iter()
+        .map(UtmpEntry::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, get_basic64_expected());
+    assert_eq!(raw[2].ut_type, utmp_raw::LOGIN_PROCESS);
+
+    let data = fs::read(&path)?;
+    let raw_from_bytes = Utmp64Parser::from_bytes(&data).raw_entries();
+    assert_eq!(raw_from_bytes.count(), raw.len());
+    Ok(())
+}
+
+#[test]
+fn time_as_system_time() {
+    let expected = get_with_host_32_expected();
+    let time = expected[7].time_as_system_time().unwrap();
+    let since_epoch = time.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+    assert_eq!(since_epoch, Duration::new(1675757226, 139552_000));
+    assert_eq!(UtmpEntry::Empty.time_as_system_time(), None);
+}
+
+#[test]
+fn build_parser_with_format() -> Result<()> {
+    for (name, format, expected) in [
+        ("basic32.utmp", Format::X32, get_basic32_expected()),
+        ("basic64.utmp", Format::X64, get_basic64_expected()),
+    ] {
+        let path = SAMPLES_PATH.join(name);
+        let parser = UtmpParser::builder()
+            .format(Format::Auto)
+            .from_path(&path)?;
+        assert_eq!(parser.format(), format);
+        assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, expected);
+
+        // Without the size of the input, the format is detected from the content.
+        let data = fs::read(&path)?;
+        let parser = UtmpParser::builder()
+            .format(Format::Auto)
+            .buffer_capacity(1)
+            .from_reader(&data[..])?;
+        assert_eq!(parser.format(), format);
+        assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, expected);
+    }
+    let parser = Utmp64Parser::builder().from_bytes(&[]);
+    assert_eq!(parser.format(), Format::X64);
+    Ok(())
+}
+
+#[test]
+fn parse_other_endian() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    // Numeric fields of 32-bit records: type, pid, exit status, session and time.
+    let fields = [
+        (0, 2),
+        (4, 4),
+        (332, 2),
+        (334, 2),
+        (336, 4),
+        (340, 4),
+        (344, 4),
+    ];
+    for record in data.chunks_exact_mut(384) {
+        for &(offset, len) in &fields {
+            record[offset..][..len].reverse();
+        }
+    }
+    let endianness = if cfg!(target_endian = "little") {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    };
+    let builder = UtmpParser::builder()
+        .format(Format::Auto)
+        .endianness(endianness);
+    let parser = builder.clone().from_reader(&data[..])?;
+    assert_eq!(parser.format(), Format::X32);
+    let expected = get_with_host_32_expected();
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, expected);
+    let parser = builder.from_bytes(&data);
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, expected);
+    Ok(())
+}
+
+#[test]
+fn error_policy() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    data[9 * 384 + 8] = 0xff;
+    let total = data.len() / 384;
+    let parse = |policy| {
+        let parser = Utmp32Parser::builder().on_error(policy);
+        parser.from_bytes(&data).collect::<Vec<_>>()
+    };
+    let entries = parse(ErrorPolicy::Continue);
+    assert_eq!(entries.len(), total);
+    assert!(entries[9].is_err());
+    let entries = parse(ErrorPolicy::Stop);
+    assert_eq!(entries.len(), 10);
+    assert!(entries[9].is_err());
+    let entries = parse(ErrorPolicy::Skip);
+    assert_eq!(entries.len(), total - 1);
+    assert!(entries.iter().all(Result::is_ok));
+    Ok(())
+}
+
+#[test]
+fn resync_after_corruption() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let expected = get_with_host_32_expected();
+    // Corrupt a record, and insert some garbage after another one.
+    data[5 * 384 + 8] = 0xff;
+    data.splice(8 * 384..8 * 384, vec![0x55; 100]);
+    let check = |entries: Vec<Result<UtmpEntry, ParseError>>| {
+        let mut entries = entries.into_iter();
+        for (expected, entry) in expected[..5].iter().zip(entries.by_ref()) {
+            assert_eq!(&entry.unwrap(), expected);
+        }
+        match entries.next() {
+            Some(Err(ParseError::Corrupted { start, end })) => {
+                assert_eq!((start, end), (5 * 384, 6 * 384))
+            }
+            entry => panic!("unexpected entry {:?}", entry),
+        }
+        for (expected, entry) in expected[6..8].iter().zip(entries.by_ref()) {
+            assert_eq!(&entry.unwrap(), expected);
+        }
+        match entries.next() {
+            Some(Err(ParseError::Corrupted { start, end })) => {
+                assert_eq!((start, end), (8 * 384, 8 * 384 + 100))
+            }
+            entry => panic!("unexpected entry {:?}", entry),
+        }
+        let rest = entries.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(rest, &expected[8..]);
+    };
+    let builder = Utmp32Parser::builder().on_error(ErrorPolicy::Resync);
+    check(builder.clone().from_bytes(&data).collect());
+    check(
+        builder
+            .buffer_capacity(500)
+            .from_reader(&data[..])?
+            .collect(),
+    );
+    Ok(())
+}
+
+#[test]
+fn misaligned_file() -> Result<()> {
+    let path = SAMPLES_PATH.join("basic64.utmp");
+    let mut parser = Utmp32Parser::from_path(&path)?;
+    assert_eq!(parser.size_hint(), (4, Some(4)));
+    let err = parser.next().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::Misaligned {
+            file_size: 1200,
+            record_size: 384,
+            trailing_bytes: 48,
+        }
+    ));
+    // Whole records are still parsed, without an error for the partial one.
+    assert_eq!(parser.count(), 3);
+
+    let mut parser = Utmp64Parser::from_path(SAMPLES_PATH.join("basic32.utmp"))?;
+    let err = parser.next_back().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::Misaligned {
+            trailing_bytes: 320,
+            ..
+        }
+    ));
+    assert_eq!(parser.rev().count(), 4);
+
+    // Nothing is parsed when stopping at the first error.
+    let builder = Utmp32Parser::builder().format(Format::X32);
+    let mut parser = builder.on_error(ErrorPolicy::Stop).from_path(&path)?;
+    let err = parser.next().unwrap().unwrap_err();
+    assert!(matches!(err, ParseError::Misaligned { .. }));
+    assert!(parser.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn parse_run_levels() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("basic32.utmp"))?;
+    // Change from run-level 5 to 3.
+    let pid = i32::from(b'3') | i32::from(b'5') << 8;
+    data[384 + 4..][..4].copy_from_slice(&pid.to_ne_bytes());
+    let entry = Utmp32Parser::from_bytes(&data).nth(1).unwrap()?;
+    assert!(matches!(
+        entry,
+        UtmpEntry::RunLevel {
+            level: '3',
+            previous: Some('5'),
+            ..
+        }
+    ));
+    assert_eq!(
+        entry.to_string(),
+        "run-level 3           2020-02-08 22:04                  last=5"
+    );
+    // Run-levels are compared as well.
+    let from_5_to_3 = entry.clone();
+    let mut from_5_to_2 = entry.clone();
+    let mut from_none_to_3 = entry.clone();
+    if let UtmpEntry::RunLevel { level, .. } = &mut from_5_to_2 {
+        *level = '2';
+    }
+    if let UtmpEntry::RunLevel { previous, .. } = &mut from_none_to_3 {
+        *previous = None;
+    }
+    assert!(from_5_to_2 < from_5_to_3);
+    assert!(from_none_to_3 < from_5_to_3);
+    let mut entries = vec![
+        from_5_to_3.clone(),
+        from_5_to_2,
+        from_5_to_3,
+        from_none_to_3,
+    ];
+    entries.sort();
+    entries.dedup();
+    assert_eq!(entries.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn entries_between() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let mut data = fs::read(&path)?;
+    let (from, to) = (
+        timestamp(1_675_757_226_284_647_000),
+        timestamp(1_675_760_000_000_000_000),
+    );
+    let expected = get_with_host_32_expected()
+        .into_iter()
+        .filter(|entry| entry.time().is_some_and(|time| from <= time && time < to))
+        .collect::<Vec<_>>();
+    assert_eq!(expected.len(), 8);
+    let actual = Utmp32Parser::from_bytes(&data).entries_between(from, to);
+    assert_eq!(actual.collect::<Result<Vec<_>, _>>()?, expected);
+    let actual = Utmp32Parser::from_reader(&*data).entries_between(from, to);
+    assert_eq!(actual.collect::<Result<Vec<_>, _>>()?, expected);
+
+    // Strings of records outside the period are not decoded.
+    data[384 + 76] = 0xff;
+    assert!(Utmp32Parser::from_bytes(&data).nth(1).unwrap().is_err());
+    let actual = Utmp32Parser::from_bytes(&data).entries_between(from, to);
+    assert_eq!(actual.collect::<Result<Vec<_>, _>>()?, expected);
+
+    // Records in order of time are searched for.
+    let path = SAMPLES_PATH.join("basic32.utmp");
+    let expected = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let (from, to) = (expected[1].time().unwrap(), expected[3].time().unwrap());
+    let actual = Utmp32Parser::from_path(&path)?.entries_between_in_order(from, to)?;
+    assert_eq!(actual.collect::<Result<Vec<_>, _>>()?, expected[1..3]);
+    Ok(())
+}
+
+#[test]
+fn check_integrity() -> Result<()> {
+    let mut data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let checker = IntegrityChecker::new().format(Format::X32);
+    let kinds = |checker: &IntegrityChecker, data: &[u8]| {
+        let anomalies = checker.check_bytes(data).into_iter();
+        anomalies.map(|a| (a.index, a.kind)).collect::<Vec<_>>()
+    };
+    let anomalies = kinds(&checker, &data);
+    assert_eq!(anomalies.len(), 2);
+    assert!(matches!(anomalies[0], (1, AnomalyKind::TimeGap { .. })));
+    assert!(
+        matches!(anomalies[1], (6, AnomalyKind::TimeBackwards { previous, time }) if time < previous)
+    );
+    let checker = checker.max_gap(Duration::from_secs(60 * 24 * 60 * 60));
+    assert_eq!(kinds(&checker, &data).len(), 1);
+
+    // Wipe a record, fill the padding of another, and leave zeros at the end.
+    data[10 * 384..11 * 384].fill(0);
+    data[12 * 384 + 370] = 1;
+    data.extend_from_slice(&[0; 384]);
+    let anomalies = kinds(&checker, &data);
+    assert_eq!(anomalies.len(), 3);
+    assert_eq!(anomalies[1], (10, AnomalyKind::ZeroedRecord));
+    assert_eq!(anomalies[2], (12, AnomalyKind::NonZeroPadding));
+    let detected = IntegrityChecker::new().format(Format::Auto);
+    assert_eq!(
+        detected
+            .max_gap(Duration::from_secs(60 * 24 * 60 * 60))
+            .check_bytes(&data)
+            .len(),
+        3
+    );
+
+    // Forge the address of a login to be another one than its host.
+    data[7 * 384 + 348] = 113;
+    let anomalies = kinds(&checker, &data);
+    assert_eq!(anomalies.len(), 4);
+    assert_eq!(
+        anomalies[1],
+        (
+            7,
+            AnomalyKind::HostAddressMismatch {
+                host: "112.124.2.209".to_owned(),
+                address: [113, 124, 2, 209].into(),
+            }
+        )
+    );
+    Ok(())
+}
+
+#[test]
+fn anonymize_records() -> Result<()> {
+    let data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let entries = Utmp32Parser::from_bytes(&data).collect::<Result<Vec<_>, _>>()?;
+    let anonymizer = Anonymizer::new().format(Format::X32).salt("salt");
+    let anonymized = anonymizer.anonymize_bytes(&data);
+    assert_eq!(anonymized.len(), data.len());
+    let result = Utmp32Parser::from_bytes(&anonymized).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(result.len(), entries.len());
+    for (anonymized, entry) in result.iter().zip(&entries) {
+        assert_eq!(anonymized, &anonymizer.anonymize_entry(entry));
+        assert_eq!(anonymized.time(), entry.time());
+        assert_eq!(anonymized.kind(), entry.kind());
+    }
+    // Markers in the user field of other records are kept.
+    assert_eq!(result[..7], entries[..7]);
+    let pseudonym = match &result[7] {
+        UtmpEntry::UserProcess {
+            user,
+            host,
+            address,
+            ..
+        } => {
+            assert!(user.starts_with("user-") && user.len() == 13);
+            assert!(host.starts_with("10."));
+            assert_eq!(address.map(|a| a.to_string()).as_ref(), Some(host));
+            user.clone()
+        }
+        entry => panic!("unexpected entry {:?}", entry),
+    };
+    // Pseudonyms are stable across records and depend on the salt.
+    assert!(matches!(&result[8], UtmpEntry::UserProcess { user, .. } if *user == pseudonym));
+    let other = Anonymizer::new().format(Format::X32).anonymize_bytes(&data);
+    assert_ne!(other, anonymized);
+
+    let anonymizer = Anonymizer::new()
+        .users(Redaction::Map(Box::new(|user| user.to_uppercase())))
+        .hosts(Redaction::Drop);
+    let entry = anonymizer.anonymize_entry(&entries[7]);
+    assert!(matches!(
+        entry,
+        UtmpEntry::UserProcess { user, host, address: None, .. } if user == "ROOT" && host.is_empty()
+    ));
+    Ok(())
+}
+
+#[test]
+fn convert_between_layouts() -> Result<()> {
+    let data = fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let entries = Utmp32Parser::from_bytes(&data).collect::<Result<Vec<_>, _>>()?;
+    let converted = Converter::new(Format::X64).convert_bytes(&data)?;
+    assert_eq!(converted.len(), data.len() / 384 * 400);
+    let result = Utmp64Parser::from_bytes(&converted).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(result, entries);
+    let back = Converter::new(Format::X32).convert_bytes(&converted)?;
+    assert_eq!(back, data);
+
+    let big = Converter::new(Format::X64)
+        .format(Format::X32)
+        .target_endianness(Endianness::Big)
+        .convert_bytes(&data)?;
+    let result = UtmpParser::builder()
+        .format(Format::X64)
+        .endianness(Endianness::Big)
+        .from_bytes(&big)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(result, entries);
+    let back = Converter::new(Format::X32)
+        .endianness(Endianness::Big)
+        .convert_bytes(&big)?;
+    assert_eq!(back, data);
+
+    let dir = std::env::temp_dir().join(format!("utmp-rs-convert-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    utmp_rs::convert(
+        SAMPLES_PATH.join("with_host_32.utmp"),
+        dir.join("wtmp"),
+        Format::X64,
+    )?;
+    assert_eq!(fs::read(dir.join("wtmp"))?, converted);
+    fs::remove_dir_all(&dir)?;
+
+    assert!(matches!(
+        Converter::new(Format::X64).convert_bytes(&data[..500]),
+        Err(ConvertError::Misaligned {
+            size: 500,
+            record_size: 384
+        })
+    ));
+    Ok(())
+}
+
+#[test]
+fn parse_timestamps() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let entry = &entries[7];
+    let time = Timestamp {
+        secs: 1_675_757_226,
+        micros: 139_552,
+    };
+    assert_eq!(entry.time(), Some(time));
+    assert_eq!(
+        entry.time_as_system_time(),
+        Some(SystemTime::UNIX_EPOCH + Duration::from_micros(1_675_757_226_139_552)),
+    );
+    assert_eq!(
+        entry.to_string(),
+        "root     pts/0        2023-02-07 08:07 (112.124.2.209)",
+    );
+    assert_eq!(time.to_string(), "2023-02-07T08:07:06.139552Z");
+    #[cfg(feature = "time")]
+    {
+        use time::OffsetDateTime;
+        let datetime = OffsetDateTime::try_from(time)?;
+        assert_eq!(datetime.unix_timestamp_nanos(), 1_675_757_226_139_552_000);
+        assert_eq!(Timestamp::from(datetime), time);
+        let later = time + time::Duration::milliseconds(1500);
+        assert_eq!(later - time, time::Duration::milliseconds(1500));
+        assert_eq!(time - later, time::Duration::milliseconds(-1500));
+        let far = Timestamp::from_secs(i64::MAX);
+        assert!(OffsetDateTime::try_from(far).is_err());
+        assert_eq!(
+            far + time::Duration::DAY,
+            Timestamp {
+                secs: i64::MAX,
+                micros: 999_999
+            }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "cli")]
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::fs;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn utmp(args: &[&str]) -> Result<Output> {
+    Ok(Command::new(env!("CARGO_BIN_EXE_utmp"))
+        .args(args)
+        .output()?)
+}
+
+fn sample(name: &str) -> String {
+    SAMPLES_PATH.join(name).to_string_lossy().into_owned()
+}
+
+fn stdout(output: &Output) -> &str {
+    assert!(output.status.success(), "{:?}", output);
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+// The sample is in the native format of x86_64.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn who_and_last() -> Result<()> {
+    let path = sample("with_host_32.utmp");
+    let output = utmp(&["who", &path])?;
+    assert_eq!(
+        stdout(&output),
+        "root     pts/1        2023-02-07 09:03\n\
+         root     pts/0        2023-02-07 11:20 (112.124.2.209)\n",
+    );
+    let output = utmp(&["last", "-f", &path])?;
+    let lines = stdout(&output).lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[2],
+        "root     pts/0        112.124.2.209    Tue Feb  7 08:52 - 09:23  (00:30)",
+    );
+    assert_eq!(
+        lines.last(),
+        Some(&"with_host_32.utmp begins Wed Dec 28 10:33:17 2022"),
+    );
+    let output = utmp(&["stats", "--file", &path, "--utmp", &path])?;
+    assert_eq!(
+        stdout(&output),
+        "users          1\nlogins         8\nboots          1\nfailed logins  0\n",
+    );
+    Ok(())
+}
+
+// The sample is in the native format of x86_64.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn who_flags() -> Result<()> {
+    let path = sample("with_host_32.utmp");
+    let output = utmp(&["who", "-b", &path])?;
+    assert_eq!(stdout(&output), "system boot           2023-02-07 08:01\n");
+    let output = utmp(&["who", "-r", "--boot", &path])?;
+    assert_eq!(
+        stdout(&output),
+        "system boot           2023-02-07 08:01\n\
+         run-level 5           2023-02-07 08:01\n",
+    );
+    // Idle times depend on ttys of the system running the test.
+    let output = utmp(&["who", "-u", "--ips", &path])?;
+    let lines = stdout(&output).lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with("root     pts/0        2023-02-07 11:20 "));
+    assert!(lines[1].ends_with(" 13369 (112.124.2.209)"));
+    let output = utmp(&["who", "-b", "-u", &path])?;
+    assert_eq!(stdout(&output).lines().count(), 3);
+    Ok(())
+}
+
+// The sample is in the native format of x86_64.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn last_flags() -> Result<()> {
+    let path = sample("with_host_32.utmp");
+    let last = |args: &[&str]| -> Result<Vec<String>> {
+        let output = utmp(&[&["last", "-f", &path], args].concat())?;
+        let lines = stdout(&output).lines().map(str::to_owned);
+        // Leave out the footer.
+        Ok(lines.take_while(|line| !line.is_empty()).collect())
+    };
+    assert_eq!(last(&[])?.len(), 9);
+    assert_eq!(last(&["-n", "2"])?.len(), 2);
+    let lines = last(&["-x", "-F"])?;
+    assert_eq!(lines.len(), 11);
+    assert_eq!(
+        lines[8],
+        "runlevel (to lvl 5)   5.4.0-135-generic Tue Feb  7 08:01:14 2023"
+    );
+    assert!(lines[10]
+        .starts_with("shutdown system down  5.4.0-135-generic Wed Dec 28 10:33:17 2022 - "));
+    let lines = last(&[
+        "--since",
+        "2023-02-07 08:20",
+        "--until=2023-02-07 10:00",
+        "pts/1",
+    ])?;
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| line.starts_with("root     pts/1 ")));
+    assert_eq!(last(&["reboot"])?.len(), 1);
+    let output = utmp(&["last", "-s", "yesterday-ish"])?;
+    assert_eq!(output.status.code(), Some(2));
+    Ok(())
+}
+
+// The sample is in the native format of x86_64.
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+#[test]
+fn follow_since() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let dir = std::env::temp_dir().join(format!("utmp-rs-cli-follow-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("wtmp");
+    fs::copy(SAMPLES_PATH.join("with_host_32.utmp"), &path)?;
+    let path = path.to_string_lossy().into_owned();
+    let follow = |args: &[&str], count| -> Result<Vec<String>> {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_utmp"))
+            .args([&["follow"], args, &[&path]].concat())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let lines = stdout.lines().take(count).collect::<Result<Vec<_>, _>>();
+        child.kill()?;
+        child.wait()?;
+        Ok(lines?)
+    };
+    let lines = follow(&["--since", "2023-02-07"], 2)?;
+    assert_eq!(
+        lines,
+        [
+            "2023-02-07 08:01:00 boot 5.4.0-135-generic",
+            "2023-02-07 08:07:06 login root pts/0 112.124.2.209",
+        ]
+    );
+    let lines = follow(&["--json", "-s", "2023-02-07 11:00"], 1)?;
+    assert_eq!(
+        lines,
+        [concat!(
+            r#"{"type":"UserLoggedIn","user":"root","line":"pts/0","host":"112.124.2.209","#,
+            r#""time":"2023-02-07T11:20:06.832709Z"}"#,
+        )]
+    );
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn dump_and_convert() -> Result<()> {
+    let path = sample("with_host_32.utmp");
+    let output = utmp(&["dump", &path])?;
+    let dump = stdout(&output).to_owned();
+    assert!(dump.starts_with("[1] [00000] [~~  ] [shutdown] "));
+
+    let dir = std::env::temp_dir().join(format!("utmp-rs-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let converted = dir.join("wtmp").to_string_lossy().into_owned();
+    stdout(&utmp(&["convert", "--to", "64", &path, &converted])?);
+    let records = fs::metadata(&path)?.len() / 384;
+    assert_eq!(fs::metadata(&converted)?.len(), records * 400);
+    let output = utmp(&["dump", &converted])?;
+    assert_eq!(stdout(&output), dump);
+
+    // Layouts and byte orders round trip through each other.
+    let file = |name: &str| dir.join(name).to_string_lossy().into_owned();
+    let (big, back) = (file("wtmp.be"), file("wtmp.back"));
+    let convert = |from: &str, to: &str, input: &str, output: &str| -> Result<()> {
+        stdout(&utmp(&[
+            "convert", "--from", from, "--to", to, input, output,
+        ])?);
+        Ok(())
+    };
+    convert("64", "linux32-be", &converted, &big)?;
+    convert("linux32-be", "32", &big, &back)?;
+    assert_eq!(fs::read(&back)?, fs::read(&path)?);
+    // Text of utmpdump is detected, and rebuilds the fields it has.
+    let text = file("wtmp.txt");
+    convert("auto", "utmpdump", &path, &text)?;
+    assert_eq!(fs::read_to_string(&text)?, dump);
+    convert("auto", "linux64-le", &text, &back)?;
+    assert_eq!(fs::metadata(&back)?.len(), records * 400);
+    assert_eq!(stdout(&utmp(&["dump", &back])?), dump);
+    let output = utmp(&["convert", "--to", "linux16", &path, &back])?;
+    assert_eq!(output.status.code(), Some(2));
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn usage_errors() -> Result<()> {
+    let output = utmp(&["bogus"])?;
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.starts_with("utmp: unknown command bogus\n"));
+    assert!(stderr.contains("Usage: utmp <command>"));
+
+    let output = utmp(&["last", "--bogus"])?;
+    assert_eq!(output.status.code(), Some(2));
+    let output = utmp(&["last", "-f", "/nonexistent/wtmp"])?;
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr)?;
+    assert!(stderr.starts_with("utmp: /nonexistent/wtmp: "));
+    Ok(())
+}
@@ -0,0 +1,192 @@
+#![cfg(feature = "time")]
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use time::{Duration, OffsetDateTime};
+use utmp_rs::{interpret_events, Event, Timestamp, Utmp32Parser};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn timestamp(nanos: i128) -> Timestamp {
+    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .unwrap()
+        .into()
+}
+
+#[test]
+fn interpret_with_host_32() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let mut events = Vec::new();
+    interpret_events(&entries, &mut |event| events.push(event));
+
+    assert_eq!(events.len(), 16);
+    assert_eq!(
+        events[..5],
+        [
+            Event::SystemShutdown {
+                kernel_version: "5.4.0-135-generic".to_owned(),
+                time: timestamp(1_672_223_597_077_918_000),
+            },
+            Event::SystemBooted {
+                kernel_version: "5.4.0-135-generic".to_owned(),
+                time: timestamp(1_675_756_860_150_698_000),
+            },
+            Event::UserLoggedIn {
+                user: "root".to_owned(),
+                line: "pts/0".to_owned(),
+                host: "112.124.2.209".to_owned(),
+                time: timestamp(1_675_757_226_139_552_000),
+            },
+            Event::UserLoggedIn {
+                user: "root".to_owned(),
+                line: "pts/1".to_owned(),
+                host: "112.124.2.209".to_owned(),
+                time: timestamp(1_675_757_226_284_647_000),
+            },
+            Event::UserLoggedOut {
+                user: "root".to_owned(),
+                line: "pts/0".to_owned(),
+                host: "112.124.2.209".to_owned(),
+                time: timestamp(1_675_757_226_404_205_000),
+                duration: Duration::microseconds(264_653),
+            },
+        ]
+    );
+    let logouts = events
+        .iter()
+        .filter(|event| matches!(event, Event::UserLoggedOut { .. }))
+        .count();
+    assert_eq!(logouts, 6);
+    // Logins on a line logged in again are logged out by the next login.
+    let next_login = events.iter().position(|event| {
+        matches!(event, Event::UserLoggedOut { line, duration, .. }
+            if line == "pts/1" && *duration > Duration::minutes(1))
+    });
+    let next_login = next_login.unwrap();
+    assert!(matches!(
+        (&events[next_login], &events[next_login + 1]),
+        (
+            Event::UserLoggedOut { time: logout, .. },
+            Event::UserLoggedIn { time: login, .. },
+        ) if logout == login
+    ));
+    Ok(())
+}
+
+#[test]
+fn interpret_logins_without_line_and_shutdown() {
+    use utmp_rs::UtmpEntry;
+
+    let login = |user: &str, id: &str, secs| UtmpEntry::UserProcess {
+        pid: 1000,
+        line: "".to_owned(),
+        id: id.to_owned(),
+        user: user.to_owned(),
+        host: "example.com".to_owned(),
+        address: None,
+        session: 0,
+        time: Timestamp::from_secs(secs),
+    };
+    let entries = vec![
+        login("alice", "a", 10),
+        login("bob", "b", 20),
+        UtmpEntry::DeadProcess {
+            pid: 1000,
+            line: "".to_owned(),
+            id: "a".to_owned(),
+            termination: 0,
+            exit: 0,
+            time: Timestamp::from_secs(30),
+        },
+        login("carol", "c", 40),
+        UtmpEntry::ShutdownTime {
+            kernel_version: "5.4.0".to_owned(),
+            time: Timestamp::from_secs(100),
+        },
+        UtmpEntry::BootTime {
+            kernel_version: "5.4.0".to_owned(),
+            time: Timestamp::from_secs(200),
+        },
+    ];
+    let mut events = Vec::new();
+    interpret_events(&entries, &mut |event| events.push(event));
+
+    let logouts = (events.iter())
+        .filter_map(|event| match event {
+            Event::UserLoggedOut {
+                user,
+                time,
+                duration,
+                ..
+            } => Some((user.as_str(), time.secs, duration.whole_seconds())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // Logins without a line are told apart by ID, and open ones are logged out at shutdown.
+    assert_eq!(
+        logouts,
+        [("alice", 30, 20), ("bob", 100, 80), ("carol", 100, 60)]
+    );
+    assert_eq!(events.len(), 8);
+    assert!(matches!(events[6], Event::SystemShutdown { .. }));
+    assert!(matches!(events[7], Event::SystemBooted { .. }));
+}
+
+#[cfg(feature = "syslog")]
+#[test]
+fn forward_events_to_syslog() -> Result<()> {
+    use std::io::Read;
+    use std::net::{TcpListener, UdpSocket};
+    use utmp_rs::SyslogSink;
+
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let pid = std::process::id();
+
+    let server = UdpSocket::bind("127.0.0.1:0")?;
+    let mut sink = SyslogSink::udp(server.local_addr()?)?.hostname("host1");
+    interpret_events(&entries, &mut sink);
+    assert!(sink.take_error().is_none());
+    let mut buffer = [0; 2048];
+    let len = server.recv(&mut buffer)?;
+    assert_eq!(
+        std::str::from_utf8(&buffer[..len])?,
+        format!(
+            "<86>1 2022-12-28T10:33:17.077918Z host1 utmp-rs {} shutdown \
+             [utmp@32473 kernel_version=\"5.4.0-135-generic\"] \
+             system shut down with kernel 5.4.0-135-generic",
+            pid
+        )
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let mut sink = SyslogSink::tcp(listener.local_addr()?)?
+        .hostname("host1")
+        .app_name("wtmp")
+        .facility(4)
+        .severity(5)
+        .sd_id("login@64 \"x]=");
+    let (mut stream, _) = listener.accept()?;
+    sink.send(&Event::UserLoggedOut {
+        user: "root".to_owned(),
+        line: "pts/0".to_owned(),
+        host: "a\"b]".to_owned(),
+        time: timestamp(1_675_757_226_404_205_000),
+        duration: Duration::seconds(90),
+    })?;
+    drop(sink);
+    let mut received = String::new();
+    stream.read_to_string(&mut received)?;
+    let message = format!(
+        "<37>1 2023-02-07T08:07:06.404205Z host1 wtmp {} logout \
+         [login@64x user=\"root\" line=\"pts/0\" host=\"a\\\"b\\]\" duration=\"90\"] \
+         root logged out of pts/0 from a\"b]",
+        pid
+    );
+    assert_eq!(received, format!("{} {}", message.len(), message));
+    Ok(())
+}
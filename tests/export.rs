@@ -0,0 +1,410 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use utmp_rs::{
+    CsvWriter, EntrySink, FanOut, Format, JsonWriter, Summary, Template, TemplateError,
+    Utmp32Parser, UtmpDump, UtmpEntry, UtmpEntryKind,
+};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn load_entries() -> Result<Vec<UtmpEntry>> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    Ok(Utmp32Parser::from_path(path)?.collect::<Result<Vec<_>, _>>()?)
+}
+
+#[test]
+fn export_entries_csv() -> Result<()> {
+    let entries = load_entries()?;
+    let mut output = Vec::new();
+    utmp_rs::write_entries_csv(&mut output, &entries)?;
+    let output = String::from_utf8(output)?;
+    let rows = output.split_terminator("\r\n").collect::<Vec<_>>();
+    assert_eq!(rows.len(), entries.len() + 1);
+    assert_eq!(rows[0], utmp_rs::ENTRY_COLUMNS.join(","));
+    assert_eq!(
+        rows[1],
+        "ShutdownTime,,,,,,,,,,,,5.4.0-135-generic,,2022-12-28T10:33:17.077918Z"
+    );
+    let login = rows.iter().find(|row| row.starts_with("UserProcess,"));
+    assert_eq!(
+        login.copied(),
+        Some("UserProcess,1125,pts/0,ts/0,root,112.124.2.209,112.124.2.209,0,,,,,,,2023-02-07T08:07:06.139552Z")
+    );
+
+    // Fields with separators, quotes or surrounding spaces are quoted.
+    let entry = UtmpEntry::InitProcess {
+        pid: 1,
+        id: "a,\"b\"".to_owned(),
+        time: entries[0].time().unwrap(),
+    };
+    let entry2 = UtmpEntry::BootTime {
+        kernel_version: " 6.1\n".to_owned(),
+        time: entries[0].time().unwrap(),
+    };
+    let mut output = Vec::new();
+    utmp_rs::write_entries_csv(&mut output, [&entry, &entry2])?;
+    let output = String::from_utf8(output)?;
+    let rows = output.split_terminator("\r\n").skip(1).collect::<Vec<_>>();
+    assert_eq!(
+        rows,
+        [
+            "InitProcess,1,,\"a,\"\"b\"\"\",,,,,,,,,,,2022-12-28T10:33:17.077918Z",
+            "BootTime,,,,,,,,,,,,\" 6.1\n\",,2022-12-28T10:33:17.077918Z",
+        ]
+    );
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn export_sessions_csv() -> Result<()> {
+    let entries = load_entries()?;
+    let mut output = Vec::new();
+    utmp_rs::write_sessions_csv(&mut output, utmp_rs::sessions(&entries))?;
+    let output = String::from_utf8(output)?;
+    let rows = output.split_terminator("\r\n").collect::<Vec<_>>();
+    assert_eq!(rows.len(), 9);
+    assert_eq!(
+        rows[0],
+        "user,line,host,address,pid,login,logout,duration,end"
+    );
+    assert_eq!(
+        rows[1],
+        "root,pts/0,112.124.2.209,112.124.2.209,1125,2023-02-07T08:07:06.139552Z,\
+         2023-02-07T08:07:06.404205Z,0,Logout"
+    );
+    assert!(rows[8].ends_with(",,,Missing"));
+    Ok(())
+}
+
+#[test]
+fn export_entries_json() -> Result<()> {
+    let entries = load_entries()?;
+    let mut writer = JsonWriter::lines(Vec::new());
+    writer.write_entries(&entries)?;
+    let output = String::from_utf8(writer.finish()?)?;
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), entries.len());
+    assert_eq!(
+        lines[0],
+        r#"{"type":"ShutdownTime","kernel_version":"5.4.0-135-generic","time":"2022-12-28T10:33:17.077918Z"}"#
+    );
+    let login = lines
+        .iter()
+        .find(|line| line.contains(r#""type":"UserProcess""#));
+    assert_eq!(
+        login.copied(),
+        Some(concat!(
+            r#"{"type":"UserProcess","pid":1125,"line":"pts/0","id":"ts/0","user":"root","#,
+            r#""host":"112.124.2.209","address":"112.124.2.209","session":0,"#,
+            r#""time":"2023-02-07T08:07:06.139552Z"}"#,
+        ))
+    );
+
+    let mut writer = JsonWriter::pretty_array(Vec::new());
+    let entry = UtmpEntry::BootTime {
+        kernel_version: "\"6.1\"\n\u{1}".to_owned(),
+        time: entries[0].time().unwrap(),
+    };
+    writer.write_entries([&entries[0], &entry])?;
+    let output = String::from_utf8(writer.finish()?)?;
+    assert_eq!(
+        output,
+        r#"[
+  {
+    "type": "ShutdownTime",
+    "kernel_version": "5.4.0-135-generic",
+    "time": "2022-12-28T10:33:17.077918Z"
+  },
+  {
+    "type": "BootTime",
+    "kernel_version": "\"6.1\"\n\u0001",
+    "time": "2022-12-28T10:33:17.077918Z"
+  }
+]
+"#
+    );
+    let empty = JsonWriter::pretty_array(Vec::new()).finish()?;
+    assert_eq!(empty, b"[]\n");
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn export_events_json() -> Result<()> {
+    let entries = load_entries()?;
+    let mut writer = JsonWriter::lines(Vec::new());
+    utmp_rs::interpret_events(&entries, &mut |event| writer.write_event(&event).unwrap());
+    let attempts = utmp_rs::failed_logins(&entries[7..8]);
+    writer.write_failed_login(&attempts[0])?;
+    let output = String::from_utf8(writer.finish()?)?;
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0],
+        r#"{"type":"SystemShutdown","kernel_version":"5.4.0-135-generic","time":"2022-12-28T10:33:17.077918Z"}"#
+    );
+    assert_eq!(
+        lines[2],
+        r#"{"type":"UserLoggedIn","user":"root","line":"pts/0","host":"112.124.2.209","time":"2023-02-07T08:07:06.139552Z"}"#
+    );
+    let logout = lines.iter().find(|line| line.contains("UserLoggedOut"));
+    assert!(logout.unwrap().ends_with(r#","duration":0}"#));
+    assert_eq!(
+        lines.last().copied(),
+        Some(concat!(
+            r#"{"type":"FailedLogin","user":"root","line":"pts/0","host":"112.124.2.209","#,
+            r#""pid":1125,"time":"2023-02-07T08:07:06.139552Z"}"#,
+        ))
+    );
+    Ok(())
+}
+
+#[test]
+fn dump_and_undump_text() -> Result<()> {
+    let bytes = std::fs::read(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let dump = UtmpDump::new().format(Format::X32);
+    let mut text = Vec::new();
+    dump.write(&bytes, &mut text)?;
+    let text = String::from_utf8(text)?;
+    let lines = text.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), bytes.len() / 384);
+    assert_eq!(
+        lines[0],
+        "[1] [00000] [~~  ] [shutdown] [~           ] [5.4.0-135-generic   ] [0.0.0.0        ] [2022-12-28T10:33:17,077918+00:00]"
+    );
+    assert_eq!(
+        lines[7],
+        "[7] [01125] [ts/0] [root    ] [pts/0       ] [112.124.2.209       ] [112.124.2.209  ] [2023-02-07T08:07:06,139552+00:00]"
+    );
+
+    // Records rebuilt from the text are dumped into the same text.
+    let rebuilt = dump.undump(&text)?;
+    assert_eq!(rebuilt.len(), bytes.len());
+    let entries = Utmp32Parser::from_bytes(&rebuilt).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(entries[7], load_entries()?[7]);
+    let mut text2 = Vec::new();
+    dump.write(&rebuilt, &mut text2)?;
+    assert_eq!(String::from_utf8(text2)?, text);
+
+    // Times in other offsets and rebuilding as 64-bit records.
+    let line = "[7] [00042] [ts/3] [alice   ] [pts/3       ] [host.example.com    ] [2001:db8::1    ] [2023-02-07T09:07:06.5+01:00]";
+    let dump = UtmpDump::new().format(Format::X64);
+    let mut text = Vec::new();
+    dump.write(&dump.undump(line)?, &mut text)?;
+    assert_eq!(
+        String::from_utf8(text)?,
+        "[7] [00042] [ts/3] [alice   ] [pts/3       ] [host.example.com    ] [2001:db8::1    ] [2023-02-07T08:07:06,500000+00:00]\n"
+    );
+    assert!(dump.undump("[7] [00042] [ts/3]\n").is_err());
+    Ok(())
+}
+
+#[test]
+fn render_templates() -> Result<()> {
+    let entries = load_entries()?;
+    let template: Template = r"[%-6u|%8l|%h|%p|%k] %t{%a %b %e %T.%f %s}\n".parse()?;
+    assert_eq!(
+        template.render_entry(&entries[7]),
+        "[root  |   pts/0|112.124.2.209|1125|UserProcess] Tue Feb  7 08:07:06.139552 1675757226\n"
+    );
+    let template = Template::parse("%k %h %a at %t, 100%%")?;
+    assert_eq!(
+        template.render_entry(&entries[0]),
+        "ShutdownTime 5.4.0-135-generic  at 2022-12-28T10:33:17.077918Z, 100%"
+    );
+
+    assert_eq!(
+        Template::parse("%u %x"),
+        Err(TemplateError::UnknownDirective {
+            offset: 3,
+            directive: 'x'
+        })
+    );
+    assert_eq!(
+        Template::parse("%t{%F %q}"),
+        Err(TemplateError::UnknownTimeCode {
+            offset: 0,
+            code: 'q'
+        })
+    );
+    assert_eq!(
+        Template::parse("%t{%F"),
+        Err(TemplateError::Unterminated { offset: 0 })
+    );
+    assert_eq!(
+        Template::parse("%u %99999999999999999999999u"),
+        Err(TemplateError::WidthTooLarge { offset: 3 })
+    );
+    assert_eq!(
+        Template::parse("%1000000000u"),
+        Err(TemplateError::WidthTooLarge { offset: 0 })
+    );
+    assert!(Template::parse("%1024u").is_ok());
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn render_session_templates() -> Result<()> {
+    let entries = load_entries()?;
+    let sessions = utmp_rs::sessions(&entries).collect::<Vec<_>>();
+    let template = Template::parse("%u %l %t{%H:%M} - %o{%H:%M} (%d) %e")?;
+    let rendered = sessions.iter().map(|s| template.render_session(s));
+    assert_eq!(
+        rendered.collect::<Vec<_>>()[0],
+        "root pts/0 08:07 - 08:07 (00:00) Logout"
+    );
+    Ok(())
+}
+
+#[test]
+fn render_metrics() -> Result<()> {
+    let entries = load_entries()?;
+    let mut metrics = utmp_rs::Metrics::new();
+    metrics.set_current(&entries);
+    metrics.count_history(&entries);
+    metrics.count_failed(&entries[..8]);
+    let login = UtmpEntry::UserProcess {
+        pid: 1,
+        line: "pts/9".to_owned(),
+        id: "ts/9".to_owned(),
+        user: "a\"b\\".to_owned(),
+        host: String::new(),
+        address: None,
+        session: 0,
+        time: entries[0].time().unwrap(),
+    };
+    metrics.set_current(entries.iter().chain(std::iter::once(&login)));
+    assert_eq!(metrics.users(), 2);
+    assert_eq!(metrics.sessions_of("root"), 8);
+    assert_eq!(
+        metrics.to_string(),
+        "# HELP utmp_users Number of distinct users logged in.\n\
+         # TYPE utmp_users gauge\n\
+         utmp_users 2\n\
+         # HELP utmp_sessions Number of sessions logged in by user.\n\
+         # TYPE utmp_sessions gauge\n\
+         utmp_sessions{user=\"a\\\"b\\\\\"} 1\n\
+         utmp_sessions{user=\"root\"} 8\n\
+         # HELP utmp_boots_total Number of boots recorded in wtmp.\n\
+         # TYPE utmp_boots_total counter\n\
+         utmp_boots_total 1\n\
+         # HELP utmp_logins_total Number of logins recorded in wtmp.\n\
+         # TYPE utmp_logins_total counter\n\
+         utmp_logins_total 8\n\
+         # HELP utmp_failed_logins_total Number of failed logins recorded in btmp.\n\
+         # TYPE utmp_failed_logins_total counter\n\
+         utmp_failed_logins_total 3\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn summarize_entries() -> Result<()> {
+    let entries = load_entries()?;
+    let summary = Summary::from_entries(&entries);
+    assert_eq!(summary.records(), 19);
+    assert_eq!(summary.count(UtmpEntryKind::UserProcess), 8);
+    assert_eq!(summary.count(UtmpEntryKind::ShutdownTime), 1);
+    assert_eq!(summary.count(UtmpEntryKind::Accounting), 0);
+    assert_eq!(summary.counts().map(|(_, count)| count).sum::<u64>(), 19);
+    assert_eq!(summary.first(), entries[0].time());
+    assert_eq!(summary.last(), entries[18].time());
+    assert_eq!(Vec::from_iter(summary.users()), ["root"]);
+    assert_eq!(Vec::from_iter(summary.hosts()), ["112.124.2.209"]);
+    assert_eq!(Summary::from_entries(entries.iter().rev()), summary);
+    Ok(())
+}
+
+#[test]
+fn fan_out_to_sinks() -> Result<()> {
+    let entries = load_entries()?;
+    let mut csv = Vec::new();
+    utmp_rs::write_entries_csv(&mut csv, &entries)?;
+    let mut json = JsonWriter::pretty_array(Vec::new());
+    json.write_entries(&entries)?;
+    let json = json.finish()?;
+
+    let mut metrics = utmp_rs::Metrics::new();
+    let mut sink = FanOut::<Box<dyn EntrySink>>::new()
+        .with(Box::new(CsvWriter::entries(Vec::new())?))
+        .with(Box::new(JsonWriter::pretty_array(Vec::new())))
+        .with(Box::new(&mut metrics));
+    for entry in &entries {
+        sink.entry(entry)?;
+    }
+    sink.finish()?;
+    assert_eq!(sink.sinks().len(), 3);
+    drop(sink);
+    assert_eq!(metrics.boots(), 1);
+
+    // Sinks are given back to check their output.
+    let mut csv_writer = CsvWriter::entries(Vec::new())?;
+    let mut json_writer = JsonWriter::pretty_array(Vec::new());
+    let mut sink = FanOut::new()
+        .with(&mut csv_writer as &mut dyn EntrySink)
+        .with(&mut json_writer);
+    entries.iter().try_for_each(|entry| sink.entry(entry))?;
+    sink.finish()?;
+    assert_eq!(csv_writer.finish()?, csv);
+    assert_eq!(json_writer.finish()?, json);
+    Ok(())
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn session_sinks() -> Result<()> {
+    use utmp_rs::SessionSink;
+
+    let entries = load_entries()?;
+    let mut expected = Vec::new();
+    utmp_rs::write_sessions_csv(&mut expected, utmp_rs::sessions(&entries))?;
+    let mut sink = FanOut::new().with(CsvWriter::sessions(Vec::new())?);
+    for session in utmp_rs::sessions(&entries) {
+        sink.session(&session)?;
+    }
+    SessionSink::finish(&mut sink)?;
+    let mut writer = sink.into_inner().pop().unwrap();
+    assert_eq!(
+        writer.write_entry(&entries[0]).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidInput
+    );
+    assert_eq!(writer.finish()?, expected);
+    Ok(())
+}
+
+#[test]
+fn export_entries_ecs() -> Result<()> {
+    let entries = load_entries()?;
+    let mut writer = JsonWriter::lines(Vec::new()).ecs();
+    writer.write_entries(&entries)?;
+    let output = String::from_utf8(writer.finish()?)?;
+    let lines = output.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), entries.len());
+    assert_eq!(
+        lines[7],
+        concat!(
+            r#"{"@timestamp":"2023-02-07T08:07:06.139552Z","ecs":{"version":"8.11.0"},"#,
+            r#""event":{"kind":"event","category":["authentication","session"],"type":["start"],"#,
+            r#""action":"user-login","outcome":"success"},"user":{"name":"root"},"#,
+            r#""source":{"ip":"112.124.2.209"},"process":{"pid":1125},"#,
+            r#""utmp":{"type":"UserProcess","line":"pts/0","id":"ts/0","session":0}}"#,
+        )
+    );
+
+    let mut writer = JsonWriter::pretty_array(Vec::new()).ecs_failures();
+    writer.write_entry(&entries[0])?;
+    writer.write_entry(&entries[7])?;
+    let output = String::from_utf8(writer.finish()?)?;
+    assert!(output.starts_with(
+        "[\n  {\n    \"@timestamp\": \"2022-12-28T10:33:17.077918Z\",\n    \"ecs\": {\n      \"version\": \"8.11.0\"\n    },"
+    ));
+    assert!(output.contains(
+        "\"host\": {\n      \"os\": {\n        \"kernel\": \"5.4.0-135-generic\"\n      }\n    },"
+    ));
+    assert!(output.contains("\"outcome\": \"failure\""));
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use utmp_rs::{Cidr, NewHostDetector, Query, Utmp32Parser, UtmpEntry, UtmpEntryKind, UtmpLog};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn load_log() -> Result<(Vec<UtmpEntry>, UtmpLog)> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let log = entries.iter().cloned().collect();
+    Ok((entries, log))
+}
+
+#[test]
+fn lookup_entries() -> Result<()> {
+    let (entries, log) = load_log()?;
+    assert_eq!(log.len(), entries.len());
+    assert_eq!(log.entries(), &entries[..]);
+
+    let pids = log
+        .entries_for_user("root")
+        .map(|entry| match entry {
+            UtmpEntry::UserProcess { pid, .. } => *pid,
+            entry => panic!("unexpected entry {:?}", entry),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(pids, [1125, 1127, 1225, 2454, 2714, 4343, 5022, 13369]);
+    assert_eq!(log.entries_for_user("LOGIN").count(), 2);
+    assert_eq!(log.entries_for_user("nobody").count(), 0);
+
+    assert_eq!(log.entries_for_line("pts/1").count(), 5);
+    let by_pid = log.entries_for_pid(644).collect::<Vec<_>>();
+    assert_eq!(by_pid, [&entries[4], &entries[5]]);
+    Ok(())
+}
+
+#[test]
+fn entries_in_time_order() -> Result<()> {
+    let (mut entries, log) = load_log()?;
+    let mut reversed = entries.iter().rev().cloned().collect::<UtmpLog>();
+    reversed.push(UtmpEntry::Empty);
+    entries.sort_by_key(|entry| entry.time());
+    assert_eq!(
+        log.entries_by_time().collect::<Vec<_>>(),
+        entries.iter().collect::<Vec<_>>()
+    );
+    // Entries with the same time stay in the order they were added.
+    let times = |log: &UtmpLog| log.entries_by_time().map(|e| e.time()).collect::<Vec<_>>();
+    assert_eq!(times(&reversed), times(&log));
+    assert_eq!(reversed.len(), entries.len() + 1);
+    Ok(())
+}
+
+#[test]
+fn query_entries() -> Result<()> {
+    let (entries, log) = load_log()?;
+    let (from, to) = (entries[3].time().unwrap(), entries[12].time().unwrap());
+    let queries = [
+        Query::new(),
+        Query::new().user("root"),
+        Query::new().user("root").line("pts/1"),
+        Query::new().line("pts/1").kind(UtmpEntryKind::DeadProcess),
+        Query::new().pid(644),
+        Query::new().host_prefix("192.168."),
+        Query::new().user("nobody"),
+        Query::new().between(from, to),
+        Query::new().since(from).kind(UtmpEntryKind::UserProcess),
+        Query::new().until(to).user("root"),
+        Query::new().between(to, from),
+        Query::new().until(from).kind(UtmpEntryKind::BootTime),
+    ];
+    for query in &queries {
+        let mut expected = query.apply(&entries).collect::<Vec<_>>();
+        let mut actual = log.query(query).collect::<Vec<_>>();
+        assert!(actual.iter().all(|entry| query.matches(entry)));
+        expected.sort_by_key(|entry| entry.time());
+        actual.sort_by_key(|entry| entry.time());
+        assert_eq!(actual, expected, "{:?}", query);
+    }
+
+    let root = log.query(&queries[1]).count();
+    assert_eq!(root, 8);
+    // Only querying the period yields entries in order of time.
+    let in_period = log.query(&queries[7]).collect::<Vec<_>>();
+    let by_time = log.entries_by_time().collect::<Vec<_>>();
+    let start = by_time.iter().position(|e| e.time() >= Some(from)).unwrap();
+    assert_eq!(in_period, by_time[start..start + in_period.len()]);
+    let none = Query::new().kind(UtmpEntryKind::BootTime).user("root");
+    assert_eq!(none.apply(&entries).count(), 0);
+    Ok(())
+}
+
+#[test]
+fn query_cidrs() -> Result<()> {
+    let (entries, log) = load_log()?;
+    let cidr = |s: &str| s.parse::<Cidr>().unwrap();
+    let count = |query: Query| {
+        let count = query.apply(&entries).count();
+        assert_eq!(log.query(&query).count(), count);
+        count
+    };
+    assert_eq!(count(Query::new().cidr(cidr("112.124.0.0/16"))), 5);
+    assert_eq!(count(Query::new().cidr(cidr("112.124.2.208/31"))), 5);
+    assert_eq!(count(Query::new().cidr(cidr("10.0.0.0/8"))), 0);
+    let both = Query::new()
+        .cidr(cidr("10.0.0.0/8"))
+        .cidr(cidr("112.124.2.209"));
+    assert_eq!(count(both), 5);
+    // Entries without an address are only excluded by including ranges.
+    let internal = Query::new().exclude_cidr(cidr("112.0.0.0/8"));
+    assert_eq!(count(internal), entries.len() - 5);
+
+    // Hosts are matched if there is no address.
+    let query = Query::new().cidr(cidr("2001:db8::/32"));
+    assert!(query.matches_address(None, "2001:db8::1"));
+    assert!(!query.matches_address(None, "example.com"));
+    assert!(!query.matches_address(Some("2001:db9::1".parse()?), "2001:db8::1"));
+    let query = Query::new().cidr(cidr("10.0.0.0/8"));
+    assert!(query.matches_address(Some("::ffff:10.1.2.3".parse()?), ""));
+
+    assert_eq!(cidr("10.1.2.3/8").to_string(), "10.0.0.0/8");
+    assert_eq!(cidr("::1").prefix_len(), 128);
+    assert!(cidr("0.0.0.0/0").contains("192.0.2.1".parse()?));
+    assert!(!cidr("0.0.0.0/0").contains("2001:db8::1".parse()?));
+    for invalid in &["10.0.0.0/33", "10.0.0.0/", "example.com/8", "::/129"] {
+        assert!(invalid.parse::<Cidr>().is_err(), "{}", invalid);
+    }
+    Ok(())
+}
+
+#[test]
+fn detect_new_hosts() -> Result<()> {
+    let (entries, _) = load_log()?;
+    let mut detector = NewHostDetector::new();
+    let first = entries
+        .iter()
+        .find_map(|entry| detector.check(entry))
+        .unwrap();
+    assert_eq!((&*first.user, &*first.host), ("root", "112.124.2.209"));
+    assert_eq!(first.known_hosts, 0);
+    assert_eq!(Some(first.time), entries[7].time());
+    detector.learn(&entries);
+    assert_eq!(Vec::from_iter(detector.hosts_of("root")), ["112.124.2.209"]);
+
+    let from = |host: &str| match entries[7].clone() {
+        UtmpEntry::UserProcess {
+            pid,
+            line,
+            id,
+            user,
+            address,
+            session,
+            time,
+            ..
+        } => UtmpEntry::UserProcess {
+            pid,
+            line,
+            id,
+            user,
+            host: host.to_owned(),
+            address,
+            session,
+            time,
+        },
+        entry => panic!("unexpected entry {:?}", entry),
+    };
+    assert_eq!(detector.check(&from("112.124.2.209")), None);
+    let new = detector.check(&from("evil\\tab\there")).unwrap();
+    assert_eq!((&*new.host, new.known_hosts), ("evil\\tab\there", 1));
+    assert_eq!(detector.check(&from("evil\\tab\there")), None);
+
+    // The state survives saving and loading.
+    let mut state = Vec::new();
+    detector.save(&mut state)?;
+    assert_eq!(
+        String::from_utf8(state.clone())?,
+        "root\t112.124.2.209\nroot\tevil\\\\tab\\there\n"
+    );
+    let loaded = NewHostDetector::load(&state[..])?;
+    assert_eq!(loaded, detector);
+    assert!(NewHostDetector::load(&b"root\n"[..]).is_err());
+
+    // Including hosts ending in a carriage return, which reading lines would strip.
+    detector.check(&from("crlf\r"));
+    let mut state = Vec::new();
+    detector.save(&mut state)?;
+    let mut loaded = NewHostDetector::load(&state[..])?;
+    assert_eq!(loaded, detector);
+    assert_eq!(loaded.check(&from("crlf\r")), None);
+    Ok(())
+}
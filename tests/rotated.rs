@@ -0,0 +1,178 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::fs;
+use std::io::{self, Read};
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use std::process;
+use utmp_rs::{merge, parse_from_path, ParseError, Rotated32Parser, Utmp32Parser, UtmpEntry};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn temp_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("utmp-rs-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[test]
+fn parse_numbered_generations() -> Result<()> {
+    let dir = temp_dir("rotated-numbered")?;
+    let sample = SAMPLES_PATH.join("with_host_32.utmp");
+    let data = fs::read(&sample)?;
+    let entries = Utmp32Parser::from_path(&sample)?.collect::<Result<Vec<_>, _>>()?;
+    let record_size = data.len() / entries.len();
+    // Split the sample into generations, with the oldest part in the highest number.
+    fs::write(dir.join("wtmp.10"), &data[..record_size * 3])?;
+    fs::write(dir.join("wtmp.2"), &data[record_size * 3..record_size * 10])?;
+    fs::write(
+        dir.join("wtmp.1"),
+        &data[record_size * 10..record_size * 12],
+    )?;
+    fs::write(dir.join("wtmp"), &data[record_size * 12..])?;
+    fs::write(dir.join("wtmpx"), &data)?;
+    fs::write(dir.join("wtmp.old"), &data)?;
+
+    let parser = Rotated32Parser::from_path(dir.join("wtmp"))?;
+    let paths = parser.paths().map(|p| p.to_owned()).collect::<Vec<_>>();
+    let names = ["wtmp.10", "wtmp.2", "wtmp.1", "wtmp"];
+    assert_eq!(paths, names.map(|name| dir.join(name)));
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, entries);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn parse_dated_and_compressed_generations() -> Result<()> {
+    let dir = temp_dir("rotated-dated")?;
+    let sample = SAMPLES_PATH.join("basic32.utmp");
+    let data = fs::read(&sample)?;
+    let entries = Utmp32Parser::from_path(&sample)?.collect::<Result<Vec<_>, _>>()?;
+    let record_size = data.len() / entries.len();
+    fs::write(dir.join("wtmp-20230101.gz"), b"not really gzip")?;
+    fs::write(dir.join("wtmp-20230201"), &data[..record_size * 2])?;
+    fs::write(dir.join("wtmp"), &data[record_size * 2..])?;
+
+    let mut parser = Rotated32Parser::from_path(dir.join("wtmp"))?;
+    let err = parser.next().unwrap().unwrap_err();
+    let compressed = dir.join("wtmp-20230101.gz");
+    assert!(matches!(err, ParseError::Io { path: Some(path), .. } if path == compressed));
+    let actual = parser.collect::<Result<Vec<UtmpEntry>, _>>()?;
+    assert_eq!(actual, entries);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn path_of_failed_file() -> Result<()> {
+    let dir = temp_dir("failed-path")?;
+    let data = fs::read(SAMPLES_PATH.join("basic32.utmp"))?;
+    let path = dir.join("wtmp");
+    fs::write(&path, &data[..data.len() - 1])?;
+
+    let is_at_path =
+        |err: &ParseError| matches!(err, ParseError::Io { path: Some(p), .. } if *p == path);
+    // Truncated files are reported up front, without the path.
+    let err = Utmp32Parser::from_path(&path)?
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(err, ParseError::Misaligned { .. }));
+    // So are generations of rotated files.
+    let mut parser = Rotated32Parser::from_path(&path)?;
+    assert!(matches!(
+        parser.next(),
+        Some(Err(ParseError::Misaligned { .. }))
+    ));
+    fs::remove_file(&path)?;
+    let err = parse_from_path(&path).unwrap_err();
+    assert!(is_at_path(&err));
+    assert!(err.to_string().starts_with(&path.display().to_string()));
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn parse_with_decoder() -> Result<()> {
+    let dir = temp_dir("rotated-decoder")?;
+    let sample = SAMPLES_PATH.join("basic32.utmp");
+    let data = fs::read(&sample)?;
+    let entries = Utmp32Parser::from_path(&sample)?.collect::<Result<Vec<_>, _>>()?;
+    // Store the generation with every byte inverted as its "compression".
+    let encoded = data.iter().map(|b| !b).collect::<Vec<_>>();
+    fs::write(dir.join("wtmp.1.xz"), encoded)?;
+    fs::write(dir.join("wtmp"), b"")?;
+
+    let parser = Rotated32Parser::from_path(dir.join("wtmp"))?
+        .with_decoder("xz", |file| Ok(Box::new(Inverted(file))));
+    assert_eq!(parser.collect::<Result<Vec<_>, _>>()?, entries);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+struct Inverted<R>(R);
+
+impl<R: Read> Read for Inverted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.read(buf)?;
+        buf[..n].iter_mut().for_each(|b| *b = !*b);
+        Ok(n)
+    }
+}
+
+#[test]
+fn merge_sources() -> Result<()> {
+    let sample = SAMPLES_PATH.join("with_host_32.utmp");
+    let mut entries = Utmp32Parser::from_path(&sample)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.time());
+    let source = |entries: Vec<UtmpEntry>| entries.into_iter().map(Ok::<_, ParseError>);
+    let (even, odd) = (0..entries.len()).partition::<Vec<_>, _>(|i| i % 2 == 0);
+    let pick = |indices: Vec<usize>| indices.into_iter().map(|i| entries[i].clone()).collect();
+    let merged = merge(vec![source(pick(odd)), source(pick(even))]);
+    assert_eq!(merged.size_hint(), (entries.len(), Some(entries.len())));
+    let merged = merged.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(merged.len(), entries.len());
+    assert!(merged.windows(2).all(|w| w[0].time() <= w[1].time()));
+
+    // Entries with the same time are yielded in the order of the sources.
+    let tagged = |tag: &str| {
+        let user = tag.to_owned();
+        let entries = entries.iter().map(move |entry| match entry {
+            UtmpEntry::UserProcess { time, .. } => Ok(UtmpEntry::UserProcess {
+                pid: 0,
+                line: String::new(),
+                id: String::new(),
+                user: user.clone(),
+                host: String::new(),
+                address: None,
+                session: 0,
+                time: *time,
+            }),
+            entry => Ok(entry.clone()),
+        });
+        entries.collect::<Vec<Result<_, ParseError>>>()
+    };
+    let merged = merge(vec![tagged("a"), tagged("b")]);
+    let users = merged
+        .filter_map(|entry| match entry {
+            Ok(UtmpEntry::UserProcess { user, .. }) => Some(user),
+            _ => None,
+        })
+        .collect::<String>();
+    assert_eq!(users, "ab".repeat(users.len() / 2));
+
+    // Errors and entries without a time are yielded as soon as they are read.
+    let first = vec![Ok(entries[5].clone()), Ok(entries[6].clone())];
+    let second = vec![Ok(entries[0].clone()), Err("broken"), Ok(UtmpEntry::Empty)];
+    let merged = merge(vec![first, second]).collect::<Vec<_>>();
+    let expected = [
+        Ok(entries[0].clone()),
+        Err("broken"),
+        Ok(UtmpEntry::Empty),
+        Ok(entries[5].clone()),
+        Ok(entries[6].clone()),
+    ];
+    assert_eq!(merged, expected);
+    Ok(())
+}
@@ -0,0 +1,335 @@
+#![cfg(feature = "serde")]
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde_core::de::value::{Error, MapDeserializer};
+use serde_core::de::{Deserialize, Deserializer, IntoDeserializer, Visitor};
+use serde_core::ser::{self, Impossible, Serialize, SerializeMap, Serializer};
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use utmp_rs::{Utmp32Parser, UtmpEntry};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+/// Minimal data model for checking what entries are serialized into.
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Int(i64),
+    Str(String),
+}
+
+type Map = Vec<(String, Value)>;
+
+fn to_map(entry: &UtmpEntry) -> Result<Map, Error> {
+    entry.serialize(MapSerializer)
+}
+
+fn from_map(map: Map) -> Result<UtmpEntry, Error> {
+    UtmpEntry::deserialize(MapDeserializer::new(map.into_iter()))
+}
+
+#[test]
+fn serialize_entries() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let str = |s: &str| Value::Str(s.to_owned());
+    assert_eq!(
+        to_map(&entries[7])?,
+        vec![
+            ("type".to_owned(), str("UserProcess")),
+            ("pid".to_owned(), Value::Int(1125)),
+            ("line".to_owned(), str("pts/0")),
+            ("id".to_owned(), str("ts/0")),
+            ("user".to_owned(), str("root")),
+            ("host".to_owned(), str("112.124.2.209")),
+            ("address".to_owned(), str("112.124.2.209")),
+            ("session".to_owned(), Value::Int(0)),
+            ("time".to_owned(), str("2023-02-07T08:07:06.139552Z")),
+        ]
+    );
+    for entry in entries.iter().chain(&[UtmpEntry::Empty]) {
+        assert_eq!(&from_map(to_map(entry)?)?, entry);
+    }
+
+    // Raw records of unknown types are kept as hexadecimal.
+    let mut data = std::fs::read(&path)?;
+    data[..2].copy_from_slice(&42i16.to_ne_bytes());
+    let unknown = Utmp32Parser::from_bytes(&data[..384]).next().unwrap()?;
+    let map = to_map(&unknown)?;
+    assert_eq!(map[0], ("type".to_owned(), str("Unknown")));
+    let hex = data[..384].iter().map(|b| format!("{:02x}", b));
+    assert_eq!(map[3], ("raw".to_owned(), Value::Str(hex.collect())));
+    assert_eq!(from_map(map)?, unknown);
+    Ok(())
+}
+
+#[test]
+fn deserialize_entries() -> Result<()> {
+    let str = |k: &str, v: &str| (k.to_owned(), Value::Str(v.to_owned()));
+    let entry = from_map(vec![
+        str("type", "DeadProcess"),
+        ("pid".to_owned(), Value::Int(1020)),
+        str("line", "pts/0"),
+        str("id", ""),
+        ("termination".to_owned(), Value::Int(0)),
+        ("exit".to_owned(), Value::Int(1)),
+        str("unknown", "ignored"),
+        str("time", "2023-02-07T16:07:06.404205+08:00"),
+    ])?;
+    let time = utmp_rs::Timestamp {
+        secs: 1_675_757_226,
+        micros: 404_205,
+    };
+    let expected = UtmpEntry::DeadProcess {
+        pid: 1020,
+        line: "pts/0".to_owned(),
+        id: "".to_owned(),
+        termination: 0,
+        exit: 1,
+        time,
+    };
+    assert_eq!(entry, expected);
+
+    let entry = UtmpEntry::RunLevel {
+        level: '3',
+        previous: Some('5'),
+        kernel_version: "5.4.0".to_owned(),
+        time,
+    };
+    let map = to_map(&entry)?;
+    assert_eq!(map[1..3], [str("level", "3"), str("previous", "5")]);
+    assert_eq!(from_map(map)?, entry);
+
+    assert!(from_map(vec![str("type", "DeadProcess")]).is_err());
+    assert!(from_map(vec![str("type", "Unknown")]).is_err());
+    assert!(from_map(vec![str("type", "NewTime"), str("time", "2023-02-07")]).is_err());
+    Ok(())
+}
+
+/// Implement the given and the other methods of `Serializer` by failing.
+macro_rules! unsupported {
+    ($($ty:ident)*) => {
+        $(unsupported!(@method $ty);)*
+
+        fn serialize_none(self) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_unit(self) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_unit_struct(self, _: &'static str) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_unit_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+        ) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_newtype_struct<T: Serialize + ?Sized>(
+            self,
+            _: &'static str,
+            _: &T,
+        ) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: &T,
+        ) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeTupleStruct, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeTupleVariant, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_struct(
+            self,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeStruct, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _: &'static str,
+            _: u32,
+            _: &'static str,
+            _: usize,
+        ) -> Result<Self::SerializeStructVariant, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+    };
+    (@method bool) => { unsupported!(@scalar serialize_bool bool); };
+    (@method i8) => { unsupported!(@scalar serialize_i8 i8); };
+    (@method i16) => { unsupported!(@scalar serialize_i16 i16); };
+    (@method i32) => { unsupported!(@scalar serialize_i32 i32); };
+    (@method i64) => { unsupported!(@scalar serialize_i64 i64); };
+    (@method u8) => { unsupported!(@scalar serialize_u8 u8); };
+    (@method u16) => { unsupported!(@scalar serialize_u16 u16); };
+    (@method u32) => { unsupported!(@scalar serialize_u32 u32); };
+    (@method u64) => { unsupported!(@scalar serialize_u64 u64); };
+    (@method f32) => { unsupported!(@scalar serialize_f32 f32); };
+    (@method f64) => { unsupported!(@scalar serialize_f64 f64); };
+    (@method char) => { unsupported!(@scalar serialize_char char); };
+    (@method str) => { unsupported!(@scalar serialize_str &str); };
+    (@method bytes) => { unsupported!(@scalar serialize_bytes &[u8]); };
+    (@scalar $name:ident $ty:ty) => {
+        fn $name(self, _: $ty) -> Result<Self::Ok, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+    };
+}
+
+struct MapSerializer;
+
+struct MapBuilder(Map, Option<String>);
+
+impl Serializer for MapSerializer {
+    type Ok = Map;
+    type Error = Error;
+    type SerializeSeq = Impossible<Map, Error>;
+    type SerializeTuple = Impossible<Map, Error>;
+    type SerializeTupleStruct = Impossible<Map, Error>;
+    type SerializeTupleVariant = Impossible<Map, Error>;
+    type SerializeMap = MapBuilder;
+    type SerializeStruct = Impossible<Map, Error>;
+    type SerializeStructVariant = Impossible<Map, Error>;
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapBuilder, Error> {
+        Ok(MapBuilder(Vec::new(), None))
+    }
+
+    unsupported! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str bytes
+    }
+}
+
+impl SerializeMap for MapBuilder {
+    type Ok = Map;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Value::Str(key) => self.1 = Some(key),
+            key => return Err(ser::Error::custom(format!("unexpected key {:?}", key))),
+        }
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.1.take().unwrap();
+        self.0.push((key, value.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Map, Error> {
+        Ok(self.0)
+    }
+}
+
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = Impossible<Value, Error>;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        Ok(Value::Int(v.into()))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Int(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Str(v.to_owned()))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(ser::Error::custom("unsupported"))
+    }
+
+    unsupported! {
+        bool i8 u8 u16 u32 u64 f32 f64 bytes
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self {
+            Value::Int(v) => visitor.visit_i64(v),
+            Value::Str(v) => visitor.visit_string(v),
+        }
+    }
+
+    serde_core::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
@@ -0,0 +1,645 @@
+#![cfg(feature = "time")]
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use time::{Duration, OffsetDateTime};
+use utmp_rs::{
+    failed_logins, last, sessions, BootHistory, LastEntry, LastLine, SessionEnd, Timestamp,
+    Utmp32Parser, UtmpEntry,
+};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn timestamp(nanos: i128) -> Timestamp {
+    OffsetDateTime::from_unix_timestamp_nanos(nanos)
+        .unwrap()
+        .into()
+}
+
+fn login(pid: i32, line: &str, user: &str, secs: i64) -> UtmpEntry {
+    UtmpEntry::UserProcess {
+        pid,
+        line: line.to_owned(),
+        id: String::new(),
+        user: user.to_owned(),
+        host: String::new(),
+        address: None,
+        session: 0,
+        time: timestamp(i128::from(secs) * 1_000_000_000),
+    }
+}
+
+fn logout(pid: i32, line: &str, secs: i64) -> UtmpEntry {
+    UtmpEntry::DeadProcess {
+        pid,
+        line: line.to_owned(),
+        id: String::new(),
+        termination: 0,
+        exit: 0,
+        time: timestamp(i128::from(secs) * 1_000_000_000),
+    }
+}
+
+#[test]
+fn sessions_with_host_32() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let sessions = sessions(&entries).collect::<Vec<_>>();
+    assert_eq!(sessions.len(), 8);
+
+    let first = &sessions[0];
+    assert_eq!((&*first.user, &*first.line), ("root", "pts/0"));
+    assert_eq!(first.host, "112.124.2.209");
+    assert_eq!(first.address, Some([112, 124, 2, 209].into()));
+    assert_eq!(first.pid, 1125);
+    assert_eq!(first.login, timestamp(1_675_757_226_139_552_000));
+    assert_eq!(first.logout, Some(timestamp(1_675_757_226_404_205_000)));
+    assert_eq!(first.duration, Some(Duration::microseconds(264_653)));
+    assert_eq!(first.end, SessionEnd::Logout);
+
+    // Logins on a line without a logout in between end at the next login.
+    assert_eq!(sessions[2].pid, 2454);
+    assert_eq!(sessions[2].end, SessionEnd::NextLogin);
+    assert_eq!(
+        sessions[2].logout,
+        Some(timestamp(1_675_758_522_887_514_000))
+    );
+
+    let open = sessions.iter().filter(|s| s.end == SessionEnd::Missing);
+    assert_eq!(open.map(|s| s.pid).collect::<Vec<_>>(), [5022, 13369]);
+    Ok(())
+}
+
+fn boot(secs: i64) -> UtmpEntry {
+    UtmpEntry::BootTime {
+        kernel_version: "5.4.0".to_owned(),
+        time: timestamp(i128::from(secs) * 1_000_000_000),
+    }
+}
+
+fn shutdown(secs: i64) -> UtmpEntry {
+    UtmpEntry::ShutdownTime {
+        kernel_version: "5.4.0".to_owned(),
+        time: timestamp(i128::from(secs) * 1_000_000_000),
+    }
+}
+
+fn entries_across_reboots() -> Vec<UtmpEntry> {
+    vec![
+        boot(100),
+        login(10, "tty1", "alice", 110),
+        login(11, "pts/0", "bob", 120),
+        logout(11, "pts/0", 130),
+        shutdown(200),
+        boot(300),
+        login(12, "tty1", "alice", 310),
+        boot(400),
+        login(13, "tty1", "alice", 410),
+    ]
+}
+
+#[test]
+fn sessions_across_reboots() {
+    let ends = sessions(entries_across_reboots())
+        .map(|s| (s.pid, s.end, s.duration.map(|d| d.whole_seconds())))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ends,
+        [
+            (11, SessionEnd::Logout, Some(10)),
+            (10, SessionEnd::Shutdown, Some(90)),
+            (12, SessionEnd::Crash, Some(90)),
+            (13, SessionEnd::Missing, None),
+        ]
+    );
+
+    // Sessions not ended in the live wtmp are still logged in.
+    let now = timestamp(500_000_000_000);
+    let last = sessions(entries_across_reboots())
+        .live_at(now)
+        .last()
+        .unwrap();
+    assert_eq!(last.end, SessionEnd::LoggedIn);
+    assert_eq!(last.logout, None);
+    assert_eq!(last.duration, Some(Duration::seconds(90)));
+}
+
+// The sample is in the native format of x86_64.
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn current_users_with_host_32() -> Result<()> {
+    let users = utmp_rs::current_users(SAMPLES_PATH.join("with_host_32.utmp"))?;
+    let users = users
+        .iter()
+        .map(|s| (&*s.user, &*s.line, &*s.host, s.pid))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        users,
+        [
+            ("root", "pts/1", "", 5022),
+            ("root", "pts/0", "112.124.2.209", 13369),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn list_like_last() {
+    let entries = last(entries_across_reboots());
+    let listed = entries
+        .iter()
+        .map(|entry| match entry {
+            LastEntry::Session(s) => s.pid.to_string(),
+            LastEntry::Reboot { .. } => "reboot".to_owned(),
+            LastEntry::Shutdown { .. } => "shutdown".to_owned(),
+            _ => unreachable!(),
+        })
+        .collect::<Vec<_>>();
+    let expected = [
+        "13", "reboot", "12", "reboot", "shutdown", "11", "10", "reboot",
+    ];
+    assert_eq!(listed, expected);
+
+    let lines = entries.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        lines[..5],
+        [
+            "alice    tty1                          Thu Jan  1 00:06   gone - no logout",
+            "reboot   system boot  5.4.0            Thu Jan  1 00:06   still running",
+            "alice    tty1                          Thu Jan  1 00:05 - crash  (00:01)",
+            "reboot   system boot  5.4.0            Thu Jan  1 00:05 - 00:06  (00:01)",
+            "shutdown system down  5.4.0            Thu Jan  1 00:03 - 00:05  (00:01)",
+        ]
+    );
+    assert_eq!(
+        lines[6],
+        "alice    tty1                          Thu Jan  1 00:01 - down   (00:01)"
+    );
+}
+
+#[test]
+fn last_line_options() -> Result<()> {
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let listed = last(&entries);
+    assert_eq!(
+        LastLine::new(&listed[2]).full_times(true).to_string(),
+        "root     pts/0        112.124.2.209    Tue Feb  7 08:52:35 2023 - Tue Feb  7 09:23:05 2023  (00:30)"
+    );
+    assert_eq!(
+        LastLine::new(&listed[0]).full_times(true).to_string(),
+        "root     pts/0        112.124.2.209    Tue Feb  7 11:20:06 2023   gone - no logout"
+    );
+    let mut session = match &listed[2] {
+        LastEntry::Session(session) => session.clone(),
+        entry => panic!("unexpected entry {:?}", entry),
+    };
+    session.host = "example.com".to_owned();
+    let entry = LastEntry::Session(session);
+    assert_eq!(
+        LastLine::new(&entry).to_string(),
+        listed[2]
+            .to_string()
+            .replace("112.124.2.209", "example.com  ")
+    );
+    assert_eq!(
+        LastLine::new(&entry).ips(true).to_string(),
+        listed[2].to_string()
+    );
+    Ok(())
+}
+
+#[test]
+fn list_like_lastb() {
+    let attempt = |pid, user: &str, secs| UtmpEntry::LoginProcess {
+        pid,
+        line: "ssh:notty".to_owned(),
+        id: String::new(),
+        user: user.to_owned(),
+        host: "203.0.113.7".to_owned(),
+        time: timestamp(i128::from(secs) * 1_000_000_000),
+    };
+    let entries = [attempt(1, "root", 60), boot(90), attempt(2, "admin", 120)];
+    let attempts = failed_logins(&entries);
+    let users = attempts.iter().map(|a| &*a.user).collect::<Vec<_>>();
+    assert_eq!(users, ["admin", "root"]);
+    assert_eq!(
+        attempts[1].to_string(),
+        "root     ssh:notty    203.0.113.7      Thu Jan  1 00:01 - 00:01  (00:00)"
+    );
+}
+
+#[test]
+fn boot_history() {
+    let history = BootHistory::new(entries_across_reboots());
+    let boots = history.boots();
+    assert_eq!(boots.len(), 3);
+    assert_eq!(boots[0].end(), Some(timestamp(200_000_000_000)));
+    assert!(!boots[0].crashed());
+    assert_eq!(boots[1].end(), Some(timestamp(400_000_000_000)));
+    assert!(boots[1].crashed());
+    assert_eq!(boots[2].end(), None);
+    assert_eq!(history.last_boot(), boots.last());
+
+    let (start, end) = (timestamp(0), timestamp(500_000_000_000));
+    assert_eq!(
+        history.uptime_between(start, end),
+        Duration::seconds(100 + 100 + 100)
+    );
+    assert_eq!(
+        history.downtime_between(start, end),
+        Duration::seconds(100 + 100)
+    );
+    let (start, end) = (timestamp(150_000_000_000), timestamp(350_000_000_000));
+    assert_eq!(
+        history.uptime_between(start, end),
+        Duration::seconds(50 + 50)
+    );
+}
+
+#[cfg(all(feature = "dns", unix))]
+#[test]
+fn resolve_hosts() {
+    use std::net::{IpAddr, Ipv4Addr};
+    let resolver = utmp_rs::Resolver::new();
+    let loopback = IpAddr::from(Ipv4Addr::LOCALHOST);
+    let mut entry = login(1125, "pts/0", "root", 1_675_757_226);
+    if let UtmpEntry::UserProcess { host, .. } = &mut entry {
+        *host = "localhost".to_owned();
+    }
+    let mut by_name = sessions(vec![entry]).next().unwrap();
+    resolver.resolve_address(&mut by_name);
+    assert!(by_name.address.is_some_and(|addr| addr.is_loopback()));
+
+    // Lookups are cached, whether they succeed or not.
+    let name = resolver.lookup_name(loopback);
+    assert_eq!(resolver.lookup_name(loopback), name);
+    let mut entry = login(1125, "pts/0", "root", 1_675_757_226);
+    if let UtmpEntry::UserProcess { host, .. } = &mut entry {
+        *host = "127.0.0.1".to_owned();
+    }
+    let mut by_address = sessions(vec![entry]).next().unwrap();
+    resolver.resolve_address(&mut by_address);
+    assert_eq!(by_address.address, None);
+    let enriched = utmp_rs::enrich(vec![by_address], resolver).next().unwrap();
+    assert_eq!(enriched.data, name);
+}
+
+#[test]
+fn enrich_remote_hosts() -> Result<()> {
+    use utmp_rs::{enrich, Remote, UtmpEntryKind};
+    let path = SAMPLES_PATH.join("with_host_32.utmp");
+    let entries = Utmp32Parser::from_path(&path)?.collect::<Result<Vec<_>, _>>()?;
+    let country = |remote: &Remote<'_>| match remote.address?.to_string() {
+        address if address.starts_with("112.") => Some("CN"),
+        _ => None,
+    };
+    let enriched = enrich(sessions(&entries), country).collect::<Vec<_>>();
+    assert_eq!(enriched.len(), 8);
+    assert_eq!((enriched[0].item.pid, enriched[0].data), (1125, Some("CN")));
+    let mut other = enriched.iter().filter(|e| e.data.is_none());
+    assert!(other.all(|e| !e.item.host.starts_with("112.")));
+
+    // Only logins have remote hosts.
+    let enriched = enrich(&entries, country);
+    let mut hosts = enriched.filter(|e| e.data.is_some()).map(|e| e.item.kind());
+    assert!(hosts.all(|kind| kind == UtmpEntryKind::UserProcess));
+    let local = enrich(vec![login(1, "tty1", "root", 0)], country);
+    assert_eq!(local.map(|e| e.data).collect::<Vec<_>>(), [None]);
+    Ok(())
+}
+
+#[test]
+fn concurrent_sessions_timeline() {
+    use utmp_rs::Timeline;
+    let entries = vec![
+        login(10, "pts/0", "root", 100),
+        login(11, "pts/1", "alice", 150),
+        logout(10, "pts/0", 200),
+        // Another login right at the logout isn't counted together with it.
+        login(12, "pts/0", "bob", 200),
+        login(13, "pts/2", "carol", 250),
+        logout(11, "pts/1", 300),
+    ];
+    let all = sessions(&entries).collect::<Vec<_>>();
+    let timeline = Timeline::new(&all);
+    let steps = timeline.steps().iter().map(|s| (s.time.secs, s.count));
+    assert_eq!(
+        steps.collect::<Vec<_>>(),
+        [(100, 1), (150, 2), (250, 3), (300, 2)]
+    );
+    assert_eq!(timeline.count_at(timestamp(0)), 0);
+    assert_eq!(timeline.count_at(timestamp(200_000_000_000)), 2);
+    assert_eq!(timeline.count_at(timestamp(1_000_000_000_000)), 2);
+    let peak = timeline.peak().unwrap();
+    assert_eq!((peak.time.secs, peak.count), (250, 3));
+
+    let buckets = timeline.buckets(
+        timestamp(0),
+        timestamp(320_000_000_000),
+        Duration::seconds(100),
+    );
+    let peaks = buckets.iter().map(|b| (b.end.secs, b.peak));
+    assert_eq!(
+        peaks.collect::<Vec<_>>(),
+        [(100, 0), (200, 2), (300, 3), (320, 2)]
+    );
+
+    // Sessions still logged in count up to the reference time.
+    let live = sessions(&entries[..2]).live_at(timestamp(400_000_000_000));
+    let timeline = Timeline::new(live);
+    assert_eq!(timeline.count_at(timestamp(399_000_000_000)), 2);
+    assert_eq!(timeline.count_at(timestamp(400_000_000_000)), 0);
+    assert_eq!(Timeline::new(Vec::<utmp_rs::Session>::new()).peak(), None);
+}
+
+#[test]
+fn login_histogram() {
+    use time::{UtcOffset, Weekday};
+    use utmp_rs::LoginHistogram;
+    let monday = 4 * 86400;
+    let entries = vec![
+        login(10, "pts/0", "root", 100),
+        logout(10, "pts/0", 200),
+        login(11, "pts/0", "root", 3700),
+        login(12, "pts/1", "alice", monday + 3700),
+    ];
+    let mut histogram = LoginHistogram::new();
+    histogram.count_logins(&entries);
+    assert_eq!(histogram.total(), 3);
+    assert_eq!(histogram.count(Weekday::Thursday, 0), 1);
+    assert_eq!(histogram.count(Weekday::Thursday, 1), 1);
+    assert_eq!(histogram.count(Weekday::Monday, 1), 1);
+    assert_eq!(histogram.matrix()[0][1], 1);
+    assert_eq!(histogram.by_hour()[1], 2);
+    assert_eq!(histogram.by_weekday(), [1, 0, 0, 2, 0, 0, 0]);
+
+    // Times are bucketed in local time of the offset.
+    let offset = UtcOffset::from_hms(-1, 0, 0).unwrap();
+    let mut histogram = LoginHistogram::new().offset(offset);
+    histogram.count_logins(&entries);
+    assert_eq!(histogram.count(Weekday::Wednesday, 23), 1);
+    assert_eq!(histogram.count(Weekday::Thursday, 0), 1);
+    assert_eq!(histogram.count(Weekday::Monday, 0), 1);
+
+    let attempts = failed_logins(&entries[..1]);
+    let mut failed = LoginHistogram::new();
+    failed.count_failed(&attempts);
+    assert_eq!(failed.by_weekday(), [0, 0, 0, 1, 0, 0, 0]);
+}
+
+#[test]
+fn detect_brute_force() {
+    use utmp_rs::BruteForceDetector;
+    let attempt = |host: &str, user: &str, secs| UtmpEntry::LoginProcess {
+        pid: 1,
+        line: "ssh:notty".to_owned(),
+        id: String::new(),
+        user: user.to_owned(),
+        host: host.to_owned(),
+        time: timestamp(i128::from(secs) * 1_000_000_000),
+    };
+    let mut entries = Vec::new();
+    // Many attempts at root within a minute.
+    entries.extend((0..12).map(|i| attempt("203.0.113.7", "root", 100 + i * 5)));
+    // Few attempts, but at many usernames.
+    for (i, user) in ["admin", "oracle", "pi", "test", "ubuntu"]
+        .iter()
+        .enumerate()
+    {
+        entries.push(attempt("198.51.100.2", user, 100 + i as i64 * 10));
+    }
+    // Someone mistyping a password now and then.
+    entries.extend((0..12).map(|i| attempt("192.0.2.1", "alice", i * 100)));
+    let attempts = failed_logins(&entries);
+
+    let reports = BruteForceDetector::new().detect(&attempts);
+    let hosts = reports.iter().map(|r| &*r.host).collect::<Vec<_>>();
+    assert_eq!(hosts, ["203.0.113.7", "198.51.100.2"]);
+    let report = &reports[0];
+    assert_eq!((report.attempts, report.users, report.total), (12, 1, 12));
+    assert_eq!(report.start, timestamp(100_000_000_000));
+    assert_eq!(report.end, timestamp(155_000_000_000));
+    assert_eq!(report.usernames, ["root"]);
+    assert_eq!((reports[1].attempts, reports[1].users), (5, 5));
+
+    let detector = BruteForceDetector::new()
+        .window(Duration::minutes(20))
+        .max_attempts(15)
+        .max_users(10);
+    let reports = detector.detect(&attempts);
+    assert_eq!(reports.len(), 0);
+    let reports = detector.max_attempts(12).detect(&attempts);
+    let hosts = reports.iter().map(|r| &*r.host).collect::<Vec<_>>();
+    assert_eq!(hosts, ["192.0.2.1", "203.0.113.7"]);
+
+    // Windows which aren't positive only count attempts at the same time.
+    let attempts = failed_logins(vec![
+        attempt("203.0.113.7", "root", 100),
+        attempt("203.0.113.7", "admin", 100),
+        attempt("203.0.113.7", "root", 101),
+    ]);
+    for window in [Duration::ZERO, Duration::seconds(-10)] {
+        let detector = BruteForceDetector::new().window(window).max_attempts(2);
+        let reports = detector.detect(&attempts);
+        assert_eq!(reports.len(), 1);
+        assert_eq!((reports[0].attempts, reports[0].users), (2, 2));
+        assert_eq!(reports[0].end, timestamp(100_000_000_000));
+    }
+}
+
+#[test]
+fn out_of_hours_report() {
+    use time::{Time, UtcOffset, Weekday};
+    use utmp_rs::BusinessHours;
+    let hour = 3600;
+    // Thursday, 1 January 1970.
+    let entries = vec![
+        login(10, "pts/0", "alice", 10 * hour),
+        logout(10, "pts/0", 11 * hour),
+        login(11, "pts/0", "alice", 20 * hour),
+        logout(11, "pts/0", 21 * hour),
+        login(12, "pts/1", "bob", 2 * hour),
+        // Saturday.
+        login(13, "pts/2", "alice", 2 * 86400 + 10 * hour),
+    ];
+    let all = sessions(&entries).collect::<Vec<_>>();
+    let hours = BusinessHours::new(
+        Time::from_hms(9, 0, 0).unwrap(),
+        Time::from_hms(18, 0, 0).unwrap(),
+    );
+    let report = hours.report(&all, timestamp(0), timestamp(7 * 86400 * 1_000_000_000));
+    let logins = report.logins.iter().map(|s| s.pid).collect::<Vec<_>>();
+    assert_eq!(logins, [12, 11, 13]);
+    let users = report
+        .users
+        .iter()
+        .map(|u| (&*u.user, u.logins, u.first.secs, u.last.secs))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        users,
+        [
+            ("alice", 2, 20 * hour, 2 * 86400 + 10 * hour),
+            ("bob", 1, 2 * hour, 2 * hour)
+        ]
+    );
+    let report = hours.report(&all, timestamp(0), timestamp(86400 * 1_000_000_000));
+    assert_eq!(report.logins.len(), 2);
+
+    // Hours are in local time, and can span midnight.
+    let hours = hours.offset(UtcOffset::from_hms(-9, 0, 0).unwrap());
+    assert!(hours.contains(timestamp(20 * hour as i128 * 1_000_000_000)));
+    let night = BusinessHours::new(
+        Time::from_hms(22, 0, 0).unwrap(),
+        Time::from_hms(6, 0, 0).unwrap(),
+    )
+    .days(vec![Weekday::Wednesday]);
+    // Thursday 02:00 is within the shift started on Wednesday.
+    assert!(night.contains(timestamp(2 * hour as i128 * 1_000_000_000)));
+    assert!(!night.contains(timestamp(23 * hour as i128 * 1_000_000_000)));
+}
+
+#[test]
+fn audit_concurrent_logins() {
+    use utmp_rs::{ConcurrentLoginAuditor, ConcurrentLoginKind};
+    let from = |pid, line: &str, user: &str, host: &str, secs| match login(pid, line, user, secs) {
+        UtmpEntry::UserProcess {
+            pid,
+            line,
+            id,
+            user,
+            address,
+            session,
+            time,
+            ..
+        } => UtmpEntry::UserProcess {
+            pid,
+            line,
+            id,
+            user,
+            host: host.to_owned(),
+            address,
+            session,
+            time,
+        },
+        entry => unreachable!("{:?}", entry),
+    };
+    let entries = vec![
+        from(10, "pts/0", "alice", "203.0.113.7", 100),
+        from(11, "pts/1", "alice", "203.0.113.7", 150),
+        from(12, "pts/2", "bob", "198.51.100.2", 160),
+        // Someone else logs in as alice while she's logged in.
+        from(13, "pts/3", "alice", "192.0.2.1", 200),
+        logout(10, "pts/0", 300),
+        logout(11, "pts/1", 300),
+        logout(13, "pts/3", 300),
+        // Logging in from elsewhere after logging out is fine.
+        from(14, "pts/0", "alice", "192.0.2.9", 400),
+        // So is a login whose logout is missing, e.g. at the end of an archived wtmp.
+        from(15, "pts/1", "alice", "192.0.2.9", 500),
+        logout(14, "pts/0", 550),
+        from(16, "pts/2", "alice", "203.0.113.7", 600),
+    ];
+    let all = sessions(&entries).collect::<Vec<_>>();
+    let flagged = ConcurrentLoginAuditor::new().audit(&all);
+    assert_eq!(flagged.len(), 1);
+    assert_eq!(flagged[0].user, "alice");
+    assert_eq!(flagged[0].kind, ConcurrentLoginKind::DifferentHosts);
+    assert_eq!(flagged[0].time, timestamp(200_000_000_000));
+    let pids = flagged[0]
+        .sessions
+        .iter()
+        .map(|s| s.pid)
+        .collect::<Vec<_>>();
+    assert_eq!(pids, [10, 11, 13]);
+
+    let flagged = ConcurrentLoginAuditor::new().max_sessions(1).audit(&all);
+    let kinds = flagged.iter().map(|l| (l.time.secs, l.kind));
+    assert_eq!(
+        kinds.collect::<Vec<_>>(),
+        [
+            (150, ConcurrentLoginKind::TooManySessions),
+            (200, ConcurrentLoginKind::DifferentHosts),
+            (200, ConcurrentLoginKind::TooManySessions),
+            (500, ConcurrentLoginKind::TooManySessions),
+        ]
+    );
+
+    // Unless sessions without a logout are counted as logged in forever.
+    let flagged = ConcurrentLoginAuditor::new()
+        .count_missing(true)
+        .audit(&all);
+    let kinds = flagged.iter().map(|l| (l.time.secs, l.kind));
+    assert_eq!(
+        kinds.collect::<Vec<_>>(),
+        [
+            (200, ConcurrentLoginKind::DifferentHosts),
+            (600, ConcurrentLoginKind::DifferentHosts),
+        ]
+    );
+}
+
+#[test]
+fn diff_snapshots() {
+    let old = vec![
+        login(10, "pts/0", "root", 100),
+        login(11, "pts/1", "alice", 150),
+        login(12, "pts/2", "bob", 160),
+    ];
+    let mut changed = old[1].clone();
+    if let UtmpEntry::UserProcess { host, .. } = &mut changed {
+        *host = "192.0.2.1".to_owned();
+    }
+    let new = vec![
+        old[0].clone(),
+        changed,
+        // Another login on the line of a session gone.
+        login(13, "pts/2", "carol", 200),
+        login(14, "pts/3", "dave", 210),
+    ];
+    let diff = utmp_rs::diff(&old, &new);
+    let pids = |sessions: &[utmp_rs::Session]| sessions.iter().map(|s| s.pid).collect::<Vec<_>>();
+    assert_eq!(pids(&diff.added), [13, 14]);
+    assert_eq!(pids(&diff.removed), [12]);
+    assert_eq!(diff.changed.len(), 1);
+    let (before, after) = &diff.changed[0];
+    assert_eq!((&*before.host, &*after.host), ("", "192.0.2.1"));
+    assert!(utmp_rs::diff(&new, &new).is_empty());
+    let diff = utmp_rs::diff(&new, Vec::<UtmpEntry>::new());
+    assert_eq!(pids(&diff.removed), [10, 11, 13, 14]);
+}
+
+#[test]
+fn replay_utmp_state() -> Result<()> {
+    let users = |state: &utmp_rs::UtmpState| {
+        (state.users())
+            .map(|e| match e {
+                UtmpEntry::UserProcess { pid, .. } => *pid,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut state = utmp_rs::UtmpState::new();
+    let entries = entries_across_reboots();
+    state.extend(&entries[..3]);
+    assert_eq!(users(&state), [10, 11]);
+    state.apply(&entries[3]);
+    assert_eq!(users(&state), [10]);
+    assert!(matches!(
+        state.entry_for_line("pts/0"),
+        Some(UtmpEntry::DeadProcess { pid: 11, .. })
+    ));
+    // Reboots start over.
+    state.extend(&entries[3..]);
+    assert_eq!(users(&state), [13]);
+    assert_eq!(state.entries().len(), 2);
+
+    // Replaying the sample gives back the users logged in.
+    let state = Utmp32Parser::from_path(SAMPLES_PATH.join("with_host_32.utmp"))?
+        .collect::<Result<utmp_rs::UtmpState, _>>()?;
+    assert_eq!(users(&state), [13369, 5022]);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+#![cfg(all(feature = "time", target_os = "linux"))]
+
+use anyhow::Result;
+use std::process;
+use utmp_rs::{StaleChecker, Staleness, Timestamp, UtmpEntry};
+
+fn user_process(pid: i32, time: Timestamp) -> UtmpEntry {
+    UtmpEntry::UserProcess {
+        pid,
+        line: ":0".to_owned(),
+        id: "".to_owned(),
+        user: "user".to_owned(),
+        host: "".to_owned(),
+        address: None,
+        session: 0,
+        time,
+    }
+}
+
+#[test]
+fn check_live_process() -> Result<()> {
+    let checker = StaleChecker::new()?;
+    let entry = user_process(process::id() as i32, Timestamp::now());
+    assert_eq!(checker.check(&entry)?, None);
+    Ok(())
+}
+
+#[test]
+fn check_stale_process() -> Result<()> {
+    let checker = StaleChecker::new()?;
+    // This is larger than the maximum PID allowed by Linux.
+    let entry = user_process(i32::MAX, Timestamp::now());
+    assert_eq!(checker.check(&entry)?, Some(Staleness::ProcessGone));
+    let entry = user_process(process::id() as i32, Timestamp::UNIX_EPOCH);
+    assert_eq!(checker.check(&entry)?, Some(Staleness::PidReused));
+    Ok(())
+}
+
+#[test]
+fn check_other_entries() -> Result<()> {
+    let checker = StaleChecker::new()?;
+    let entry = UtmpEntry::InitProcess {
+        pid: i32::MAX,
+        id: "si".to_owned(),
+        time: Timestamp::UNIX_EPOCH,
+    };
+    assert_eq!(checker.check(&entry)?, None);
+    Ok(())
+}
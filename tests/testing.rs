@@ -0,0 +1,170 @@
+#![cfg(feature = "testing")]
+
+use anyhow::Result;
+use utmp_rs::testing::{inject_defect, Defect, UtmpFileBuilder};
+use utmp_rs::{ConvertError, Endianness, Format, Timestamp, UtmpEntry, UtmpParser};
+
+fn timestamp(nanos: i128) -> Timestamp {
+    Timestamp {
+        secs: (nanos / 1_000_000_000) as i64,
+        micros: (nanos % 1_000_000_000 / 1000) as u32,
+    }
+}
+
+#[test]
+fn build_utmp_files() -> Result<()> {
+    let boot = UtmpEntry::BootTime {
+        kernel_version: "5.4.0-135-generic".to_owned(),
+        time: timestamp(1_675_756_860_150_698_000),
+    };
+    let run_level = UtmpEntry::RunLevel {
+        level: '5',
+        previous: Some('N'),
+        kernel_version: "5.4.0-135-generic".to_owned(),
+        time: timestamp(1_675_756_874_594_747_000),
+    };
+    let host = "h".repeat(300);
+    let builder = UtmpFileBuilder::new()
+        .entries(vec![boot.clone(), run_level.clone()])
+        .login(
+            "root",
+            "pts/0",
+            "112.124.2.209",
+            1125,
+            timestamp(1_675_757_226_139_552_000),
+        )
+        .login(
+            "root",
+            "pts/1",
+            &host,
+            1127,
+            timestamp(1_675_757_226_284_647_000),
+        )
+        .logout("pts/0", 1125, timestamp(1_675_759_026_000_000_000))
+        .shutdown("5.4.0-135-generic", timestamp(1_675_760_000_000_000_000));
+
+    for &(format, endianness) in &[
+        (Format::X32, Endianness::Little),
+        (Format::X64, Endianness::Big),
+    ] {
+        let bytes = builder
+            .clone()
+            .format(format)
+            .endianness(endianness)
+            .build()?;
+        let entries = UtmpParser::builder()
+            .format(format)
+            .endianness(endianness)
+            .from_bytes(&bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0], boot);
+        assert_eq!(entries[1], run_level);
+        assert_eq!(
+            entries[2],
+            UtmpEntry::UserProcess {
+                pid: 1125,
+                line: "pts/0".to_owned(),
+                id: "ts/0".to_owned(),
+                user: "root".to_owned(),
+                host: "112.124.2.209".to_owned(),
+                address: Some("112.124.2.209".parse()?),
+                session: 0,
+                time: timestamp(1_675_757_226_139_552_000),
+            }
+        );
+        // The host fills its field without a terminating NUL.
+        match &entries[3] {
+            UtmpEntry::UserProcess { host, address, .. } => {
+                assert_eq!(host.len(), utmp_raw::UT_HOSTSIZE);
+                assert_eq!(*address, None);
+            }
+            entry => panic!("unexpected entry {:?}", entry),
+        }
+        assert!(matches!(
+            entries[4],
+            UtmpEntry::DeadProcess { pid: 1125, .. }
+        ));
+        assert!(matches!(entries[5], UtmpEntry::ShutdownTime { .. }));
+    }
+
+    let dir = std::env::temp_dir().join(format!("utmp-rs-testing-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join("wtmp");
+    builder.write_to_path(&path)?;
+    assert_eq!(utmp_rs::parse_from_path(&path)?.len(), 6);
+    std::fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn build_overflowing_times() {
+    let builder = UtmpFileBuilder::new()
+        .boot("5.4.0-135-generic", timestamp(1_675_756_860_000_000_000))
+        .boot("5.4.0-135-generic", timestamp(4_102_444_800_000_000_000));
+    assert!(builder.clone().format(Format::X64).build().is_ok());
+    let result = builder.format(Format::X32).build();
+    assert!(matches!(
+        result,
+        Err(ConvertError::Overflow {
+            index: 1,
+            field: "ut_tv",
+        })
+    ));
+}
+
+#[test]
+fn inject_defects() -> Result<()> {
+    let bytes = UtmpFileBuilder::new()
+        .format(Format::X64)
+        .login(
+            "root",
+            "pts/0",
+            "",
+            1125,
+            timestamp(1_675_757_226_139_552_000),
+        )
+        .login(
+            "root",
+            "pts/1",
+            "",
+            1127,
+            timestamp(1_675_757_226_284_647_000),
+        )
+        .logout("pts/0", 1125, timestamp(1_675_759_026_000_000_000))
+        .build()?;
+    let parse = |defect| {
+        let mut bytes = bytes.clone();
+        inject_defect(&mut bytes, Format::Auto, defect);
+        let parser = UtmpParser::builder().format(Format::X64).from_bytes(&bytes);
+        parser.collect::<Vec<_>>()
+    };
+
+    let entries = parse(Defect::TruncateMidRecord);
+    assert_eq!(entries.len(), 3);
+    assert!(entries[2].is_err());
+    let entries = parse(Defect::FlipType(1));
+    assert!(matches!(
+        entries[1],
+        Ok(UtmpEntry::Unknown { ut_type: -8, .. })
+    ));
+    // Raw records of unknown types are written back as they are.
+    let mut flipped = bytes.clone();
+    inject_defect(&mut flipped, Format::X64, Defect::FlipType(1));
+    let unknown = entries[1].as_ref().unwrap().clone();
+    let rebuilt = UtmpFileBuilder::new().format(Format::X64).entry(unknown);
+    assert_eq!(rebuilt.build()?, &flipped[400..800]);
+    let entries = parse(Defect::ZeroRecord(0));
+    assert!(matches!(entries[0], Ok(UtmpEntry::Empty)));
+    let entries = parse(Defect::SwapEndianness(2));
+    assert!(entries[..2].iter().all(Result::is_ok));
+    // Numeric fields read in the wrong byte order make no sense.
+    match &entries[2] {
+        Ok(UtmpEntry::Unknown { ut_type, pid, .. }) => {
+            assert_eq!(*ut_type, utmp_raw::DEAD_PROCESS.swap_bytes());
+            assert_eq!(*pid, 1125i32.swap_bytes());
+        }
+        entry => panic!("unexpected entry {:?}", entry),
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+#![cfg(all(feature = "time", unix))]
+
+use anyhow::Result;
+use std::fs::{self, File, FileTimes, Permissions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, SystemTime};
+use utmp_rs::{sessions, Timestamp, TtyChecker, UtmpEntry, WhoLine};
+
+fn temp_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("utmp-rs-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("pts"))?;
+    Ok(dir)
+}
+
+#[test]
+fn tty_status() -> Result<()> {
+    let dir = temp_dir("tty")?;
+    let accessed = SystemTime::UNIX_EPOCH + Duration::from_secs(1_675_757_226);
+    let tty = File::create(dir.join("pts/0"))?;
+    tty.set_times(FileTimes::new().set_accessed(accessed))?;
+    tty.set_permissions(Permissions::from_mode(0o620))?;
+    let checker = TtyChecker::with_dev_path(&dir);
+
+    let now = Timestamp::from_system_time(accessed) + time::Duration::minutes(40);
+    let status = checker.status_at("pts/0", now)?.unwrap();
+    assert_eq!(status.idle, time::Duration::minutes(40));
+    assert_eq!(status.mesg(), '+');
+    tty.set_permissions(Permissions::from_mode(0o600))?;
+    assert_eq!(checker.status_at("pts/0", now)?.unwrap().mesg(), '-');
+    assert_eq!(checker.status_at("pts/1", now)?, None);
+    assert_eq!(checker.status_at("", now)?, None);
+    assert_eq!(checker.status_at("../pts/0", now)?, None);
+
+    let login = UtmpEntry::UserProcess {
+        pid: 1125,
+        line: "pts/0".to_owned(),
+        id: "ts/0".to_owned(),
+        user: "root".to_owned(),
+        host: "112.124.2.209".to_owned(),
+        address: None,
+        session: 0,
+        time: Timestamp::from_system_time(accessed),
+    };
+    let session = sessions(vec![login]).next().unwrap();
+    let status = checker.status_at("pts/0", now)?;
+    assert_eq!(
+        WhoLine::new(&session, status.as_ref()).to_string(),
+        "root     - pts/0        2023-02-07 08:07 00:40        1125 (112.124.2.209)"
+    );
+    let status = checker.status_at("pts/0", Timestamp::from_system_time(accessed))?;
+    assert_eq!(
+        WhoLine::new(&session, status.as_ref()).to_string(),
+        "root     - pts/0        2023-02-07 08:07   .          1125 (112.124.2.209)"
+    );
+    assert_eq!(
+        WhoLine::new(&session, None).to_string(),
+        "root     ? pts/0        2023-02-07 08:07              1125 (112.124.2.209)"
+    );
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
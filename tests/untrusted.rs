@@ -0,0 +1,144 @@
+//! Parsing arbitrary bytes must never panic, whatever their content and alignment.
+
+use once_cell::sync::Lazy;
+use std::fs;
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use utmp_rs::{ErrorPolicy, Format, ParseError, Utmp32Parser, Utmp64Parser, UtmpEntry};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+/// Deterministic xorshift generator, so failures can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Inputs made of random bytes, and of samples with random bytes changed,
+/// with the type of records set to known types, so that most fields get decoded.
+fn inputs() -> Vec<Vec<u8>> {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let samples = ["basic32.utmp", "basic64.utmp", "with_host_32.utmp"]
+        .iter()
+        .map(|name| fs::read(SAMPLES_PATH.join(name)).unwrap())
+        .collect::<Vec<_>>();
+    let mut inputs = Vec::new();
+    for _ in 0..50 {
+        let len = rng.below(4 * 400);
+        inputs.push((0..len).map(|_| rng.next() as u8).collect());
+    }
+    for sample in &samples {
+        for _ in 0..50 {
+            let mut data = sample.clone();
+            for _ in 0..rng.below(64) {
+                let pos = rng.below(data.len());
+                data[pos] = rng.next() as u8;
+            }
+            let size = [384, 400][rng.below(2)];
+            for record in data.chunks_mut(size) {
+                if record.len() >= 2 && rng.below(2) == 0 {
+                    record[0] = rng.below(12) as u8;
+                    record[1] = 0;
+                }
+            }
+            data.truncate(data.len() - rng.below(size));
+            inputs.push(data);
+        }
+    }
+    // Extreme times in x64 records.
+    for &sec in &[i64::MAX, i64::MIN, -1] {
+        let mut record = vec![0; 400];
+        record[0] = 7;
+        record[344..352].copy_from_slice(&sec.to_ne_bytes());
+        record[352..360].copy_from_slice(&999_999i64.to_ne_bytes());
+        inputs.push(record);
+    }
+    inputs
+}
+
+fn exercise(entries: impl Iterator<Item = Result<UtmpEntry, ParseError>>) {
+    for entry in entries {
+        match entry {
+            Ok(entry) => {
+                let _ = entry.to_string();
+                let _ = entry.time_as_system_time();
+            }
+            Err(e) => {
+                let _ = e.to_string();
+            }
+        }
+    }
+}
+
+macro_rules! exercise_parser {
+    ($parser:ident, $data:expr) => {{
+        let data = $data;
+        for &(lossy, lenient, skip_empty) in &[(false, false, false), (true, true, true)] {
+            let parser = || {
+                $parser::from_bytes(data)
+                    .lossy(lossy)
+                    .lenient(lenient)
+                    .skip_empty(skip_empty)
+            };
+            exercise(parser());
+            exercise(parser().rev());
+            let mut parser = parser();
+            while let Some(entry) = parser.next_ref() {
+                let _ = entry.map(|entry| entry.to_owned());
+            }
+            let mut parser = $parser::from_bytes(data).skip_empty(skip_empty);
+            while let Some(entry) = parser.next_lazy() {
+                if let Ok(entry) = entry {
+                    let _ = (entry.line(), entry.id(), entry.user(), entry.host());
+                    let _ = (entry.time(), entry.decode());
+                }
+            }
+
+            let reader = || {
+                $parser::from_reader(data)
+                    .with_buffer_capacity(500)
+                    .lenient(lenient)
+                    .skip_empty(skip_empty)
+            };
+            exercise(reader());
+            let mut reader = reader();
+            while let Ok(Some(entry)) = reader.next_entry() {
+                let _ = entry.to_owned();
+            }
+        }
+    }};
+}
+
+#[test]
+fn parse_arbitrary_input() {
+    // Spare room for misaligning the input.
+    let mut buffer = Vec::new();
+    for input in inputs() {
+        for shift in 0..4 {
+            buffer.clear();
+            buffer.resize(shift, 0);
+            buffer.extend_from_slice(&input);
+            let data = &buffer[shift..];
+            exercise_parser!(Utmp32Parser, data);
+            exercise_parser!(Utmp64Parser, data);
+            for &policy in &[ErrorPolicy::Stop, ErrorPolicy::Skip, ErrorPolicy::Resync] {
+                let builder = Utmp32Parser::builder()
+                    .format(Format::Auto)
+                    .on_error(policy);
+                exercise(builder.clone().from_bytes(data));
+                exercise(builder.buffer_capacity(1).from_reader(data).unwrap());
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+#![cfg(all(feature = "notify", target_os = "linux"))]
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::iter::FromIterator;
+use std::path::PathBuf;
+use std::process;
+use utmp_rs::{parse_from_path, Source, UtmpEntry, UtmpWatcher, WatchEvent};
+
+static SAMPLES_PATH: Lazy<PathBuf> =
+    Lazy::new(|| PathBuf::from_iter(&[env!("CARGO_MANIFEST_DIR"), "tests", "samples"]));
+
+fn temp_dir(name: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("utmp-rs-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn load_sample() -> Result<(Vec<u8>, Vec<UtmpEntry>, usize)> {
+    let sample = SAMPLES_PATH.join("basic32.utmp");
+    let data = fs::read(&sample)?;
+    let entries = parse_from_path(&sample)?;
+    let record_size = data.len() / entries.len();
+    Ok((data, entries, record_size))
+}
+
+fn entries(events: &[WatchEvent]) -> Vec<UtmpEntry> {
+    events
+        .iter()
+        .map(|event| match event {
+            WatchEvent::Entry(_, entry) => entry.clone(),
+            event => panic!("unexpected event {:?}", event),
+        })
+        .collect()
+}
+
+#[test]
+fn watch_appended_entries() -> Result<()> {
+    let (data, expected, record_size) = load_sample()?;
+    let dir = temp_dir("watch")?;
+    let path = dir.join("wtmp");
+    fs::write(&path, &data[..record_size])?;
+    let source = Source::Other(path.clone());
+    let mut watcher = UtmpWatcher::from_paths([&path])?;
+
+    let mut file = OpenOptions::new().append(true).open(&path)?;
+    // Write one and a half records first, the partial one shouldn't be yielded yet.
+    file.write_all(&data[record_size..record_size * 5 / 2])?;
+    assert_eq!(
+        watcher.next().unwrap()?,
+        WatchEvent::Entry(source.clone(), expected[1].clone())
+    );
+    file.write_all(&data[record_size * 5 / 2..])?;
+    let actual = watcher
+        .take(expected.len() - 2)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(entries(&actual), &expected[2..]);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn watch_rotated_file() -> Result<()> {
+    let (data, expected, record_size) = load_sample()?;
+    let dir = temp_dir("watch-rotated")?;
+    let path = dir.join("wtmp");
+    fs::write(&path, &data[..record_size])?;
+    let source = Source::Other(path.clone());
+    let mut watcher = UtmpWatcher::from_paths([&path])?;
+
+    fs::rename(&path, dir.join("wtmp.1"))?;
+    fs::write(&path, &data[record_size..record_size * 3])?;
+    assert_eq!(
+        watcher.next().unwrap()?,
+        WatchEvent::Rotated(source.clone())
+    );
+    let actual = (&mut watcher).take(2).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(entries(&actual), &expected[1..3]);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn watch_truncated_file() -> Result<()> {
+    let (data, expected, record_size) = load_sample()?;
+    let dir = temp_dir("watch-truncated")?;
+    let path = dir.join("wtmp");
+    fs::write(&path, &data)?;
+    let source = Source::Other(path.clone());
+    let mut watcher = UtmpWatcher::from_paths([&path])?;
+
+    let file = OpenOptions::new().write(true).open(&path)?;
+    file.set_len(0)?;
+    assert_eq!(
+        watcher.next().unwrap()?,
+        WatchEvent::Rotated(source.clone())
+    );
+    (&file).write_all(&data[..record_size])?;
+    assert_eq!(
+        watcher.next().unwrap()?,
+        WatchEvent::Entry(source.clone(), expected[0].clone())
+    );
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn watch_rewritten_records() -> Result<()> {
+    let (data, expected, record_size) = load_sample()?;
+    let dir = temp_dir("watch-rewritten")?;
+    let path = dir.join("utmp");
+    fs::write(&path, &data[..record_size * 2])?;
+    let source = Source::Other(path.clone());
+    let mut watcher = UtmpWatcher::from_sources([])?;
+    watcher.watch_rewritten(source.clone())?;
+
+    // Rewrite the second record in place, as `pututline` does, then append another.
+    let file = OpenOptions::new().write(true).open(&path)?;
+    (&file).seek(SeekFrom::Start(record_size as u64))?;
+    (&file).write_all(&data[record_size * 3..record_size * 4])?;
+    assert_eq!(
+        watcher.next().unwrap()?,
+        WatchEvent::Entry(source.clone(), expected[3].clone())
+    );
+    (&file).write_all(&data[record_size * 2..record_size * 3])?;
+    assert_eq!(
+        watcher.next().unwrap()?,
+        WatchEvent::Entry(source.clone(), expected[2].clone())
+    );
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn watch_since_time() -> Result<()> {
+    let (data, expected, record_size) = load_sample()?;
+    let dir = temp_dir("watch-since")?;
+    let first = Source::Other(dir.join("wtmp"));
+    let second = Source::Other(dir.join("btmp"));
+    let record = |i: usize| &data[record_size * i..record_size * (i + 1)];
+    fs::write(first.path(), [record(0), record(2), record(3)].concat())?;
+    fs::write(second.path(), record(1))?;
+    let since = expected[1].time().unwrap();
+    let mut watcher = UtmpWatcher::from_paths(Vec::<PathBuf>::new())?;
+    watcher.watch_since(first.clone(), since)?;
+    watcher.watch_since(second.clone(), since)?;
+
+    // Existing entries come first in order of timestamp, then new ones.
+    let mut file = OpenOptions::new().append(true).open(first.path())?;
+    file.write_all(record(4))?;
+    let actual = (&mut watcher).take(4).collect::<Result<Vec<_>, _>>()?;
+    let source = |i| if i == 1 { &second } else { &first };
+    let expected = (1..5)
+        .map(|i| WatchEvent::Entry(source(i).clone(), expected[i].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+#[test]
+fn watch_multiple_files() -> Result<()> {
+    let (data, expected, record_size) = load_sample()?;
+    let dir = temp_dir("watch-multiple")?;
+    let first = Source::Other(dir.join("wtmp"));
+    let second = Source::Other(dir.join("btmp"));
+    fs::write(first.path(), b"")?;
+    fs::write(second.path(), b"")?;
+    let mut watcher = UtmpWatcher::from_sources([first.clone(), second.clone()])?;
+
+    // Records go into both files out of order,
+    // and they should come out ordered by timestamp.
+    let record = |i: usize| &data[record_size * i..record_size * (i + 1)];
+    fs::write(second.path(), [record(0), record(3)].concat())?;
+    fs::write(first.path(), [record(1), record(2)].concat())?;
+    let actual = (&mut watcher).take(4).collect::<Result<Vec<_>, _>>()?;
+    let source = |i| if i == 1 || i == 2 { &first } else { &second };
+    let expected = (0..4)
+        .map(|i| WatchEvent::Entry(source(i).clone(), expected[i].clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}